@@ -1,7 +1,7 @@
-use crate::database::DatabaseManager;
+use crate::database::{parse_sqlite_datetime, DatabaseManager};
 use rusqlite::{Result, Row};
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc, Duration};
+use chrono::{DateTime, Utc, Duration, SecondsFormat};
 use uuid::Uuid;
 use std::sync::Arc;
 
@@ -60,14 +60,14 @@ impl AuthManager {
                 &session.email,
                 &session.access_token,
                 &session.refresh_token,
-                session.expires_at.to_rfc3339(),
+                session.expires_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                 &session.user_metadata,
                 &session.role,
-                session.created_at.to_rfc3339(),
-                session.updated_at.to_rfc3339(),
-                session.last_activity.to_rfc3339(),
+                session.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                session.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                session.last_activity.to_rfc3339_opts(SecondsFormat::Millis, true),
                 session.session_valid,
-                session.offline_expiry.to_rfc3339(),
+                session.offline_expiry.to_rfc3339_opts(SecondsFormat::Millis, true),
                 &session.device_fingerprint,
             ),
         )?;
@@ -81,7 +81,7 @@ impl AuthManager {
             "SELECT id, user_id, email, access_token, refresh_token, expires_at, user_metadata, 
                     role, created_at, updated_at, last_activity, session_valid, offline_expiry, device_fingerprint
              FROM user_sessions 
-             WHERE email = ?1 AND session_valid = 1 AND offline_expiry > datetime('now')
+             WHERE email = ?1 AND session_valid = 1 AND datetime(offline_expiry) > datetime('now')
              ORDER BY last_activity DESC LIMIT 1"
         )?;
 
@@ -114,8 +114,8 @@ impl AuthManager {
         conn.execute(
             "UPDATE user_sessions SET last_activity = ?1, updated_at = ?2 WHERE id = ?3",
             (
-                session.last_activity.to_rfc3339(),
-                Utc::now().to_rfc3339(),
+                session.last_activity.to_rfc3339_opts(SecondsFormat::Millis, true),
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
                 &session.id,
             ),
         )?;
@@ -127,7 +127,7 @@ impl AuthManager {
         let conn = self.db.connection.lock().unwrap();
         conn.execute(
             "UPDATE user_sessions SET session_valid = 0, updated_at = ?1 WHERE id = ?2",
-            (Utc::now().to_rfc3339(), session_id),
+            (Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true), session_id),
         )?;
         Ok(())
     }
@@ -136,7 +136,7 @@ impl AuthManager {
     pub async fn cleanup_expired_sessions(&self) -> Result<()> {
         let conn = self.db.connection.lock().unwrap();
         conn.execute(
-            "DELETE FROM user_sessions WHERE offline_expiry < datetime('now')",
+            "DELETE FROM user_sessions WHERE datetime(offline_expiry) < datetime('now')",
             [],
         )?;
         Ok(())
@@ -190,14 +190,14 @@ impl AuthManager {
             email: row.get(2)?,
             access_token: row.get(3)?,
             refresh_token: row.get(4)?,
-            expires_at: DateTime::parse_from_rfc3339(&expires_str).unwrap().with_timezone(&Utc),
+            expires_at: parse_sqlite_datetime(&expires_str)?,
             user_metadata: row.get(6)?,
             role: row.get(7)?,
-            created_at: DateTime::parse_from_rfc3339(&created_str).unwrap().with_timezone(&Utc),
-            updated_at: DateTime::parse_from_rfc3339(&updated_str).unwrap().with_timezone(&Utc),
-            last_activity: DateTime::parse_from_rfc3339(&activity_str).unwrap().with_timezone(&Utc),
+            created_at: parse_sqlite_datetime(&created_str)?,
+            updated_at: parse_sqlite_datetime(&updated_str)?,
+            last_activity: parse_sqlite_datetime(&activity_str)?,
             session_valid: session_valid == 1,
-            offline_expiry: DateTime::parse_from_rfc3339(&offline_expiry_str).unwrap().with_timezone(&Utc),
+            offline_expiry: parse_sqlite_datetime(&offline_expiry_str)?,
             device_fingerprint: row.get(13)?,
         })
     }
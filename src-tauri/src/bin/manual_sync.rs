@@ -57,7 +57,7 @@ async fn main() -> Result<()> {
                 INSERT OR REPLACE INTO books (
                     id, title, author, isbn, publisher, publication_year, 
                     total_copies, available_copies, status, created_at, updated_at
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'available', datetime('now'), datetime('now'))
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'available', strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
             "#;
             
             match sqlx::query(query)
@@ -111,7 +111,7 @@ async fn main() -> Result<()> {
                 let query = r#"
                     INSERT OR REPLACE INTO categories (
                         id, name, description, created_at, updated_at
-                    ) VALUES (?, ?, ?, datetime('now'), datetime('now'))
+                    ) VALUES (?, ?, ?, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
                 "#;
                 
                 if let Ok(_) = sqlx::query(query)
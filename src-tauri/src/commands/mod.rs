@@ -1,18 +1,97 @@
-use crate::database::{DatabaseManager, LibraryStats};
+use crate::database::{BookCopyChange, BookReturnResult, BorrowingError, DatabaseManager, LibraryStats, PaginatedBorrowings, StudentError, StudentReturnResult};
+use crate::database::audit::{write_integrity_report, ReportFormat, ValidationReport};
+use crate::database::reports::*;
+use crate::database::maintenance::*;
+use crate::database::pdf::write_text_pdf;
+use crate::database::imports::StudentImportReport;
+use crate::database::hashing::HashRebuildResult;
+use crate::database::trash::{DeletedRecord, PurgePreview, PurgeTableCount};
+use crate::database::merge::{MergePreview, MergeResult, MergeStrategy};
+use crate::database::group_borrowings::GroupReturnResult;
+use crate::database::tags::TagCount;
+use crate::database::exports::{diff_exports as diff_export_files, ExportDiff};
+use crate::database::fine_settings::parse_fine_type;
+use crate::database::nightly::{MaintenanceReport, WalCheckpoint};
+use crate::database::backup::{BackupFile, BackupOutcome, IncrementalBackupOutcome, RestoreReport};
+use crate::database::datetimes::DatetimeNormalizationReport;
+use crate::database::renewals::{DueDateFilter, RenewalResult};
+use crate::database::checkout::{CheckoutVerdict, ScannedCopy};
+use crate::database::self_check::SelfCheckReport;
+use crate::database::batch::BatchCreateResult;
+use crate::database::remote_records::check_remote_table;
+use crate::database::conflicts::SyncConflictEntry;
+use crate::database::archive::ArchiveResult;
+use crate::database::book_duplicates::{BookMergeResult, DuplicateBookCandidate};
+use crate::database::reservations::Reservation;
+use crate::database::settings::{
+    BORROW_BLOCK_THRESHOLD_KEY, CONFLICT_LOG_RETENTION_DAYS_KEY, DEFAULT_LOAN_DAYS_KEY, NAMED_SETTINGS,
+    TRASH_RETENTION_DAYS_KEY, VERIFY_RETURN_TRACKING_CODES_KEY,
+};
+use crate::database::clear::ClearTablesResult;
+use crate::database::history::{BookCirculationHistory, HistoryPage, StudentHistoryEntry};
 use crate::models::*;
 use crate::sync::{SyncEngine, SyncStatus};
+use crate::simple_sync::{SyncConfig, SyncLock, SyncProgress, SyncResult, SyncSummary};
 // use crate::auth::{AuthManager, AuthCredentials, AuthResponse, UserSession};
 use serde_json::{Value, json};
-use std::sync::Arc;
-use tauri::State;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Emitter, State};
 use uuid::Uuid;
 use tracing::{info, warn, error};
-use chrono::{Duration, Utc};
+use chrono::{Duration, NaiveDate, Utc};
 
 pub type DatabaseState = Arc<DatabaseManager>;
+pub type SyncConfigState = Arc<RwLock<Option<SyncConfig>>>;
 // pub type AuthState = Arc<AuthManager>;
 // pub type SyncState = Arc<SyncEngine>; // Disabled for build
 
+// Supabase config loaded at startup or saved through setup_sync_config
+fn current_sync_config(state: &SyncConfigState) -> Result<SyncConfig, String> {
+    state.read()
+        .map_err(|_| "Sync config is unavailable".to_string())?
+        .clone()
+        .ok_or_else(|| "Sync is not configured - set SUPABASE_URL and SUPABASE_ANON_KEY or save a sync config first".to_string())
+}
+
+// Role gating for privileged commands, based on the cached offline session
+async fn require_role(db: &DatabaseManager, allowed_roles: &[&str]) -> Result<(), String> {
+    let session = db.get_any_valid_session().await
+        .map_err(|e| format!("Failed to check session: {}", e))?
+        .ok_or("No active session - please sign in again")?;
+
+    if allowed_roles.iter().any(|role| session.role.eq_ignore_ascii_case(role)) {
+        Ok(())
+    } else {
+        warn!("Role '{}' denied for privileged command", session.role);
+        Err(format!("Your role ({}) is not allowed to perform this action", session.role))
+    }
+}
+
+// Record a local change in the sync queue; a failure here must not undo the local write
+async fn queue_sync(db: &DatabaseManager, table_name: &str, operation_type: &str, record_id: &str, payload: Value) {
+    if let Err(e) = db.enqueue_operation(table_name, operation_type, record_id, &payload).await {
+        warn!("Failed to queue {} of {} {} for sync: {}", operation_type, table_name, record_id, e);
+    }
+}
+
+// Refuse a destructive action while local changes haven't reached Supabase, unless forced
+async fn ensure_nothing_unsynced(db: &DatabaseManager, force: bool, action: &str) -> Result<(), String> {
+    let changes = db.check_unsynced_changes().await
+        .map_err(|e| format!("Failed to check unsynced changes: {}", e))?;
+    if !changes.has_unsynced() {
+        return Ok(());
+    }
+    if force {
+        warn!("Proceeding with {} despite unsynced changes ({})", action, changes.summary());
+        return Ok(());
+    }
+    Err(format!(
+        "Unsynced local changes would be lost by {} ({}). Push them first or retry with force.",
+        action, changes.summary()
+    ))
+}
+
 // Book Commands - Core offline-capable CRUD operations
 #[tauri::command]
 pub async fn create_book(
@@ -28,12 +107,7 @@ pub async fn create_book(
         .map_err(|e| format!("Failed to create book: {}", e))?;
 
     // Queue for sync to Supabase when online
-    // sync_engine.queue_operation(
-    //     "books",
-    //     OperationType::Create,
-    //     &book.id.to_string(),
-    //     book_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    queue_sync(&db, "books", "insert", &book.id.to_string(), book_data).await;
 
     Ok(book.id.to_string())
 }
@@ -62,7 +136,7 @@ pub async fn search_books(
 pub async fn batch_create_books(
     books_data: Vec<Value>,
     db: State<'_, DatabaseState>,
-) -> Result<usize, String> {
+) -> Result<BatchCreateResult, String> {
     let mut books = Vec::new();
     for book_data in books_data {
         let book: Book = serde_json::from_value(book_data)
@@ -70,18 +144,34 @@ pub async fn batch_create_books(
         books.push(book);
     }
     
-    // Use optimized batch insert
-    let mut successful = 0;
-    for book in books {
-        match db.create_book(&book).await {
-            Ok(_) => successful += 1,
-            Err(e) => {
-                info!("Failed to create book {}: {}", book.title, e);
-            }
-        }
+    let result = db.batch_create_books(&books).await
+        .map_err(|e| format!("Failed to create books: {}", e))?;
+    for failure in &result.failures {
+        info!("Failed to create book {}: {}", failure.record, failure.error);
     }
     
-    Ok(successful)
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn batch_create_students(
+    students_data: Vec<Value>,
+    db: State<'_, DatabaseState>,
+) -> Result<BatchCreateResult, String> {
+    let mut students = Vec::new();
+    for student_data in students_data {
+        let student: Student = serde_json::from_value(student_data)
+            .map_err(|e| format!("Failed to parse student data: {}", e))?;
+        students.push(student);
+    }
+
+    let result = db.batch_create_students(&students).await
+        .map_err(|e| format!("Failed to create students: {}", e))?;
+    for failure in &result.failures {
+        info!("Failed to create student {}: {}", failure.record, failure.error);
+    }
+
+    Ok(result)
 }
 
 // Parallel search across multiple entity types
@@ -131,40 +221,18 @@ pub async fn global_search(
 pub async fn get_books_paginated(
     page: usize,
     page_size: usize,
-    _category_filter: Option<String>,
+    category_filter: Option<String>,
     search_query: Option<String>,
     db: State<'_, DatabaseState>,
 ) -> Result<serde_json::Value, String> {
-    let all_books = db.get_books().await
+    let page_size = page_size.max(1);
+    let (books, total_count) = db.get_books_paginated(page, page_size, category_filter.as_deref(), search_query.as_deref()).await
         .map_err(|e| format!("Failed to get books: {}", e))?;
-    
-    // Apply filters
-    let mut filtered_books = all_books;
-    
-    if let Some(query) = search_query {
-        let query_lower = query.to_lowercase();
-        filtered_books = filtered_books.into_iter()
-            .filter(|book| {
-                book.title.to_lowercase().contains(&query_lower) ||
-                book.author.to_lowercase().contains(&query_lower) ||
-                book.isbn.as_ref().map_or(false, |isbn| isbn.to_lowercase().contains(&query_lower))
-            })
-            .collect();
-    }
-    
-    // Apply pagination
-    let total_count = filtered_books.len();
-    let total_pages = (total_count as f64 / page_size as f64).ceil() as usize;
-    let offset = page * page_size;
-    
-    let paginated_books: Vec<Book> = filtered_books
-        .into_iter()
-        .skip(offset)
-        .take(page_size)
-        .collect();
-    
+
+    let total_pages = total_count.div_ceil(page_size);
+
     Ok(json!({
-        "books": paginated_books,
+        "books": books,
         "current_page": page,
         "page_size": page_size,
         "total_count": total_count,
@@ -197,16 +265,44 @@ pub async fn create_category(
         .map_err(|e| format!("Failed to create category: {}", e))?;
 
     // Queue for sync
-    // sync_engine.queue_operation(
-    //     "categories",
-    //     OperationType::Create,
-    //     &category.id.to_string(),
-    //     category_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    queue_sync(&db, "categories", "insert", &category.id.to_string(), category_data).await;
 
     Ok(category.id.to_string())
 }
 
+#[tauri::command]
+pub async fn find_duplicate_categories(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<DuplicateCategoryGroup>, String> {
+    db.find_duplicate_categories().await
+        .map_err(|e| format!("Failed to find duplicate categories: {}", e))
+}
+
+#[tauri::command]
+pub async fn merge_categories(
+    keep_id: String,
+    merge_ids: Vec<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<CategoryMergeResult, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let result = db.merge_categories(&keep_id, &merge_ids).await
+        .map_err(|e| format!("Failed to merge categories: {}", e))?;
+
+    for book_id in &result.repointed_book_ids {
+        queue_sync(&db, "books", "update", book_id, json!({"category_id": keep_id})).await;
+    }
+    for category_id in &result.merged_category_ids {
+        queue_sync(&db, "categories", "delete", category_id, json!({"id": category_id})).await;
+    }
+
+    info!(
+        "Merged {} categories into {} ({} books repointed)",
+        result.merged_category_ids.len(), keep_id, result.repointed_book_ids.len()
+    );
+    Ok(result)
+}
+
 // Student Commands
 #[tauri::command]
 pub async fn get_students(
@@ -227,19 +323,50 @@ pub async fn create_student(
     
     // Local-first storage
     db.create_student(&student).await
-        .map_err(|e| format!("Failed to create student: {}", e))?;
+        .map_err(|e| match e {
+            StudentError::Database(e) => format!("Failed to create student: {}", e),
+            e => e.to_string(),
+        })?;
 
     // Queue for sync
-    // sync_engine.queue_operation(
-    //     "students",
-    //     OperationType::Create,
-    //     &student.id.to_string(),
-    //     student_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    queue_sync(&db, "students", "insert", &student.id.to_string(), student_data).await;
 
     Ok(student.id.to_string())
 }
 
+#[tauri::command]
+pub async fn get_student_details(
+    student_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<StudentWithClass, String> {
+    db.get_student_with_fines(&student_id).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Student {} not found", student_id),
+            e => format!("Failed to get student details: {}", e),
+        })
+}
+
+#[tauri::command]
+pub async fn import_students_csv(
+    path: String,
+    dry_run: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<StudentImportReport, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    let (report, imported) = db.import_students_csv(std::path::Path::new(&path), dry_run).await
+        .map_err(|e| format!("Failed to import students from {}: {}", path, e))?;
+
+    for student in &imported {
+        queue_sync(&db, "students", "insert", &student.id, json!(student)).await;
+    }
+
+    info!(
+        "Student import from {} ({}): {} imported, {} failed",
+        path, if dry_run { "dry run" } else { "applied" }, report.imported, report.failed
+    );
+    Ok(report)
+}
+
 // Staff Commands - Core offline-capable CRUD operations
 #[tauri::command]
 pub async fn get_staff(
@@ -263,12 +390,7 @@ pub async fn create_staff(
         .map_err(|e| format!("Failed to create staff: {}", e))?;
 
     // Queue for sync
-    // sync_engine.queue_operation(
-    //     "staff",
-    //     OperationType::Create,
-    //     &staff.id.to_string(),
-    //     staff_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    queue_sync(&db, "staff", "insert", &staff.id.to_string(), staff_data).await;
 
     Ok(staff.id.to_string())
 }
@@ -279,11 +401,14 @@ pub async fn update_staff(
     staff_data: Value,
     db: State<'_, DatabaseState>,
 ) -> Result<(), String> {
-    let staff: Staff = serde_json::from_value(staff_data)
+    let staff: Staff = serde_json::from_value(staff_data.clone())
         .map_err(|e| format!("Failed to parse staff data: {}", e))?;
     
     db.update_staff(&staff).await
-        .map_err(|e| format!("Failed to update staff: {}", e))
+        .map_err(|e| format!("Failed to update staff: {}", e))?;
+
+    queue_sync(&db, "staff", "update", &staff.id.to_string(), staff_data).await;
+    Ok(())
 }
 
 #[tauri::command]
@@ -292,7 +417,10 @@ pub async fn delete_staff(
     db: State<'_, DatabaseState>,
 ) -> Result<(), String> {
     db.delete_staff(&staff_id).await
-        .map_err(|e| format!("Failed to delete staff: {}", e))
+        .map_err(|e| format!("Failed to delete staff: {}", e))?;
+
+    queue_sync(&db, "staff", "delete", &staff_id, json!({"id": staff_id})).await;
+    Ok(())
 }
 
 // Class Commands - Core offline-capable CRUD operations
@@ -318,12 +446,7 @@ pub async fn create_class(
         .map_err(|e| format!("Failed to create class: {}", e))?;
 
     // Queue for sync
-    // sync_engine.queue_operation(
-    //     "classes",
-    //     OperationType::Create,
-    //     &class.id.to_string(),
-    //     class_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    queue_sync(&db, "classes", "insert", &class.id.to_string(), class_data).await;
 
     Ok(class.id.to_string())
 }
@@ -334,11 +457,14 @@ pub async fn update_class(
     class_data: Value,
     db: State<'_, DatabaseState>,
 ) -> Result<(), String> {
-    let class: Class = serde_json::from_value(class_data)
+    let class: Class = serde_json::from_value(class_data.clone())
         .map_err(|e| format!("Failed to parse class data: {}", e))?;
     
     db.update_class(&class).await
-        .map_err(|e| format!("Failed to update class: {}", e))
+        .map_err(|e| format!("Failed to update class: {}", e))?;
+
+    queue_sync(&db, "classes", "update", &class.id.to_string(), class_data).await;
+    Ok(())
 }
 
 #[tauri::command]
@@ -347,7 +473,10 @@ pub async fn delete_class(
     db: State<'_, DatabaseState>,
 ) -> Result<(), String> {
     db.delete_class(&class_id).await
-        .map_err(|e| format!("Failed to delete class: {}", e))
+        .map_err(|e| format!("Failed to delete class: {}", e))?;
+
+    queue_sync(&db, "classes", "delete", &class_id, json!({"id": class_id})).await;
+    Ok(())
 }
 
 // Borrowing Commands - Core offline-capable CRUD operations
@@ -360,143 +489,1798 @@ pub async fn get_borrowings(
         .map_err(|e| format!("Failed to get borrowings: {}", e))
 }
 
+// Paged borrowings so the overdue screen doesn't pull the whole history
+#[tauri::command]
+pub async fn get_borrowings_paginated(
+    page: usize,
+    page_size: usize,
+    status_filter: Option<String>,
+    student_id_filter: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<PaginatedBorrowings, String> {
+    db.get_borrowings_paginated(page, page_size, status_filter.as_deref(), student_id_filter.as_deref()).await
+        .map_err(|e| format!("Failed to get borrowings: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_student_borrowing_history(
+    student_id: String,
+    include_returned: Option<bool>,
+    page: Option<usize>,
+    page_size: Option<usize>,
+    db: State<'_, DatabaseState>,
+) -> Result<HistoryPage<StudentHistoryEntry>, String> {
+    db.get_student_borrowing_history(&student_id, include_returned.unwrap_or(true), page.unwrap_or(0), page_size.unwrap_or(25)).await
+        .map_err(|e| format!("Failed to get borrowing history: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_book_circulation_history(
+    book_id: String,
+    page: Option<usize>,
+    page_size: Option<usize>,
+    db: State<'_, DatabaseState>,
+) -> Result<BookCirculationHistory, String> {
+    db.get_book_circulation_history(&book_id, page.unwrap_or(0), page_size.unwrap_or(25)).await
+        .map_err(|e| format!("Failed to get circulation history: {}", e))
+}
+
 #[tauri::command]
 pub async fn create_borrowing(
     borrowing_data: Value,
+    override_fine_block: Option<bool>,
+    override_reason: Option<String>,
     db: State<'_, DatabaseState>,
     // sync_engine: State<'_, SyncState>, // Disabled for build
 ) -> Result<String, String> {
-    let borrowing: crate::models::Borrowing = serde_json::from_value(borrowing_data.clone())
+    let borrowing: crate::models::Borrowing = serde_json::from_value(borrowing_data)
         .map_err(|e| format!("Failed to parse borrowing data: {}", e))?;
+
+    let override_fine_block = override_fine_block.unwrap_or(false);
+    if override_fine_block {
+        require_role(&db, &["admin"]).await?;
+        info!("Fine block override requested for borrowing {}", borrowing.id);
+    }
     
     // Save to local SQLite first (offline-first approach)
-    db.create_borrowing(&borrowing).await
-        .map_err(|e| format!("Failed to create borrowing: {}", e))?;
+    let borrowing = db.create_borrowing(&borrowing, override_fine_block, override_reason.as_deref()).await
+        .map_err(|e| match e {
+            BorrowingError::Database(e) => format!("Failed to create borrowing: {}", e),
+            e => e.to_string(),
+        })?;
+
+    if borrowing.due_date_override {
+        info!("Borrowing {} created with due date override until {}", borrowing.id, borrowing.due_date);
+    }
 
     // Queue for sync to Supabase when online
-    // sync_engine.queue_operation(
-    //     "borrowings",
-    //     OperationType::Create,
-    //     &borrowing.id.to_string(),
-    //     borrowing_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    queue_sync(&db, "borrowings", "insert", &borrowing.id.to_string(), json!(borrowing)).await;
 
     Ok(borrowing.id.to_string())
 }
 
+#[tauri::command]
+pub async fn validate_checkout(
+    borrower_id: String,
+    book_code: String,
+    db: State<'_, DatabaseState>,
+) -> Result<CheckoutVerdict, String> {
+    db.validate_checkout(&borrower_id, &book_code).await
+        .map_err(|e| format!("Failed to validate checkout: {}", e))
+}
+
+#[tauri::command]
+pub async fn checkout_book(
+    book_id: Option<String>,
+    book_code: Option<String>,
+    student_id: Option<String>,
+    staff_id: Option<String>,
+    loan_days: Option<i64>,
+    db: State<'_, DatabaseState>,
+) -> Result<BorrowingWithDetails, String> {
+    let book = book_id.or(book_code)
+        .filter(|b| !b.trim().is_empty())
+        .ok_or("A book id or book code is required")?;
+    let (borrower_type, borrower_id) = match (student_id, staff_id) {
+        (Some(student_id), None) => (BorrowerType::Student, student_id),
+        (None, Some(staff_id)) => (BorrowerType::Staff, staff_id),
+        _ => return Err("Give either a student_id or a staff_id".to_string()),
+    };
+    let issued_by = db.get_any_valid_session().await
+        .ok()
+        .flatten()
+        .and_then(|session| Uuid::parse_str(&session.user_id).ok());
+
+    let details = db.checkout_book(&book, borrower_type, &borrower_id, loan_days, issued_by).await
+        .map_err(|e| match e {
+            BorrowingError::Database(e) => format!("Failed to check out book: {}", e),
+            e => e.to_string(),
+        })?;
+
+    let borrowing = &details.borrowing;
+    queue_sync(&db, "borrowings", "insert", &borrowing.id.to_string(), json!(borrowing)).await;
+    if let Some(copy) = &details.book_copy {
+        queue_sync(&db, "book_copies", "update", &copy.id.to_string(), json!({"status": copy.status})).await;
+    }
+    if let Some(book) = &details.book {
+        queue_sync(&db, "books", "update", &book.id.to_string(), json!({"available_copies": book.available_copies})).await;
+    }
+
+    Ok(details)
+}
+
+#[tauri::command]
+pub async fn get_book_copy_by_code(
+    code: String,
+    db: State<'_, DatabaseState>,
+) -> Result<ScannedCopy, String> {
+    db.get_book_copy_by_code(&code).await
+        .map_err(|e| format!("Failed to look up book copy: {}", e))?
+        .ok_or_else(|| format!("No book copy matches {}", code.trim()))
+}
+
 #[tauri::command]
 pub async fn return_book(
-    _borrowing_id: String,
-    _return_data: Value,
-    _db: State<'_, DatabaseState>,
+    borrowing_id: String,
+    return_data: Value,
+    app: AppHandle,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    let return_data: ReturnData = serde_json::from_value(return_data)
+        .map_err(|e| format!("Failed to parse return data: {}", e))?;
+
+    let returned_date = return_data.returned_date.unwrap_or_else(|| Utc::now().date_naive());
+    let condition_at_return = return_data.condition_at_return.clone();
+    let return_notes = return_data.return_notes.clone();
+    let reported_by = db.get_any_valid_session().await
+        .ok()
+        .flatten()
+        .map(|session| session.user_id);
+
+    let BookReturnResult { theft_report, copy_change, fines, flagged_code, reservation_ready } = db
+        .return_book(&borrowing_id, return_data, reported_by.as_deref()).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Borrowing {} not found", borrowing_id),
+            e => format!("Failed to return book: {}", e),
+        })?;
+
+    // A different copy coming back is filed as a theft instead of completing the return
+    if let Some(report) = theft_report {
+        queue_sync(&db, "theft_reports", "insert", &report.id.to_string(), json!(report)).await;
+        queue_sync(&db, "borrowings", "update", &borrowing_id, json!({"status": "lost", "is_lost": true})).await;
+        for fine in &fines {
+            queue_sync(&db, "fines", "insert", &fine.id.to_string(), json!(fine)).await;
+        }
+        if let Some(change) = &copy_change {
+            queue_sync(&db, "book_copies", "update", &change.copy_id, json!({"status": change.status})).await;
+            queue_book_counts(&db, change).await;
+        }
+
+        warn!("Borrowing {} returned with tracking code {} instead of {}, theft report {} filed",
+            borrowing_id, report.returned_tracking_code, report.expected_tracking_code, report.id);
+        return Ok(json!({
+            "success": false,
+            "theft_reported": true,
+            "borrowing_id": borrowing_id,
+            "theft_report": report,
+            "fine": fines.first()
+        }));
+    }
+
+    queue_sync(&db, "borrowings", "update", &borrowing_id, json!({
+        "status": "returned",
+        "returned_date": returned_date.to_string(),
+        "condition_at_return": condition_at_return,
+        "return_notes": return_notes
+    })).await;
+    for fine in &fines {
+        queue_sync(&db, "fines", "insert", &fine.id.to_string(), json!(fine)).await;
+    }
+
+    if let Some(flag) = &flagged_code {
+        warn!("Flagged tracking code {} returned on borrowing {}: {}", flag.code, borrowing_id, flag.reason);
+    }
+
+    if let Some(reservation) = &reservation_ready {
+        emit_reservation_ready(&app, reservation);
+    }
+
+    info!("Borrowing {} returned", borrowing_id);
+    Ok(json!({
+        "success": true,
+        "theft_reported": false,
+        "borrowing_id": borrowing_id,
+        "fine_amount": fines.iter().map(|f| f.amount).sum::<f64>(),
+        "fine": fines.first(),
+        "fines": fines,
+        "flagged_code_alert": flagged_code
+    }))
+}
+
+#[tauri::command]
+pub async fn return_all_for_student(
+    student_id: String,
+    return_date: Option<NaiveDate>,
+    app: AppHandle,
+    db: State<'_, DatabaseState>,
+) -> Result<StudentReturnResult, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+    let returned_by = db.get_any_valid_session().await
+        .ok()
+        .flatten()
+        .and_then(|session| Uuid::parse_str(&session.user_id).ok());
+    let returned_date = return_date.unwrap_or_else(|| Utc::now().date_naive());
+
+    let result = db.return_all_for_student(&student_id, returned_date, returned_by).await
+        .map_err(|e| format!("Failed to return borrowings for student {}: {}", student_id, e))?;
+
+    let payload = json!({
+        "status": "returned",
+        "returned_date": returned_date.to_string(),
+    });
+    for borrowing_id in &result.returned_borrowing_ids {
+        queue_sync(&db, "borrowings", "update", borrowing_id, payload.clone()).await;
+    }
+    for fine in &result.fines {
+        queue_sync(&db, "fines", "insert", &fine.id.to_string(), json!(fine)).await;
+    }
+    for reservation in &result.reservations_ready {
+        emit_reservation_ready(&app, reservation);
+    }
+
+    info!("Returned {} loans for student {} ({:.2} in fines)",
+        result.returned_borrowing_ids.len(), student_id, result.total_fine_amount);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn renew_book(
+    borrowing_id: String,
+    extra_days: Option<i64>,
+    db: State<'_, DatabaseState>,
+) -> Result<RenewalResult, String> {
+    let renewal = db.renew_book(&borrowing_id, extra_days).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Borrowing {} not found", borrowing_id),
+            e => format!("Failed to renew book: {}", e),
+        })?;
+
+    queue_sync(&db, "borrowings", "update", &borrowing_id, json!({
+        "due_date": renewal.new_due_date.to_string(),
+        "renewal_count": renewal.renewal_count
+    })).await;
+
+    info!("Borrowing {} renewed until {} ({} renewals remaining)",
+        borrowing_id, renewal.new_due_date, renewal.renewals_remaining);
+    Ok(renewal)
+}
+
+// Same renewal as renew_book, for callers that only need the new due date
+#[tauri::command]
+pub async fn renew_borrowing(
+    borrowing_id: String,
+    extra_days: Option<i64>,
+    db: State<'_, DatabaseState>,
+) -> Result<NaiveDate, String> {
+    renew_book(borrowing_id, extra_days, db).await
+        .map(|renewal| renewal.new_due_date)
+}
+
+// Reservation Commands
+// Tell the desk a copy is now held for the next student in a book's queue
+fn emit_reservation_ready(app: &AppHandle, reservation: &Reservation) {
+    info!("Copy of book {} held for reservation {} (student {})",
+        reservation.book_id, reservation.id, reservation.student_id);
+    if let Err(e) = app.emit("reservation-ready", reservation) {
+        warn!("Failed to emit reservation-ready for {}: {}", reservation.id, e);
+    }
+}
+
+#[tauri::command]
+pub async fn create_reservation(
+    book_id: String,
+    student_id: String,
+    notes: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<Reservation, String> {
+    db.create_reservation(&book_id, &student_id, notes.as_deref()).await
+        .map_err(|e| format!("Failed to create reservation: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_reservations_for_book(
+    book_id: String,
+    include_closed: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<Reservation>, String> {
+    db.get_reservations_for_book(&book_id, include_closed.unwrap_or(false)).await
+        .map_err(|e| format!("Failed to get reservations: {}", e))
+}
+
+#[tauri::command]
+pub async fn cancel_reservation(
+    reservation_id: String,
+    app: AppHandle,
+    db: State<'_, DatabaseState>,
+) -> Result<Reservation, String> {
+    let (cancelled, next) = db.cancel_reservation(&reservation_id).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Reservation {} not found", reservation_id),
+            e => format!("Failed to cancel reservation: {}", e),
+        })?;
+
+    if let Some(next) = &next {
+        emit_reservation_ready(&app, next);
+    }
+    Ok(cancelled)
+}
+
+#[tauri::command]
+pub async fn bulk_extend_due_dates(
+    days: i64,
+    filter: Option<DueDateFilter>,
+    confirm: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<usize, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+    let filter = filter.unwrap_or_default();
+
+    if !confirm.unwrap_or(false) {
+        let matching = db.count_extendable_loans(&filter).await
+            .map_err(|e| format!("Failed to count loans: {}", e))?;
+        return Err(format!("This would extend {} open loans by {} days - call again with confirm to apply", matching, days));
+    }
+
+    let changed = db.bulk_extend_due_dates(days, &filter).await
+        .map_err(|e| format!("Failed to extend due dates: {}", e))?;
+
+    for (borrowing_id, due_date, status) in &changed {
+        queue_sync(&db, "borrowings", "update", borrowing_id, json!({
+            "due_date": due_date,
+            "status": status
+        })).await;
+    }
+
+    info!("Extended {} open loans by {} days", changed.len(), days);
+    Ok(changed.len())
+}
+
+#[tauri::command]
+pub async fn archive_old_borrowings(
+    before_date: NaiveDate,
+    confirm: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<ArchiveResult, String> {
+    require_role(&db, &["admin"]).await?;
+
+    if !confirm.unwrap_or(false) {
+        let matching = db.count_archivable_borrowings(before_date).await
+            .map_err(|e| format!("Failed to count borrowings: {}", e))?;
+        return Err(format!("This would archive {} returned borrowings from before {} - call again with confirm to apply", matching, before_date));
+    }
+
+    let result = db.archive_old_borrowings(before_date).await
+        .map_err(|e| format!("Failed to archive borrowings: {}", e))?;
+
+    info!("Archived {} returned borrowings from before {}", result.archived, before_date);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_archived_borrowings(
+    student_id: Option<String>,
+    book_id: Option<String>,
+    limit: Option<i64>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<Borrowing>, String> {
+    db.get_archived_borrowings(student_id.as_deref(), book_id.as_deref(), limit.unwrap_or(500)).await
+        .map_err(|e| format!("Failed to get archived borrowings: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_borrowing_details(
+    borrowing_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    db.get_borrowing_details(&borrowing_id).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Borrowing {} not found", borrowing_id),
+            e => format!("Failed to get borrowing details: {}", e),
+        })
+}
+
+#[tauri::command]
+pub async fn mark_overdue_borrowings(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<String>, String> {
+    let ids = db.mark_overdue_borrowings().await
+        .map_err(|e| format!("Failed to mark overdue borrowings: {}", e))?;
+
+    info!("{} borrowings marked overdue", ids.len());
+    Ok(ids)
+}
+
+// Group borrowing commands - one title issued to a whole group of students
+#[tauri::command]
+pub async fn create_group_borrowing(
+    group_data: Value,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    let group: GroupBorrowing = serde_json::from_value(group_data)
+        .map_err(|e| format!("Failed to parse group borrowing data: {}", e))?;
+
+    let (group, borrowings) = db.create_group_borrowing(&group).await
+        .map_err(|e| match e {
+            BorrowingError::Database(e) => format!("Failed to create group borrowing: {}", e),
+            e => e.to_string(),
+        })?;
+
+    queue_sync(&db, "group_borrowings", "insert", &group.id.to_string(), json!(group)).await;
+    for borrowing in &borrowings {
+        queue_sync(&db, "borrowings", "insert", &borrowing.id.to_string(), json!(borrowing)).await;
+    }
+
+    info!("Group borrowing {} issued to {} students", group.id, borrowings.len());
+    Ok(json!({
+        "group_borrowing_id": group.id,
+        "due_date": group.due_date,
+        "borrowing_ids": borrowings.iter().map(|b| b.id).collect::<Vec<_>>()
+    }))
+}
+
+#[tauri::command]
+pub async fn return_group_borrowing(
+    group_borrowing_id: String,
+    return_data: Value,
+    db: State<'_, DatabaseState>,
+) -> Result<GroupReturnResult, String> {
+    let return_data: ReturnData = serde_json::from_value(return_data)
+        .map_err(|e| format!("Failed to parse return data: {}", e))?;
+    let returned_date = return_data.returned_date.unwrap_or_else(|| Utc::now().date_naive());
+    let payload = json!({
+        "status": "returned",
+        "returned_date": returned_date.to_string(),
+        "condition_at_return": return_data.condition_at_return,
+        "return_notes": return_data.return_notes
+    });
+
+    let result = db.return_group_borrowing(&group_borrowing_id, return_data).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Group borrowing {} not found", group_borrowing_id),
+            e => format!("Failed to return group borrowing: {}", e),
+        })?;
+
+    let mut group_payload = payload.clone();
+    group_payload["fine_amount"] = json!(result.total_fine_amount);
+    queue_sync(&db, "group_borrowings", "update", &group_borrowing_id, group_payload).await;
+    for borrowing_id in &result.returned_borrowing_ids {
+        queue_sync(&db, "borrowings", "update", borrowing_id, payload.clone()).await;
+    }
+    for fine in &result.fines {
+        queue_sync(&db, "fines", "insert", &fine.id.to_string(), json!(fine)).await;
+    }
+
+    info!("Group borrowing {} returned ({} loans)", group_borrowing_id, result.returned_borrowing_ids.len());
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn unmark_book_lost(
+    borrowing_id: String,
+    returned: Option<bool>,
+    waive_fine: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let result = db.unmark_borrowing_lost(&borrowing_id, returned.unwrap_or(true), waive_fine.unwrap_or(true)).await
+        .map_err(|e| format!("Failed to unmark book as lost: {}", e))?;
+
+    info!("Borrowing {} is no longer marked as lost", borrowing_id);
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_borrow_block_threshold(
+    db: State<'_, DatabaseState>,
+) -> Result<Option<f64>, String> {
+    db.get_borrow_block_threshold().await
+        .map_err(|e| format!("Failed to get borrow block threshold: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_borrow_block_threshold(
+    threshold: Option<f64>,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    if threshold.map_or(false, |t| t < 0.0) {
+        return Err("Borrow block threshold cannot be negative".to_string());
+    }
+
+    db.set_borrow_block_threshold(threshold).await
+        .map_err(|e| format!("Failed to save borrow block threshold: {}", e))?;
+
+    info!("Borrow block threshold set to {:?}", threshold);
+    Ok(())
+}
+
+// Fine Commands
+#[tauri::command]
+pub async fn get_fines(
+    student_id: Option<String>,
+    status: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<Fine>, String> {
+    let status = status.map(|s| s.to_lowercase());
+    db.get_fines(student_id.as_deref(), status.as_deref()).await
+        .map_err(|e| format!("Failed to get fines: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_fine(
+    _fine_id: String,
+    fine_data: Value,
+    db: State<'_, DatabaseState>,
 ) -> Result<(), String> {
-    // TODO: Implement return_book method in DatabaseManager
-    // For now, just return success to prevent crashes
+    let fine: Fine = serde_json::from_value(fine_data.clone())
+        .map_err(|e| format!("Failed to parse fine data: {}", e))?;
+
+    db.update_fine(&fine).await
+        .map_err(|e| format!("Failed to update fine: {}", e))?;
+
+    queue_sync(&db, "fines", "update", &fine.id.to_string(), fine_data).await;
     Ok(())
 }
 
-// Update Commands
 #[tauri::command]
-pub async fn update_book(
-    _book_id: String,
-    book_data: Value,
+pub async fn pay_fine(
+    fine_id: String,
+    payment_data: Option<Value>,
     db: State<'_, DatabaseState>,
-    // sync_engine: State<'_, SyncState>, // Disabled for build
 ) -> Result<(), String> {
-    let book: Book = serde_json::from_value(book_data.clone())
-        .map_err(|e| format!("Failed to parse book data: {}", e))?;
-    
-    // Update local SQLite first
-    db.update_book(&book).await
-        .map_err(|e| format!("Failed to update book: {}", e))?;
+    let payment_method = payment_data.as_ref()
+        .and_then(|data| data.get("payment_method"))
+        .and_then(Value::as_str);
+    db.pay_fine(&fine_id, payment_method).await
+        .map_err(|e| format!("Failed to pay fine: {}", e))?;
 
-    // Queue for sync to Supabase
-    // sync_engine.queue_operation(
-    //     "books",
-    //     OperationType::Update,
-    //     &book_id,
-    //     book_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    queue_sync(&db, "fines", "update", &fine_id, json!({"status": "paid"})).await;
+
+    info!("Fine {} marked as paid", fine_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn waive_fine(
+    fine_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    db.waive_fine(&fine_id).await
+        .map_err(|e| format!("Failed to waive fine: {}", e))?;
+
+    queue_sync(&db, "fines", "update", &fine_id, json!({"status": "waived"})).await;
+
+    info!("Fine {} waived", fine_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_fine(
+    fine_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    db.delete_fine(&fine_id).await
+        .map_err(|e| format!("Failed to delete fine: {}", e))?;
+
+    queue_sync(&db, "fines", "delete", &fine_id, json!({"id": fine_id})).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_fine_settings(db: State<'_, DatabaseState>) -> Result<Vec<FineSetting>, String> {
+    db.get_fine_settings().await
+        .map_err(|e| format!("Failed to get fine settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn upsert_fine_setting(
+    fine_type: String,
+    amount: f64,
+    description: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<FineSetting, String> {
+    require_role(&db, &["admin"]).await?;
+
+    let parsed = parse_fine_type(&fine_type)
+        .ok_or_else(|| format!("Unknown fine type: {}", fine_type))?;
+    let setting = db.upsert_fine_setting(&parsed, amount, description.as_deref()).await
+        .map_err(|e| format!("Failed to save fine setting: {}", e))?;
+
+    queue_sync(&db, "fine_settings", "insert", &setting.id.to_string(), json!(setting)).await;
+    info!("{} fine set to {:.2}", fine_type, setting.amount);
+    Ok(setting)
+}
+
+// Update Commands
+#[tauri::command]
+pub async fn update_book(
+    _book_id: String,
+    book_data: Value,
+    db: State<'_, DatabaseState>,
+    // sync_engine: State<'_, SyncState>, // Disabled for build
+) -> Result<(), String> {
+    let book: Book = serde_json::from_value(book_data.clone())
+        .map_err(|e| format!("Failed to parse book data: {}", e))?;
+    
+    // Update local SQLite first
+    db.update_book(&book).await
+        .map_err(|e| format!("Failed to update book: {}", e))?;
+
+    // Queue for sync to Supabase
+    queue_sync(&db, "books", "update", &book.id.to_string(), book_data).await;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn find_possible_duplicate_books(
+    title: String,
+    author: Option<String>,
+    isbn: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<DuplicateBookCandidate>, String> {
+    db.find_possible_duplicate_books(&title, author.as_deref(), isbn.as_deref()).await
+        .map_err(|e| format!("Failed to look for duplicate books: {}", e))
+}
+
+#[tauri::command]
+pub async fn merge_books(
+    keep_id: String,
+    merge_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<BookMergeResult, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let result = db.merge_books(&keep_id, &merge_id).await
+        .map_err(|e| format!("Failed to merge books: {}", e))?;
+
+    for copy_id in &result.moved_copy_ids {
+        queue_sync(&db, "book_copies", "update", copy_id, json!({"book_id": keep_id})).await;
+    }
+    for borrowing_id in &result.moved_borrowing_ids {
+        queue_sync(&db, "borrowings", "update", borrowing_id, json!({"book_id": keep_id})).await;
+    }
+    queue_sync(&db, "books", "update", &keep_id, json!({
+        "total_copies": result.total_copies,
+        "available_copies": result.available_copies
+    })).await;
+    queue_sync(&db, "books", "delete", &merge_id, json!({"id": merge_id})).await;
+
+    info!(
+        "Merged book {} into {} ({} copies, {} borrowings moved)",
+        merge_id, keep_id, result.moved_copy_ids.len(), result.moved_borrowing_ids.len()
+    );
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn set_book_cost(
+    book_id: String,
+    cost: Option<f64>,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    db.set_book_cost(&book_id, cost).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Book {} not found", book_id),
+            e => format!("Failed to set book cost: {}", e),
+        })?;
+
+    queue_sync(&db, "books", "update", &book_id, json!({ "acquisition_cost": cost })).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn update_student(
+    _student_id: String,
+    student_data: Value,
+    db: State<'_, DatabaseState>,
+    // sync_engine: State<'_, SyncState>, // Disabled for build
+) -> Result<(), String> {
+    let student: Student = serde_json::from_value(student_data.clone())
+        .map_err(|e| format!("Failed to parse student data: {}", e))?;
+    
+    // Update local SQLite first
+    db.update_student(&student).await
+        .map_err(|e| match e {
+            StudentError::Database(e) => format!("Failed to update student: {}", e),
+            e => e.to_string(),
+        })?;
+
+    // Queue for sync
+    queue_sync(&db, "students", "update", &student.id.to_string(), student_data).await;
+
+    Ok(())
+}
+
+// Delete Commands
+#[tauri::command]
+pub async fn delete_book(
+    book_id: String,
+    db: State<'_, DatabaseState>,
+    // sync_engine: State<'_, SyncState>, // Disabled for build
+) -> Result<(), String> {
+    // Delete from local SQLite first
+    db.delete_book(&book_id).await
+        .map_err(|e| format!("Failed to delete book: {}", e))?;
+
+    // Queue for sync to Supabase
+    queue_sync(&db, "books", "delete", &book_id, json!({"id": book_id})).await;
+
+    Ok(())
+}
+
+// Book copy commands
+#[tauri::command]
+pub async fn update_book_copy(
+    copy_id: String,
+    copy_data: Value,
+    db: State<'_, DatabaseState>,
+) -> Result<BookCopyChange, String> {
+    let update: BookCopyUpdate = serde_json::from_value(copy_data)
+        .map_err(|e| format!("Failed to parse book copy data: {}", e))?;
+
+    let change = db.update_book_copy(&copy_id, &update).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Book copy {} not found", copy_id),
+            e => format!("Failed to update book copy: {}", e),
+        })?;
+
+    let mut payload = json!({
+        "condition": change.condition,
+        "status": change.status,
+    });
+    if let Some(notes) = &update.notes {
+        payload["notes"] = json!(notes);
+    }
+    if let Some(tracking_code) = &update.tracking_code {
+        payload["tracking_code"] = json!(tracking_code);
+    }
+    queue_sync(&db, "book_copies", "update", &copy_id, payload).await;
+    queue_book_counts(&db, &change).await;
+
+    info!("Book copy {} updated ({} / {})", copy_id, change.condition, change.status);
+    Ok(change)
+}
+
+#[tauri::command]
+pub async fn delete_book_copy(
+    copy_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<BookCopyChange, String> {
+    let change = db.delete_book_copy(&copy_id).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Book copy {} not found", copy_id),
+            e => format!("Failed to delete book copy: {}", e),
+        })?;
+
+    queue_sync(&db, "book_copies", "delete", &copy_id, json!({"id": copy_id})).await;
+    queue_book_counts(&db, &change).await;
+
+    info!("Book copy {} deleted", copy_id);
+    Ok(change)
+}
+
+async fn queue_book_counts(db: &DatabaseManager, change: &BookCopyChange) {
+    if let (Some(book_id), Some(total), Some(available)) = (&change.book_id, change.book_total_copies, change.book_available_copies) {
+        queue_sync(db, "books", "update", book_id, json!({
+            "total_copies": total,
+            "available_copies": available,
+        })).await;
+    }
+}
+
+#[tauri::command]
+pub async fn delete_student(
+    student_id: String,
+    db: State<'_, DatabaseState>,
+    // sync_engine: State<'_, SyncState>, // Disabled for build
+) -> Result<(), String> {
+    // Delete from local SQLite first
+    db.delete_student(&student_id).await
+        .map_err(|e| format!("Failed to delete student: {}", e))?;
+
+    // Queue for sync
+    queue_sync(&db, "students", "delete", &student_id, json!({"id": student_id})).await;
+
+    Ok(())
+}
+
+// Trash Commands - recover soft-deleted records within the retention window
+#[tauri::command]
+pub async fn get_deleted_books(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<DeletedRecord>, String> {
+    db.get_deleted_books().await
+        .map_err(|e| format!("Failed to get deleted books: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_book(
+    book_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let restored = db.restore_book(&book_id).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Book {} not found", book_id),
+            e => format!("Failed to restore book: {}", e),
+        })?;
+
+    // The delete removed it remotely, so push the full record back
+    queue_sync(&db, "books", "insert", &book_id, restored).await;
+
+    info!("Book {} restored from trash", book_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_deleted_students(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<DeletedRecord>, String> {
+    db.get_deleted_students().await
+        .map_err(|e| format!("Failed to get deleted students: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_student(
+    student_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let restored = db.restore_student(&student_id).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Student {} not found", student_id),
+            e => format!("Failed to restore student: {}", e),
+        })?;
+
+    queue_sync(&db, "students", "insert", &student_id, restored).await;
+
+    info!("Student {} restored from trash", student_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_deleted_staff(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<DeletedRecord>, String> {
+    db.get_deleted_staff().await
+        .map_err(|e| format!("Failed to get deleted staff: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_staff(
+    staff_id: String,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let restored = db.restore_staff(&staff_id).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Staff {} not found", staff_id),
+            e => format!("Failed to restore staff: {}", e),
+        })?;
+
+    queue_sync(&db, "staff", "insert", &staff_id, restored).await;
+
+    info!("Staff {} restored from trash", staff_id);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_purge_preview(
+    older_than_days: i64,
+    db: State<'_, DatabaseState>,
+) -> Result<PurgePreview, String> {
+    require_role(&db, &["admin"]).await?;
+
+    if older_than_days < 0 {
+        return Err("older_than_days cannot be negative".to_string());
+    }
+
+    db.get_purge_preview(older_than_days).await
+        .map_err(|e| format!("Failed to preview purge: {}", e))
+}
+
+#[tauri::command]
+pub async fn purge_deleted(
+    older_than_days: i64,
+    confirmation_token: String,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<PurgeTableCount>, String> {
+    require_role(&db, &["admin"]).await?;
+
+    if older_than_days < 0 {
+        return Err("older_than_days cannot be negative".to_string());
+    }
+
+    let purged = db.purge_deleted(older_than_days, &confirmation_token).await
+        .map_err(|e| format!("Failed to purge deleted records: {}", e))?;
+
+    info!("Purged {} soft-deleted rows older than {} days", purged.iter().map(|t| t.rows).sum::<i64>(), older_than_days);
+    Ok(purged)
+}
+
+#[tauri::command]
+pub async fn get_trash_retention_days(
+    db: State<'_, DatabaseState>,
+) -> Result<i64, String> {
+    db.get_trash_retention_days().await
+        .map_err(|e| format!("Failed to get trash retention: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_trash_retention_days(
+    days: i64,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    if days < 1 {
+        return Err("Retention must be at least 1 day".to_string());
+    }
+
+    db.set_trash_retention_days(days).await
+        .map_err(|e| format!("Failed to save trash retention: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_conflict_log(
+    table_name: Option<String>,
+    record_id: Option<String>,
+    limit: Option<i64>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<SyncConflictEntry>, String> {
+    db.get_conflict_log(table_name.as_deref(), record_id.as_deref(), limit.unwrap_or(200)).await
+        .map_err(|e| format!("Failed to get conflict log: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_conflict_log_retention_days(
+    db: State<'_, DatabaseState>,
+) -> Result<i64, String> {
+    db.get_conflict_log_retention_days().await
+        .map_err(|e| format!("Failed to get conflict log retention: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_conflict_log_retention_days(
+    days: i64,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    if days < 1 {
+        return Err("Retention must be at least 1 day".to_string());
+    }
+
+    db.set_conflict_log_retention_days(days).await
+        .map_err(|e| format!("Failed to save conflict log retention: {}", e))
+}
+
+// Analytics Commands - Efficient large database queries
+#[tauri::command]
+pub async fn get_library_stats(
+    db: State<'_, DatabaseState>,
+) -> Result<LibraryStats, String> {
+    // Optimized queries with indexes for large datasets
+    db.get_library_stats().await
+        .map_err(|e| format!("Failed to get library stats: {}", e))
+}
+
+#[tauri::command]
+pub async fn validate_database(
+    db: State<'_, DatabaseState>,
+) -> Result<ValidationReport, String> {
+    let report = db.validate_all().await
+        .map_err(|e| format!("Failed to validate database: {}", e))?;
+
+    info!(
+        "Database validation found {} errors, {} warnings, {} info issues",
+        report.error_count, report.warning_count, report.info_count
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn export_integrity_report(
+    path: String,
+    format: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    let format: ReportFormat = serde_json::from_value(json!(format.unwrap_or_else(|| "json".to_string()).to_lowercase()))
+        .map_err(|_| "Unsupported report format - use json or csv".to_string())?;
+
+    let report = db.validate_all().await
+        .map_err(|e| format!("Failed to validate database: {}", e))?;
+
+    write_integrity_report(&report, std::path::Path::new(&path), format)
+        .map_err(|e| format!("Failed to write integrity report to {}: {}", path, e))?;
+
+    info!("Integrity report with {} issues written to {}", report.issues.len(), path);
+    Ok(json!({
+        "success": true,
+        "path": path,
+        "issues": report.issues.len(),
+        "errors": report.error_count,
+        "warnings": report.warning_count
+    }))
+}
+
+#[tauri::command]
+pub async fn diff_exports(path_a: String, path_b: String) -> Result<ExportDiff, String> {
+    let diff = diff_export_files(std::path::Path::new(&path_a), std::path::Path::new(&path_b))
+        .map_err(|e| format!("Failed to diff exports: {}", e))?;
+
+    info!("Export diff {} -> {}: {} added, {} removed, {} changed",
+          path_a, path_b, diff.total_added, diff.total_removed, diff.total_changed);
+    Ok(diff)
+}
+
+// CSV Export Commands - path comes from the save dialog on the frontend
+#[tauri::command]
+pub async fn export_books_csv(
+    path: String,
+    db: State<'_, DatabaseState>,
+) -> Result<usize, String> {
+    let rows = db.export_books_csv(std::path::Path::new(&path)).await
+        .map_err(|e| format!("Failed to export books to {}: {}", path, e))?;
+
+    info!("Exported {} books to {}", rows, path);
+    Ok(rows)
+}
+
+#[tauri::command]
+pub async fn export_students_csv(
+    path: String,
+    db: State<'_, DatabaseState>,
+) -> Result<usize, String> {
+    let rows = db.export_students_csv(std::path::Path::new(&path)).await
+        .map_err(|e| format!("Failed to export students to {}: {}", path, e))?;
+
+    info!("Exported {} students to {}", rows, path);
+    Ok(rows)
+}
+
+#[tauri::command]
+pub async fn export_borrowings_csv(
+    path: String,
+    db: State<'_, DatabaseState>,
+) -> Result<usize, String> {
+    let rows = db.export_borrowings_csv(std::path::Path::new(&path)).await
+        .map_err(|e| format!("Failed to export borrowings to {}: {}", path, e))?;
+
+    info!("Exported {} borrowings to {}", rows, path);
+    Ok(rows)
+}
+
+#[tauri::command]
+pub async fn export_for_supabase(
+    table: String,
+    out_path: String,
+    db: State<'_, DatabaseState>,
+) -> Result<usize, String> {
+    let rows = db.export_for_supabase(&table, std::path::Path::new(&out_path)).await
+        .map_err(|e| format!("Failed to export {} to {}: {}", table, out_path, e))?;
+
+    info!("Exported {} {} rows for Supabase to {}", rows, table, out_path);
+    Ok(rows)
+}
+
+// Database Merge Commands - combine another branch's library.db into this one
+#[tauri::command]
+pub async fn preview_merge_database(
+    source_path: String,
+    strategy: Option<MergeStrategy>,
+    db: State<'_, DatabaseState>,
+) -> Result<MergePreview, String> {
+    require_role(&db, &["admin"]).await?;
+
+    db.preview_merge(std::path::Path::new(&source_path), strategy).await
+        .map_err(|e| format!("Failed to preview merge of {}: {}", source_path, e))
+}
+
+#[tauri::command]
+pub async fn merge_database(
+    source_path: String,
+    confirm_token: String,
+    strategy: Option<MergeStrategy>,
+    db: State<'_, DatabaseState>,
+) -> Result<MergeResult, String> {
+    require_role(&db, &["admin"]).await?;
+
+    let result = db.merge_database(std::path::Path::new(&source_path), strategy, &confirm_token).await
+        .map_err(|e| format!("Failed to merge {}: {}", source_path, e))?;
+
+    for table in &result.tables {
+        info!(
+            "Merged {} from {}: {} inserted, {} updated, {} skipped",
+            table.table_name, source_path, table.inserted, table.updated, table.skipped
+        );
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_merge_strategy(
+    db: State<'_, DatabaseState>,
+) -> Result<MergeStrategy, String> {
+    db.get_merge_strategy().await
+        .map_err(|e| format!("Failed to get merge strategy: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_merge_strategy(
+    strategy: MergeStrategy,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    db.set_merge_strategy(strategy).await
+        .map_err(|e| format!("Failed to save merge strategy: {}", e))
+}
+
+#[tauri::command]
+pub async fn recompute_availability(
+    db: State<'_, DatabaseState>,
+) -> Result<AvailabilityReport, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let report = db.recompute_availability().await
+        .map_err(|e| format!("Failed to recompute availability: {}", e))?;
+
+    info!("Availability recomputed: {} of {} books corrected", report.books_corrected, report.books_checked);
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn normalize_datetimes(
+    db: State<'_, DatabaseState>,
+) -> Result<DatetimeNormalizationReport, String> {
+    require_role(&db, &["admin"]).await?;
+
+    let report = db.normalize_datetimes().await
+        .map_err(|e| format!("Failed to normalize timestamps: {}", e))?;
+
+    info!("Normalized {} timestamps ({} unreadable left as they were)", report.total_normalized, report.total_unparseable);
+    Ok(report)
+}
+
+// Enum Repair Commands
+#[tauri::command]
+pub async fn enum_value_report(
+    table: Option<String>,
+    column: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<InvalidEnumValue>, String> {
+    let result = match (table, column) {
+        (Some(table), Some(column)) => db.find_invalid_enum_values(&table, &column).await,
+        _ => db.get_enum_value_report().await,
+    };
+    result.map_err(|e| format!("Failed to check enum values: {}", e))
+}
+
+#[tauri::command]
+pub async fn fix_enum_values(
+    table: Option<String>,
+    column: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<EnumRepair>, String> {
+    require_role(&db, &["admin"]).await?;
+
+    let repairs = db.fix_enum_values(table.as_deref(), column.as_deref()).await
+        .map_err(|e| format!("Failed to fix enum values: {}", e))?;
+
+    let fixed: usize = repairs.iter().map(|r| r.rows_updated).sum();
+    let unmapped = repairs.iter().filter(|r| r.replacement.is_none()).count();
+    info!("Enum repair updated {} rows, {} values could not be mapped", fixed, unmapped);
+    Ok(repairs)
+}
+
+#[tauri::command]
+pub async fn get_enum_aliases(
+    db: State<'_, DatabaseState>,
+) -> Result<HashMap<String, String>, String> {
+    db.get_enum_aliases().await
+        .map_err(|e| format!("Failed to get enum aliases: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_enum_aliases(
+    aliases: HashMap<String, String>,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    db.set_enum_aliases(&aliases).await
+        .map_err(|e| format!("Failed to save enum aliases: {}", e))
+}
+
+// Shelf Capacity Commands
+#[tauri::command]
+pub async fn get_shelf_capacities(
+    db: State<'_, DatabaseState>,
+) -> Result<HashMap<String, i64>, String> {
+    db.get_shelf_capacities().await
+        .map_err(|e| format!("Failed to get shelf capacities: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_shelf_capacities(
+    capacities: HashMap<String, i64>,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    if let Some((shelf, _)) = capacities.iter().find(|(_, capacity)| **capacity < 0) {
+        return Err(format!("Capacity for shelf '{}' cannot be negative", shelf));
+    }
+
+    // Normalise shelf names the same way the occupancy report groups them
+    let capacities: HashMap<String, i64> = capacities.into_iter()
+        .map(|(shelf, capacity)| (shelf.trim().to_string(), capacity))
+        .filter(|(shelf, _)| !shelf.is_empty())
+        .collect();
+
+    db.set_shelf_capacities(&capacities).await
+        .map_err(|e| format!("Failed to save shelf capacities: {}", e))
+}
+
+#[tauri::command]
+pub async fn shelf_occupancy_report(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<ShelfOccupancy>, String> {
+    db.get_shelf_occupancy().await
+        .map_err(|e| format!("Failed to get shelf occupancy: {}", e))
+}
+
+#[tauri::command]
+pub async fn fine_by_borrower_type_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<BorrowerTypeFineStats>, String> {
+    db.get_average_fine_by_borrower_type(from, to).await
+        .map_err(|e| format!("Failed to get fines by borrower type: {}", e))
+}
+
+#[tauri::command]
+pub async fn staff_issuance_trend(
+    staff_id: String,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    granularity: Option<TrendGranularity>,
+    include_archived: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<StaffIssuancePoint>, String> {
+    db.get_staff_issuance_trend(&staff_id, from, to, granularity.unwrap_or_default(), include_archived.unwrap_or(false)).await
+        .map_err(|e| format!("Failed to get staff issuance trend: {}", e))
+}
+
+#[tauri::command]
+pub async fn overdue_trend_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    granularity: Option<TrendGranularity>,
+    include_archived: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<OverdueTrendPoint>, String> {
+    db.get_overdue_trend(from, to, granularity.unwrap_or_default(), include_archived.unwrap_or(false)).await
+        .map_err(|e| format!("Failed to get overdue trend: {}", e))
+}
+
+#[tauri::command]
+pub async fn never_borrowed_books_report(
+    acquired_before: Option<i32>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<NeverBorrowedBook>, String> {
+    db.get_books_never_borrowed(acquired_before).await
+        .map_err(|e| format!("Failed to get never borrowed books: {}", e))
+}
+
+#[tauri::command]
+pub async fn time_to_first_loan_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    db: State<'_, DatabaseState>,
+) -> Result<TimeToFirstLoanStats, String> {
+    db.get_time_to_first_loan(from, to).await
+        .map_err(|e| format!("Failed to get time to first loan: {}", e))
+}
+
+#[tauri::command]
+pub async fn fines_by_book_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    limit: Option<u32>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<BookFineTotal>, String> {
+    db.get_fines_by_book(from, to, limit).await
+        .map_err(|e| format!("Failed to get fines by book: {}", e))
+}
+
+#[tauri::command]
+pub async fn class_allowance_report(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<ClassAllowanceUtilization>, String> {
+    db.get_class_allowance_utilization().await
+        .map_err(|e| format!("Failed to get class allowance utilization: {}", e))
+}
+
+#[tauri::command]
+pub async fn active_borrowers_count(
+    db: State<'_, DatabaseState>,
+) -> Result<ActiveBorrowerCount, String> {
+    db.get_active_borrower_count().await
+        .map_err(|e| format!("Failed to count active borrowers: {}", e))
+}
+
+#[tauri::command]
+pub async fn renewal_stats_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    top: Option<u32>,
+    db: State<'_, DatabaseState>,
+) -> Result<RenewalStats, String> {
+    db.get_renewal_stats(from, to, top.unwrap_or(10)).await
+        .map_err(|e| format!("Failed to get renewal stats: {}", e))
+}
+
+#[tauri::command]
+pub async fn acquisition_pressure_report(
+    days: Option<i64>,
+    limit: Option<u32>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<UnavailablePopularBook>, String> {
+    let days = days.unwrap_or(30);
+    if days < 1 {
+        return Err("days must be at least 1".to_string());
+    }
+    db.get_currently_unavailable_popular_books(days, limit.unwrap_or(50)).await
+        .map_err(|e| format!("Failed to get acquisition pressure: {}", e))
+}
+
+#[tauri::command]
+pub async fn sync_metrics_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<SyncTableMetrics>, String> {
+    db.get_sync_metrics(from, to).await
+        .map_err(|e| format!("Failed to get sync metrics: {}", e))
+}
+
+#[tauri::command]
+pub async fn demographics_report(
+    db: State<'_, DatabaseState>,
+) -> Result<DemographicBreakdown, String> {
+    db.get_demographic_breakdown().await
+        .map_err(|e| format!("Failed to get demographic breakdown: {}", e))
+}
+
+#[tauri::command]
+pub async fn payment_method_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<PaymentMethodTotal>, String> {
+    db.get_payments_by_method(from, to).await
+        .map_err(|e| format!("Failed to get payment method breakdown: {}", e))
+}
+
+#[tauri::command]
+pub async fn acquisition_spend_report(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    granularity: Option<TrendGranularity>,
+    db: State<'_, DatabaseState>,
+) -> Result<AcquisitionSpend, String> {
+    db.get_acquisition_spend(from, to, granularity.unwrap_or(TrendGranularity::Month)).await
+        .map_err(|e| format!("Failed to get acquisition spend: {}", e))
+}
+
+#[tauri::command]
+pub async fn availability_heatmap(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<CategoryAvailability>, String> {
+    db.get_availability_heatmap().await
+        .map_err(|e| format!("Failed to build availability heatmap: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_due_report(
+    days_ahead: Option<i64>,
+    db: State<'_, DatabaseState>,
+) -> Result<DueReport, String> {
+    let days_ahead = days_ahead.unwrap_or(3);
+    if days_ahead < 0 {
+        return Err("days_ahead cannot be negative".to_string());
+    }
+
+    db.get_due_report(days_ahead).await
+        .map_err(|e| format!("Failed to get due report: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_dashboard_analytics(
+    top: Option<u32>,
+    db: State<'_, DatabaseState>,
+) -> Result<DashboardAnalytics, String> {
+    db.get_dashboard_analytics(top.unwrap_or(10)).await
+        .map_err(|e| format!("Failed to get dashboard analytics: {}", e))
+}
+
+#[tauri::command]
+pub async fn condition_distribution_report(
+    book_id: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<ConditionCount>, String> {
+    db.get_condition_distribution(book_id.as_deref()).await
+        .map_err(|e| format!("Failed to get condition distribution: {}", e))
+}
+
+#[tauri::command]
+pub async fn daily_summary_report(
+    date: Option<NaiveDate>,
+    db: State<'_, DatabaseState>,
+) -> Result<DailySummary, String> {
+    db.get_daily_summary(date.unwrap_or_else(|| Utc::now().date_naive())).await
+        .map_err(|e| format!("Failed to get daily summary: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_daily_summary_pdf(
+    date: Option<NaiveDate>,
+    path: String,
+    db: State<'_, DatabaseState>,
+) -> Result<DailySummary, String> {
+    let summary = db.get_daily_summary(date.unwrap_or_else(|| Utc::now().date_naive())).await
+        .map_err(|e| format!("Failed to get daily summary: {}", e))?;
+
+    write_text_pdf(std::path::Path::new(&path), "Circulation Desk Daily Summary", &summary.to_lines())
+        .map_err(|e| format!("Failed to write daily summary to {}: {}", path, e))?;
+
+    info!("Daily summary for {} written to {}", summary.date, path);
+    Ok(summary)
+}
+
+// Loan Rule Commands
+#[tauri::command]
+pub async fn get_loan_rules(
+    db: State<'_, DatabaseState>,
+) -> Result<LoanRules, String> {
+    db.get_loan_rules().await
+        .map_err(|e| format!("Failed to get loan rules: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_loan_rules(
+    rules: Value,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    let rules: LoanRules = serde_json::from_value(rules)
+        .map_err(|e| format!("Failed to parse loan rules: {}", e))?;
+
+    if rules.loan_period_days < 1 {
+        return Err("Loan period must be at least 1 day".to_string());
+    }
+    if rules.max_renewals < 0 {
+        return Err("Maximum renewals cannot be negative".to_string());
+    }
+    if rules.staff_loan_period_days < 1 {
+        return Err("Staff loan period must be at least 1 day".to_string());
+    }
+    if rules.staff_max_active_loans < 1 {
+        return Err("Staff must be allowed at least 1 loan".to_string());
+    }
+
+    db.set_loan_rules(&rules).await
+        .map_err(|e| format!("Failed to save loan rules: {}", e))?;
+
+    info!("Loan period set to {} days ({} for staff)", rules.loan_period_days, rules.staff_loan_period_days);
+    Ok(())
+}
+
+// Named Setting Commands
+#[tauri::command]
+pub async fn get_setting(
+    key: String,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    db.get_named_setting(&key).await
+        .map_err(|e| format!("Failed to get setting {}: {}", key, e))
+}
+
+// Each key goes through the same role check and validation as its own setter
+#[tauri::command]
+pub async fn set_setting(
+    key: String,
+    value: Value,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    let invalid = || format!("Invalid value for {}: {}", key, value);
+    match key.as_str() {
+        DEFAULT_LOAN_DAYS_KEY => {
+            require_role(&db, &["admin"]).await?;
+            let days = value.as_i64().ok_or_else(invalid)?;
+            if days < 1 {
+                return Err("Loan period must be at least 1 day".to_string());
+            }
+            db.set_default_loan_days(days).await
+                .map_err(|e| format!("Failed to save loan period: {}", e))?;
+            info!("Loan period set to {} days", days);
+        }
+        BORROW_BLOCK_THRESHOLD_KEY => {
+            let threshold = match &value {
+                Value::Null => None,
+                value => Some(value.as_f64().ok_or_else(invalid)?),
+            };
+            set_borrow_block_threshold(threshold, db.clone()).await?;
+        }
+        TRASH_RETENTION_DAYS_KEY => set_trash_retention_days(value.as_i64().ok_or_else(invalid)?, db.clone()).await?,
+        CONFLICT_LOG_RETENTION_DAYS_KEY => set_conflict_log_retention_days(value.as_i64().ok_or_else(invalid)?, db.clone()).await?,
+        VERIFY_RETURN_TRACKING_CODES_KEY => set_verify_return_tracking_codes(value.as_bool().ok_or_else(invalid)?, db.clone()).await?,
+        _ => return Err(format!("Unknown setting {}; expected one of {}", key, NAMED_SETTINGS.join(", "))),
+    }
+
+    db.get_named_setting(&key).await
+        .map_err(|e| format!("Failed to get setting {}: {}", key, e))
+}
+
+// Flagged Code Commands
+#[tauri::command]
+pub async fn register_flagged_code(
+    code: String,
+    reason: String,
+    db: State<'_, DatabaseState>,
+) -> Result<FlaggedCode, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let flagged_by = db.get_any_valid_session().await
+        .ok()
+        .flatten()
+        .map(|session| session.user_id);
+
+    let flag = db.register_flagged_code(&code, &reason, flagged_by.as_deref()).await
+        .map_err(|e| format!("Failed to flag tracking code: {}", e))?;
+
+    info!("Tracking code {} flagged: {}", flag.code, flag.reason);
+    Ok(flag)
+}
+
+#[tauri::command]
+pub async fn list_flagged_codes(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<FlaggedCode>, String> {
+    db.list_flagged_codes().await
+        .map_err(|e| format!("Failed to get flagged codes: {}", e))
+}
+
+#[tauri::command]
+pub async fn check_flagged_code(
+    code: String,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    let flag = db.check_flagged_code(&code).await
+        .map_err(|e| format!("Failed to check tracking code: {}", e))?;
+
+    if let Some(flag) = &flag {
+        warn!("Flagged tracking code {} scanned: {}", flag.code, flag.reason);
+    }
+    Ok(json!({
+        "code": code,
+        "alert": flag.is_some(),
+        "flag": flag
+    }))
+}
+
+#[tauri::command]
+pub async fn remove_flagged_code(
+    code: String,
+    db: State<'_, DatabaseState>,
+) -> Result<bool, String> {
+    require_role(&db, &["admin"]).await?;
+
+    db.remove_flagged_code(&code).await
+        .map_err(|e| format!("Failed to remove flagged code: {}", e))
+}
+
+// Theft Report Commands
+#[tauri::command]
+pub async fn create_theft_report(
+    borrowing_id: String,
+    returned_tracking_code: String,
+    theft_reason: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<TheftReport, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let reported_by = db.get_any_valid_session().await
+        .ok()
+        .flatten()
+        .map(|session| session.user_id);
+
+    let (report, copy_change) = db.create_theft_report(&borrowing_id, &returned_tracking_code, theft_reason.as_deref(), reported_by.as_deref()).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Borrowing {} not found", borrowing_id),
+            e => format!("Failed to create theft report: {}", e),
+        })?;
+
+    queue_sync(&db, "theft_reports", "insert", &report.id.to_string(), json!(report)).await;
+    if let Some(change) = &copy_change {
+        queue_sync(&db, "book_copies", "update", &change.copy_id, json!({"status": change.status})).await;
+        queue_book_counts(&db, change).await;
+    }
+
+    warn!("Theft report {} raised for borrowing {}: expected {}, got {}",
+        report.id, borrowing_id, report.expected_tracking_code, report.returned_tracking_code);
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_verify_return_tracking_codes(
+    db: State<'_, DatabaseState>,
+) -> Result<bool, String> {
+    db.get_verify_return_tracking_codes().await
+        .map_err(|e| format!("Failed to get tracking code setting: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_verify_return_tracking_codes(
+    enabled: bool,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    db.set_verify_return_tracking_codes(enabled).await
+        .map_err(|e| format!("Failed to save tracking code setting: {}", e))?;
 
+    info!("Return tracking code verification {}", if enabled { "enabled" } else { "disabled" });
     Ok(())
 }
 
 #[tauri::command]
-pub async fn update_student(
-    _student_id: String,
-    student_data: Value,
+pub async fn get_theft_reports(
+    status: Option<String>,
     db: State<'_, DatabaseState>,
-    // sync_engine: State<'_, SyncState>, // Disabled for build
-) -> Result<(), String> {
-    let student: Student = serde_json::from_value(student_data.clone())
-        .map_err(|e| format!("Failed to parse student data: {}", e))?;
-    
-    // Update local SQLite first
-    db.update_student(&student).await
-        .map_err(|e| format!("Failed to update student: {}", e))?;
+) -> Result<Vec<TheftReport>, String> {
+    let status = status.map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty() && s != "all");
+    db.get_theft_reports(status.as_deref()).await
+        .map_err(|e| format!("Failed to get theft reports: {}", e))
+}
 
-    // Queue for sync
-    // sync_engine.queue_operation(
-    //     "students",
-    //     OperationType::Update,
-    //     &student_id,
-    //     student_data,
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+#[tauri::command]
+pub async fn update_theft_report_status(
+    report_id: String,
+    status: TheftStatus,
+    investigation_notes: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<TheftReport, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
 
-    Ok(())
+    let resolved_by = db.get_any_valid_session().await
+        .ok()
+        .flatten()
+        .map(|session| session.user_id);
+
+    let report = db.update_theft_report_status(&report_id, status, investigation_notes.as_deref(), resolved_by.as_deref()).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Theft report {} not found", report_id),
+            e => format!("Failed to update theft report: {}", e),
+        })?;
+
+    queue_sync(&db, "theft_reports", "update", &report_id, json!({
+        "status": report.status,
+        "investigation_notes": report.investigation_notes,
+        "resolved_date": report.resolved_date,
+        "resolved_by": report.resolved_by
+    })).await;
+
+    info!("Theft report {} moved to {:?}", report_id, report.status);
+    Ok(report)
 }
 
-// Delete Commands
+// Book Tag Commands
 #[tauri::command]
-pub async fn delete_book(
+pub async fn add_book_tag(
     book_id: String,
+    tag: String,
     db: State<'_, DatabaseState>,
-    // sync_engine: State<'_, SyncState>, // Disabled for build
-) -> Result<(), String> {
-    // Delete from local SQLite first
-    db.delete_book(&book_id).await
-        .map_err(|e| format!("Failed to delete book: {}", e))?;
+) -> Result<String, String> {
+    db.add_book_tag(&book_id, &tag).await
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Book {} not found", book_id),
+            e => format!("Failed to tag book: {}", e),
+        })
+}
 
-    // Queue for sync to Supabase
-    // sync_engine.queue_operation(
-    //     "books",
-    //     OperationType::Delete,
-    //     &book_id,
-    //     serde_json::json!({"id": book_id}),
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+#[tauri::command]
+pub async fn remove_book_tag(
+    book_id: String,
+    tag: String,
+    db: State<'_, DatabaseState>,
+) -> Result<bool, String> {
+    db.remove_book_tag(&book_id, &tag).await
+        .map_err(|e| format!("Failed to remove book tag: {}", e))
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn get_books_by_tag(
+    tag: String,
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<Book>, String> {
+    db.get_books_by_tag(&tag).await
+        .map_err(|e| format!("Failed to get books by tag: {}", e))
 }
 
 #[tauri::command]
-pub async fn delete_student(
-    student_id: String,
+pub async fn get_all_tags(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<TagCount>, String> {
+    db.get_all_tags().await
+        .map_err(|e| format!("Failed to get tags: {}", e))
+}
+
+// Overdue Escalation Commands
+#[tauri::command]
+pub async fn get_escalation_policy(
+    db: State<'_, DatabaseState>,
+) -> Result<EscalationPolicy, String> {
+    db.get_escalation_policy().await
+        .map_err(|e| format!("Failed to get escalation policy: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_escalation_policy(
+    policy: Value,
     db: State<'_, DatabaseState>,
-    // sync_engine: State<'_, SyncState>, // Disabled for build
 ) -> Result<(), String> {
-    // Delete from local SQLite first
-    db.delete_student(&student_id).await
-        .map_err(|e| format!("Failed to delete student: {}", e))?;
+    require_role(&db, &["admin"]).await?;
 
-    // Queue for sync
-    // sync_engine.queue_operation(
-    //     "students",
-    //     OperationType::Delete,
-    //     &student_id,
-    //     serde_json::json!({"id": student_id}),
-    // ).await.map_err(|e| format!("Failed to queue sync operation: {}", e))?;
+    let policy: EscalationPolicy = serde_json::from_value(policy)
+        .map_err(|e| format!("Failed to parse escalation policy: {}", e))?;
+
+    if policy.stages.iter().any(|stage| stage.min_days_overdue < 1) {
+        return Err("Escalation thresholds must be at least 1 day overdue".to_string());
+    }
+
+    db.set_escalation_policy(&policy).await
+        .map_err(|e| format!("Failed to save escalation policy: {}", e))?;
 
+    info!("Escalation policy updated with {} stages", policy.stages.len());
     Ok(())
 }
 
-// Analytics Commands - Efficient large database queries
 #[tauri::command]
-pub async fn get_library_stats(
+pub async fn apply_escalation(
     db: State<'_, DatabaseState>,
-) -> Result<LibraryStats, String> {
-    // Optimized queries with indexes for large datasets
-    db.get_library_stats().await
-        .map_err(|e| format!("Failed to get library stats: {}", e))
+) -> Result<Vec<EscalationRecord>, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let records = db.apply_escalation().await
+        .map_err(|e| format!("Failed to apply escalation: {}", e))?;
+
+    info!(
+        "Escalation applied to {} overdue loans ({} new actions)",
+        records.len(),
+        records.iter().filter(|r| r.newly_recorded).count()
+    );
+    Ok(records)
 }
 
 // Sync Commands - Hybrid online/offline capabilities
@@ -638,6 +2422,133 @@ pub async fn optimize_database(
     Ok(())
 }
 
+// Nightly maintenance commands
+#[tauri::command]
+pub async fn run_maintenance_now(
+    db: State<'_, DatabaseState>,
+) -> Result<MaintenanceReport, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let report = db.run_nightly_maintenance().await
+        .map_err(|e| format!("Failed to run maintenance: {}", e))?;
+
+    info!("Maintenance ran {} tasks, succeeded: {}", report.tasks.len(), report.succeeded);
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_maintenance_schedule(
+    db: State<'_, DatabaseState>,
+) -> Result<MaintenanceSchedule, String> {
+    db.get_maintenance_schedule().await
+        .map_err(|e| format!("Failed to get maintenance schedule: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_maintenance_schedule(
+    enabled: bool,
+    hour: u32,
+    backup_dir: Option<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<MaintenanceSchedule, String> {
+    require_role(&db, &["admin"]).await?;
+
+    if hour > 23 {
+        return Err("Maintenance hour must be between 0 and 23".to_string());
+    }
+
+    let schedule = db.set_maintenance_schedule(enabled, hour, backup_dir).await
+        .map_err(|e| format!("Failed to save maintenance schedule: {}", e))?;
+
+    info!("Nightly maintenance {} at {:02}:00", if enabled { "scheduled" } else { "disabled" }, hour);
+    Ok(schedule)
+}
+
+// Backup commands
+#[tauri::command]
+pub async fn backup_database_now(
+    db: State<'_, DatabaseState>,
+) -> Result<BackupOutcome, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let outcome = db.create_rolling_backup().await
+        .map_err(|e| format!("Failed to back up database: {}", e))?;
+
+    info!("Database backed up to {} ({} old backups removed)", outcome.backup.path, outcome.removed.len());
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub async fn export_incremental_backup(
+    db: State<'_, DatabaseState>,
+) -> Result<IncrementalBackupOutcome, String> {
+    require_role(&db, &["admin", "librarian"]).await?;
+
+    let outcome = db.export_incremental_backup().await
+        .map_err(|e| format!("Failed to export incremental backup: {}", e))?;
+
+    info!("Incremental backup of {} rows written to {}", outcome.total_rows, outcome.path);
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub async fn restore_with_incrementals(
+    base_path: String,
+    incremental_paths: Vec<String>,
+    db: State<'_, DatabaseState>,
+) -> Result<RestoreReport, String> {
+    require_role(&db, &["admin"]).await?;
+
+    let incremental_paths: Vec<std::path::PathBuf> = incremental_paths.iter().map(std::path::PathBuf::from).collect();
+    let report = db.restore_with_incrementals(std::path::Path::new(&base_path), &incremental_paths).await
+        .map_err(|e| format!("Failed to restore backup: {}", e))?;
+
+    info!(
+        "Database restored from {} plus {} incrementals (previous state saved to {})",
+        report.base_path, report.applied_incrementals.len(), report.safety_backup.path
+    );
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn get_backup_history(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<BackupFile>, String> {
+    db.get_backup_history().await
+        .map_err(|e| format!("Failed to list backups: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_backup_schedule(
+    db: State<'_, DatabaseState>,
+) -> Result<BackupSchedule, String> {
+    db.get_backup_schedule().await
+        .map_err(|e| format!("Failed to get backup schedule: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_backup_schedule(
+    enabled: bool,
+    interval_hours: i64,
+    keep: usize,
+    db: State<'_, DatabaseState>,
+) -> Result<BackupSchedule, String> {
+    require_role(&db, &["admin"]).await?;
+
+    if interval_hours < 1 {
+        return Err("Backup interval must be at least 1 hour".to_string());
+    }
+    if keep < 1 {
+        return Err("At least one backup must be kept".to_string());
+    }
+
+    let schedule = db.set_backup_schedule(enabled, interval_hours, keep).await
+        .map_err(|e| format!("Failed to save backup schedule: {}", e))?;
+
+    info!("Automatic backups every {} hours keeping {}", interval_hours, keep);
+    Ok(schedule)
+}
+
 #[tauri::command]
 pub async fn get_database_info(
     db: State<'_, DatabaseState>,
@@ -765,6 +2676,18 @@ pub async fn enhance_database_performance(
     }))
 }
 
+#[tauri::command]
+pub async fn checkpoint_wal(
+    db: State<'_, DatabaseState>,
+) -> Result<WalCheckpoint, String> {
+    let checkpoint = db.checkpoint_wal().await
+        .map_err(|e| format!("Failed to checkpoint WAL: {}", e))?;
+
+    info!("WAL checkpoint: {} of {} frames, {:?} -> {:?} bytes",
+        checkpoint.checkpointed_frames, checkpoint.log_frames, checkpoint.wal_bytes_before, checkpoint.wal_bytes_after);
+    Ok(checkpoint)
+}
+
 // Session Management Commands for Offline Authentication
 #[tauri::command]
 pub async fn save_user_session(
@@ -851,13 +2774,46 @@ pub async fn cleanup_expired_sessions(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn self_check(
+    db: State<'_, DatabaseState>,
+) -> Result<SelfCheckReport, String> {
+    let report = db.self_check().await
+        .map_err(|e| format!("Failed to run self check: {}", e))?;
+
+    if report.passed {
+        info!("Self check passed ({} tables)", report.tables_checked);
+    } else {
+        warn!("Self check found {} problems: {}", report.problems.len(), report.problems.join("; "));
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn find_corrupt_sessions(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<CorruptSession>, String> {
+    db.find_corrupt_sessions().await
+        .map_err(|e| format!("Failed to check sessions: {}", e))
+}
+
+#[tauri::command]
+pub async fn remove_corrupt_sessions(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<CorruptSession>, String> {
+    let removed = db.remove_corrupt_sessions().await
+        .map_err(|e| format!("Failed to remove corrupt sessions: {}", e))?;
+
+    info!("Removed {} corrupt sessions", removed.len());
+    Ok(removed)
+}
+
 #[tauri::command]
 pub async fn setup_sync_config(
     sync_engine: State<'_, SyncEngine>,
+    sync_config: State<'_, SyncConfigState>,
     config: serde_json::Value,
 ) -> Result<(), String> {
-    info!("Setting up sync config: {:?}", config);
-    
     // Extract configuration values
     let supabase_url = config.get("supabaseUrl")
         .and_then(|v| v.as_str())
@@ -869,10 +2825,26 @@ pub async fn setup_sync_config(
     
     info!("Configuring sync with Supabase URL: {}", supabase_url);
     
+    // Persist so the config survives restarts, then use it for this session
+    let mut new_config = SyncConfig::new(supabase_url, supabase_anon_key)
+        .map_err(|e| format!("Invalid sync config: {}", e))?;
+    if let Some(retry) = config.get("retry") {
+        new_config.retry = serde_json::from_value(retry.clone())
+            .map_err(|e| format!("Invalid retry settings: {}", e))?;
+    }
+    new_config.connectivity_fallback_url = config.get("connectivityFallbackUrl")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_string);
+    new_config.save()
+        .map_err(|e| format!("Failed to save sync config: {}", e))?;
+    *sync_config.write().map_err(|_| "Sync config is unavailable".to_string())? = Some(new_config.clone());
+    
     // Update the sync engine configuration
     let mut engine_config = sync_engine.config.clone();
-    engine_config.url = supabase_url.to_string();
-    engine_config.anon_key = supabase_anon_key.to_string();
+    engine_config.url = new_config.supabase_url.clone();
+    engine_config.anon_key = new_config.supabase_anon_key.clone();
     
     // Test connectivity and perform initial data pull
     let is_online = sync_engine.check_connectivity().await;
@@ -980,18 +2952,17 @@ pub async fn cleanup_expired_auth_sessions(
 #[tauri::command]
 pub async fn sync_books_only(
     limit: Option<u32>,
-) -> Result<Value, String> {
+    sync_config: State<'_, SyncConfigState>,
+    app: AppHandle,
+) -> Result<SyncResult, String> {
     info!("Manual books sync triggered with limit: {:?}", limit);
     
     // Use the simple sync for books specifically
-    match crate::simple_sync::sync_books_from_supabase(limit.unwrap_or(100)).await {
-        Ok(count) => {
-            info!("Books sync completed: {} records", count);
-            Ok(json!({
-                "success": true,
-                "recordsSync": count,
-                "entity": "books"
-            }))
+    let config = current_sync_config(&sync_config)?;
+    match crate::simple_sync::sync_books_from_supabase(&config, limit.unwrap_or(100), &SyncProgress::new(app)).await {
+        Ok(result) => {
+            info!("Books sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         },
         Err(e) => {
             warn!("Books sync failed: {}", e);
@@ -1002,18 +2973,16 @@ pub async fn sync_books_only(
 
 #[tauri::command]
 pub async fn sync_categories_only(
-) -> Result<Value, String> {
+    sync_config: State<'_, SyncConfigState>,
+) -> Result<SyncResult, String> {
     info!("Manual categories sync triggered");
     
     // Use the simple sync for categories specifically
-    match crate::simple_sync::sync_categories_from_supabase().await {
-        Ok(count) => {
-            info!("Categories sync completed: {} records", count);
-            Ok(json!({
-                "success": true,
-                "recordsSync": count,
-                "entity": "categories"
-            }))
+    let config = current_sync_config(&sync_config)?;
+    match crate::simple_sync::sync_categories_from_supabase(&config).await {
+        Ok(result) => {
+            info!("Categories sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         },
         Err(e) => {
             warn!("Categories sync failed: {}", e);
@@ -1025,18 +2994,17 @@ pub async fn sync_categories_only(
 #[tauri::command]
 pub async fn sync_students_only(
     limit: Option<u32>,
-) -> Result<Value, String> {
+    sync_config: State<'_, SyncConfigState>,
+    app: AppHandle,
+) -> Result<SyncResult, String> {
     info!("Manual students sync triggered with limit: {:?}", limit);
     
     // Use the simple sync for students specifically
-    match crate::simple_sync::sync_students_from_supabase(limit.unwrap_or(100)).await {
-        Ok(count) => {
-            info!("Students sync completed: {} records", count);
-            Ok(json!({
-                "success": true,
-                "recordsSync": count,
-                "entity": "students"
-            }))
+    let config = current_sync_config(&sync_config)?;
+    match crate::simple_sync::sync_students_from_supabase(&config, limit.unwrap_or(100), &SyncProgress::new(app)).await {
+        Ok(result) => {
+            info!("Students sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         },
         Err(e) => {
             warn!("Students sync failed: {}", e);
@@ -1045,33 +3013,92 @@ pub async fn sync_students_only(
     }
 }
 
+#[tauri::command]
+pub async fn rebuild_hashes(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<HashRebuildResult>, String> {
+    require_role(&db, &["admin"]).await?;
+
+    let results = db.rebuild_hashes().await
+        .map_err(|e| format!("Failed to rebuild content hashes: {}", e))?;
+
+    info!("Rebuilt content hashes for {} rows", results.iter().map(|r| r.rows_hashed).sum::<usize>());
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn get_auto_sync_state(
+    db: State<'_, DatabaseState>,
+) -> Result<AutoSyncState, String> {
+    db.get_auto_sync_state().await
+        .map_err(|e| format!("Failed to get auto-sync state: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_auto_sync_interval(
+    minutes: i64,
+    db: State<'_, DatabaseState>,
+) -> Result<(), String> {
+    require_role(&db, &["admin"]).await?;
+
+    if minutes < 1 {
+        return Err("Auto-sync interval must be at least 1 minute".to_string());
+    }
+
+    db.set_auto_sync_interval(minutes).await
+        .map_err(|e| format!("Failed to save auto-sync interval: {}", e))?;
+
+    info!("Startup auto-sync interval set to {} minutes", minutes);
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn sync_all_data(
-) -> Result<Value, String> {
+    sync_config: State<'_, SyncConfigState>,
+    db: State<'_, DatabaseState>,
+    app: AppHandle,
+) -> Result<SyncSummary, String> {
     info!("Manual full sync triggered");
     
-    // Use the simple sync for all data
-    match crate::simple_sync::sync_data_from_supabase().await {
-        Ok(_) => {
-            info!("Full sync completed successfully");
-            Ok(json!({
-                "success": true,
-                "message": "All data synchronized successfully"
-            }))
-        },
-        Err(e) => {
-            warn!("Full sync failed: {}", e);
-            Err(format!("Full sync failed: {}", e))
+    let config = current_sync_config(&sync_config)?;
+    let _lock = SyncLock::try_acquire().ok_or("A sync is already running")?;
+    let summary = crate::simple_sync::sync_all_tables(&config, &SyncProgress::new(app)).await;
+
+    if summary.cancelled {
+        info!("Full sync cancelled after {} of 8 entities", summary.results.len());
+        return Ok(summary);
+    }
+    if summary.failed_entities.is_empty() {
+        info!("Full sync completed: {} records", summary.total_inserted);
+        if let Err(e) = db.mark_sync_seeded().await {
+            warn!("Failed to record completed sync: {}", e);
         }
+    } else {
+        warn!("Full sync finished with failures in: {}", summary.failed_entities.join(", "));
+    }
+    if let Err(e) = db.mark_pulled_rows_synced().await {
+        warn!("Failed to mark pulled rows as synced: {}", e);
     }
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn check_unsynced_changes(
+    db: State<'_, DatabaseState>,
+) -> Result<UnsyncedChanges, String> {
+    db.check_unsynced_changes().await
+        .map_err(|e| format!("Failed to check unsynced changes: {}", e))
 }
 
 #[tauri::command]
 pub async fn clear_local_database(
+    force: Option<bool>,
     db: State<'_, DatabaseState>,
 ) -> Result<Value, String> {
     info!("Clearing local database");
     
+    ensure_nothing_unsynced(&db, force.unwrap_or(false), "clearing the local database").await?;
+    
     // This would clear all tables - implement carefully
     match db.clear_all_tables().await {
         Ok(_) => {
@@ -1088,6 +3115,40 @@ pub async fn clear_local_database(
     }
 }
 
+// Empty just the named tables, e.g. to re-pull book_copies without wiping everything
+#[tauri::command]
+pub async fn clear_tables(
+    table_names: Vec<String>,
+    force: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<ClearTablesResult, String> {
+    require_role(&db, &["admin"]).await?;
+    if table_names.is_empty() {
+        return Err("No tables given to clear".to_string());
+    }
+    ensure_nothing_unsynced(&db, force.unwrap_or(false), "clearing local tables").await?;
+
+    let result = db.clear_tables(&table_names).await
+        .map_err(|e| format!("Failed to clear tables: {}", e))?;
+
+    for table in &result.cleared {
+        info!("Cleared {} rows from {}", table.rows, table.table);
+    }
+    for warning in &result.warnings {
+        warn!("{}", warning);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn clear_table(
+    table_name: String,
+    force: Option<bool>,
+    db: State<'_, DatabaseState>,
+) -> Result<ClearTablesResult, String> {
+    clear_tables(vec![table_name], force, db).await
+}
+
 #[tauri::command]
 pub async fn get_local_data_stats(
     db: State<'_, DatabaseState>,
@@ -1128,14 +3189,16 @@ pub async fn get_local_data_stats(
 }
 
 #[tauri::command]
-pub async fn sync_borrowings_only(limit: Option<u32>) -> Result<u32, String> {
+pub async fn sync_borrowings_only(limit: Option<u32>, sync_config: State<'_, SyncConfigState>) -> Result<SyncResult, String> {
     info!("Manual borrowings sync triggered with limit: {:?}", limit);
     let limit = limit.unwrap_or(1000);
     
-    match crate::simple_sync::sync_borrowings_from_supabase(limit).await {
-        Ok(count) => {
-            info!("Borrowings sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_borrowings_from_supabase(&config, limit).await {
+        Ok(result) => {
+            info!("Borrowings sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Borrowings sync failed: {}", e);
@@ -1145,14 +3208,16 @@ pub async fn sync_borrowings_only(limit: Option<u32>) -> Result<u32, String> {
 }
 
 #[tauri::command]
-pub async fn sync_staff_only(limit: Option<u32>) -> Result<u32, String> {
+pub async fn sync_staff_only(limit: Option<u32>, sync_config: State<'_, SyncConfigState>) -> Result<SyncResult, String> {
     info!("Manual staff sync triggered with limit: {:?}", limit);
     let limit = limit.unwrap_or(100);
     
-    match crate::simple_sync::sync_staff_from_supabase(limit).await {
-        Ok(count) => {
-            info!("Staff sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_staff_from_supabase(&config, limit).await {
+        Ok(result) => {
+            info!("Staff sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Staff sync failed: {}", e);
@@ -1162,13 +3227,15 @@ pub async fn sync_staff_only(limit: Option<u32>) -> Result<u32, String> {
 }
 
 #[tauri::command]
-pub async fn sync_classes_only() -> Result<u32, String> {
+pub async fn sync_classes_only(sync_config: State<'_, SyncConfigState>) -> Result<SyncResult, String> {
     info!("Manual classes sync triggered");
     
-    match crate::simple_sync::sync_classes_from_supabase().await {
-        Ok(count) => {
-            info!("Classes sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_classes_from_supabase(&config).await {
+        Ok(result) => {
+            info!("Classes sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Classes sync failed: {}", e);
@@ -1178,13 +3245,43 @@ pub async fn sync_classes_only() -> Result<u32, String> {
 }
 
 #[tauri::command]
-pub async fn pull_all_database() -> Result<String, String> {
+pub async fn cancel_sync() -> Result<bool, String> {
+    let cancelling = crate::simple_sync::request_sync_cancel();
+    if cancelling {
+        info!("Sync cancellation requested");
+    }
+    Ok(cancelling)
+}
+
+#[tauri::command]
+pub async fn pull_all_database(
+    force: Option<bool>,
+    sync_config: State<'_, SyncConfigState>,
+    db: State<'_, DatabaseState>,
+    app: AppHandle,
+) -> Result<Vec<SyncResult>, String> {
     info!("🚀 FULL DATABASE PULL initiated by user");
     
-    match crate::simple_sync::pull_all_database_from_supabase().await {
-        Ok(_) => {
+    let config = current_sync_config(&sync_config)?;
+    // Pulled rows replace local ones, so local edits that weren't pushed would be overwritten
+    ensure_nothing_unsynced(&db, force.unwrap_or(false), "pulling the full database").await?;
+    let _lock = SyncLock::try_acquire().ok_or("A sync is already running")?;
+    
+    match crate::simple_sync::pull_all_database_from_supabase(&config, &SyncProgress::new(app)).await {
+        Ok(results) if crate::simple_sync::sync_cancel_requested() => {
+            // A partial pull keeps its committed batches but doesn't count as the initial seed
+            warn!("⏹️ Full database pull cancelled after {} tables", results.len());
+            Ok(results)
+        }
+        Ok(results) => {
             info!("✅ Full database pull completed successfully");
-            Ok("🎉 Complete database synchronization finished! All tables have been pulled from remote server.".to_string())
+            if let Err(e) = db.mark_sync_seeded().await {
+                warn!("Failed to record completed pull: {}", e);
+            }
+            if let Err(e) = db.mark_pulled_rows_synced().await {
+                warn!("Failed to mark pulled rows as synced: {}", e);
+            }
+            Ok(results)
         }
         Err(e) => {
             error!("❌ Full database pull failed: {}", e);
@@ -1194,14 +3291,16 @@ pub async fn pull_all_database() -> Result<String, String> {
 }
 
 #[tauri::command]
-pub async fn sync_book_copies_only(limit: Option<u32>) -> Result<u32, String> {
+pub async fn sync_book_copies_only(limit: Option<u32>, sync_config: State<'_, SyncConfigState>, app: AppHandle) -> Result<SyncResult, String> {
     info!("Manual book copies sync triggered with limit: {:?}", limit);
     let limit = limit.unwrap_or(100000); // Default to 100K for massive dataset
     
-    match crate::simple_sync::sync_book_copies_from_supabase(limit).await {
-        Ok(count) => {
-            info!("Book copies sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_book_copies_from_supabase(&config, limit, &SyncProgress::new(app)).await {
+        Ok(result) => {
+            info!("Book copies sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Book copies sync failed: {}", e);
@@ -1211,14 +3310,16 @@ pub async fn sync_book_copies_only(limit: Option<u32>) -> Result<u32, String> {
 }
 
 #[tauri::command]
-pub async fn sync_fines_only(limit: Option<u32>) -> Result<u32, String> {
+pub async fn sync_fines_only(limit: Option<u32>, sync_config: State<'_, SyncConfigState>, app: AppHandle) -> Result<SyncResult, String> {
     info!("Manual fines sync triggered with limit: {:?}", limit);
     let limit = limit.unwrap_or(10000);
     
-    match crate::simple_sync::sync_fines_from_supabase(Some(limit)).await {
-        Ok(count) => {
-            info!("Fines sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_fines_from_supabase(&config, Some(limit), &SyncProgress::new(app)).await {
+        Ok(result) => {
+            info!("Fines sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Fines sync failed: {}", e);
@@ -1228,13 +3329,87 @@ pub async fn sync_fines_only(limit: Option<u32>) -> Result<u32, String> {
 }
 
 #[tauri::command]
-pub async fn sync_fine_settings_only() -> Result<u32, String> {
+pub async fn push_sync_queue(sync_config: State<'_, SyncConfigState>) -> Result<SyncResult, String> {
+    info!("Manual sync queue push triggered");
+    
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::process_sync_queue(&config).await {
+        Ok(result) => {
+            info!("Sync queue push completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
+        }
+        Err(e) => {
+            error!("Sync queue push failed: {}", e);
+            Err(format!("Sync queue push failed: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_failed_sync_operations(
+    db: State<'_, DatabaseState>,
+) -> Result<Vec<QueuedSyncOperation>, String> {
+    db.get_failed_sync_operations().await
+        .map_err(|e| format!("Failed to get failed sync operations: {}", e))
+}
+
+#[tauri::command]
+pub async fn retry_sync_operation(
+    queue_id: String,
+    sync_config: State<'_, SyncConfigState>,
+) -> Result<SyncResult, String> {
+    let config = current_sync_config(&sync_config)?;
+
+    let result = crate::simple_sync::retry_sync_operation(&config, &queue_id).await
+        .map_err(|e| format!("Failed to retry sync operation: {}", e))?;
+
+    if result.failed > 0 {
+        warn!("Retry of sync operation {} failed: {}", queue_id, result.errors.join("; "));
+    } else {
+        info!("Sync operation {} pushed on retry", queue_id);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn pull_single_record(
+    table: String,
+    id: String,
+    sync_config: State<'_, SyncConfigState>,
+    db: State<'_, DatabaseState>,
+) -> Result<Value, String> {
+    check_remote_table(&table).map_err(|e| e.to_string())?;
+    let config = current_sync_config(&sync_config)?;
+
+    let remote = crate::simple_sync::fetch_remote_record(&config, &table, &id).await
+        .map_err(|e| format!("Failed to fetch {} {} from Supabase: {}", table, id, e))?
+        .ok_or_else(|| format!("{} {} does not exist on Supabase", table, id))?;
+
+    let row = db.upsert_remote_record(&table, &remote).await
+        .map_err(|e| format!("Failed to store {} {}: {}", table, id, e))?;
+    info!("Re-pulled {} {} from Supabase", table, id);
+    Ok(row)
+}
+
+#[tauri::command]
+pub async fn get_sync_queue_count(
+    db: State<'_, DatabaseState>,
+) -> Result<i32, String> {
+    db.get_sync_queue_count().await
+        .map_err(|e| format!("Failed to get sync queue count: {}", e))
+}
+
+#[tauri::command]
+pub async fn sync_fine_settings_only(sync_config: State<'_, SyncConfigState>) -> Result<SyncResult, String> {
     info!("Manual fine settings sync triggered");
     
-    match crate::simple_sync::sync_fine_settings_from_supabase(Some(1000)).await {
-        Ok(count) => {
-            info!("Fine settings sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_fine_settings_from_supabase(&config, Some(1000)).await {
+        Ok(result) => {
+            info!("Fine settings sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Fine settings sync failed: {}", e);
@@ -1244,14 +3419,16 @@ pub async fn sync_fine_settings_only() -> Result<u32, String> {
 }
 
 #[tauri::command]
-pub async fn sync_group_borrowings_only(limit: Option<u32>) -> Result<u32, String> {
+pub async fn sync_group_borrowings_only(limit: Option<u32>, sync_config: State<'_, SyncConfigState>, app: AppHandle) -> Result<SyncResult, String> {
     info!("Manual group borrowings sync triggered with limit: {:?}", limit);
     let limit = limit.unwrap_or(10000);
     
-    match crate::simple_sync::sync_group_borrowings_from_supabase(Some(limit)).await {
-        Ok(count) => {
-            info!("Group borrowings sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_group_borrowings_from_supabase(&config, Some(limit), &SyncProgress::new(app)).await {
+        Ok(result) => {
+            info!("Group borrowings sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Group borrowings sync failed: {}", e);
@@ -1261,14 +3438,16 @@ pub async fn sync_group_borrowings_only(limit: Option<u32>) -> Result<u32, Strin
 }
 
 #[tauri::command]
-pub async fn sync_theft_reports_only(limit: Option<u32>) -> Result<u32, String> {
+pub async fn sync_theft_reports_only(limit: Option<u32>, sync_config: State<'_, SyncConfigState>, app: AppHandle) -> Result<SyncResult, String> {
     info!("Manual theft reports sync triggered with limit: {:?}", limit);
     let limit = limit.unwrap_or(10000);
     
-    match crate::simple_sync::sync_theft_reports_from_supabase(Some(limit)).await {
-        Ok(count) => {
-            info!("Theft reports sync completed: {} records", count);
-            Ok(count)
+    let config = current_sync_config(&sync_config)?;
+    
+    match crate::simple_sync::sync_theft_reports_from_supabase(&config, Some(limit), &SyncProgress::new(app)).await {
+        Ok(result) => {
+            info!("Theft reports sync completed: {} inserted, {} skipped, {} failed", result.inserted, result.skipped, result.failed);
+            Ok(result)
         }
         Err(e) => {
             error!("Theft reports sync failed: {}", e);
@@ -1276,3 +3455,28 @@ pub async fn sync_theft_reports_only(limit: Option<u32>) -> Result<u32, String>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn clearing_with_unsynced_rows_needs_force() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "UNSYNCED", 1);
+
+        let blocked = ensure_nothing_unsynced(&db, false, "clearing the local database").await.unwrap_err();
+        assert!(blocked.contains("books: 1"), "{}", blocked);
+        assert!(blocked.contains("book_copies: 1"), "{}", blocked);
+        ensure_nothing_unsynced(&db, true, "clearing the local database").await.unwrap();
+
+        // A queued push is just as much at risk as an unsynced row
+        execute(&db, "UPDATE books SET synced = 1", []);
+        execute(&db, "UPDATE book_copies SET synced = 1", []);
+        ensure_nothing_unsynced(&db, false, "clearing the local database").await.unwrap();
+        db.enqueue_operation("books", "update", &book_id, &json!({ "title": "Edited" })).await.unwrap();
+        let blocked = ensure_nothing_unsynced(&db, false, "clearing the local database").await.unwrap_err();
+        assert!(blocked.contains("queued operations: 1"), "{}", blocked);
+    }
+}
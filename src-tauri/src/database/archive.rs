@@ -0,0 +1,158 @@
+use super::backup::main_columns;
+use super::{row_to_borrowing, DatabaseManager, BORROWING_COLUMNS};
+use crate::models::*;
+use chrono::NaiveDate;
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+/// Borrowings plus archived loans, with the columns the history reports read.
+/// Reports that take include_archived select from this in place of borrowings.
+pub(crate) const BORROWINGS_WITH_ARCHIVE: &str = "(
+    SELECT id, student_id, staff_id, book_id, borrowed_date, due_date, returned_date,
+           status, issued_by, returned_by, deleted
+    FROM borrowings
+    UNION ALL
+    SELECT id, student_id, staff_id, book_id, borrowed_date, due_date, returned_date,
+           status, issued_by, returned_by, deleted
+    FROM borrowings_archive
+)";
+
+pub(crate) fn borrowings_source(include_archived: bool) -> &'static str {
+    if include_archived { BORROWINGS_WITH_ARCHIVE } else { "borrowings" }
+}
+
+// Returned loans from before the cutoff with nothing left that still needs the live
+// row: no queued push and no fine still owing on it
+const ARCHIVABLE_WHERE: &str = "
+    WHERE status = 'returned' AND deleted = 0
+      AND date(COALESCE(returned_date, due_date)) < ?1
+      AND NOT EXISTS (
+          SELECT 1 FROM sync_queue q WHERE q.table_name = 'borrowings' AND q.record_id = borrowings.id
+      )
+      AND NOT EXISTS (
+          SELECT 1 FROM fines f WHERE f.borrowing_id = borrowings.id
+          AND f.status IN ('unpaid', 'partial') AND f.deleted = 0
+      )";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveResult {
+    pub before_date: NaiveDate,
+    pub archived: usize,
+}
+
+// Columns present in both tables, so borrowings columns added later don't break archiving
+fn archive_columns(conn: &Connection) -> Result<String> {
+    let archive = main_columns(conn, "borrowings_archive")?;
+    Ok(main_columns(conn, "borrowings")?.into_iter()
+        .filter(|column| archive.contains(column))
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+impl DatabaseManager {
+    pub async fn count_archivable_borrowings(&self, before: NaiveDate) -> Result<i64> {
+        let conn = self.lock_connection()?;
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM borrowings {}", ARCHIVABLE_WHERE),
+            [before.to_string()],
+            |row| row.get(0),
+        )
+    }
+
+    /// Move returned loans older than `before` out of borrowings into
+    /// borrowings_archive. Both steps run in one transaction.
+    pub async fn archive_old_borrowings(&self, before: NaiveDate) -> Result<ArchiveResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+        let columns = archive_columns(&tx)?;
+
+        tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO borrowings_archive ({0}, archived_at)
+                 SELECT {0}, strftime('%Y-%m-%dT%H:%M:%fZ', 'now') FROM borrowings {1}",
+                columns, ARCHIVABLE_WHERE
+            ),
+            [before.to_string()],
+        )?;
+        let archived = tx.execute(
+            &format!("DELETE FROM borrowings {}", ARCHIVABLE_WHERE),
+            [before.to_string()],
+        )?;
+
+        tx.commit()?;
+        Ok(ArchiveResult { before_date: before, archived })
+    }
+
+    /// Archived loans, newest first, optionally for one student or book
+    pub async fn get_archived_borrowings(&self, student_id: Option<&str>, book_id: Option<&str>, limit: i64) -> Result<Vec<Borrowing>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM borrowings_archive
+             WHERE (?1 IS NULL OR student_id = ?1)
+               AND (?2 IS NULL OR book_id = ?2)
+             ORDER BY COALESCE(returned_date, due_date) DESC
+             LIMIT ?3",
+            BORROWING_COLUMNS
+        ))?;
+        let borrowings = stmt.query_map((student_id, book_id, limit), row_to_borrowing)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(borrowings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::reports::{OverdueTrendPoint, TrendGranularity};
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn archived_loans_leave_borrowings_but_stay_queryable() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "OLD", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let late = seed_dated_loan(&db, &student_id, &book_id, "2023-03-01", "2023-03-15", Some("2023-03-20"));
+        let on_time = seed_dated_loan(&db, &student_id, &book_id, "2023-03-02", "2023-03-16", Some("2023-03-10"));
+        // Kept back: a fine still owed, a loan never returned, and one after the cutoff
+        let owing = seed_dated_loan(&db, &student_id, &book_id, "2023-03-05", "2023-03-19", Some("2023-03-18"));
+        execute(
+            &db,
+            "INSERT INTO fines (id, student_id, borrowing_id, fine_type, amount, status) VALUES ('fine-1', ?1, ?2, 'damage', 5, 'unpaid')",
+            [&student_id, &owing],
+        );
+        let still_out = seed_dated_loan(&db, &student_id, &book_id, "2023-03-01", "2023-03-15", None);
+        let recent = seed_dated_loan(&db, &student_id, &book_id, "2024-06-01", "2024-06-15", Some("2024-06-10"));
+
+        let cutoff = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(db.count_archivable_borrowings(cutoff).await.unwrap(), 2);
+        assert_eq!(db.archive_old_borrowings(cutoff).await.unwrap().archived, 2);
+        assert_eq!(db.archive_old_borrowings(cutoff).await.unwrap().archived, 0);
+
+        let mut remaining: Vec<String> = {
+            let conn = db.get_connection().lock().unwrap();
+            let mut stmt = conn.prepare("SELECT id FROM borrowings").unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<_>>().unwrap()
+        };
+        remaining.sort();
+        let mut expected = vec![owing, still_out, recent];
+        expected.sort();
+        assert_eq!(remaining, expected);
+
+        let archived: Vec<String> = db.get_archived_borrowings(Some(&student_id), None, 10).await.unwrap()
+            .into_iter()
+            .map(|borrowing| borrowing.id.to_string())
+            .collect();
+        assert_eq!(archived, [late, on_time]);
+        assert!(db.get_archived_borrowings(None, Some("another-book"), 10).await.unwrap().is_empty());
+
+        // Reports only see the archived loans when asked to
+        let (march, end_of_march) = (NaiveDate::from_ymd_opt(2023, 3, 1), NaiveDate::from_ymd_opt(2023, 3, 31));
+        let due_in_march = |points: Vec<OverdueTrendPoint>| {
+            points.iter().map(|point| (point.loans_due, point.became_overdue)).collect::<Vec<_>>()
+        };
+        let live = db.get_overdue_trend(march, end_of_march, TrendGranularity::Month, false).await.unwrap();
+        assert_eq!(due_in_march(live), [(2, 1)]);
+        let with_archive = db.get_overdue_trend(march, end_of_march, TrendGranularity::Month, true).await.unwrap();
+        assert_eq!(due_in_march(with_archive), [(4, 2)]);
+    }
+}
@@ -0,0 +1,367 @@
+use super::DatabaseManager;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub check: String,
+    pub severity: IssueSeverity,
+    pub table_name: String,
+    pub record_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub checked_at: DateTime<Utc>,
+    pub is_valid: bool,
+    pub error_count: usize,
+    pub warning_count: usize,
+    pub info_count: usize,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    fn from_issues(mut issues: Vec<ValidationIssue>) -> Self {
+        // Most severe issues first so the UI can show them at the top
+        issues.sort_by(|a, b| b.severity.cmp(&a.severity));
+
+        let count = |severity: IssueSeverity| issues.iter().filter(|i| i.severity == severity).count();
+        let error_count = count(IssueSeverity::Error);
+        let warning_count = count(IssueSeverity::Warning);
+        let info_count = count(IssueSeverity::Info);
+
+        Self {
+            checked_at: Utc::now(),
+            is_valid: error_count == 0,
+            error_count,
+            warning_count,
+            info_count,
+            issues,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+// RFC 4180 quoting for fields containing separators, quotes or newlines
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write a validation report to disk as pretty JSON or one CSV row per issue
+pub fn write_integrity_report(report: &ValidationReport, path: &Path, format: ReportFormat) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+    match format {
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut file, report)?;
+        }
+        ReportFormat::Csv => {
+            writeln!(file, "severity,check,table_name,record_id,message")?;
+            for issue in &report.issues {
+                let severity = match issue.severity {
+                    IssueSeverity::Info => "info",
+                    IssueSeverity::Warning => "warning",
+                    IssueSeverity::Error => "error",
+                };
+                writeln!(
+                    file,
+                    "{},{},{},{},{}",
+                    severity,
+                    csv_field(&issue.check),
+                    csv_field(&issue.table_name),
+                    csv_field(issue.record_id.as_deref().unwrap_or("")),
+                    csv_field(&issue.message),
+                )?;
+            }
+        }
+    }
+
+    file.flush()
+}
+
+// Runs a query returning (record_id, message) rows and turns each row into an issue
+fn collect_issues(
+    conn: &Connection,
+    check: &str,
+    severity: IssueSeverity,
+    table_name: &str,
+    sql: &str,
+) -> Result<Vec<ValidationIssue>> {
+    let mut stmt = conn.prepare(sql)?;
+    let issues = stmt.query_map([], |row| {
+        Ok(ValidationIssue {
+            check: check.to_string(),
+            severity,
+            table_name: table_name.to_string(),
+            record_id: row.get(0)?,
+            message: row.get(1)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    Ok(issues)
+}
+
+// Individual audit helpers
+
+pub(crate) fn audit_date_sanity(conn: &Connection) -> Result<Vec<ValidationIssue>> {
+    let mut issues = collect_issues(conn, "date_sanity", IssueSeverity::Error, "borrowings",
+        "SELECT id, 'Due date ' || due_date || ' is before borrowed date ' || borrowed_date
+         FROM borrowings WHERE deleted = 0 AND due_date < borrowed_date")?;
+
+    issues.extend(collect_issues(conn, "date_sanity", IssueSeverity::Error, "borrowings",
+        "SELECT id, 'Returned date ' || returned_date || ' is before borrowed date ' || borrowed_date
+         FROM borrowings WHERE deleted = 0 AND returned_date IS NOT NULL AND returned_date < borrowed_date")?);
+
+    issues.extend(collect_issues(conn, "date_sanity", IssueSeverity::Warning, "borrowings",
+        "SELECT id, 'Borrowed date ' || borrowed_date || ' is in the future'
+         FROM borrowings WHERE deleted = 0 AND borrowed_date > date('now')")?);
+
+    issues.extend(collect_issues(conn, "date_sanity", IssueSeverity::Warning, "students",
+        "SELECT id, 'Date of birth ' || date_of_birth || ' is in the future'
+         FROM students WHERE deleted = 0 AND date_of_birth IS NOT NULL AND date_of_birth > date('now')")?);
+
+    Ok(issues)
+}
+
+pub(crate) fn audit_copy_counts(conn: &Connection) -> Result<Vec<ValidationIssue>> {
+    let mut issues = collect_issues(conn, "copy_counts", IssueSeverity::Error, "books",
+        "SELECT id, 'Negative copy counts (total ' || total_copies || ', available ' || available_copies || ')'
+         FROM books WHERE deleted = 0 AND (total_copies < 0 OR available_copies < 0)")?;
+
+    issues.extend(collect_issues(conn, "copy_counts", IssueSeverity::Error, "books",
+        "SELECT id, 'Available copies ' || available_copies || ' exceed total copies ' || total_copies
+         FROM books WHERE deleted = 0 AND available_copies > total_copies")?);
+
+    issues.extend(collect_issues(conn, "copy_counts", IssueSeverity::Warning, "books",
+        "SELECT b.id, 'Total copies ' || b.total_copies || ' does not match ' || COUNT(bc.id) || ' physical copies'
+         FROM books b
+         JOIN book_copies bc ON bc.book_id = b.id AND bc.deleted = 0
+         WHERE b.deleted = 0
+         GROUP BY b.id
+         HAVING b.total_copies != COUNT(bc.id)")?);
+
+    Ok(issues)
+}
+
+pub(crate) fn audit_status_consistency(conn: &Connection) -> Result<Vec<ValidationIssue>> {
+    let mut issues = collect_issues(conn, "status_consistency", IssueSeverity::Error, "borrowings",
+        "SELECT id, 'Borrowing is returned but has no returned date'
+         FROM borrowings WHERE deleted = 0 AND status = 'returned' AND returned_date IS NULL")?;
+
+    issues.extend(collect_issues(conn, "status_consistency", IssueSeverity::Error, "borrowings",
+        "SELECT id, 'Borrowing is ' || status || ' but has returned date ' || returned_date
+         FROM borrowings WHERE deleted = 0 AND status IN ('active', 'overdue') AND returned_date IS NOT NULL")?);
+
+    issues.extend(collect_issues(conn, "status_consistency", IssueSeverity::Warning, "borrowings",
+        "SELECT id, 'Borrowing is flagged lost but status is ' || status
+         FROM borrowings WHERE deleted = 0 AND is_lost = 1 AND status != 'lost'")?);
+
+    issues.extend(collect_issues(conn, "status_consistency", IssueSeverity::Error, "book_copies",
+        "SELECT bc.id, 'Copy ' || bc.book_code || ' is available but has an open borrowing'
+         FROM book_copies bc
+         WHERE bc.deleted = 0 AND bc.status = 'available'
+           AND EXISTS (SELECT 1 FROM borrowings br WHERE br.book_copy_id = bc.id
+                       AND br.status IN ('active', 'overdue') AND br.deleted = 0)")?);
+
+    issues.extend(collect_issues(conn, "status_consistency", IssueSeverity::Warning, "book_copies",
+        "SELECT bc.id, 'Copy ' || bc.book_code || ' is borrowed but has no open borrowing'
+         FROM book_copies bc
+         WHERE bc.deleted = 0 AND bc.status = 'borrowed'
+           AND NOT EXISTS (SELECT 1 FROM borrowings br WHERE br.book_copy_id = bc.id
+                           AND br.status IN ('active', 'overdue') AND br.deleted = 0)")?);
+
+    Ok(issues)
+}
+
+pub(crate) fn audit_orphan_references(conn: &Connection) -> Result<Vec<ValidationIssue>> {
+    let mut issues = collect_issues(conn, "orphan_references", IssueSeverity::Error, "borrowings",
+        "SELECT br.id, 'References missing book ' || br.book_id
+         FROM borrowings br
+         WHERE br.deleted = 0 AND br.book_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM books b WHERE b.id = br.book_id)")?;
+
+    issues.extend(collect_issues(conn, "orphan_references", IssueSeverity::Error, "borrowings",
+        "SELECT br.id, 'References missing student ' || br.student_id
+         FROM borrowings br
+         WHERE br.deleted = 0 AND br.student_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM students s WHERE s.id = br.student_id)")?);
+
+    issues.extend(collect_issues(conn, "orphan_references", IssueSeverity::Error, "borrowings",
+        "SELECT br.id, 'References missing staff member ' || br.staff_id
+         FROM borrowings br
+         WHERE br.deleted = 0 AND br.staff_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM staff st WHERE st.id = br.staff_id)")?);
+
+    issues.extend(collect_issues(conn, "orphan_references", IssueSeverity::Error, "borrowings",
+        "SELECT br.id, 'References missing book copy ' || br.book_copy_id
+         FROM borrowings br
+         WHERE br.deleted = 0 AND br.book_copy_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM book_copies bc WHERE bc.id = br.book_copy_id)")?);
+
+    issues.extend(collect_issues(conn, "orphan_references", IssueSeverity::Error, "book_copies",
+        "SELECT bc.id, 'References missing book ' || bc.book_id
+         FROM book_copies bc
+         WHERE bc.deleted = 0 AND bc.book_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM books b WHERE b.id = bc.book_id)")?);
+
+    issues.extend(collect_issues(conn, "orphan_references", IssueSeverity::Warning, "fines",
+        "SELECT f.id, 'References missing borrowing ' || f.borrowing_id
+         FROM fines f
+         WHERE f.deleted = 0 AND f.borrowing_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM borrowings br WHERE br.id = f.borrowing_id)")?);
+
+    issues.extend(collect_issues(conn, "orphan_references", IssueSeverity::Warning, "students",
+        "SELECT s.id, 'References missing class ' || s.class_id
+         FROM students s
+         WHERE s.deleted = 0 AND s.class_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM classes c WHERE c.id = s.class_id)")?);
+
+    issues.extend(collect_issues(conn, "orphan_references", IssueSeverity::Warning, "books",
+        "SELECT b.id, 'References missing category ' || b.category_id
+         FROM books b
+         WHERE b.deleted = 0 AND b.category_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM categories c WHERE c.id = b.category_id)")?);
+
+    Ok(issues)
+}
+
+pub(crate) fn audit_duplicate_codes(conn: &Connection) -> Result<Vec<ValidationIssue>> {
+    let mut issues = collect_issues(conn, "duplicate_codes", IssueSeverity::Error, "book_copies",
+        "SELECT NULL, 'Book code ' || book_code || ' is shared by ' || COUNT(*) || ' copies'
+         FROM book_copies WHERE deleted = 0
+         GROUP BY book_code HAVING COUNT(*) > 1")?;
+
+    issues.extend(collect_issues(conn, "duplicate_codes", IssueSeverity::Warning, "book_copies",
+        "SELECT NULL, 'Copy number ' || copy_number || ' is used ' || COUNT(*) || ' times for book ' || book_id
+         FROM book_copies WHERE deleted = 0 AND book_id IS NOT NULL
+         GROUP BY book_id, copy_number HAVING COUNT(*) > 1")?);
+
+    issues.extend(collect_issues(conn, "duplicate_codes", IssueSeverity::Warning, "students",
+        "SELECT NULL, 'Admission number ' || admission_number || ' differs only by case/spacing across ' || COUNT(*) || ' students'
+         FROM students WHERE deleted = 0
+         GROUP BY LOWER(TRIM(admission_number)) HAVING COUNT(*) > 1")?);
+
+    Ok(issues)
+}
+
+impl DatabaseManager {
+    /// Run every model-level check and return a consolidated report
+    pub async fn validate_all(&self) -> Result<ValidationReport> {
+        let conn = self.lock_connection()?;
+
+        let mut issues = Vec::new();
+        issues.extend(audit_date_sanity(&conn)?);
+        issues.extend(audit_copy_counts(&conn)?);
+        issues.extend(audit_status_consistency(&conn)?);
+        issues.extend(audit_orphan_references(&conn)?);
+        issues.extend(audit_duplicate_codes(&conn)?);
+
+        Ok(ValidationReport::from_issues(issues))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn clean_database_is_valid() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "AUD", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+
+        let report = db.validate_all().await.unwrap();
+        assert!(report.is_valid);
+        assert!(report.issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn seeded_issues_are_reported_with_their_severity() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "AUD", 1);
+        // More on the shelf than the library owns
+        execute(&db, "UPDATE books SET available_copies = 3 WHERE id = ?1", [&book_id]);
+        // A loan to a student that no longer exists, borrowed in the future
+        let loan_id = seed_loan(&db, "missing-student", &book_id, &copies[0], 7);
+        execute(&db, "UPDATE borrowings SET borrowed_date = date('now', '+3 days') WHERE id = ?1", [&loan_id]);
+
+        let report = db.validate_all().await.unwrap();
+        assert!(!report.is_valid);
+        assert_eq!(report.error_count, 2);
+        assert_eq!(report.warning_count, 1);
+        assert_eq!(report.info_count, 0);
+
+        let severity_of = |check: &str, record_id: &str| {
+            report.issues.iter()
+                .find(|i| i.check == check && i.record_id.as_deref() == Some(record_id))
+                .map(|i| i.severity)
+        };
+        assert_eq!(severity_of("copy_counts", &book_id), Some(IssueSeverity::Error));
+        assert_eq!(severity_of("orphan_references", &loan_id), Some(IssueSeverity::Error));
+        assert_eq!(severity_of("date_sanity", &loan_id), Some(IssueSeverity::Warning));
+        // Errors sort ahead of warnings
+        assert_eq!(report.issues[0].severity, IssueSeverity::Error);
+        assert_eq!(report.issues[2].severity, IssueSeverity::Warning);
+    }
+
+
+    #[tokio::test]
+    async fn exported_report_contains_every_issue() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "AUD", 1);
+        execute(&db, "UPDATE books SET available_copies = 3 WHERE id = ?1", [&book_id]);
+        let report = db.validate_all().await.unwrap();
+        assert_eq!(report.issues.len(), 1);
+
+        let dir = std::env::temp_dir().join(format!("integrity-report-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let csv_path = dir.join("report.csv");
+        write_integrity_report(&report, &csv_path, ReportFormat::Csv).unwrap();
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "severity,check,table_name,record_id,message");
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with(&format!("error,copy_counts,books,{},", book_id)));
+
+        let json_path = dir.join("report.json");
+        write_integrity_report(&report, &json_path, ReportFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(json["error_count"], 1);
+        assert_eq!(json["issues"][0]["record_id"], book_id.as_str());
+        assert_eq!(json["issues"][0]["severity"], "error");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn csv_fields_with_separators_are_quoted() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
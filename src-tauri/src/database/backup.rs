@@ -0,0 +1,406 @@
+use super::datetimes::CANONICAL_TIMESTAMP_FORMAT;
+use super::hashing::row_as_json;
+use super::merge::{attach_source, detach_source, shared_columns, source_has_table, SOURCE_SCHEMA};
+use super::settings::{read_setting, write_setting, BACKUP_SCHEDULE_KEY, LAST_BACKUP_AT_KEY};
+use super::DatabaseManager;
+use crate::models::*;
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{Connection, Result, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const BACKUP_PREFIX: &str = "library-";
+const BACKUP_EXTENSION: &str = "db";
+const INCREMENTAL_PREFIX: &str = "incremental-";
+
+// Restoring must not sign out the admin doing it, or rewind the migration log
+const RESTORE_SKIPPED_TABLES: &[&str] = &["user_sessions", "schema_version"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("The database has no file location to back up next to")]
+    NoDatabaseFile,
+
+    #[error("No full backup has been made yet to build an incremental on")]
+    NoBaseBackup,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupFile {
+    pub file_name: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupOutcome {
+    pub backup: BackupFile,
+    pub removed: Vec<String>,
+}
+
+/// Rows changed between two backups. Soft deletes travel as rows with deleted = 1;
+/// tables without an updated_at column are only covered by full backups.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncrementalBackup {
+    pub since: String,
+    pub until: String,
+    pub tables: BTreeMap<String, Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IncrementalBackupOutcome {
+    pub path: String,
+    pub since: String,
+    pub until: String,
+    pub row_counts: BTreeMap<String, usize>,
+    pub total_rows: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub base_path: String,
+    pub applied_incrementals: Vec<String>,
+    pub base_rows: usize,
+    pub incremental_rows: usize,
+    pub safety_backup: BackupFile,
+}
+
+/// library-YYYYMMDD-HHMMSS.db inside the given folder
+pub fn timestamped_backup_path(dir: &Path, now: DateTime<Local>) -> PathBuf {
+    dir.join(format!("{}{}.{}", BACKUP_PREFIX, now.format("%Y%m%d-%H%M%S"), BACKUP_EXTENSION))
+}
+
+// Snapshots written by this module, newest first (the timestamped names sort by age)
+fn list_backups(dir: &Path) -> std::io::Result<Vec<BackupFile>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let is_backup = file_name.starts_with(BACKUP_PREFIX)
+            && Path::new(&file_name).extension().is_some_and(|ext| ext == BACKUP_EXTENSION);
+        if !is_backup {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        backups.push(BackupFile {
+            file_name,
+            path: entry.path().display().to_string(),
+            size_bytes: metadata.len(),
+            created_at: metadata.modified().ok().map(DateTime::<Utc>::from),
+        });
+    }
+
+    backups.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    Ok(backups)
+}
+
+// Delete all but the newest `keep` snapshots
+fn rotate_backups(dir: &Path, keep: usize) -> std::io::Result<Vec<String>> {
+    let mut removed = Vec::new();
+    for stale in list_backups(dir)?.into_iter().skip(keep) {
+        std::fs::remove_file(&stale.path)?;
+        removed.push(stale.file_name);
+    }
+    Ok(removed)
+}
+
+fn current_timestamp(conn: &Connection) -> Result<String> {
+    conn.query_row("SELECT strftime(?1, 'now')", [CANONICAL_TIMESTAMP_FORMAT], |row| row.get(0))
+}
+
+// Tables a restore rewrites, named from the live schema so nothing read from a
+// backup file ever ends up in SQL text
+fn restorable_tables(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM main.sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    )?;
+    let tables = stmt.query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<String>>>()?
+        .into_iter()
+        .filter(|table| !RESTORE_SKIPPED_TABLES.contains(&table.as_str()))
+        .collect();
+    Ok(tables)
+}
+
+pub(super) fn main_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+    let columns = stmt.query_map([table], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>>>()?;
+    Ok(columns)
+}
+
+pub(super) fn sql_value(value: &Value) -> rusqlite::types::Value {
+    use rusqlite::types::Value as SqlValue;
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => SqlValue::Text(text.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+// Replace every table with the attached base's copy, then upsert each
+// incremental's rows on top in order
+fn restore_attached(conn: &Connection, chain: &[(String, IncrementalBackup)]) -> Result<(usize, usize)> {
+    let tx = conn.unchecked_transaction()?;
+    let tables = restorable_tables(&tx)?;
+
+    let mut base_rows = 0;
+    for table in &tables {
+        tx.execute(&format!("DELETE FROM main.{table}"), [])?;
+        if !source_has_table(&tx, table)? {
+            continue;
+        }
+        let column_list = shared_columns(&tx, table)?.join(", ");
+        base_rows += tx.execute(
+            &format!("INSERT INTO main.{table} ({column_list}) SELECT {column_list} FROM {SOURCE_SCHEMA}.{table}"),
+            [],
+        )?;
+    }
+
+    let mut incremental_rows = 0;
+    for (_, backup) in chain {
+        for (table_name, rows) in &backup.tables {
+            let Some(table) = tables.iter().find(|t| *t == table_name) else { continue };
+            let columns = main_columns(&tx, table)?;
+
+            for row in rows {
+                let Value::Object(fields) = row else { continue };
+                let (names, values): (Vec<&str>, Vec<_>) = columns.iter()
+                    .filter_map(|column| fields.get(column).map(|value| (column.as_str(), sql_value(value))))
+                    .unzip();
+                if names.is_empty() {
+                    continue;
+                }
+
+                let placeholders = vec!["?"; names.len()].join(", ");
+                incremental_rows += tx.execute(
+                    &format!("INSERT OR REPLACE INTO main.{} ({}) VALUES ({})", table, names.join(", "), placeholders),
+                    rusqlite::params_from_iter(values),
+                )?;
+            }
+        }
+    }
+
+    // The restored state isn't a single file, so the next incremental needs a fresh full backup
+    tx.execute("DELETE FROM app_settings WHERE key = ?1", [LAST_BACKUP_AT_KEY])?;
+    tx.commit()?;
+    Ok((base_rows, incremental_rows))
+}
+
+impl DatabaseManager {
+    /// Copy the live database to a new file while the app keeps running.
+    /// VACUUM INTO writes a consistent, compacted snapshot and refuses to
+    /// overwrite an existing file. Incremental backups continue from here.
+    pub async fn backup_database(&self, destination: &Path) -> Result<()> {
+        let conn = self.lock_connection()?;
+        let started_at = current_timestamp(&conn)?;
+        conn.execute("VACUUM INTO ?1", [destination.to_string_lossy()])?;
+        write_setting(&conn, LAST_BACKUP_AT_KEY, &started_at)?;
+        Ok(())
+    }
+
+    /// Write the rows changed since the last full or incremental backup to
+    /// backups/incremental-YYYYMMDD-HHMMSS.json. Rows touched while the previous
+    /// backup ran may appear twice, which replays harmlessly.
+    pub async fn export_incremental_backup(&self) -> Result<IncrementalBackupOutcome, BackupError> {
+        let dir = self.backups_dir().await?;
+        std::fs::create_dir_all(&dir)?;
+
+        let conn = self.lock_connection()?;
+        let since: String = read_setting(&conn, LAST_BACKUP_AT_KEY)?.ok_or(BackupError::NoBaseBackup)?;
+        let until = current_timestamp(&conn)?;
+
+        let mut tables = BTreeMap::new();
+        for table in restorable_tables(&conn)? {
+            if !main_columns(&conn, &table)?.iter().any(|c| c == "updated_at") {
+                continue;
+            }
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE updated_at >= ?1 ORDER BY rowid", table))?;
+            let rows = stmt.query_map([&since], row_as_json)?.collect::<Result<Vec<_>>>()?;
+            if !rows.is_empty() {
+                tables.insert(table, rows);
+            }
+        }
+
+        let backup = IncrementalBackup { since, until, tables };
+        let path = dir.join(format!("{}{}.json", INCREMENTAL_PREFIX, Local::now().format("%Y%m%d-%H%M%S")));
+        let file = std::fs::OpenOptions::new().write(true).create_new(true).open(&path)?;
+        serde_json::to_writer(std::io::BufWriter::new(file), &backup)?;
+        write_setting(&conn, LAST_BACKUP_AT_KEY, &backup.until)?;
+
+        let row_counts: BTreeMap<String, usize> = backup.tables.iter()
+            .map(|(table, rows)| (table.clone(), rows.len()))
+            .collect();
+        Ok(IncrementalBackupOutcome {
+            path: path.display().to_string(),
+            total_rows: row_counts.values().sum(),
+            row_counts,
+            since: backup.since,
+            until: backup.until,
+        })
+    }
+
+    /// Replace the database with a full backup plus the incrementals taken after
+    /// it, applied oldest first. A rolling backup of the current state is taken
+    /// beforehand so the restore itself can be undone.
+    pub async fn restore_with_incrementals(&self, base_path: &Path, incremental_paths: &[PathBuf]) -> Result<RestoreReport, BackupError> {
+        let mut chain = Vec::with_capacity(incremental_paths.len());
+        for path in incremental_paths {
+            let file = std::fs::File::open(path)?;
+            let backup: IncrementalBackup = serde_json::from_reader(std::io::BufReader::new(file))?;
+            chain.push((path.display().to_string(), backup));
+        }
+        chain.sort_by(|a, b| a.1.since.cmp(&b.1.since));
+
+        let safety_backup = self.create_rolling_backup().await?.backup;
+
+        let conn = self.lock_connection()?;
+        attach_source(&conn, base_path)?;
+        // Tables are refilled one at a time, so children may land before their parents
+        conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+        let result = restore_attached(&conn, &chain);
+        let _ = conn.execute_batch("PRAGMA foreign_keys = ON");
+        detach_source(&conn);
+        let (base_rows, incremental_rows) = result?;
+
+        Ok(RestoreReport {
+            base_path: base_path.display().to_string(),
+            applied_incrementals: chain.into_iter().map(|(path, _)| path).collect(),
+            base_rows,
+            incremental_rows,
+            safety_backup,
+        })
+    }
+
+    /// The backups/ folder beside the database file
+    pub async fn backups_dir(&self) -> Result<PathBuf, BackupError> {
+        let conn = self.lock_connection()?;
+        let db_path = conn.path()
+            .filter(|p| !p.is_empty())
+            .ok_or(BackupError::NoDatabaseFile)?;
+        let parent = Path::new(db_path).parent().ok_or(BackupError::NoDatabaseFile)?;
+        Ok(parent.join("backups"))
+    }
+
+    pub async fn get_backup_schedule(&self) -> Result<BackupSchedule> {
+        Ok(self.get_setting(BACKUP_SCHEDULE_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_backup_schedule(&self, enabled: bool, interval_hours: i64, keep: usize) -> Result<BackupSchedule> {
+        let conn = self.lock_connection()?;
+        let mut schedule: BackupSchedule = read_setting(&conn, BACKUP_SCHEDULE_KEY)?.unwrap_or_default();
+        schedule.enabled = enabled;
+        schedule.interval_hours = interval_hours;
+        schedule.keep = keep;
+        write_setting(&conn, BACKUP_SCHEDULE_KEY, &schedule)?;
+        Ok(schedule)
+    }
+
+    /// Record a scheduled backup attempt if one is due
+    pub async fn claim_scheduled_backup(&self, now: DateTime<Utc>) -> Result<bool> {
+        let conn = self.lock_connection()?;
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)?;
+
+        let mut schedule: BackupSchedule = read_setting(&tx, BACKUP_SCHEDULE_KEY)?.unwrap_or_default();
+        if !schedule.is_due(now) {
+            return Ok(false);
+        }
+
+        schedule.last_backup = Some(now);
+        write_setting(&tx, BACKUP_SCHEDULE_KEY, &schedule)?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Write a timestamped snapshot into backups/ and prune it down to the
+    /// configured number of files
+    pub async fn create_rolling_backup(&self) -> Result<BackupOutcome, BackupError> {
+        let dir = self.backups_dir().await?;
+        std::fs::create_dir_all(&dir)?;
+
+        let path = timestamped_backup_path(&dir, Local::now());
+        self.backup_database(&path).await?;
+
+        let keep = self.get_backup_schedule().await?.keep.max(1);
+        let removed = rotate_backups(&dir, keep)?;
+
+        let metadata = std::fs::metadata(&path)?;
+        Ok(BackupOutcome {
+            backup: BackupFile {
+                file_name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+                path: path.display().to_string(),
+                size_bytes: metadata.len(),
+                created_at: Some(Utc::now()),
+            },
+            removed,
+        })
+    }
+
+    pub async fn get_backup_history(&self) -> Result<Vec<BackupFile>, BackupError> {
+        let dir = self.backups_dir().await?;
+        Ok(list_backups(&dir)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn restoring_a_base_and_an_incremental_gives_the_combined_state() {
+        let dir = std::env::temp_dir().join(format!("backup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = DatabaseManager::new(dir.join("library.db").to_str().unwrap()).unwrap();
+        let (kept, _) = seed_book(&db, "BASE", 1);
+        assert!(matches!(db.export_incremental_backup().await, Err(BackupError::NoBaseBackup)));
+
+        let base_path = dir.join("base.db");
+        db.backup_database(&base_path).await.unwrap();
+
+        execute(&db, "UPDATE books SET title = 'Renamed after base' WHERE id = ?1", [&kept]);
+        let (added, _) = seed_book(&db, "INCR", 2);
+        let incremental = db.export_incremental_backup().await.unwrap();
+        assert_eq!(incremental.row_counts.get("books"), Some(&2));
+        assert_eq!(incremental.row_counts.get("book_copies"), Some(&2));
+
+        // Made after the last incremental, so no backup has it
+        execute(&db, "UPDATE books SET title = 'Never backed up' WHERE id = ?1", [&kept]);
+        let (lost, _) = seed_book(&db, "LATE", 1);
+
+        let report = db.restore_with_incrementals(&base_path, &[PathBuf::from(&incremental.path)]).await.unwrap();
+        assert_eq!(report.applied_incrementals, [incremental.path.clone()]);
+        assert!(Path::new(&report.safety_backup.path).exists());
+
+        assert_eq!(scalar::<String, _>(&db, "SELECT title FROM books WHERE id = ?1", [&kept]), "Renamed after base");
+        assert_eq!(book_counts(&db, &added), (2, 2));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM books WHERE id = ?1", [&lost]), 0);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM book_copies", []), 3);
+        // The next incremental needs a fresh full backup to build on
+        assert!(matches!(db.export_incremental_backup().await, Err(BackupError::NoBaseBackup)));
+
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
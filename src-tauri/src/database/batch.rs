@@ -0,0 +1,117 @@
+use super::nightly::checkpoint;
+use super::{check_admission_number_free, normalize_gender, DatabaseManager};
+use crate::models::*;
+use chrono::SecondsFormat;
+use rusqlite::{Result, Statement, Transaction};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFailure {
+    pub record: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchCreateResult {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub failures: Vec<BatchFailure>,
+}
+
+// Run one prepared insert per row inside the caller's transaction. A row that
+// fails only undoes its own statement, so the rest of the batch still goes in.
+fn insert_each<T>(
+    tx: &Transaction<'_>,
+    sql: &str,
+    rows: &[T],
+    label: impl Fn(&T) -> String,
+    mut insert: impl FnMut(&Transaction<'_>, &mut Statement<'_>, &T) -> std::result::Result<(), String>,
+) -> Result<BatchCreateResult> {
+    let mut stmt = tx.prepare(sql)?;
+    let mut result = BatchCreateResult::default();
+    for row in rows {
+        match insert(tx, &mut stmt, row) {
+            Ok(()) => result.succeeded += 1,
+            Err(error) => {
+                result.failed += 1;
+                result.failures.push(BatchFailure { record: label(row), error });
+            }
+        }
+    }
+    Ok(result)
+}
+
+impl DatabaseManager {
+    /// Insert many books in a single transaction with one prepared statement.
+    /// Rows that fail (e.g. a duplicate ISBN) are reported by title and skipped.
+    pub async fn batch_create_books(&self, books: &[Book]) -> Result<BatchCreateResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let result = insert_each(
+            &tx,
+            "INSERT INTO books (id, title, author, isbn, publisher, publication_year, category_id, total_copies, available_copies, shelf_location, description, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            books,
+            |book| book.title.clone(),
+            |_, stmt, book| {
+                stmt.execute((
+                    book.id.to_string(),
+                    &book.title,
+                    &book.author,
+                    &book.isbn,
+                    &book.publisher,
+                    book.publication_year,
+                    book.category_id.map(|id| id.to_string()),
+                    book.total_copies,
+                    book.available_copies,
+                    &book.shelf_location,
+                    &book.description,
+                    book.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    book.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                )).map(|_| ()).map_err(|e| e.to_string())
+            },
+        )?;
+
+        tx.commit()?;
+        checkpoint(&conn)?;
+        Ok(result)
+    }
+
+    /// Students counterpart of batch_create_books. Admission numbers already on
+    /// file, or repeated earlier in the batch, fail that row only.
+    pub async fn batch_create_students(&self, students: &[Student]) -> Result<BatchCreateResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let result = insert_each(
+            &tx,
+            "INSERT INTO students (id, first_name, last_name, admission_number, class_id, email, phone, address, created_at, updated_at, gender)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            students,
+            |student| format!("{} ({} {})", student.admission_number, student.first_name, student.last_name),
+            |tx, stmt, student| {
+                let gender = normalize_gender(student.gender.as_deref()).map_err(|e| e.to_string())?;
+                check_admission_number_free(tx, &student.admission_number, &student.id.to_string())
+                    .map_err(|e| e.to_string())?;
+                stmt.execute((
+                    student.id.to_string(),
+                    &student.first_name,
+                    &student.last_name,
+                    &student.admission_number,
+                    student.class_id.map(|id| id.to_string()),
+                    &student.email,
+                    &student.phone,
+                    &student.address,
+                    student.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    student.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    gender,
+                )).map(|_| ()).map_err(|e| e.to_string())
+            },
+        )?;
+
+        tx.commit()?;
+        checkpoint(&conn)?;
+        Ok(result)
+    }
+}
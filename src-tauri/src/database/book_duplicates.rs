@@ -0,0 +1,243 @@
+use super::{constraint_error, DatabaseManager};
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateBookCandidate {
+    pub book_id: String,
+    pub title: String,
+    pub author: String,
+    pub isbn: Option<String>,
+    pub book_code: Option<String>,
+    pub total_copies: i64,
+    pub match_reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BookMergeResult {
+    pub kept_book_id: String,
+    pub merged_book_id: String,
+    pub moved_copy_ids: Vec<String>,
+    pub moved_borrowing_ids: Vec<String>,
+    pub total_copies: i64,
+    pub available_copies: i64,
+}
+
+const LEADING_ARTICLES: &[&str] = &["the", "a", "an"];
+
+/// Digits (and a trailing X check digit) only, with ISBN-10s converted to their
+/// ISBN-13 form so both spellings of the same edition compare equal
+pub fn normalize_isbn(isbn: &str) -> Option<String> {
+    let compact: String = isbn.chars()
+        .filter(|c| c.is_ascii_digit() || *c == 'x' || *c == 'X')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    match compact.len() {
+        13 if compact.chars().all(|c| c.is_ascii_digit()) => Some(compact),
+        10 if compact[..9].chars().all(|c| c.is_ascii_digit()) => {
+            let body = format!("978{}", &compact[..9]);
+            let sum: u32 = body.chars().enumerate()
+                .map(|(i, c)| c.to_digit(10).unwrap_or(0) * if i % 2 == 0 { 1 } else { 3 })
+                .sum();
+            Some(format!("{}{}", body, (10 - sum % 10) % 10))
+        }
+        _ => None,
+    }
+}
+
+// Lowercased words without punctuation or a leading article
+fn title_words(title: &str) -> Vec<String> {
+    let cleaned: String = title.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+        .collect();
+    let mut words: Vec<String> = cleaned.split_whitespace().map(str::to_string).collect();
+    if words.len() > 1 && LEADING_ARTICLES.contains(&words[0].as_str()) {
+        words.remove(0);
+    }
+    words
+}
+
+// Same words, or one title is the other with a subtitle added
+fn titles_match(a: &[String], b: &[String]) -> bool {
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    longer.starts_with(shorter)
+}
+
+// Authors match on surname, so "C. Achebe" and "Chinua Achebe" agree; a missing
+// author on either side doesn't rule a match out
+fn authors_match(a: &str, b: &str) -> bool {
+    let surname = |author: &str| title_words(author).last().cloned();
+    match (surname(a), surname(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+fn ids_for_book(conn: &Connection, table: &str, book_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("SELECT id FROM {} WHERE book_id = ?1", table))?;
+    let ids = stmt.query_map([book_id], |row| row.get(0))?.collect::<Result<Vec<_>>>()?;
+    Ok(ids)
+}
+
+fn book_counts(conn: &Connection, book_id: &str) -> Result<Option<(i64, i64)>> {
+    conn.query_row(
+        "SELECT COALESCE(total_copies, 0), COALESCE(available_copies, 0) FROM books WHERE id = ?1 AND deleted = 0",
+        [book_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()
+}
+
+impl DatabaseManager {
+    /// Fold a duplicate book record into the one being kept: its copies, loans
+    /// (current, archived and group), theft reports and tags move over, its copy
+    /// counts are added on, and the duplicate is soft-deleted. Fines follow their
+    /// borrowings, so they need no change.
+    pub async fn merge_books(&self, keep_id: &str, merge_id: &str) -> Result<BookMergeResult> {
+        if keep_id == merge_id {
+            return Err(constraint_error("A book cannot be merged into itself"));
+        }
+
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (keep_total, keep_available) = book_counts(&tx, keep_id)?
+            .ok_or_else(|| constraint_error(format!("Book {} not found", keep_id)))?;
+        let (merge_total, merge_available) = book_counts(&tx, merge_id)?
+            .ok_or_else(|| constraint_error(format!("Book {} not found", merge_id)))?;
+
+        let moved_copy_ids = ids_for_book(&tx, "book_copies", merge_id)?;
+        let moved_borrowing_ids = ids_for_book(&tx, "borrowings", merge_id)?;
+
+        for table in ["book_copies", "borrowings", "group_borrowings", "theft_reports"] {
+            tx.execute(
+                &format!("UPDATE {} SET book_id = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE book_id = ?2", table),
+                (keep_id, merge_id),
+            )?;
+        }
+        tx.execute("UPDATE borrowings_archive SET book_id = ?1 WHERE book_id = ?2", (keep_id, merge_id))?;
+        tx.execute(
+            "INSERT OR IGNORE INTO book_tags (book_id, tag, created_at)
+             SELECT ?1, tag, created_at FROM book_tags WHERE book_id = ?2",
+            (keep_id, merge_id),
+        )?;
+        tx.execute("DELETE FROM book_tags WHERE book_id = ?1", [merge_id])?;
+
+        let total_copies = keep_total + merge_total;
+        let available_copies = keep_available + merge_available;
+        tx.execute(
+            "UPDATE books SET total_copies = ?2, available_copies = ?3, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            (keep_id, total_copies, available_copies),
+        )?;
+        tx.execute(
+            "UPDATE books SET deleted = 1, total_copies = 0, available_copies = 0, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            [merge_id],
+        )?;
+
+        tx.commit()?;
+
+        Ok(BookMergeResult {
+            kept_book_id: keep_id.to_string(),
+            merged_book_id: merge_id.to_string(),
+            moved_copy_ids,
+            moved_borrowing_ids,
+            total_copies,
+            available_copies,
+        })
+    }
+
+    /// Existing books that look like the one about to be catalogued: the same ISBN,
+    /// or the same title (ignoring case, punctuation and a subtitle) by the same author.
+    /// Nothing is blocked; the candidates are for the cataloguer to review or merge.
+    pub async fn find_possible_duplicate_books(&self, title: &str, author: Option<&str>, isbn: Option<&str>) -> Result<Vec<DuplicateBookCandidate>> {
+        let isbn = isbn.and_then(normalize_isbn);
+        let words = title_words(title);
+        let author = author.unwrap_or_default();
+
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title, COALESCE(author, ''), isbn, book_code, COALESCE(total_copies, 0)
+             FROM books WHERE deleted = 0
+             ORDER BY title"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DuplicateBookCandidate {
+                book_id: row.get(0)?,
+                title: row.get(1)?,
+                author: row.get(2)?,
+                isbn: row.get(3)?,
+                book_code: row.get(4)?,
+                total_copies: row.get(5)?,
+                match_reason: String::new(),
+            })
+        })?;
+
+        let mut isbn_matches = Vec::new();
+        let mut title_matches = Vec::new();
+        for row in rows {
+            let mut book = row?;
+            if isbn.is_some() && book.isbn.as_deref().and_then(normalize_isbn) == isbn {
+                book.match_reason = "isbn".to_string();
+                isbn_matches.push(book);
+            } else if titles_match(&words, &title_words(&book.title)) && authors_match(author, &book.author) {
+                book.match_reason = "title_author".to_string();
+                title_matches.push(book);
+            }
+        }
+
+        isbn_matches.extend(title_matches);
+        Ok(isbn_matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::{book_counts, execute, scalar, seed_book, seed_dated_loan, seed_loan, seed_student, test_db};
+
+    #[tokio::test]
+    async fn merging_moves_copies_and_loans_onto_the_kept_book() {
+        let db = test_db();
+        let (keep_id, _) = seed_book(&db, "KEEP", 2);
+        let (merge_id, merge_copies) = seed_book(&db, "DUP", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let open_loan = seed_loan(&db, &student_id, &merge_id, &merge_copies[0], 7);
+        let old_loan = seed_dated_loan(&db, &student_id, &merge_id, "2024-01-10", "2024-01-24", Some("2024-01-30"));
+        execute(
+            &db,
+            "INSERT INTO fines (id, student_id, borrowing_id, fine_type, amount, status) VALUES ('fine-1', ?1, ?2, 'overdue', 6, 'unpaid')",
+            [&student_id, &old_loan],
+        );
+
+        let result = db.merge_books(&keep_id, &merge_id).await.unwrap();
+        let sorted = |mut ids: Vec<String>| { ids.sort(); ids };
+        assert_eq!(sorted(result.moved_copy_ids), sorted(merge_copies.clone()));
+        assert_eq!(sorted(result.moved_borrowing_ids), sorted(vec![open_loan.clone(), old_loan.clone()]));
+        assert_eq!((result.total_copies, result.available_copies), (4, 3));
+        assert_eq!(book_counts(&db, &keep_id), (4, 3));
+
+        // Every copy and loan now points at the kept book, and nothing at the duplicate
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM book_copies WHERE book_id = ?1", [&keep_id]), 4);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM borrowings WHERE book_id = ?1", [&keep_id]), 2);
+        for table in ["book_copies", "borrowings"] {
+            let left: i64 = scalar(&db, &format!("SELECT COUNT(*) FROM {} WHERE book_id = ?1", table), [&merge_id]);
+            assert_eq!(left, 0, "{}", table);
+        }
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM book_copies WHERE id = ?1", [&merge_copies[0]]), "borrowed");
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT b.book_id FROM fines f JOIN borrowings b ON b.id = f.borrowing_id WHERE f.id = 'fine-1'", []),
+            keep_id
+        );
+
+        // The duplicate is soft-deleted with nothing left on it
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT deleted || ' ' || total_copies || ' ' || available_copies FROM books WHERE id = ?1", [&merge_id]),
+            "1 0 0"
+        );
+        assert!(db.merge_books(&keep_id, &merge_id).await.is_err());
+        assert!(db.merge_books(&keep_id, &keep_id).await.is_err());
+    }
+}
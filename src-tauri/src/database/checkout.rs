@@ -0,0 +1,547 @@
+use super::settings::{read_setting, LOAN_RULES_KEY};
+use super::{
+    apply_borrowing_rules, checkout_blockers, constraint_error, parse_enum, parse_sqlite_date, parse_sqlite_datetime,
+    row_to_book_at, row_to_staff, row_to_student, store_borrowing, BorrowingError, DatabaseManager, STAFF_COLUMNS,
+    STUDENT_COLUMNS,
+};
+use crate::models::*;
+use chrono::{NaiveDate, Utc};
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutVerdict {
+    pub allowed: bool,
+    pub borrower_type: Option<BorrowerType>,
+    pub borrower_id: Option<String>,
+    pub borrower_name: Option<String>,
+    pub book_id: Option<String>,
+    pub book_copy_id: Option<String>,
+    pub title: Option<String>,
+    pub due_date: NaiveDate,
+    pub blocking_reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CopyLoan {
+    pub borrowing_id: String,
+    pub borrower_type: BorrowerType,
+    pub borrower_id: Option<String>,
+    pub borrower_name: Option<String>,
+    pub borrowed_date: Option<NaiveDate>,
+    pub due_date: NaiveDate,
+    pub status: BorrowingStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedCopy {
+    pub copy: BookCopy,
+    pub book: Option<Book>,
+    pub active_borrowing: Option<CopyLoan>,
+}
+
+// One row per copy: the copy (0-10), its book (11-23, row_to_book's columns) and the
+// open loan on it, if any (24-30)
+const COPY_DETAILS_SELECT: &str = "
+    SELECT bc.id, bc.book_id, bc.copy_number, bc.book_code, bc.condition, bc.status,
+           bc.created_at, bc.updated_at, bc.tracking_code, bc.notes, bc.legacy_book_id,
+           b.id, b.title, b.author, b.isbn, b.publisher, b.publication_year, b.category_id,
+           b.total_copies, b.available_copies, b.shelf_location, b.description, b.created_at, b.updated_at,
+           br.id, COALESCE(br.borrower_type, 'student'), COALESCE(br.student_id, br.staff_id),
+           COALESCE(s.first_name || ' ' || s.last_name, st.first_name || ' ' || st.last_name),
+           br.borrowed_date, br.due_date, br.status
+    FROM book_copies bc
+    LEFT JOIN books b ON b.id = bc.book_id AND b.deleted = 0
+    LEFT JOIN borrowings br ON br.book_copy_id = bc.id AND br.deleted = 0
+         AND br.returned_date IS NULL AND br.status IN ('active', 'overdue')
+    LEFT JOIN students s ON s.id = br.student_id
+    LEFT JOIN staff st ON st.id = br.staff_id";
+
+// Both codes are indexed, and an exact book_code match sorts ahead of a tracking_code match
+const SCAN_WHERE: &str = "
+    WHERE (bc.book_code = ?1 OR bc.tracking_code = ?1) AND bc.deleted = 0
+    ORDER BY bc.book_code = ?1 DESC
+    LIMIT 1";
+
+fn row_to_scanned_copy(row: &rusqlite::Row) -> Result<ScannedCopy> {
+    let parse_uuid = |value: Option<String>| value.and_then(|s| Uuid::parse_str(&s).ok());
+    let id_str: String = row.get(0)?;
+    let condition: Option<String> = row.get(4)?;
+    let status: Option<String> = row.get(5)?;
+    let created_str: String = row.get(6)?;
+    let updated_str: String = row.get(7)?;
+
+    let copy = BookCopy {
+        id: Uuid::parse_str(&id_str).map_err(|_| {
+            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+        })?,
+        book_id: parse_uuid(row.get(1)?),
+        copy_number: row.get(2)?,
+        book_code: row.get(3)?,
+        condition: condition.as_deref().and_then(parse_enum).unwrap_or(BookCondition::Good),
+        status: status.as_deref().and_then(parse_enum).unwrap_or(CopyStatus::Available),
+        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+        tracking_code: row.get(8)?,
+        notes: row.get(9)?,
+        legacy_book_id: row.get(10)?,
+    };
+
+    let book = match row.get::<_, Option<String>>(11)? {
+        Some(_) => Some(row_to_book_at(row, 11)?),
+        None => None,
+    };
+
+    let active_borrowing = match row.get::<_, Option<String>>(24)? {
+        Some(borrowing_id) => {
+            let borrower_type: String = row.get(25)?;
+            let borrowed_str: Option<String> = row.get(28)?;
+            let due_str: String = row.get(29)?;
+            let status: Option<String> = row.get(30)?;
+            Some(CopyLoan {
+                borrowing_id,
+                borrower_type: parse_enum(&borrower_type).unwrap_or(BorrowerType::Student),
+                borrower_id: row.get(26)?,
+                borrower_name: row.get(27)?,
+                borrowed_date: borrowed_str.as_deref().map(parse_sqlite_date).transpose()?,
+                due_date: parse_sqlite_date(&due_str)?,
+                status: status.as_deref().and_then(parse_enum).unwrap_or(BorrowingStatus::Active),
+            })
+        }
+        None => None,
+    };
+
+    Ok(ScannedCopy { copy, book, active_borrowing })
+}
+
+struct CheckoutBorrower {
+    borrower_type: BorrowerType,
+    id: String,
+    name: String,
+}
+
+struct CheckoutBook {
+    book_id: String,
+    book_copy_id: Option<String>,
+    title: String,
+}
+
+// Students match on id or admission number, staff on id or staff number
+fn find_borrower(conn: &Connection, borrower_id: &str) -> Result<Option<CheckoutBorrower>> {
+    let student = conn.query_row(
+        "SELECT id, first_name || ' ' || last_name FROM students
+         WHERE (id = ?1 OR admission_number = ?1) AND deleted = 0",
+        [borrower_id],
+        |row| Ok(CheckoutBorrower {
+            borrower_type: BorrowerType::Student,
+            id: row.get(0)?,
+            name: row.get(1)?,
+        }),
+    ).optional()?;
+    if student.is_some() {
+        return Ok(student);
+    }
+
+    conn.query_row(
+        "SELECT id, first_name || ' ' || last_name FROM staff
+         WHERE (id = ?1 OR staff_id = ?1) AND deleted = 0",
+        [borrower_id],
+        |row| Ok(CheckoutBorrower {
+            borrower_type: BorrowerType::Staff,
+            id: row.get(0)?,
+            name: row.get(1)?,
+        }),
+    ).optional()
+}
+
+// A scanned copy code pins the copy; otherwise the code is a book code, ISBN or id
+fn find_book(conn: &Connection, code: &str) -> Result<Option<CheckoutBook>> {
+    let copy = conn.query_row(
+        "SELECT b.id, bc.id, b.title FROM book_copies bc
+         JOIN books b ON b.id = bc.book_id AND b.deleted = 0
+         WHERE (bc.tracking_code = ?1 OR bc.book_code = ?1 OR bc.id = ?1) AND bc.deleted = 0
+         LIMIT 1",
+        [code],
+        |row| Ok(CheckoutBook {
+            book_id: row.get(0)?,
+            book_copy_id: row.get(1)?,
+            title: row.get(2)?,
+        }),
+    ).optional()?;
+    if copy.is_some() {
+        return Ok(copy);
+    }
+
+    conn.query_row(
+        "SELECT id, title FROM books
+         WHERE (book_code = ?1 OR isbn = ?1 OR id = ?1) AND deleted = 0
+         LIMIT 1",
+        [code],
+        |row| Ok(CheckoutBook {
+            book_id: row.get(0)?,
+            book_copy_id: None,
+            title: row.get(1)?,
+        }),
+    ).optional()
+}
+
+impl DatabaseManager {
+    /// Look up a scanned copy by book_code, falling back to tracking_code, together
+    /// with its book and the loan it is currently out on. None when neither matches.
+    pub async fn get_book_copy_by_code(&self, code: &str) -> Result<Option<ScannedCopy>> {
+        let conn = self.lock_connection()?;
+        conn.query_row(&format!("{}{}", COPY_DETAILS_SELECT, SCAN_WHERE), [code.trim()], row_to_scanned_copy).optional()
+    }
+
+    /// Run the checks create_borrowing would apply to lending this book to this
+    /// borrower today, without writing anything. Every failing check is listed
+    /// so the desk can sort them all out before scanning again.
+    pub async fn validate_checkout(&self, borrower_id: &str, book_code: &str) -> Result<CheckoutVerdict> {
+        let conn = self.lock_connection()?;
+        let borrower_id = borrower_id.trim();
+        let book_code = book_code.trim();
+
+        let rules: LoanRules = read_setting(&conn, LOAN_RULES_KEY)?.unwrap_or_default();
+        let borrower = find_borrower(&conn, borrower_id)?;
+        let book = find_book(&conn, book_code)?;
+        let borrower_type = borrower.as_ref().map_or(BorrowerType::Student, |b| b.borrower_type.clone());
+        let due_date = rules.max_due_date(&borrower_type, Utc::now().date_naive());
+
+        let mut blockers = Vec::new();
+        if borrower.is_none() {
+            blockers.push(BorrowingError::BorrowerNotFound(borrower_id.to_string()));
+        }
+        if book.is_none() {
+            blockers.push(BorrowingError::BookNotFound(book_code.to_string()));
+        }
+        blockers.extend(checkout_blockers(
+            &conn,
+            &borrower_type,
+            borrower.as_ref().map(|b| b.id.as_str()),
+            book.as_ref().map(|b| b.book_id.as_str()),
+            book.as_ref().and_then(|b| b.book_copy_id.as_deref()),
+            &rules,
+            false,
+        )?);
+        let blocking_reasons: Vec<String> = blockers.iter().map(|blocker| blocker.to_string()).collect();
+
+        Ok(CheckoutVerdict {
+            allowed: blocking_reasons.is_empty(),
+            borrower_type: borrower.as_ref().map(|_| borrower_type),
+            borrower_id: borrower.as_ref().map(|b| b.id.clone()),
+            borrower_name: borrower.map(|b| b.name),
+            book_id: book.as_ref().map(|b| b.book_id.clone()),
+            book_copy_id: book.as_ref().and_then(|b| b.book_copy_id.clone()),
+            title: book.map(|b| b.title),
+            due_date,
+            blocking_reasons,
+        })
+    }
+
+    /// Lend a book to a student or staff member in one step: pick a free copy (or
+    /// the scanned copy, when `book` is a copy code), run the usual borrowing rules,
+    /// mark the copy borrowed and record the loan, all in one transaction so two
+    /// desks can't hand out the same copy. `loan_days` defaults to the borrower's
+    /// standard loan period.
+    pub async fn checkout_book(&self, book: &str, borrower_type: BorrowerType, borrower_id: &str, loan_days: Option<i64>, issued_by: Option<Uuid>) -> Result<BorrowingWithDetails, BorrowingError> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+        let book_ref = book.trim();
+        let borrower_ref = borrower_id.trim();
+
+        // Status, loan limits and fines are checked with the other borrowing rules below
+        let (student, staff) = match borrower_type {
+            BorrowerType::Student => {
+                let student = tx.query_row(
+                    &format!("SELECT {} FROM students WHERE (id = ?1 OR admission_number = ?1) AND deleted = 0", STUDENT_COLUMNS),
+                    [borrower_ref],
+                    row_to_student,
+                ).optional()?
+                    .ok_or_else(|| BorrowingError::BorrowerNotFound(borrower_ref.to_string()))?;
+                (Some(student), None)
+            }
+            BorrowerType::Staff => {
+                let staff = tx.query_row(
+                    &format!("SELECT {} FROM staff WHERE (id = ?1 OR staff_id = ?1) AND deleted = 0", STAFF_COLUMNS),
+                    [borrower_ref],
+                    row_to_staff,
+                ).optional()?
+                    .ok_or_else(|| BorrowingError::BorrowerNotFound(borrower_ref.to_string()))?;
+                (None, Some(staff))
+            }
+        };
+
+        let found = find_book(&tx, book_ref)?
+            .ok_or_else(|| BorrowingError::BookNotFound(book_ref.to_string()))?;
+        let copy_row = |row: &rusqlite::Row| -> Result<(String, String, Option<String>)> {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        };
+        // A scanned copy that isn't on the shelf is reported by the borrowing rules
+        let (copy_id, condition, tracking_code) = match found.book_copy_id {
+            Some(copy_id) => tx.query_row(
+                "SELECT id, COALESCE(condition, 'good'), tracking_code FROM book_copies WHERE id = ?1",
+                [&copy_id],
+                copy_row,
+            ).optional()?,
+            None => tx.query_row(
+                "SELECT id, COALESCE(condition, 'good'), tracking_code FROM book_copies
+                 WHERE book_id = ?1 AND deleted = 0 AND COALESCE(status, 'available') = 'available'
+                   AND COALESCE(condition, 'good') != 'lost'
+                 ORDER BY copy_number
+                 LIMIT 1",
+                [&found.book_id],
+                copy_row,
+            ).optional()?,
+        }.ok_or(BorrowingError::NoCopiesAvailable)?;
+
+        let parse_id = |id: &str| Uuid::parse_str(id).map_err(|_| constraint_error(format!("Invalid id {}", id)));
+        let rules: LoanRules = read_setting(&tx, LOAN_RULES_KEY)?.unwrap_or_default();
+        let today = Utc::now().date_naive();
+        let now = Utc::now();
+        let mut borrowing = Borrowing {
+            id: Uuid::new_v4(),
+            student_id: student.as_ref().map(|s| s.id),
+            book_id: Some(parse_id(&found.book_id)?),
+            borrowed_date: today,
+            due_date: today + chrono::Duration::days(loan_days.unwrap_or(rules.loan_period_for(&borrower_type)).max(1)),
+            returned_date: None,
+            status: BorrowingStatus::Active,
+            fine_amount: 0.0,
+            notes: None,
+            issued_by,
+            returned_by: None,
+            created_at: now,
+            updated_at: now,
+            fine_paid: false,
+            book_copy_id: Some(parse_id(&copy_id)?),
+            condition_at_issue: condition,
+            condition_at_return: None,
+            is_lost: false,
+            tracking_code,
+            return_notes: None,
+            copy_condition: None,
+            group_borrowing_id: None,
+            borrower_type,
+            staff_id: staff.as_ref().map(|s| s.id),
+            due_date_override: false,
+        };
+        apply_borrowing_rules(&tx, &mut borrowing, false, None)?;
+        store_borrowing(&tx, &borrowing)?;
+
+        // Read back after the write so the copy and book show the new status and counts
+        let copy = tx.query_row(
+            &format!("{} WHERE bc.id = ?1", COPY_DETAILS_SELECT),
+            [&copy_id],
+            row_to_scanned_copy,
+        )?;
+        tx.commit()?;
+
+        Ok(BorrowingWithDetails {
+            borrowing,
+            book: copy.book,
+            student,
+            staff,
+            book_copy: Some(copy.copy),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    async fn reasons(db: &DatabaseManager, borrower: &str, book: &str) -> Vec<String> {
+        let verdict = db.validate_checkout(borrower, book).await.unwrap();
+        assert_eq!(verdict.allowed, verdict.blocking_reasons.is_empty());
+        verdict.blocking_reasons
+    }
+
+    #[tokio::test]
+    async fn a_clean_checkout_passes_validation_and_goes_through() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "CLEAN", 1);
+        let student_id = seed_student(&db, "ADM-1");
+
+        let verdict = db.validate_checkout("ADM-1", "CLEAN-1").await.unwrap();
+        assert!(verdict.allowed, "{:?}", verdict.blocking_reasons);
+        assert_eq!(verdict.borrower_id.as_deref(), Some(student_id.as_str()));
+
+        let details = db.checkout_book("CLEAN-1", BorrowerType::Student, "ADM-1", None, None).await.unwrap();
+        assert_eq!(details.borrowing.book_id.map(|id| id.to_string()), Some(book_id.clone()));
+        assert_eq!(book_counts(&db, &book_id), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn an_unknown_borrower_or_book_is_blocked() {
+        let db = test_db();
+        seed_book(&db, "KNOWN", 1);
+        seed_student(&db, "ADM-1");
+
+        assert_eq!(reasons(&db, "ADM-404", "KNOWN").await, ["No student or staff member matches ADM-404"]);
+        assert_eq!(reasons(&db, "ADM-1", "NOPE").await, ["No book or copy matches NOPE"]);
+        let err = db.checkout_book("NOPE", BorrowerType::Student, "ADM-1", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::BookNotFound(code) if code == "NOPE"));
+        let err = db.checkout_book("KNOWN", BorrowerType::Student, "ADM-404", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::BorrowerNotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn an_inactive_borrower_is_blocked() {
+        let db = test_db();
+        seed_book(&db, "INACT", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        execute(&db, "UPDATE students SET status = 'suspended' WHERE id = ?1", [&student_id]);
+
+        assert_eq!(reasons(&db, "ADM-1", "INACT").await, ["Borrowing blocked: Test ADM-1 is suspended"]);
+        let err = db.checkout_book("INACT", BorrowerType::Student, "ADM-1", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::BorrowerInactive { status, .. } if status == "suspended"));
+    }
+
+    #[tokio::test]
+    async fn a_student_owing_too_much_is_blocked() {
+        let db = test_db();
+        seed_book(&db, "OWES", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_fine(&db, &student_id, 150.0);
+        db.set_borrow_block_threshold(Some(100.0)).await.unwrap();
+
+        assert_eq!(
+            reasons(&db, "ADM-1", "OWES").await,
+            ["Borrowing blocked: outstanding fines of 150.00 exceed the limit of 100.00"]
+        );
+        let err = db.checkout_book("OWES", BorrowerType::Student, "ADM-1", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::FineThresholdExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn a_student_at_their_class_allowance_is_blocked() {
+        let db = test_db();
+        execute(&db, "INSERT INTO classes (id, class_name, form_level, max_books_allowed) VALUES ('c1', 'Form 1', 1, 1)", []);
+        let (book_id, copies) = seed_book(&db, "CAP", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+
+        assert_eq!(reasons(&db, "ADM-1", "CAP").await, ["Borrowing blocked: 1 books already on loan, the limit is 1"]);
+        let err = db.checkout_book("CAP", BorrowerType::Student, "ADM-1", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::LoanLimitReached { active: 1, limit: 1 }));
+
+        // Raising the class allowance lets the same student through
+        execute(&db, "UPDATE classes SET max_books_allowed = 2 WHERE id = 'c1'", []);
+        assert!(reasons(&db, "ADM-1", "CAP").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn staff_at_their_loan_limit_are_blocked() {
+        let db = test_db();
+        db.set_loan_rules(&LoanRules { staff_max_active_loans: 1, ..LoanRules::default() }).await.unwrap();
+        seed_book(&db, "STAFF", 2);
+        seed_staff(&db, "T-01");
+
+        db.checkout_book("STAFF", BorrowerType::Staff, "T-01", None, None).await.unwrap();
+        assert_eq!(reasons(&db, "T-01", "STAFF").await, ["Borrowing blocked: 1 books already on loan, the limit is 1"]);
+        let err = db.checkout_book("STAFF", BorrowerType::Staff, "T-01", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::LoanLimitReached { active: 1, limit: 1 }));
+    }
+
+    #[tokio::test]
+    async fn a_scanned_copy_that_is_out_or_lost_is_blocked() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "SCAN", 3);
+        let holder = seed_student(&db, "ADM-1");
+        seed_student(&db, "ADM-2");
+        seed_loan(&db, &holder, &book_id, &copies[0], 7);
+        execute(&db, "UPDATE book_copies SET status = 'lost' WHERE id = ?1", [&copies[1]]);
+
+        // The book still has a copy on the shelf, but not the one scanned
+        assert_eq!(reasons(&db, "ADM-2", "SCAN-1").await, ["Copy SCAN-1 is borrowed, not on the shelf"]);
+        assert_eq!(reasons(&db, "ADM-2", "SCAN-2").await, ["Copy SCAN-2 is lost, not on the shelf"]);
+        let err = db.checkout_book("SCAN-1", BorrowerType::Student, "ADM-2", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::CopyUnavailable { copy, status } if copy == "SCAN-1" && status == "borrowed"));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM borrowings", []), 1);
+
+        assert!(reasons(&db, "ADM-2", "SCAN-3").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_book_with_no_copies_left_is_blocked() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "GONE", 1);
+        let holder = seed_student(&db, "ADM-1");
+        seed_student(&db, "ADM-2");
+        seed_loan(&db, &holder, &book_id, &copies[0], 7);
+
+        assert_eq!(reasons(&db, "ADM-2", "GONE").await, ["No copies available"]);
+        let err = db.checkout_book("GONE", BorrowerType::Student, "ADM-2", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::NoCopiesAvailable));
+    }
+
+    #[tokio::test]
+    async fn every_blocking_reason_is_listed_at_once() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "MANY", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let other = seed_student(&db, "ADM-2");
+        seed_loan(&db, &other, &book_id, &copies[0], 7);
+        execute(&db, "UPDATE students SET status = 'graduated' WHERE id = ?1", [&student_id]);
+        seed_fine(&db, &student_id, 50.0);
+        db.set_borrow_block_threshold(Some(10.0)).await.unwrap();
+
+        assert_eq!(reasons(&db, "ADM-1", "MANY").await.len(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_checkouts_of_the_last_copy_lend_it_once() {
+        let db = std::sync::Arc::new(test_db());
+        let (book_id, _) = seed_book(&db, "LAST", 1);
+        seed_student(&db, "ADM-1");
+        seed_student(&db, "ADM-2");
+
+        let checkouts = ["ADM-1", "ADM-2"].map(|borrower| {
+            let db = db.clone();
+            tokio::spawn(async move { db.checkout_book("LAST", BorrowerType::Student, borrower, None, None).await })
+        });
+        let mut outcomes = Vec::new();
+        for checkout in checkouts {
+            outcomes.push(checkout.await.unwrap());
+        }
+
+        assert_eq!(outcomes.iter().filter(|outcome| outcome.is_ok()).count(), 1);
+        assert!(outcomes.iter().any(|outcome| matches!(outcome, Err(BorrowingError::NoCopiesAvailable))));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM borrowings", []), 1);
+        assert_eq!(book_counts(&db, &book_id), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn staff_check_out_on_the_staff_loan_period_and_return() {
+        let db = test_db();
+        db.set_loan_rules(&LoanRules { staff_loan_period_days: 45, staff_max_active_loans: 1, ..LoanRules::default() }).await.unwrap();
+        let (book_id, copies) = seed_book(&db, "TEACH", 1);
+        let staff_id = seed_staff(&db, "T-01");
+
+        let details = db.checkout_book("TEACH", BorrowerType::Staff, "T-01", None, None).await.unwrap();
+        let loan = &details.borrowing;
+        assert_eq!(loan.borrower_type, BorrowerType::Staff);
+        assert_eq!((loan.student_id, loan.staff_id.map(|id| id.to_string())), (None, Some(staff_id.clone())));
+        assert_eq!(loan.due_date, days_from_today(45));
+        assert!(details.student.is_none());
+        assert_eq!(details.staff.as_ref().map(|staff| staff.staff_id.as_str()), Some("T-01"));
+        assert_eq!(book_counts(&db, &book_id), (1, 0));
+
+        let listed = db.get_borrowings_with_details().await.unwrap();
+        assert_eq!(listed[0]["borrower_type"], "staff");
+        assert_eq!(listed[0]["staff"]["staff_id"], "T-01");
+        assert!(listed[0]["student"].is_null());
+
+        let loan_id = loan.id.to_string();
+        let returned = db.return_book(&loan_id, ReturnData::default(), None).await.unwrap();
+        assert!(returned.fines.is_empty());
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [&loan_id]), "returned");
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM book_copies WHERE id = ?1", [&copies[0]]), "available");
+        assert_eq!(book_counts(&db, &book_id), (1, 1));
+
+        // The return frees the staff member's only loan slot
+        db.checkout_book("TEACH", BorrowerType::Staff, "T-01", None, None).await.unwrap();
+    }
+}
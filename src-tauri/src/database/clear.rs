@@ -0,0 +1,100 @@
+use super::{constraint_error, DatabaseManager};
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+/// Local tables that can be emptied for a fresh pull, ordered so that every
+/// table comes before the ones it references. Clearing in this order never
+/// trips the book_copies -> books foreign key.
+pub const CLEARABLE_TABLES: &[&str] = &[
+    "reservations", "escalation_actions", "theft_reports", "fines", "borrowings", "borrowings_archive",
+    "group_borrowings", "book_tags", "book_copies", "books", "students", "staff", "categories", "classes",
+    "fine_settings",
+];
+
+// (table, column, referenced table) for the ids that point across tables. Only
+// book_copies.book_id is an enforced foreign key; the rest are left dangling.
+const TABLE_REFERENCES: &[(&str, &str, &str)] = &[
+    ("book_copies", "book_id", "books"),
+    ("borrowings", "book_id", "books"),
+    ("borrowings", "book_copy_id", "book_copies"),
+    ("borrowings", "student_id", "students"),
+    ("borrowings", "staff_id", "staff"),
+    ("fines", "borrowing_id", "borrowings"),
+    ("fines", "student_id", "students"),
+    ("reservations", "book_id", "books"),
+    ("reservations", "student_id", "students"),
+    ("group_borrowings", "book_id", "books"),
+    ("theft_reports", "borrowing_id", "borrowings"),
+    ("students", "class_id", "classes"),
+    ("books", "category_id", "categories"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClearedTable {
+    pub table: String,
+    pub rows: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClearTablesResult {
+    pub cleared: Vec<ClearedTable>,
+    pub warnings: Vec<String>,
+}
+
+// Rows in `table` whose `column` points at an existing row of `referenced`
+fn count_references(conn: &Connection, table: &str, column: &str, referenced: &str) -> Result<i64> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM {0} WHERE {1} IS NOT NULL AND {1} IN (SELECT id FROM {2})", table, column, referenced),
+        [],
+        |row| row.get(0),
+    )
+}
+
+impl DatabaseManager {
+    /// Empty the named tables, children first, and drop their queued pushes.
+    /// Names outside CLEARABLE_TABLES are refused before anything is touched.
+    /// Rows left behind in other tables that point into a cleared one are
+    /// reported as warnings; copies of a cleared book block the whole call.
+    pub async fn clear_tables(&self, tables: &[String]) -> Result<ClearTablesResult> {
+        if let Some(unknown) = tables.iter().find(|t| !CLEARABLE_TABLES.contains(&t.as_str())) {
+            return Err(constraint_error(format!(
+                "{} cannot be cleared; expected one of {}", unknown, CLEARABLE_TABLES.join(", ")
+            )));
+        }
+        // Only names from the allowlist reach the SQL below
+        let selected: Vec<&str> = CLEARABLE_TABLES.iter()
+            .copied()
+            .filter(|table| tables.iter().any(|t| t == table))
+            .collect();
+
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut warnings = Vec::new();
+        for &(table, column, referenced) in TABLE_REFERENCES {
+            if !selected.contains(&referenced) || selected.contains(&table) {
+                continue;
+            }
+            let count = count_references(&tx, table, column, referenced)?;
+            if count == 0 {
+                continue;
+            }
+            if table == "book_copies" {
+                return Err(constraint_error(format!(
+                    "{} book copies still belong to books; clear book_copies along with books", count
+                )));
+            }
+            warnings.push(format!("{} {} rows still reference cleared {} through {}", count, table, referenced, column));
+        }
+
+        let mut cleared = Vec::with_capacity(selected.len());
+        for table in selected {
+            let rows = tx.execute(&format!("DELETE FROM {}", table), [])?;
+            tx.execute("DELETE FROM sync_queue WHERE table_name = ?1", [table])?;
+            cleared.push(ClearedTable { table: table.to_string(), rows });
+        }
+
+        tx.commit()?;
+        Ok(ClearTablesResult { cleared, warnings })
+    }
+}
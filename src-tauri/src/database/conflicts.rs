@@ -0,0 +1,63 @@
+use super::settings::{read_setting, CONFLICT_LOG_RETENTION_DAYS_KEY};
+use super::DatabaseManager;
+use rusqlite::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+pub const DEFAULT_CONFLICT_LOG_RETENTION_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncConflictEntry {
+    pub id: String,
+    pub table_name: String,
+    pub record_id: String,
+    pub local_data: Value,
+    pub remote_data: Value,
+    pub winner: String,
+    pub resolved_at: String,
+}
+
+fn parse_json(text: String) -> Value {
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+impl DatabaseManager {
+    /// Conflicts resolved during sync, newest first, with both sides of the record
+    /// as they were so an overwritten value can be copied back by hand
+    pub async fn get_conflict_log(&self, table_name: Option<&str>, record_id: Option<&str>, limit: i64) -> Result<Vec<SyncConflictEntry>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, table_name, record_id, local_data, remote_data,
+                    COALESCE(winner, resolution_strategy, 'unknown'), COALESCE(resolved_at, created_at)
+             FROM sync_conflicts
+             WHERE (?1 IS NULL OR table_name = ?1)
+               AND (?2 IS NULL OR record_id = ?2)
+             ORDER BY COALESCE(resolved_at, created_at) DESC
+             LIMIT ?3"
+        )?;
+
+        let entries = stmt.query_map((table_name, record_id, limit), |row| {
+            Ok(SyncConflictEntry {
+                id: row.get(0)?,
+                table_name: row.get(1)?,
+                record_id: row.get(2)?,
+                local_data: parse_json(row.get(3)?),
+                remote_data: parse_json(row.get(4)?),
+                winner: row.get(5)?,
+                resolved_at: row.get(6)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Drop conflict log entries older than the configured retention
+    pub async fn prune_conflict_log(&self) -> Result<usize> {
+        let conn = self.lock_connection()?;
+        let days: i64 = read_setting(&conn, CONFLICT_LOG_RETENTION_DAYS_KEY)?.unwrap_or(DEFAULT_CONFLICT_LOG_RETENTION_DAYS);
+        conn.execute(
+            "DELETE FROM sync_conflicts WHERE datetime(COALESCE(resolved_at, created_at)) < datetime('now', '-' || ?1 || ' days')",
+            [days],
+        )
+    }
+}
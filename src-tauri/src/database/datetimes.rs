@@ -0,0 +1,296 @@
+use super::DatabaseManager;
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+/// Timestamps are stored as UTC RFC3339 with millisecond precision
+/// (2024-03-01T09:15:00.000Z), so plain string comparison orders them correctly
+pub const CANONICAL_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%fZ";
+const CANONICAL_TIMESTAMP_GLOB: &str =
+    "[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9].[0-9][0-9][0-9]Z";
+
+// Timestamp columns that don't follow the *_at naming
+const EXTRA_TIMESTAMP_COLUMNS: &[&str] = &["last_activity", "offline_expiry", "last_sync"];
+
+// Tables whose update trigger stamps updated_at
+const STAMPED_TABLES: &[&str] = &["categories", "books", "book_copies", "students", "borrowings"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NormalizedColumn {
+    pub table_name: String,
+    pub column_name: String,
+    pub rows_normalized: i64,
+    pub unparseable: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatetimeNormalizationReport {
+    pub canonical_format: &'static str,
+    pub columns: Vec<NormalizedColumn>,
+    pub total_normalized: i64,
+    pub total_unparseable: i64,
+}
+
+fn is_timestamp_column(name: &str) -> bool {
+    name.ends_with("_at") || EXTRA_TIMESTAMP_COLUMNS.contains(&name)
+}
+
+// Every table with at least one timestamp column, with those columns
+fn timestamp_columns(conn: &Connection) -> Result<Vec<(String, Vec<String>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    )?;
+    let tables = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+
+    let mut column_stmt = conn.prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?;
+    let mut result = Vec::new();
+    for table in tables {
+        let columns: Vec<String> = column_stmt.query_map([&table], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|name| is_timestamp_column(name))
+            .collect();
+        if !columns.is_empty() {
+            result.push((table, columns));
+        }
+    }
+    Ok(result)
+}
+
+// Only stamp when the update didn't set the timestamps itself
+fn stamp_trigger_sql(table: &str) -> String {
+    format!(
+        "DROP TRIGGER IF EXISTS update_{table}_timestamp;
+         CREATE TRIGGER update_{table}_timestamp
+             AFTER UPDATE ON {table}
+             WHEN NEW.updated_at IS OLD.updated_at AND NEW.created_at IS OLD.created_at
+             BEGIN
+                 UPDATE {table} SET updated_at = strftime('{format}', 'now') WHERE id = NEW.id;
+             END;\n",
+        table = table,
+        format = CANONICAL_TIMESTAMP_FORMAT,
+    )
+}
+
+// strftime understands both RFC3339 and datetime('now') text; anything it
+// can't parse is left as it was
+fn canonical_expr(column: &str) -> String {
+    format!("COALESCE(strftime('{}', \"{}\"), \"{}\")", CANONICAL_TIMESTAMP_FORMAT, column, column)
+}
+
+/// One-off cleanup for databases written before timestamps were canonical:
+/// swap the updated_at stamping triggers for the canonical ones (schema.sql
+/// only creates them when missing), drop the per-table normalize triggers
+/// earlier builds installed, and rewrite the stored values once.
+pub(super) fn canonicalize_stored_timestamps(conn: &Connection) -> Result<()> {
+    let mut sql = String::new();
+
+    for table in STAMPED_TABLES {
+        sql.push_str(&stamp_trigger_sql(table));
+    }
+
+    let stale_triggers = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'trigger' AND name LIKE 'normalize\\_%\\_timestamps\\_%' ESCAPE '\\'")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for trigger in stale_triggers {
+        sql.push_str(&format!("DROP TRIGGER IF EXISTS \"{}\";\n", trigger));
+    }
+
+    conn.execute_batch(&sql)?;
+    normalize_timestamps(conn)?;
+    Ok(())
+}
+
+/// Rewrite every stored timestamp into the canonical RFC3339 form. Values
+/// SQLite can't read as a date are counted and left untouched.
+pub(super) fn normalize_timestamps(conn: &Connection) -> Result<DatetimeNormalizationReport> {
+    let mut columns = Vec::new();
+    for (table, table_columns) in timestamp_columns(conn)? {
+        let mut needs_update = false;
+        for column in &table_columns {
+            let (rows_normalized, unparseable): (i64, i64) = conn.query_row(
+                &format!(
+                    "SELECT COALESCE(SUM(strftime('{format}', \"{column}\") IS NOT NULL), 0),
+                            COALESCE(SUM(strftime('{format}', \"{column}\") IS NULL), 0)
+                     FROM \"{table}\"
+                     WHERE \"{column}\" IS NOT NULL AND \"{column}\" NOT GLOB '{glob}'",
+                    format = CANONICAL_TIMESTAMP_FORMAT,
+                    glob = CANONICAL_TIMESTAMP_GLOB,
+                ),
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            needs_update |= rows_normalized > 0;
+            columns.push(NormalizedColumn {
+                table_name: table.clone(),
+                column_name: column.clone(),
+                rows_normalized,
+                unparseable,
+            });
+        }
+
+        if needs_update {
+            let assignments = table_columns.iter()
+                .map(|c| format!("\"{}\" = {}", c, canonical_expr(c)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let stale = table_columns.iter()
+                .map(|c| format!("\"{}\" NOT GLOB '{}'", c, CANONICAL_TIMESTAMP_GLOB))
+                .collect::<Vec<_>>()
+                .join(" OR ");
+
+            // Rewriting a timestamp isn't an edit, so the stamping trigger is
+            // set aside while the stale rows are rewritten
+            let stamped = STAMPED_TABLES.contains(&table.as_str())
+                && conn.query_row(
+                    "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'trigger' AND name = ?1",
+                    [format!("update_{}_timestamp", table)],
+                    |row| row.get::<_, bool>(0),
+                )?;
+            if stamped {
+                conn.execute_batch(&format!("DROP TRIGGER update_{}_timestamp;", table))?;
+            }
+            conn.execute(&format!("UPDATE \"{}\" SET {} WHERE {}", table, assignments, stale), [])?;
+            if stamped {
+                conn.execute_batch(&stamp_trigger_sql(&table))?;
+            }
+        }
+    }
+
+    let total_normalized = columns.iter().map(|c| c.rows_normalized).sum();
+    let total_unparseable = columns.iter().map(|c| c.unparseable).sum();
+    Ok(DatetimeNormalizationReport {
+        canonical_format: CANONICAL_TIMESTAMP_FORMAT,
+        columns,
+        total_normalized,
+        total_unparseable,
+    })
+}
+
+impl DatabaseManager {
+    /// Rewrite any timestamps that aren't canonical yet, e.g. rows restored
+    /// from an old backup or written by an older build.
+    pub async fn normalize_datetimes(&self) -> Result<DatetimeNormalizationReport> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+        let report = normalize_timestamps(&tx)?;
+        tx.commit()?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrations::run_migrations;
+    use crate::database::test_support::*;
+
+    fn canonical(db: &DatabaseManager, value: &str) -> bool {
+        scalar(db, "SELECT ?1 GLOB ?2", [value, CANONICAL_TIMESTAMP_GLOB])
+    }
+
+    #[tokio::test]
+    async fn new_rows_are_written_canonical() {
+        let db = test_db();
+        let category_id = seed_category(&db, "Fiction");
+        let created: String = scalar(&db, "SELECT created_at FROM categories WHERE id = ?1", [&category_id]);
+        assert!(canonical(&db, &created), "{}", created);
+
+        execute(&db, "UPDATE categories SET name = 'Novels' WHERE id = ?1", [&category_id]);
+        let updated: String = scalar(&db, "SELECT updated_at FROM categories WHERE id = ?1", [&category_id]);
+        assert!(canonical(&db, &updated), "{}", updated);
+        assert!(updated >= created);
+    }
+
+    #[tokio::test]
+    async fn mixed_formats_normalize_and_sort_in_time_order() {
+        let db = test_db();
+        for (name, created_at) in [
+            ("third", "2024-03-01 10:30:00"),
+            ("first", "2024-03-01T08:00:00Z"),
+            ("second", "2024-03-01T09:15:00.250+00:00"),
+            ("fourth", "2024-03-01T11:00:00.000Z"),
+            ("broken", "last tuesday"),
+        ] {
+            execute(&db, "INSERT INTO categories (id, name, created_at) VALUES (?1, ?1, ?2)", [name, created_at]);
+        }
+
+        let report = db.normalize_datetimes().await.unwrap();
+        let created = report.columns.iter()
+            .find(|c| c.table_name == "categories" && c.column_name == "created_at")
+            .unwrap();
+        assert_eq!((created.rows_normalized, created.unparseable), (3, 1));
+
+        let conn = db.get_connection().lock().unwrap();
+        let ordered = conn
+            .prepare("SELECT name, created_at FROM categories WHERE name != 'broken' ORDER BY created_at")
+            .unwrap()
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        drop(conn);
+        assert_eq!(
+            ordered,
+            [
+                ("first".to_string(), "2024-03-01T08:00:00.000Z".to_string()),
+                ("second".to_string(), "2024-03-01T09:15:00.250Z".to_string()),
+                ("third".to_string(), "2024-03-01T10:30:00.000Z".to_string()),
+                ("fourth".to_string(), "2024-03-01T11:00:00.000Z".to_string()),
+            ]
+        );
+        assert_eq!(scalar::<String, _>(&db, "SELECT created_at FROM categories WHERE name = 'broken'", []), "last tuesday");
+
+        // Already canonical, so a second run has nothing to do
+        let rerun = db.normalize_datetimes().await.unwrap();
+        assert_eq!((rerun.total_normalized, rerun.total_unparseable), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn migration_normalizes_a_legacy_database_once() {
+        let db = test_db();
+        let book_id = seed_book(&db, "OLD", 1).0;
+        {
+            let conn = db.get_connection().lock().unwrap();
+            conn.execute_batch(&format!(
+                "UPDATE books SET created_at = '2023-09-01 07:45:00', updated_at = '2023-09-02 16:20:05' WHERE id = '{book_id}';
+                 CREATE TRIGGER normalize_books_timestamps_insert AFTER INSERT ON books BEGIN SELECT 1; END;
+                 PRAGMA user_version = 4;"
+            )).unwrap();
+            run_migrations(&conn).unwrap();
+        }
+
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT created_at || ' ' || updated_at FROM books WHERE id = ?1", [&book_id]),
+            "2023-09-01T07:45:00.000Z 2023-09-02T16:20:05.000Z"
+        );
+        assert_eq!(
+            scalar::<i64, _>(&db, "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger' AND name LIKE 'normalize%'", []),
+            0
+        );
+        assert_eq!(scalar::<i64, _>(&db, "PRAGMA user_version", []), 5);
+    }
+
+    #[tokio::test]
+    async fn normalizing_leaves_canonical_rows_and_updated_at_alone() {
+        let db = test_db();
+        execute(
+            &db,
+            "INSERT INTO categories (id, name, created_at, updated_at) VALUES
+                 ('tidy', 'Tidy', '2024-01-05T10:00:00.000Z', '2024-01-06T10:00:00.000Z'),
+                 ('legacy', 'Legacy', '2024-01-05 10:00:00', '2024-01-06T10:00:00.000Z')",
+            [],
+        );
+
+        let report = db.normalize_datetimes().await.unwrap();
+        assert_eq!(report.total_normalized, 1);
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT group_concat(id || ' ' || created_at || ' ' || updated_at, ', ') FROM (SELECT * FROM categories ORDER BY id)", []),
+            "legacy 2024-01-05T10:00:00.000Z 2024-01-06T10:00:00.000Z, tidy 2024-01-05T10:00:00.000Z 2024-01-06T10:00:00.000Z"
+        );
+
+        // The stamping trigger is back in place for real edits
+        execute(&db, "UPDATE categories SET name = 'Renamed' WHERE id = 'tidy'", []);
+        assert!(scalar::<String, _>(&db, "SELECT updated_at FROM categories WHERE id = 'tidy'", []) > "2024-01-06T10:00:00.000Z".to_string());
+    }
+}
@@ -0,0 +1,351 @@
+use super::hashing::row_as_json;
+use super::DatabaseManager;
+use crate::sync::normalize::normalize_for_supabase;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0} cannot be exported for Supabase")]
+    UnknownTable(String),
+}
+
+const BOOKS_CSV_QUERY: &str = "
+    SELECT b.id, b.book_code, b.title, b.author, b.isbn, b.genre, b.publisher, b.publication_year,
+           c.name as category, b.total_copies, b.available_copies, b.shelf_location, b.status,
+           b.condition, b.acquisition_year, b.description, b.created_at
+    FROM books b
+    LEFT JOIN categories c ON b.category_id = c.id
+    WHERE b.deleted = 0
+    ORDER BY b.title";
+
+const STUDENTS_CSV_QUERY: &str = "
+    SELECT admission_number, first_name, last_name, class_grade, email, phone, status,
+           enrollment_date, academic_year, created_at
+    FROM students
+    WHERE deleted = 0
+    ORDER BY admission_number";
+
+const BORROWINGS_CSV_QUERY: &str = "
+    SELECT b.id, COALESCE(b.borrower_type, 'student') as borrower_type,
+           COALESCE(s.admission_number, st.staff_id) as borrower_number,
+           COALESCE(s.first_name || ' ' || s.last_name, st.first_name || ' ' || st.last_name) as borrower_name,
+           bk.title as book_title, bk.book_code, bc.copy_number, b.borrowed_date, b.due_date,
+           b.returned_date, b.status, b.fine_amount, b.notes
+    FROM borrowings b
+    LEFT JOIN students s ON b.student_id = s.id
+    LEFT JOIN staff st ON b.staff_id = st.id
+    LEFT JOIN books bk ON b.book_id = bk.id
+    LEFT JOIN book_copies bc ON b.book_copy_id = bc.id
+    WHERE b.deleted = 0
+    ORDER BY b.borrowed_date DESC";
+
+fn csv_cell(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned(),
+    }
+}
+
+/// Tables that exist on the Supabase side
+pub const SUPABASE_EXPORT_TABLES: &[&str] = &[
+    "categories", "books", "book_copies", "classes", "students", "staff",
+    "borrowings", "group_borrowings", "fines", "fine_settings", "theft_reports",
+];
+
+// Streams query rows straight into the file with the column names as the header row
+fn write_query_csv(conn: &Connection, sql: &str, path: &Path) -> Result<usize, ExportError> {
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut stmt = conn.prepare(sql)?;
+    let column_count = stmt.column_count();
+    writer.write_record(stmt.column_names())?;
+
+    let mut rows = stmt.query([])?;
+    let mut written = 0;
+    while let Some(row) = rows.next()? {
+        let mut record = Vec::with_capacity(column_count);
+        for i in 0..column_count {
+            record.push(csv_cell(row.get_ref(i)?));
+        }
+        writer.write_record(&record)?;
+        written += 1;
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+impl DatabaseManager {
+    pub async fn export_books_csv(&self, path: &Path) -> Result<usize, ExportError> {
+        let conn = self.lock_connection()?;
+        write_query_csv(&conn, BOOKS_CSV_QUERY, path)
+    }
+
+    pub async fn export_students_csv(&self, path: &Path) -> Result<usize, ExportError> {
+        let conn = self.lock_connection()?;
+        write_query_csv(&conn, STUDENTS_CSV_QUERY, path)
+    }
+
+    /// Borrowings with the borrower and book names joined in
+    pub async fn export_borrowings_csv(&self, path: &Path) -> Result<usize, ExportError> {
+        let conn = self.lock_connection()?;
+        write_query_csv(&conn, BORROWINGS_CSV_QUERY, path)
+    }
+
+    /// Write a table as a JSON array ready for Supabase's bulk import, for schools
+    /// uploading by hand when push sync isn't set up. Soft-deleted rows are left out.
+    pub async fn export_for_supabase(&self, table: &str, path: &Path) -> Result<usize, ExportError> {
+        if !SUPABASE_EXPORT_TABLES.contains(&table) {
+            return Err(ExportError::UnknownTable(table.to_string()));
+        }
+
+        let conn = self.lock_connection()?;
+        let has_deleted: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = 'deleted'",
+            [table],
+            |row| row.get(0),
+        )?;
+        let sql = if has_deleted {
+            format!("SELECT * FROM {} WHERE deleted = 0 ORDER BY rowid", table)
+        } else {
+            format!("SELECT * FROM {} ORDER BY rowid", table)
+        };
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], row_as_json)?.collect::<Result<Vec<_>, _>>()?;
+        let rows: Vec<Value> = rows.into_iter()
+            .map(|row| match row {
+                Value::Object(mut fields) => {
+                    normalize_for_supabase(&mut fields);
+                    Value::Object(fields)
+                }
+                other => other,
+            })
+            .collect();
+
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &rows)?;
+        Ok(rows.len())
+    }
+}
+
+// Local bookkeeping columns that differ between installs without the data changing
+const IGNORED_DIFF_FIELDS: &[&str] = &["synced", "sync_version"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableDiff {
+    pub table_name: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportDiff {
+    pub tables: Vec<TableDiff>,
+    pub total_added: usize,
+    pub total_removed: usize,
+    pub total_changed: usize,
+    pub identical: bool,
+}
+
+fn read_export(path: &Path) -> anyhow::Result<BTreeMap<String, BTreeMap<String, Value>>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("Cannot open {}: {}", path.display(), e))?;
+    let document: Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("{} is not valid JSON: {}", path.display(), e))?;
+
+    // Exports are either { table: [rows] } or wrapped as { tables: { table: [rows] } }
+    let tables = match document.get("tables") {
+        Some(Value::Object(tables)) => tables.clone(),
+        _ => match document {
+            Value::Object(tables) => tables,
+            _ => anyhow::bail!("{} is not a table export", path.display()),
+        },
+    };
+
+    let mut export = BTreeMap::new();
+    for (table_name, rows) in tables {
+        // Skip export metadata such as exported_at or version
+        let Value::Array(rows) = rows else { continue };
+
+        let mut by_id = BTreeMap::new();
+        for mut row in rows {
+            if let Value::Object(fields) = &mut row {
+                for field in IGNORED_DIFF_FIELDS {
+                    fields.remove(*field);
+                }
+            }
+            by_id.insert(row_key(&row), row);
+        }
+        export.insert(table_name, by_id);
+    }
+
+    Ok(export)
+}
+
+// Rows are matched on id; rows without one are keyed by their full contents
+fn row_key(row: &Value) -> String {
+    match row.get("id") {
+        Some(Value::String(id)) => id.clone(),
+        Some(Value::Null) | None => row.to_string(),
+        Some(id) => id.to_string(),
+    }
+}
+
+/// Per-table added/removed/changed row ids going from export A to export B
+pub fn diff_exports(path_a: &Path, path_b: &Path) -> anyhow::Result<ExportDiff> {
+    let mut before = read_export(path_a)?;
+    let mut after = read_export(path_b)?;
+
+    let table_names: BTreeSet<String> = before.keys().chain(after.keys()).cloned().collect();
+    let mut tables = Vec::with_capacity(table_names.len());
+
+    for table_name in table_names {
+        let old_rows = before.remove(&table_name).unwrap_or_default();
+        let new_rows = after.remove(&table_name).unwrap_or_default();
+
+        let mut diff = TableDiff {
+            table_name,
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            unchanged_count: 0,
+        };
+
+        for (id, old_row) in &old_rows {
+            match new_rows.get(id) {
+                None => diff.removed.push(id.clone()),
+                Some(new_row) if new_row != old_row => diff.changed.push(id.clone()),
+                Some(_) => diff.unchanged_count += 1,
+            }
+        }
+        diff.added = new_rows.keys()
+            .filter(|id| !old_rows.contains_key(*id))
+            .cloned()
+            .collect();
+
+        tables.push(diff);
+    }
+
+    let total_added = tables.iter().map(|t| t.added.len()).sum();
+    let total_removed = tables.iter().map(|t| t.removed.len()).sum();
+    let total_changed = tables.iter().map(|t| t.changed.len()).sum();
+
+    Ok(ExportDiff {
+        tables,
+        total_added,
+        total_removed,
+        total_changed,
+        identical: total_added == 0 && total_removed == 0 && total_changed == 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+    use serde_json::json;
+
+    fn write_json(dir: &Path, name: &str, value: &Value) -> std::path::PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, serde_json::to_string(value).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_rows() {
+        let dir = std::env::temp_dir().join(format!("export-diff-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let before = write_json(&dir, "before.json", &json!({
+            "exported_at": "2026-03-01T00:00:00Z",
+            "books": [
+                { "id": "a", "title": "Same", "synced": 0 },
+                { "id": "b", "title": "Old title" },
+                { "id": "d", "title": "Gone" }
+            ]
+        }));
+        // The second export uses the wrapped layout and only differs from the first in sync bookkeeping for "a"
+        let after = write_json(&dir, "after.json", &json!({
+            "tables": {
+                "books": [
+                    { "id": "a", "title": "Same", "synced": 1, "sync_version": 4 },
+                    { "id": "b", "title": "New title" },
+                    { "id": "c", "title": "New book" }
+                ],
+                "students": [{ "id": "s1" }]
+            }
+        }));
+
+        let diff = diff_exports(&before, &after).unwrap();
+        assert!(!diff.identical);
+        assert_eq!((diff.total_added, diff.total_removed, diff.total_changed), (2, 1, 1));
+
+        let books = diff.tables.iter().find(|t| t.table_name == "books").unwrap();
+        assert_eq!(books.added, ["c"]);
+        assert_eq!(books.removed, ["d"]);
+        assert_eq!(books.changed, ["b"]);
+        assert_eq!(books.unchanged_count, 1);
+
+        let students = diff.tables.iter().find(|t| t.table_name == "students").unwrap();
+        assert_eq!(students.added, ["s1"]);
+
+        assert!(diff_exports(&before, &before).unwrap().identical);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn supabase_export_uses_hyphenated_ids_and_lowercase_enums() {
+        let dir = std::env::temp_dir().join(format!("supabase-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("borrowings.json");
+
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "EXP", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        // Rows written by older builds keep ids as bare hex and enums as typed
+        execute(
+            &db,
+            "INSERT INTO borrowings (id, student_id, book_id, borrowed_date, due_date, condition_at_issue, is_lost, created_at)
+             VALUES ('0123456789ABCDEF0123456789abcdef', ?1, ?2, '2026-03-01', '2026-03-15', ' GOOD ', 0, '2026-03-01 09:30:00')",
+            [&student_id, &book_id],
+        );
+        let deleted = seed_dated_loan(&db, &student_id, &book_id, "2026-03-01", "2026-03-15", None);
+        execute(&db, "UPDATE borrowings SET deleted = 1 WHERE id = ?1", [&deleted]);
+
+        assert_eq!(db.export_for_supabase("borrowings", &path).await.unwrap(), 1);
+        let rows: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let row = &rows[0];
+        assert_eq!(row["id"], "01234567-89ab-cdef-0123-456789abcdef");
+        assert_eq!(row["student_id"], student_id.as_str());
+        assert_eq!(row["condition_at_issue"], "good");
+        assert_eq!(row["status"], "active");
+        assert_eq!(row["borrower_type"], "student");
+        assert_eq!(row["is_lost"], false);
+        assert_eq!(row["created_at"], "2026-03-01T09:30:00+00:00");
+        assert!(row.get("synced").is_none() && row.get("deleted").is_none());
+
+        assert!(matches!(db.export_for_supabase("app_settings", &path).await, Err(ExportError::UnknownTable(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
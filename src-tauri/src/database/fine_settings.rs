@@ -0,0 +1,143 @@
+use super::{constraint_error, insert_fine, parse_enum, parse_sqlite_datetime, snake_case_value, DatabaseManager};
+use crate::models::*;
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension, Result};
+use uuid::Uuid;
+
+// Fine types the fine_settings CHECK constraint accepts
+const FINE_SETTING_TYPES: &[&str] = &[
+    "overdue", "damaged", "lost_book", "stolen_book", "theft_victim", "condition_poor",
+    "condition_fair", "condition_excellent", "condition_good", "late_return", "replacement_cost", "processing_fee",
+];
+
+fn row_to_fine_setting(row: &rusqlite::Row) -> Result<FineSetting> {
+    let id_str: String = row.get(0)?;
+    let fine_type_str: String = row.get(1)?;
+    let created_str: String = row.get(4)?;
+    let updated_str: String = row.get(5)?;
+
+    Ok(FineSetting {
+        id: Uuid::parse_str(&id_str)
+            .map_err(|_| rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text))?,
+        fine_type: parse_fine_type(&fine_type_str)
+            .ok_or_else(|| rusqlite::Error::InvalidColumnType(1, "fine_type".to_string(), rusqlite::types::Type::Text))?,
+        amount: row.get(2)?,
+        description: row.get(3)?,
+        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+/// Fine types are stored snake_case (lost_book) while their serde names have no separators
+pub fn parse_fine_type(value: &str) -> Option<FineType> {
+    parse_enum(&value.trim().to_lowercase().replace('_', ""))
+}
+
+/// Configured amount for a fine type; per day for overdue fines, a flat charge otherwise
+pub(crate) fn fine_rate(conn: &Connection, fine_type: &FineType) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT amount FROM fine_settings WHERE fine_type = ?1",
+        [snake_case_value(fine_type)],
+        |row| row.get(0),
+    ).optional()
+}
+
+/// Raise an unpaid fine against a borrowing and add it to the borrowing's running total
+pub(crate) fn raise_fine(
+    conn: &Connection,
+    borrowing: &Borrowing,
+    fine_type: FineType,
+    amount: f64,
+    description: String,
+    created_by: Option<Uuid>,
+) -> Result<Fine> {
+    let now = Utc::now();
+    let fine = Fine {
+        id: Uuid::new_v4(),
+        student_id: borrowing.student_id,
+        borrowing_id: Some(borrowing.id),
+        fine_type,
+        amount,
+        description: Some(description),
+        status: FineStatus::Unpaid,
+        created_at: now,
+        updated_at: now,
+        created_by,
+        borrower_type: borrowing.borrower_type.clone(),
+        staff_id: borrowing.staff_id,
+    };
+    insert_fine(conn, &fine)?;
+    conn.execute(
+        "UPDATE borrowings SET fine_amount = COALESCE(fine_amount, 0) + ?2 WHERE id = ?1",
+        (borrowing.id.to_string(), amount),
+    )?;
+    Ok(fine)
+}
+
+impl DatabaseManager {
+    pub async fn get_fine_settings(&self) -> Result<Vec<FineSetting>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, fine_type, amount, description, created_at, updated_at FROM fine_settings ORDER BY fine_type"
+        )?;
+        let settings = stmt.query_map([], row_to_fine_setting)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(settings)
+    }
+
+    /// Create or change the amount charged for a fine type. New fines pick up the
+    /// change straight away; fines already raised keep their amount.
+    pub async fn upsert_fine_setting(&self, fine_type: &FineType, amount: f64, description: Option<&str>) -> Result<FineSetting> {
+        let fine_type_value = snake_case_value(fine_type);
+        if !FINE_SETTING_TYPES.contains(&fine_type_value.as_str()) {
+            return Err(constraint_error(format!("{} fines cannot be configured", fine_type_value)));
+        }
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(constraint_error("Fine amount cannot be negative"));
+        }
+
+        let conn = self.lock_connection()?;
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        conn.execute(
+            "INSERT INTO fine_settings (id, fine_type, amount, description, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+             ON CONFLICT(fine_type) DO UPDATE SET amount = excluded.amount,
+                 description = COALESCE(excluded.description, description), updated_at = excluded.updated_at",
+            (Uuid::new_v4().to_string(), &fine_type_value, amount, description, &now),
+        )?;
+
+        conn.query_row(
+            "SELECT id, fine_type, amount, description, created_at, updated_at FROM fine_settings WHERE fine_type = ?1",
+            [&fine_type_value],
+            row_to_fine_setting,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn changing_the_overdue_rate_changes_the_next_fine() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "RATE", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let first = seed_loan(&db, &student_id, &book_id, &copies[0], -5);
+        let second = seed_loan(&db, &student_id, &book_id, &copies[1], -5);
+
+        db.upsert_fine_setting(&FineType::Overdue, 10.0, Some("Per day late")).await.unwrap();
+        let result = db.return_book(&first, ReturnData::default(), None).await.unwrap();
+        assert_eq!(result.fines.iter().map(|f| f.amount).collect::<Vec<_>>(), [50.0]);
+
+        let setting = db.upsert_fine_setting(&FineType::Overdue, 20.0, None).await.unwrap();
+        assert_eq!(setting.description.as_deref(), Some("Per day late"));
+        let result = db.return_book(&second, ReturnData::default(), None).await.unwrap();
+        assert_eq!(result.fines.iter().map(|f| f.amount).collect::<Vec<_>>(), [100.0]);
+
+        // Fines already raised keep the rate they were raised at
+        assert_eq!(scalar::<f64, _>(&db, "SELECT amount FROM fines WHERE borrowing_id = ?1", [&first]), 50.0);
+        assert!(db.upsert_fine_setting(&FineType::Overdue, -1.0, None).await.is_err());
+        assert_eq!(db.get_fine_settings().await.unwrap().len(), 1);
+    }
+}
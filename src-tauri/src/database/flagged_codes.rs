@@ -0,0 +1,120 @@
+use super::{constraint_error, parse_sqlite_datetime, DatabaseManager};
+use crate::models::*;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, Result};
+
+const FLAGGED_CODE_COLUMNS: &str = "code, reason, book_copy_id, flagged_by, created_at";
+
+fn row_to_flagged_code(row: &rusqlite::Row) -> Result<FlaggedCode> {
+    let created_str: String = row.get(4)?;
+    Ok(FlaggedCode {
+        code: row.get(0)?,
+        reason: row.get(1)?,
+        book_copy_id: row.get(2)?,
+        flagged_by: row.get(3)?,
+        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn find_flagged_code(conn: &Connection, code: &str) -> Result<Option<FlaggedCode>> {
+    conn.query_row(
+        &format!("SELECT {} FROM flagged_codes WHERE code = ?1", FLAGGED_CODE_COLUMNS),
+        [code.trim()],
+        row_to_flagged_code,
+    ).optional()
+}
+
+// Flag on the copy handed back for a borrowing, checked when it is returned
+pub(super) fn flagged_code_for_borrowing(conn: &Connection, borrowing_id: &str) -> Result<Option<FlaggedCode>> {
+    let code: Option<String> = conn.query_row(
+        "SELECT COALESCE(b.tracking_code, bc.tracking_code)
+         FROM borrowings b
+         LEFT JOIN book_copies bc ON b.book_copy_id = bc.id
+         WHERE b.id = ?1",
+        [borrowing_id],
+        |row| row.get(0),
+    ).optional()?.flatten();
+
+    match code {
+        Some(code) => find_flagged_code(conn, &code),
+        None => Ok(None),
+    }
+}
+
+impl DatabaseManager {
+    /// Flag a lost/stolen tracking code so it raises an alert if it is scanned again
+    pub async fn register_flagged_code(&self, code: &str, reason: &str, flagged_by: Option<&str>) -> Result<FlaggedCode> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Err(constraint_error("Tracking code is required"));
+        }
+        if reason.trim().is_empty() {
+            return Err(constraint_error("A reason is required to flag a code"));
+        }
+
+        let conn = self.lock_connection()?;
+        let book_copy_id: Option<String> = conn.query_row(
+            "SELECT id FROM book_copies WHERE tracking_code = ?1 COLLATE NOCASE",
+            [code],
+            |row| row.get(0),
+        ).optional()?;
+
+        conn.execute(
+            "INSERT INTO flagged_codes (code, reason, book_copy_id, flagged_by) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(code) DO UPDATE SET reason = excluded.reason, flagged_by = excluded.flagged_by",
+            (code, reason.trim(), &book_copy_id, flagged_by),
+        )?;
+
+        find_flagged_code(&conn, code)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub async fn list_flagged_codes(&self) -> Result<Vec<FlaggedCode>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM flagged_codes ORDER BY created_at DESC", FLAGGED_CODE_COLUMNS
+        ))?;
+        let codes = stmt.query_map([], row_to_flagged_code)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(codes)
+    }
+
+    /// The flag for a scanned code, if any
+    pub async fn check_flagged_code(&self, code: &str) -> Result<Option<FlaggedCode>> {
+        let conn = self.lock_connection()?;
+        find_flagged_code(&conn, code)
+    }
+
+    pub async fn remove_flagged_code(&self, code: &str) -> Result<bool> {
+        let conn = self.lock_connection()?;
+        let removed = conn.execute("DELETE FROM flagged_codes WHERE code = ?1", [code.trim()])?;
+        Ok(removed > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn flagged_code_raises_an_alert_on_scan() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "FLAG", 1);
+        let student_id = seed_student(&db, "ADM-FLAG");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+        let flag_on_loan = || flagged_code_for_borrowing(&db.get_connection().lock().unwrap(), &loan_id).unwrap();
+
+        let flag = db.register_flagged_code(" flag-1 ", "Reported stolen", Some("librarian")).await.unwrap();
+        assert_eq!(flag.code, "flag-1");
+        assert_eq!(flag.book_copy_id.as_deref(), Some(copies[0].as_str()));
+
+        // The scanner reads the label as printed, whatever case it was flagged in
+        let alert = db.check_flagged_code("FLAG-1").await.unwrap().expect("scan raises an alert");
+        assert_eq!(alert.reason, "Reported stolen");
+        assert!(flag_on_loan().is_some());
+        assert!(db.check_flagged_code("FLAG-2").await.unwrap().is_none());
+
+        assert!(db.remove_flagged_code("FLAG-1").await.unwrap());
+        assert!(db.check_flagged_code("flag-1").await.unwrap().is_none());
+        assert!(flag_on_loan().is_none());
+    }
+}
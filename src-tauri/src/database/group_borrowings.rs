@@ -0,0 +1,181 @@
+use super::{constraint_error, insert_borrowing, return_borrowing, settings, BorrowingError, DatabaseManager};
+use crate::models::*;
+use chrono::{SecondsFormat, Utc};
+use rusqlite::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupReturnResult {
+    pub group_borrowing_id: String,
+    pub returned_borrowing_ids: Vec<String>,
+    pub fines: Vec<Fine>,
+    pub total_fine_amount: f64,
+}
+
+impl DatabaseManager {
+    /// Issue the same title to a group of students: one group_borrowings row plus a
+    /// borrowing per student sharing its group_borrowing_id, all or nothing.
+    /// The due date is capped by the loan rules like a single loan.
+    pub async fn create_group_borrowing(&self, group: &GroupBorrowing) -> Result<(GroupBorrowing, Vec<Borrowing>), BorrowingError> {
+        if group.student_ids.is_empty() {
+            return Err(constraint_error("A group borrowing needs at least one student").into());
+        }
+        if group.student_count as usize != group.student_ids.len() {
+            return Err(constraint_error(format!(
+                "student_count is {} but {} student ids were given", group.student_count, group.student_ids.len()
+            )).into());
+        }
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = group.student_ids.iter().find(|id| !seen.insert(**id)) {
+            return Err(constraint_error(format!("Student {} is listed more than once", duplicate)).into());
+        }
+
+        let conn = self.lock_connection()?;
+        let mut group = group.clone();
+
+        let rules: LoanRules = settings::read_setting(&conn, settings::LOAN_RULES_KEY)?.unwrap_or_default();
+        group.due_date = group.due_date.min(rules.max_due_date(&BorrowerType::Student, group.borrowed_date));
+        group.status = "active".to_string();
+
+        let tx = conn.unchecked_transaction()?;
+
+        for student_id in &group.student_ids {
+            let found: i64 = tx.query_row(
+                "SELECT COUNT(*) FROM students WHERE id = ?1 AND deleted = 0",
+                [student_id.to_string()],
+                |row| row.get(0),
+            )?;
+            if found == 0 {
+                return Err(constraint_error(format!("Student {} not found", student_id)).into());
+            }
+        }
+
+        // Every member takes a copy off the shelf
+        let updated = tx.execute(
+            "UPDATE books SET available_copies = available_copies - ?2, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+             WHERE id = ?1 AND deleted = 0 AND available_copies >= ?2",
+            (group.book_id.to_string(), group.student_ids.len() as i64),
+        )?;
+        if updated == 0 {
+            return Err(BorrowingError::NoCopiesAvailable);
+        }
+
+        let student_ids = serde_json::to_string(&group.student_ids)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        tx.execute(
+            "INSERT INTO group_borrowings (id, book_id, book_copy_id, tracking_code, borrowed_date, due_date,
+             condition_at_issue, notes, status, student_count, issued_by, created_at, updated_at, student_ids)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            rusqlite::params![
+                group.id.to_string(),
+                group.book_id.to_string(),
+                group.book_copy_id.map(|id| id.to_string()),
+                &group.tracking_code,
+                group.borrowed_date.to_string(),
+                group.due_date.to_string(),
+                &group.condition_at_issue,
+                &group.notes,
+                &group.status,
+                group.student_count,
+                group.issued_by.map(|id| id.to_string()),
+                group.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                group.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                student_ids,
+            ],
+        )?;
+
+        let now = Utc::now();
+        let mut borrowings = Vec::with_capacity(group.student_ids.len());
+        for student_id in &group.student_ids {
+            let borrowing = Borrowing {
+                id: Uuid::new_v4(),
+                student_id: Some(*student_id),
+                book_id: Some(group.book_id),
+                borrowed_date: group.borrowed_date,
+                due_date: group.due_date,
+                returned_date: None,
+                status: BorrowingStatus::Active,
+                fine_amount: 0.0,
+                notes: group.notes.clone(),
+                issued_by: group.issued_by,
+                returned_by: None,
+                created_at: now,
+                updated_at: now,
+                fine_paid: false,
+                book_copy_id: None,
+                condition_at_issue: group.condition_at_issue.clone(),
+                condition_at_return: None,
+                is_lost: false,
+                tracking_code: None,
+                return_notes: None,
+                copy_condition: None,
+                group_borrowing_id: Some(group.id),
+                borrower_type: BorrowerType::Student,
+                staff_id: None,
+                due_date_override: false,
+            };
+            insert_borrowing(&tx, &borrowing)?;
+            borrowings.push(borrowing);
+        }
+
+        tx.commit()?;
+        Ok((group, borrowings))
+    }
+
+    /// Return every open loan in a group together and close the group row.
+    /// Members are fined individually; the group keeps the total.
+    pub async fn return_group_borrowing(&self, group_borrowing_id: &str, return_data: ReturnData) -> Result<GroupReturnResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let status: String = tx.query_row(
+            "SELECT COALESCE(status, 'active') FROM group_borrowings WHERE id = ?1",
+            [group_borrowing_id],
+            |row| row.get(0),
+        )?;
+        if status == "returned" {
+            return Err(constraint_error(format!("Group borrowing {} has already been returned", group_borrowing_id)));
+        }
+
+        let mut stmt = tx.prepare(
+            "SELECT id FROM borrowings
+             WHERE group_borrowing_id = ?1 AND deleted = 0 AND status != 'returned'
+             ORDER BY created_at"
+        )?;
+        let member_ids = stmt.query_map([group_borrowing_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        drop(stmt);
+
+        let mut fines = Vec::new();
+        for borrowing_id in &member_ids {
+            fines.extend(return_borrowing(&tx, borrowing_id, &return_data)?);
+        }
+        let total_fine_amount: f64 = fines.iter().map(|f| f.amount).sum();
+
+        let returned_date = return_data.returned_date.unwrap_or_else(|| Utc::now().date_naive());
+        tx.execute(
+            "UPDATE group_borrowings SET status = 'returned', returned_date = ?2, condition_at_return = ?3,
+             return_notes = ?4, returned_by = ?5, fine_amount = COALESCE(fine_amount, 0) + ?6,
+             updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            (
+                group_borrowing_id,
+                returned_date.to_string(),
+                &return_data.condition_at_return,
+                &return_data.return_notes,
+                return_data.returned_by.map(|id| id.to_string()),
+                total_fine_amount,
+            ),
+        )?;
+
+        tx.commit()?;
+
+        Ok(GroupReturnResult {
+            group_borrowing_id: group_borrowing_id.to_string(),
+            returned_borrowing_ids: member_ids,
+            fines,
+            total_fine_amount,
+        })
+    }
+}
@@ -0,0 +1,124 @@
+use super::{constraint_error, DatabaseManager};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Tables pushed through the sync queue that carry content_hash/pushed_hash
+pub const HASHED_TABLES: &[&str] = &["books", "students", "staff", "classes", "categories", "borrowings", "fines"];
+
+// Bookkeeping columns that change without the record itself changing
+const UNHASHED_COLUMNS: &[&str] = &["content_hash", "pushed_hash", "synced", "sync_version", "updated_at"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HashRebuildResult {
+    pub table_name: String,
+    pub rows_hashed: usize,
+}
+
+fn json_value(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(t) => Value::String(String::from_utf8_lossy(t).into_owned()),
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a row's meaningful fields. serde_json keeps object
+/// keys sorted, so the same content always serializes, and hashes, the same way.
+pub fn compute_row_hash(row: &Value) -> String {
+    let meaningful = match row {
+        Value::Object(fields) => Value::Object(
+            fields.iter()
+                .filter(|(key, _)| !UNHASHED_COLUMNS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ),
+        other => other.clone(),
+    };
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in meaningful.to_string().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn check_hashed_table(table: &str) -> Result<()> {
+    if HASHED_TABLES.contains(&table) {
+        Ok(())
+    } else {
+        Err(constraint_error(format!("{} does not track content hashes", table)))
+    }
+}
+
+// Every column of the row as a JSON object keyed by column name
+pub(crate) fn row_as_json(row: &rusqlite::Row) -> Result<Value> {
+    let statement = row.as_ref();
+    let mut fields = Map::new();
+    for i in 0..statement.column_count() {
+        fields.insert(statement.column_name(i)?.to_string(), json_value(row.get_ref(i)?));
+    }
+    Ok(Value::Object(fields))
+}
+
+pub(crate) fn refresh_content_hash_in(conn: &Connection, table: &str, record_id: &str) -> Result<Option<String>> {
+    let row = conn.query_row(
+        &format!("SELECT * FROM {} WHERE id = ?1", table),
+        [record_id],
+        row_as_json,
+    ).optional()?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let hash = compute_row_hash(&row);
+    conn.execute(
+        &format!("UPDATE {} SET content_hash = ?1 WHERE id = ?2", table),
+        (&hash, record_id),
+    )?;
+    Ok(Some(hash))
+}
+
+impl DatabaseManager {
+    /// Recompute the stored content hash of one row after a local write
+    pub async fn refresh_content_hash(&self, table: &str, record_id: &str) -> Result<Option<String>> {
+        check_hashed_table(table)?;
+        let conn = self.lock_connection()?;
+        refresh_content_hash_in(&conn, table, record_id)
+    }
+
+    /// Recompute content hashes for every row, e.g. after an import or a schema change
+    pub async fn rebuild_hashes(&self) -> Result<Vec<HashRebuildResult>> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut results = Vec::new();
+        for table in HASHED_TABLES {
+            let mut stmt = tx.prepare(&format!("SELECT * FROM {}", table))?;
+            let rows = stmt.query_map([], |row| {
+                let id: String = row.get("id")?;
+                Ok((id, row_as_json(row)?))
+            })?.collect::<Result<Vec<_>, _>>()?;
+            drop(stmt);
+
+            for (id, row) in &rows {
+                tx.execute(
+                    &format!("UPDATE {} SET content_hash = ?1 WHERE id = ?2", table),
+                    (compute_row_hash(row), id),
+                )?;
+            }
+
+            results.push(HashRebuildResult {
+                table_name: table.to_string(),
+                rows_hashed: rows.len(),
+            });
+        }
+
+        tx.commit()?;
+        Ok(results)
+    }
+}
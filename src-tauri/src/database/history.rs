@@ -0,0 +1,217 @@
+use super::DatabaseManager;
+use rusqlite::Result;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage<T> {
+    pub entries: Vec<T>,
+    pub current_page: usize,
+    pub page_size: usize,
+    pub total_count: usize,
+    pub total_pages: usize,
+    pub has_next: bool,
+    pub has_previous: bool,
+}
+
+impl<T> HistoryPage<T> {
+    fn new(entries: Vec<T>, page: usize, page_size: usize, total_count: i64) -> Self {
+        let total_count = total_count as usize;
+        let total_pages = total_count.div_ceil(page_size);
+        Self {
+            entries,
+            current_page: page,
+            page_size,
+            total_count,
+            total_pages,
+            has_next: page + 1 < total_pages,
+            has_previous: page > 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StudentHistoryEntry {
+    pub borrowing_id: String,
+    pub book_id: Option<String>,
+    pub book_title: Option<String>,
+    pub book_author: Option<String>,
+    pub book_copy_id: Option<String>,
+    pub borrowed_date: String,
+    pub due_date: String,
+    pub returned_date: Option<String>,
+    pub status: String,
+    pub fine_amount: f64,
+    pub archived: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CirculationEntry {
+    pub borrowing_id: String,
+    pub book_copy_id: Option<String>,
+    pub copy_number: Option<i32>,
+    pub tracking_code: Option<String>,
+    pub borrower_type: String,
+    pub borrower_id: Option<String>,
+    pub borrower_name: Option<String>,
+    pub borrowed_date: String,
+    pub due_date: String,
+    pub returned_date: Option<String>,
+    pub status: String,
+    pub archived: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CopyCirculation {
+    pub book_copy_id: String,
+    pub copy_number: i32,
+    pub tracking_code: Option<String>,
+    pub condition: Option<String>,
+    pub loans: i64,
+    pub last_borrowed: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BookCirculationHistory {
+    pub history: HistoryPage<CirculationEntry>,
+    pub copies: Vec<CopyCirculation>,
+}
+
+// Every loan of a book, live and archived; ?1 is the book id
+const BOOK_HISTORY_SOURCE: &str = "(
+    SELECT id, book_copy_id, COALESCE(borrower_type, 'student') AS borrower_type, student_id, staff_id,
+           borrowed_date, due_date, returned_date, status, 0 AS archived
+    FROM borrowings
+    WHERE book_id = ?1 AND deleted = 0
+    UNION ALL
+    SELECT id, book_copy_id, COALESCE(borrower_type, 'student'), student_id, staff_id,
+           borrowed_date, due_date, returned_date, status, 1
+    FROM borrowings_archive
+    WHERE book_id = ?1 AND deleted = 0
+) h";
+
+// A student's loans, live and archived; ?1 is the student id and ?2 whether returned
+// loans are wanted. Each branch filters on its own indexed student_id, and archived
+// loans are all returned ones.
+const STUDENT_HISTORY_SOURCE: &str = "(
+    SELECT id, book_id, book_copy_id, borrowed_date, due_date, returned_date, status, 0 AS archived
+    FROM borrowings
+    WHERE student_id = ?1 AND deleted = 0 AND (?2 OR status != 'returned')
+    UNION ALL
+    SELECT id, book_id, book_copy_id, borrowed_date, due_date, returned_date, status, 1 AS archived
+    FROM borrowings_archive
+    WHERE student_id = ?1 AND deleted = 0 AND ?2
+) h";
+
+impl DatabaseManager {
+    /// Everything a student has borrowed, newest first, one page at a time, with
+    /// each loan's book and the fines raised on it. Returned loans, including
+    /// archived ones, are left out unless include_returned is set.
+    pub async fn get_student_borrowing_history(&self, student_id: &str, include_returned: bool, page: usize, page_size: usize) -> Result<HistoryPage<StudentHistoryEntry>> {
+        let conn = self.lock_connection()?;
+        let page_size = page_size.max(1);
+
+        let total_count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {}", STUDENT_HISTORY_SOURCE),
+            (student_id, include_returned),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT h.id, h.book_id, b.title, b.author, h.book_copy_id, h.borrowed_date, h.due_date,
+                    h.returned_date, h.status,
+                    COALESCE((SELECT SUM(f.amount) FROM fines f WHERE f.borrowing_id = h.id AND f.deleted = 0), 0),
+                    h.archived
+             FROM {}
+             LEFT JOIN books b ON b.id = h.book_id
+             ORDER BY h.borrowed_date DESC, h.id
+             LIMIT ?3 OFFSET ?4",
+            STUDENT_HISTORY_SOURCE
+        ))?;
+        let entries = stmt.query_map(
+            (student_id, include_returned, page_size as i64, (page * page_size) as i64),
+            |row| Ok(StudentHistoryEntry {
+                borrowing_id: row.get(0)?,
+                book_id: row.get(1)?,
+                book_title: row.get(2)?,
+                book_author: row.get(3)?,
+                book_copy_id: row.get(4)?,
+                borrowed_date: row.get(5)?,
+                due_date: row.get(6)?,
+                returned_date: row.get(7)?,
+                status: row.get(8)?,
+                fine_amount: row.get(9)?,
+                archived: row.get(10)?,
+            }),
+        )?.collect::<Result<Vec<_>>>()?;
+
+        Ok(HistoryPage::new(entries, page, page_size, total_count))
+    }
+
+    /// Every loan of a book across all its copies, newest first, one page at a
+    /// time, with who borrowed it. Alongside is a loan count per copy, busiest
+    /// first, for judging which physical copies are wearing out.
+    pub async fn get_book_circulation_history(&self, book_id: &str, page: usize, page_size: usize) -> Result<BookCirculationHistory> {
+        let conn = self.lock_connection()?;
+        let page_size = page_size.max(1);
+
+        let total_count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {}", BOOK_HISTORY_SOURCE),
+            [book_id],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT h.id, h.book_copy_id, bc.copy_number, bc.tracking_code, h.borrower_type,
+                    COALESCE(h.student_id, h.staff_id),
+                    COALESCE(s.first_name || ' ' || s.last_name, st.first_name || ' ' || st.last_name),
+                    h.borrowed_date, h.due_date, h.returned_date, h.status, h.archived
+             FROM {}
+             LEFT JOIN book_copies bc ON bc.id = h.book_copy_id
+             LEFT JOIN students s ON s.id = h.student_id
+             LEFT JOIN staff st ON st.id = h.staff_id
+             ORDER BY h.borrowed_date DESC, h.id
+             LIMIT ?2 OFFSET ?3",
+            BOOK_HISTORY_SOURCE
+        ))?;
+        let entries = stmt.query_map(
+            (book_id, page_size as i64, (page * page_size) as i64),
+            |row| Ok(CirculationEntry {
+                borrowing_id: row.get(0)?,
+                book_copy_id: row.get(1)?,
+                copy_number: row.get(2)?,
+                tracking_code: row.get(3)?,
+                borrower_type: row.get(4)?,
+                borrower_id: row.get(5)?,
+                borrower_name: row.get(6)?,
+                borrowed_date: row.get(7)?,
+                due_date: row.get(8)?,
+                returned_date: row.get(9)?,
+                status: row.get(10)?,
+                archived: row.get(11)?,
+            }),
+        )?.collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT bc.id, bc.copy_number, bc.tracking_code, bc.condition, COUNT(h.id), MAX(h.borrowed_date)
+             FROM book_copies bc
+             LEFT JOIN {} ON h.book_copy_id = bc.id
+             WHERE bc.book_id = ?1 AND bc.deleted = 0
+             GROUP BY bc.id
+             ORDER BY COUNT(h.id) DESC, bc.copy_number",
+            BOOK_HISTORY_SOURCE
+        ))?;
+        let copies = stmt.query_map([book_id], |row| Ok(CopyCirculation {
+            book_copy_id: row.get(0)?,
+            copy_number: row.get(1)?,
+            tracking_code: row.get(2)?,
+            condition: row.get(3)?,
+            loans: row.get(4)?,
+            last_borrowed: row.get(5)?,
+        }))?.collect::<Result<Vec<_>>>()?;
+
+        Ok(BookCirculationHistory {
+            history: HistoryPage::new(entries, page, page_size, total_count),
+            copies,
+        })
+    }
+}
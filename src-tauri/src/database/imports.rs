@@ -0,0 +1,215 @@
+use super::nightly::checkpoint;
+use super::DatabaseManager;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("Missing required column: {0}")]
+    MissingColumn(&'static str),
+}
+
+const STUDENT_IMPORT_REQUIRED: &[&str] = &["admission_number", "first_name", "last_name", "class_grade"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StudentImportRow {
+    pub line: usize,
+    pub admission_number: String,
+    pub student_id: Option<String>,
+    pub success: bool,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StudentImportReport {
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub imported: usize,
+    pub failed: usize,
+    pub rows: Vec<StudentImportRow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedStudent {
+    pub id: String,
+    pub admission_number: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub class_grade: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+impl DatabaseManager {
+    /// Bulk enrollment from a CSV with the header
+    /// admission_number, first_name, last_name, class_grade, email, phone
+    /// (spreadsheets need saving as CSV first). Valid rows are inserted in one
+    /// transaction; invalid rows and duplicate admission numbers are reported
+    /// and skipped. A dry run validates everything without writing.
+    pub async fn import_students_csv(&self, path: &Path, dry_run: bool) -> Result<(StudentImportReport, Vec<ImportedStudent>), ImportError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .flexible(true)
+            .from_path(path)?;
+
+        let headers: Vec<String> = reader.headers()?.iter().map(|h| h.to_lowercase()).collect();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        for required in STUDENT_IMPORT_REQUIRED {
+            if column(required).is_none() {
+                return Err(ImportError::MissingColumn(*required));
+            }
+        }
+        let (admission_col, first_col, last_col, class_col) = (
+            column("admission_number"), column("first_name"), column("last_name"), column("class_grade"),
+        );
+        let (email_col, phone_col) = (column("email"), column("phone"));
+
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut rows = Vec::new();
+        let mut imported = Vec::new();
+        let mut seen_admissions = HashSet::new();
+        let mut seen_emails = HashSet::new();
+
+        for (index, record) in reader.records().enumerate() {
+            // Line 1 is the header
+            let line = index + 2;
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    rows.push(StudentImportRow {
+                        line,
+                        admission_number: String::new(),
+                        student_id: None,
+                        success: false,
+                        errors: vec![format!("Unreadable row: {}", e)],
+                    });
+                    continue;
+                }
+            };
+
+            let field = |col: Option<usize>| col
+                .and_then(|c| record.get(c))
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string());
+
+            let admission_number = field(admission_col).unwrap_or_default();
+            let first_name = field(first_col).unwrap_or_default();
+            let last_name = field(last_col).unwrap_or_default();
+            let class_grade = field(class_col).unwrap_or_default();
+            let email = field(email_col).map(|e| e.to_lowercase());
+            let phone = field(phone_col);
+
+            let mut errors = Vec::new();
+            for (name, value) in [
+                ("admission_number", &admission_number),
+                ("first_name", &first_name),
+                ("last_name", &last_name),
+                ("class_grade", &class_grade),
+            ] {
+                if value.is_empty() {
+                    errors.push(format!("{} is required", name));
+                }
+            }
+
+            if !admission_number.is_empty() {
+                if !seen_admissions.insert(admission_number.to_lowercase()) {
+                    errors.push(format!("Admission number {} appears more than once in the file", admission_number));
+                }
+
+                let existing: Option<String> = tx.query_row(
+                    "SELECT id FROM students WHERE LOWER(admission_number) = LOWER(?1)",
+                    [&admission_number],
+                    |row| row.get(0),
+                ).optional()?;
+                if existing.is_some() {
+                    errors.push(format!("Admission number {} already exists", admission_number));
+                }
+            }
+
+            if let Some(email) = &email {
+                if !email.contains('@') {
+                    errors.push(format!("Invalid email {}", email));
+                } else if !seen_emails.insert(email.clone()) {
+                    errors.push(format!("Email {} appears more than once in the file", email));
+                } else {
+                    let taken: i64 = tx.query_row(
+                        "SELECT COUNT(*) FROM students WHERE LOWER(email) = ?1",
+                        [email],
+                        |row| row.get(0),
+                    )?;
+                    if taken > 0 {
+                        errors.push(format!("Email {} is already used by another student", email));
+                    }
+                }
+            }
+
+            if !errors.is_empty() {
+                rows.push(StudentImportRow { line, admission_number, student_id: None, success: false, errors });
+                continue;
+            }
+
+            let student = ImportedStudent {
+                id: Uuid::new_v4().to_string(),
+                admission_number,
+                first_name,
+                last_name,
+                class_grade,
+                email,
+                phone,
+            };
+
+            if !dry_run {
+                tx.prepare_cached(
+                    "INSERT INTO students (id, admission_number, first_name, last_name, class_grade, email, phone, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))",
+                )?.execute((
+                    &student.id,
+                    &student.admission_number,
+                    &student.first_name,
+                    &student.last_name,
+                    &student.class_grade,
+                    &student.email,
+                    &student.phone,
+                ))?;
+            }
+
+            rows.push(StudentImportRow {
+                line,
+                admission_number: student.admission_number.clone(),
+                student_id: (!dry_run).then(|| student.id.clone()),
+                success: true,
+                errors: Vec::new(),
+            });
+            imported.push(student);
+        }
+
+        if dry_run {
+            imported.clear();
+        } else {
+            tx.commit()?;
+            checkpoint(&conn)?;
+        }
+
+        let succeeded = rows.iter().filter(|r| r.success).count();
+        let report = StudentImportReport {
+            dry_run,
+            total_rows: rows.len(),
+            imported: if dry_run { 0 } else { succeeded },
+            failed: rows.len() - succeeded,
+            rows,
+        };
+
+        Ok((report, imported))
+    }
+}
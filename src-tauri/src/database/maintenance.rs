@@ -0,0 +1,656 @@
+use super::settings::{read_setting, ENUM_ALIASES_KEY};
+use super::{constraint_error, parse_sqlite_datetime, DatabaseManager};
+use crate::models::*;
+use chrono::Utc;
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryUsage {
+    pub category: Category,
+    pub book_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCategoryGroup {
+    pub normalized_name: String,
+    pub categories: Vec<CategoryUsage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryMergeResult {
+    pub kept_category_id: String,
+    pub merged_category_ids: Vec<String>,
+    pub repointed_book_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailabilityCorrection {
+    pub book_id: String,
+    pub title: String,
+    pub previous_available: i64,
+    pub corrected_available: i64,
+    pub difference: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailabilityReport {
+    pub books_checked: usize,
+    pub books_corrected: usize,
+    pub corrections: Vec<AvailabilityCorrection>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnsyncedTableCount {
+    pub table_name: String,
+    pub unsynced_rows: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnsyncedChanges {
+    pub tables: Vec<UnsyncedTableCount>,
+    pub pending_queue_operations: i64,
+    pub total_unsynced: i64,
+}
+
+impl UnsyncedChanges {
+    pub fn has_unsynced(&self) -> bool {
+        self.total_unsynced > 0 || self.pending_queue_operations > 0
+    }
+
+    /// One line per table with unsynced rows, for warnings shown before destructive actions
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = self.tables.iter()
+            .filter(|t| t.unsynced_rows > 0)
+            .map(|t| format!("{}: {}", t.table_name, t.unsynced_rows))
+            .collect();
+        if self.pending_queue_operations > 0 {
+            parts.push(format!("queued operations: {}", self.pending_queue_operations));
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedSyncOperation {
+    pub id: String,
+    pub table_name: String,
+    pub operation_type: String,
+    pub record_id: String,
+    pub payload: serde_json::Value,
+    pub created_at: String,
+    pub retry_count: i64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CorruptSession {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub email: Option<String>,
+    pub problems: Vec<String>,
+}
+
+// Session timestamp columns that must parse for the session to load
+const SESSION_TIMESTAMP_COLUMNS: &[&str] = &["expires_at", "created_at", "updated_at", "last_activity", "offline_expiry"];
+
+// Corrupt sessions by rowid, which still identifies rows whose id is unusable
+fn scan_corrupt_sessions(conn: &Connection) -> Result<Vec<(i64, CorruptSession)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT rowid, CAST(id AS TEXT), CAST(user_id AS TEXT), CAST(email AS TEXT), {} FROM user_sessions ORDER BY rowid",
+        SESSION_TIMESTAMP_COLUMNS.iter().map(|c| format!("CAST({} AS TEXT)", c)).collect::<Vec<_>>().join(", ")
+    ))?;
+
+    let mut corrupt = Vec::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let id: Option<String> = row.get(1)?;
+        let mut problems = Vec::new();
+        if id.as_deref().map_or(true, |id| Uuid::parse_str(id).is_err()) {
+            problems.push(format!("id: {:?} is not a UUID", id.as_deref().unwrap_or("")));
+        }
+        for (offset, column) in SESSION_TIMESTAMP_COLUMNS.iter().enumerate() {
+            let value: Option<String> = row.get(4 + offset)?;
+            match value.as_deref() {
+                Some(value) if parse_sqlite_datetime(value).is_ok() => {}
+                Some(value) => problems.push(format!("{}: unparseable timestamp {:?}", column, value)),
+                None => problems.push(format!("{}: missing", column)),
+            }
+        }
+
+        if !problems.is_empty() {
+            corrupt.push((row.get(0)?, CorruptSession {
+                id: id.unwrap_or_default(),
+                user_id: row.get(2)?,
+                email: row.get(3)?,
+                problems,
+            }));
+        }
+    }
+    Ok(corrupt)
+}
+
+/// Tables carrying the synced flag
+pub const SYNC_FLAG_TABLES: &[&str] = &["books", "book_copies", "classes", "students", "staff", "borrowings", "fines"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidEnumValue {
+    pub table_name: String,
+    pub column_name: String,
+    pub value: String,
+    pub row_count: i64,
+    pub suggested_value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnumRepair {
+    pub table_name: String,
+    pub column_name: String,
+    pub value: String,
+    pub replacement: Option<String>,
+    pub rows_updated: usize,
+}
+
+// Enum-like columns and the values their CHECK constraints accept
+const BOOK_CONDITIONS: &[&str] = &["excellent", "good", "fair", "poor", "damaged", "lost", "stolen"];
+const ENUM_COLUMNS: &[(&str, &str, &[&str])] = &[
+    ("books", "status", &["available", "unavailable", "damaged", "lost"]),
+    ("books", "condition", &["excellent", "good", "fair", "damaged", "lost", "stolen"]),
+    ("book_copies", "condition", &["good", "fair", "poor", "damaged", "lost"]),
+    ("book_copies", "status", &["available", "borrowed", "maintenance", "lost", "stolen"]),
+    ("classes", "academic_level_type", &["form", "grade"]),
+    ("staff", "status", &["active", "inactive"]),
+    ("borrowings", "status", &["active", "returned", "overdue", "lost"]),
+    ("borrowings", "borrower_type", &["student", "staff"]),
+    ("borrowings", "condition_at_issue", BOOK_CONDITIONS),
+    ("borrowings", "condition_at_return", BOOK_CONDITIONS),
+    ("borrowings", "copy_condition", BOOK_CONDITIONS),
+    ("fines", "fine_type", &["overdue", "damaged", "lost", "lost_book", "late_return", "damage"]),
+    ("fines", "status", &["unpaid", "paid", "cleared", "collected", "partial", "waived"]),
+    ("fines", "borrower_type", &["student", "staff"]),
+    ("theft_reports", "status", &["reported", "investigating", "resolved", "closed"]),
+];
+
+// Values seen in legacy imports; admins can add more through the enum_aliases setting
+const DEFAULT_ENUM_ALIASES: &[(&str, &str)] = &[
+    ("ok", "good"),
+    ("new", "excellent"),
+    ("very_good", "good"),
+    ("average", "fair"),
+    ("bad", "poor"),
+    ("worn", "poor"),
+    ("missing", "lost"),
+    ("borrowed", "active"),
+    ("issued", "active"),
+    ("checked_out", "active"),
+    ("in_stock", "available"),
+    ("teacher", "staff"),
+    ("pupil", "student"),
+];
+
+fn allowed_enum_values(table: &str, column: &str) -> Option<&'static [&'static str]> {
+    ENUM_COLUMNS.iter()
+        .find(|(t, c, _)| *t == table && *c == column)
+        .map(|(_, _, allowed)| *allowed)
+}
+
+// Case/spacing variants map straight to a variant, otherwise try the alias table
+fn suggest_enum_value(value: &str, allowed: &[&str], aliases: &HashMap<String, String>) -> Option<String> {
+    let normalized = value.trim().to_lowercase().replace([' ', '-'], "_");
+    let candidate = aliases.get(&normalized).cloned().unwrap_or(normalized);
+    allowed.contains(&candidate.as_str()).then_some(candidate)
+}
+
+// Adds a column to an existing table when an older database predates it
+pub(crate) fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt.query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == column);
+
+    if exists {
+        return Ok(false);
+    }
+
+    conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    Ok(true)
+}
+
+/// Rows written by the old status scheme used 'borrowed' for open loans;
+/// BorrowingStatus stores them as 'active'
+pub(crate) fn normalize_legacy_borrowing_status(conn: &Connection) -> Result<usize> {
+    conn.execute(
+        "UPDATE borrowings
+         SET status = CASE WHEN returned_date IS NULL THEN 'active' ELSE 'returned' END,
+             updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+         WHERE status = 'borrowed'",
+        [],
+    )
+}
+
+impl DatabaseManager {
+    /// Categories whose names only differ by case or surrounding whitespace
+    pub async fn find_duplicate_categories(&self) -> Result<Vec<DuplicateCategoryGroup>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.name, c.description, c.created_at, c.updated_at, LOWER(TRIM(c.name)) as normalized_name,
+                    (SELECT COUNT(*) FROM books b WHERE b.category_id = c.id AND b.deleted = 0) as book_count
+             FROM categories c
+             WHERE c.deleted = 0 AND LOWER(TRIM(c.name)) IN (
+                 SELECT LOWER(TRIM(name)) FROM categories WHERE deleted = 0
+                 GROUP BY LOWER(TRIM(name)) HAVING COUNT(*) > 1
+             )
+             ORDER BY normalized_name, book_count DESC, c.created_at"
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let id_str: String = row.get(0)?;
+            let created_str: String = row.get(3)?;
+            let updated_str: String = row.get(4)?;
+
+            Ok((
+                row.get::<_, String>(5)?,
+                CategoryUsage {
+                    category: Category {
+                        id: Uuid::parse_str(&id_str).map_err(|_| {
+                            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+                        })?,
+                        name: row.get(1)?,
+                        description: row.get(2)?,
+                        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+                        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+                    },
+                    book_count: row.get(6)?,
+                },
+            ))
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut groups: Vec<DuplicateCategoryGroup> = Vec::new();
+        for (normalized_name, usage) in rows {
+            match groups.last_mut() {
+                Some(group) if group.normalized_name == normalized_name => group.categories.push(usage),
+                _ => groups.push(DuplicateCategoryGroup {
+                    normalized_name,
+                    categories: vec![usage],
+                }),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Repoint books from the merged categories to the survivor and soft-delete the duplicates
+    pub async fn merge_categories(&self, keep_id: &str, merge_ids: &[String]) -> Result<CategoryMergeResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let keep_exists: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM categories WHERE id = ?1 AND deleted = 0",
+            [keep_id],
+            |row| row.get(0)
+        )?;
+        if keep_exists == 0 {
+            return Err(constraint_error(format!("Category {} not found", keep_id)));
+        }
+
+        let mut merged_category_ids = Vec::new();
+        let mut repointed_book_ids = Vec::new();
+        for merge_id in merge_ids.iter().filter(|id| id.as_str() != keep_id) {
+            let book_ids = tx
+                .prepare("SELECT id FROM books WHERE category_id = ?1 ORDER BY id")?
+                .query_map([merge_id], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>>>()?;
+            tx.execute(
+                "UPDATE books SET category_id = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE category_id = ?2",
+                (keep_id, merge_id),
+            )?;
+            repointed_book_ids.extend(book_ids);
+            let deleted = tx.execute(
+                "UPDATE categories SET deleted = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1 AND deleted = 0",
+                [merge_id],
+            )?;
+            if deleted > 0 {
+                merged_category_ids.push(merge_id.clone());
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(CategoryMergeResult {
+            kept_category_id: keep_id.to_string(),
+            merged_category_ids,
+            repointed_book_ids,
+        })
+    }
+
+    fn enum_aliases(conn: &Connection) -> Result<HashMap<String, String>> {
+        let mut aliases: HashMap<String, String> = DEFAULT_ENUM_ALIASES.iter()
+            .map(|(alias, canonical)| (alias.to_string(), canonical.to_string()))
+            .collect();
+        let custom: HashMap<String, String> = read_setting(conn, ENUM_ALIASES_KEY)?.unwrap_or_default();
+        for (alias, canonical) in custom {
+            aliases.insert(alias.trim().to_lowercase().replace([' ', '-'], "_"), canonical);
+        }
+        Ok(aliases)
+    }
+
+    fn invalid_enum_values_in(conn: &Connection, table: &str, column: &str) -> Result<Vec<InvalidEnumValue>> {
+        let allowed = allowed_enum_values(table, column)
+            .ok_or_else(|| constraint_error(format!("{}.{} is not a known enum column", table, column)))?;
+        let aliases = Self::enum_aliases(conn)?;
+
+        // Table and column names come from ENUM_COLUMNS, never from the caller directly
+        let placeholders = allowed.iter().map(|v| format!("'{}'", v)).collect::<Vec<_>>().join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {column}, COUNT(*) FROM {table}
+             WHERE {column} IS NOT NULL AND {column} NOT IN ({placeholders})
+             GROUP BY {column}
+             ORDER BY COUNT(*) DESC",
+        ))?;
+
+        let values = stmt.query_map([], |row| {
+            let value: String = row.get(0)?;
+            Ok(InvalidEnumValue {
+                table_name: table.to_string(),
+                column_name: column.to_string(),
+                suggested_value: suggest_enum_value(&value, allowed, &aliases),
+                value,
+                row_count: row.get(1)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(values)
+    }
+
+    /// Stored values of one enum column that don't match any variant, with the suggested replacement
+    pub async fn find_invalid_enum_values(&self, table: &str, column: &str) -> Result<Vec<InvalidEnumValue>> {
+        let conn = self.lock_connection()?;
+        Self::invalid_enum_values_in(&conn, table, column)
+    }
+
+    /// Invalid values across every known enum column
+    pub async fn get_enum_value_report(&self) -> Result<Vec<InvalidEnumValue>> {
+        let conn = self.lock_connection()?;
+        let mut report = Vec::new();
+        for (table, column, _) in ENUM_COLUMNS {
+            report.extend(Self::invalid_enum_values_in(&conn, table, column)?);
+        }
+        Ok(report)
+    }
+
+    /// Rewrite invalid values that map to a known variant. Unmappable values are
+    /// left in place and returned with no replacement so they can be fixed by hand.
+    pub async fn fix_enum_values(&self, table: Option<&str>, column: Option<&str>) -> Result<Vec<EnumRepair>> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut repairs = Vec::new();
+        for (t, c, _) in ENUM_COLUMNS.iter()
+            .filter(|(t, c, _)| table.map_or(true, |table| table == *t) && column.map_or(true, |column| column == *c))
+        {
+            for invalid in Self::invalid_enum_values_in(&tx, t, c)? {
+                let rows_updated = match &invalid.suggested_value {
+                    Some(replacement) => tx.execute(
+                        &format!("UPDATE {t} SET {c} = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE {c} = ?2"),
+                        (replacement, &invalid.value),
+                    )?,
+                    None => 0,
+                };
+
+                repairs.push(EnumRepair {
+                    table_name: invalid.table_name,
+                    column_name: invalid.column_name,
+                    value: invalid.value,
+                    replacement: invalid.suggested_value,
+                    rows_updated,
+                });
+            }
+        }
+
+        tx.commit()?;
+        Ok(repairs)
+    }
+
+    /// Recalculate available_copies from total_copies minus open loans and copies
+    /// in maintenance. Lost and stolen copies are already excluded from
+    /// total_copies. Only rows that drifted are written, so reruns are no-ops.
+    pub async fn recompute_availability(&self) -> Result<AvailabilityReport> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut stmt = tx.prepare(
+            "SELECT bk.id, bk.title, bk.available_copies,
+                    MAX(0, bk.total_copies
+                        - (SELECT COUNT(*) FROM borrowings b
+                           WHERE b.book_id = bk.id AND b.deleted = 0
+                             AND b.returned_date IS NULL AND b.status IN ('active', 'overdue'))
+                        - (SELECT COUNT(*) FROM book_copies bc
+                           WHERE bc.book_id = bk.id AND bc.deleted = 0
+                             AND bc.status = 'maintenance' AND COALESCE(bc.condition, '') != 'lost')) as expected
+             FROM books bk
+             WHERE bk.deleted = 0"
+        )?;
+        let books = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+        })?.collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+
+        let mut corrections = Vec::new();
+        for (book_id, title, previous_available, corrected_available) in &books {
+            if previous_available == corrected_available {
+                continue;
+            }
+
+            tx.execute(
+                "UPDATE books SET available_copies = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?2",
+                (corrected_available, book_id),
+            )?;
+            corrections.push(AvailabilityCorrection {
+                book_id: book_id.clone(),
+                title: title.clone(),
+                previous_available: *previous_available,
+                corrected_available: *corrected_available,
+                difference: corrected_available - previous_available,
+            });
+        }
+
+        tx.commit()?;
+
+        Ok(AvailabilityReport {
+            books_checked: books.len(),
+            books_corrected: corrections.len(),
+            corrections,
+        })
+    }
+
+    /// Count local rows not yet pushed to Supabase (synced = 0) per table, plus
+    /// anything still waiting in the sync queue. Clearing or re-pulling the
+    /// database would discard these.
+    pub async fn check_unsynced_changes(&self) -> Result<UnsyncedChanges> {
+        let conn = self.lock_connection()?;
+
+        let mut tables = Vec::with_capacity(SYNC_FLAG_TABLES.len());
+        for table in SYNC_FLAG_TABLES {
+            let unsynced_rows: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE synced = 0", table),
+                [],
+                |row| row.get(0),
+            )?;
+            tables.push(UnsyncedTableCount { table_name: table.to_string(), unsynced_rows });
+        }
+
+        let pending_queue_operations: i64 = conn.query_row("SELECT COUNT(*) FROM sync_queue", [], |row| row.get(0))?;
+        let total_unsynced = tables.iter().map(|t| t.unsynced_rows).sum();
+
+        Ok(UnsyncedChanges { tables, pending_queue_operations, total_unsynced })
+    }
+
+    /// After a pull the local rows mirror Supabase, except those with local
+    /// changes still queued for push
+    pub async fn mark_pulled_rows_synced(&self) -> Result<usize> {
+        let conn = self.lock_connection()?;
+        let mut marked = 0;
+        for table in SYNC_FLAG_TABLES {
+            marked += conn.execute(
+                &format!(
+                    "UPDATE {table} SET synced = 1
+                     WHERE synced = 0
+                       AND NOT EXISTS (SELECT 1 FROM sync_queue q WHERE q.table_name = '{table}' AND q.record_id = {table}.id)"
+                ),
+                [],
+            )?;
+        }
+        Ok(marked)
+    }
+
+    /// Queued operations that have failed at least once, oldest first
+    pub async fn get_failed_sync_operations(&self) -> Result<Vec<QueuedSyncOperation>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, table_name, operation_type, record_id, payload, created_at,
+                    COALESCE(retry_count, 0), last_error
+             FROM sync_queue
+             WHERE COALESCE(retry_count, 0) > 0 OR last_error IS NOT NULL
+             ORDER BY created_at, rowid"
+        )?;
+        let operations = stmt.query_map([], |row| {
+            let payload: String = row.get(4)?;
+            Ok(QueuedSyncOperation {
+                id: row.get(0)?,
+                table_name: row.get(1)?,
+                operation_type: row.get(2)?,
+                record_id: row.get(3)?,
+                payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+                created_at: row.get(5)?,
+                retry_count: row.get(6)?,
+                last_error: row.get(7)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(operations)
+    }
+
+    /// Sessions whose id or timestamps can't be read back; session lookups skip them
+    pub async fn find_corrupt_sessions(&self) -> Result<Vec<CorruptSession>> {
+        let conn = self.lock_connection()?;
+        Ok(scan_corrupt_sessions(&conn)?.into_iter().map(|(_, session)| session).collect())
+    }
+
+    /// Delete the sessions find_corrupt_sessions reports. Their users simply sign in again.
+    pub async fn remove_corrupt_sessions(&self) -> Result<Vec<CorruptSession>> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+        let corrupt = scan_corrupt_sessions(&tx)?;
+        for (rowid, _) in &corrupt {
+            tx.execute("DELETE FROM user_sessions WHERE rowid = ?1", [rowid])?;
+        }
+        tx.commit()?;
+        Ok(corrupt.into_iter().map(|(_, session)| session).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn merging_categories_repoints_books_and_removes_the_duplicate() {
+        let db = test_db();
+        let keep = seed_category(&db, "Science");
+        let duplicate = seed_category(&db, " science ");
+        let other = seed_category(&db, "History");
+        let (kept_book, _) = seed_book(&db, "CAT1", 1);
+        let (moved_a, _) = seed_book(&db, "CAT2", 1);
+        let (moved_b, _) = seed_book(&db, "CAT3", 1);
+        let (history_book, _) = seed_book(&db, "CAT4", 1);
+        execute(&db, "UPDATE books SET category_id = ?1 WHERE id = ?2", [&keep, &kept_book]);
+        execute(&db, "UPDATE books SET category_id = ?1 WHERE id IN (?2, ?3)", [&duplicate, &moved_a, &moved_b]);
+        execute(&db, "UPDATE books SET category_id = ?1 WHERE id = ?2", [&other, &history_book]);
+
+        let groups = db.find_duplicate_categories().await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].normalized_name, "science");
+        // The category with more books is listed first as the natural survivor
+        assert_eq!(groups[0].categories[0].category.id.to_string(), duplicate);
+
+        let result = db.merge_categories(&keep, &[duplicate.clone()]).await.unwrap();
+        let mut moved = vec![moved_a.clone(), moved_b.clone()];
+        moved.sort();
+        assert_eq!(result.repointed_book_ids, moved);
+        assert_eq!(result.merged_category_ids, [duplicate.clone()]);
+
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM books WHERE category_id = ?1", [&keep]), 3);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM books WHERE category_id = ?1", [&duplicate]), 0);
+        assert_eq!(scalar::<String, _>(&db, "SELECT category_id FROM books WHERE id = ?1", [&history_book]), other);
+        assert!(scalar::<bool, _>(&db, "SELECT deleted FROM categories WHERE id = ?1", [&duplicate]));
+        assert!(db.find_duplicate_categories().await.unwrap().is_empty());
+    }
+
+
+    #[tokio::test]
+    async fn invalid_enum_values_are_detected_and_repaired() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "ENUM", 3);
+        let student_id = seed_student(&db, "ADM-1");
+        let aliased = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+        let cased = seed_loan(&db, &student_id, &book_id, &copies[1], 7);
+        let unknown = seed_loan(&db, &student_id, &book_id, &copies[2], 7);
+        execute(&db, "UPDATE borrowings SET condition_at_issue = 'Very Good' WHERE id = ?1", [&aliased]);
+        execute(&db, "UPDATE borrowings SET condition_at_issue = ' FAIR' WHERE id = ?1", [&cased]);
+        execute(&db, "UPDATE borrowings SET condition_at_issue = 'mystery' WHERE id = ?1", [&unknown]);
+
+        let invalid = db.find_invalid_enum_values("borrowings", "condition_at_issue").await.unwrap();
+        let suggestion_for = |value: &str| invalid.iter().find(|v| v.value == value).map(|v| v.suggested_value.clone());
+        assert_eq!(invalid.len(), 3);
+        assert_eq!(suggestion_for("Very Good"), Some(Some("good".to_string())));
+        assert_eq!(suggestion_for(" FAIR"), Some(Some("fair".to_string())));
+        assert_eq!(suggestion_for("mystery"), Some(None));
+        assert!(db.find_invalid_enum_values("borrowings", "notes").await.is_err());
+
+        let repairs = db.fix_enum_values(Some("borrowings"), Some("condition_at_issue")).await.unwrap();
+        assert_eq!(repairs.iter().map(|r| r.rows_updated).sum::<usize>(), 2);
+        assert_eq!(scalar::<String, _>(&db, "SELECT condition_at_issue FROM borrowings WHERE id = ?1", [&aliased]), "good");
+        assert_eq!(scalar::<String, _>(&db, "SELECT condition_at_issue FROM borrowings WHERE id = ?1", [&cased]), "fair");
+        // Unmappable values stay for a person to fix
+        assert_eq!(scalar::<String, _>(&db, "SELECT condition_at_issue FROM borrowings WHERE id = ?1", [&unknown]), "mystery");
+
+        let remaining = db.find_invalid_enum_values("borrowings", "condition_at_issue").await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].value, "mystery");
+    }
+
+    #[tokio::test]
+    async fn a_session_with_a_bad_timestamp_is_skipped_and_reported() {
+        let db = test_db();
+        let good_id = uuid::Uuid::new_v4().to_string();
+        let bad_id = uuid::Uuid::new_v4().to_string();
+        for (id, created_at, last_activity) in [
+            (&good_id, "2025-01-01T08:00:00.000Z", "2025-01-01T08:00:00.000Z"),
+            (&bad_id, "2099-01-01T08:00:00.000Z", "sometime yesterday"),
+        ] {
+            execute(
+                &db,
+                "INSERT INTO user_sessions (id, user_id, email, access_token, expires_at, created_at, updated_at, last_activity, offline_expiry)
+                 VALUES (?1, 'user-1', 'librarian@school.test', 'token', ?2, ?2, ?2, ?3, strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '+7 days'))",
+                (id, created_at, last_activity),
+            );
+        }
+
+        // The broken session sorts first in both lookups and is passed over
+        let session = db.get_valid_user_session("user-1").await.unwrap().unwrap();
+        assert_eq!(session.id.to_string(), good_id);
+        let session = db.get_any_valid_session().await.unwrap().unwrap();
+        assert_eq!(session.id.to_string(), good_id);
+
+        let corrupt = db.find_corrupt_sessions().await.unwrap();
+        assert_eq!(corrupt.len(), 1);
+        assert_eq!(corrupt[0].id, bad_id);
+        assert_eq!(corrupt[0].problems, ["last_activity: unparseable timestamp \"sometime yesterday\""]);
+
+        assert_eq!(db.remove_corrupt_sessions().await.unwrap().len(), 1);
+        assert!(db.find_corrupt_sessions().await.unwrap().is_empty());
+        assert_eq!(scalar::<String, _>(&db, "SELECT id FROM user_sessions", []), good_id);
+    }
+}
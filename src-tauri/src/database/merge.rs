@@ -0,0 +1,313 @@
+use super::hashing::compute_row_hash;
+use super::settings::{read_setting, MERGE_STRATEGY_KEY};
+use super::{constraint_error, DatabaseManager};
+use rusqlite::{Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Parents before children so merged rows land after the rows they reference
+const MERGE_TABLES: &[&str] = &[
+    "categories", "classes", "books", "book_copies", "students", "staff",
+    "group_borrowings", "borrowings", "fines", "theft_reports",
+];
+
+pub(super) const SOURCE_SCHEMA: &str = "merge_source";
+
+/// Which copy wins when both databases hold a row with the same id
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    KeepLocal,
+    KeepSource,
+    #[default]
+    Newest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableMergePreview {
+    pub table_name: String,
+    pub source_rows: i64,
+    pub new_rows: i64,
+    pub conflicting_rows: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergePreview {
+    pub source_path: String,
+    pub strategy: MergeStrategy,
+    pub tables: Vec<TableMergePreview>,
+    pub confirm_token: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableMergeResult {
+    pub table_name: String,
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeResult {
+    pub source_path: String,
+    pub strategy: MergeStrategy,
+    pub tables: Vec<TableMergeResult>,
+}
+
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?.collect::<Result<Vec<_>, _>>()?;
+    Ok(columns)
+}
+
+// Columns present in both databases, so older or newer schemas can still merge
+pub(super) fn shared_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let source = table_columns(conn, SOURCE_SCHEMA, table)?;
+    Ok(table_columns(conn, "main", table)?
+        .into_iter()
+        .filter(|column| source.contains(column))
+        .collect())
+}
+
+pub(super) fn attach_source(conn: &Connection, source_path: &Path) -> Result<()> {
+    if !source_path.is_file() {
+        return Err(constraint_error(format!("{} is not a database file", source_path.display())));
+    }
+
+    let main_path: String = conn.query_row("SELECT file FROM pragma_database_list WHERE name = 'main'", [], |row| row.get(0))?;
+    let same_file = match (Path::new(&main_path).canonicalize(), source_path.canonicalize()) {
+        (Ok(main), Ok(source)) => main == source,
+        _ => false,
+    };
+    if same_file {
+        return Err(constraint_error("Cannot merge the database into itself"));
+    }
+
+    // Read-only so a merge can never modify the other branch's file
+    let mut uri_path = source_path.to_string_lossy()
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23")
+        .replace('\\', "/");
+    if uri_path.get(1..2) == Some(":") {
+        // Windows drive paths become file:/C:/...
+        uri_path.insert(0, '/');
+    }
+    let uri = format!("file:{}?mode=ro", uri_path);
+    conn.execute(&format!("ATTACH DATABASE ?1 AS {}", SOURCE_SCHEMA), [uri])?;
+    Ok(())
+}
+
+pub(super) fn detach_source(conn: &Connection) {
+    let _ = conn.execute(&format!("DETACH DATABASE {}", SOURCE_SCHEMA), []);
+}
+
+pub(super) fn source_has_table(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}.sqlite_master WHERE type = 'table' AND name = ?1", SOURCE_SCHEMA),
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn preview_tables(conn: &Connection) -> Result<Vec<TableMergePreview>> {
+    let mut tables = Vec::new();
+    for table in MERGE_TABLES {
+        if !source_has_table(conn, table)? {
+            continue;
+        }
+
+        let (source_rows, conflicting_rows): (i64, i64) = conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(EXISTS (SELECT 1 FROM main.{table} m WHERE m.id = s.id)), 0)
+                 FROM {SOURCE_SCHEMA}.{table} s"
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        tables.push(TableMergePreview {
+            table_name: table.to_string(),
+            source_rows,
+            new_rows: source_rows - conflicting_rows,
+            conflicting_rows,
+        });
+    }
+    Ok(tables)
+}
+
+// Changes whenever the source, its contents or the strategy change, so a stale preview can't confirm a merge
+fn merge_token(source_path: &Path, strategy: MergeStrategy, tables: &[TableMergePreview]) -> String {
+    compute_row_hash(&serde_json::json!({
+        "source_path": source_path.to_string_lossy(),
+        "strategy": strategy,
+        "tables": tables,
+    }))
+}
+
+impl DatabaseManager {
+    fn merge_strategy_or_configured(conn: &Connection, strategy: Option<MergeStrategy>) -> Result<MergeStrategy> {
+        match strategy {
+            Some(strategy) => Ok(strategy),
+            None => Ok(read_setting(conn, MERGE_STRATEGY_KEY)?.unwrap_or_default()),
+        }
+    }
+
+    /// Per-table counts of what merging another library.db would add or conflict with, plus the confirm token
+    pub async fn preview_merge(&self, source_path: &Path, strategy: Option<MergeStrategy>) -> Result<MergePreview> {
+        let conn = self.lock_connection()?;
+        let strategy = Self::merge_strategy_or_configured(&conn, strategy)?;
+
+        attach_source(&conn, source_path)?;
+        let tables = preview_tables(&conn);
+        detach_source(&conn);
+        let tables = tables?;
+
+        Ok(MergePreview {
+            source_path: source_path.to_string_lossy().into_owned(),
+            strategy,
+            confirm_token: merge_token(source_path, strategy, &tables),
+            tables,
+        })
+    }
+
+    /// Upsert every row of another library.db into this one by id. New ids are inserted,
+    /// shared ids are resolved by the strategy, and rows clashing on other unique
+    /// columns (ISBN, admission number...) are skipped.
+    pub async fn merge_database(&self, source_path: &Path, strategy: Option<MergeStrategy>, confirm_token: &str) -> Result<MergeResult> {
+        let conn = self.lock_connection()?;
+        let strategy = Self::merge_strategy_or_configured(&conn, strategy)?;
+
+        attach_source(&conn, source_path)?;
+        // Rows may arrive before the parents they reference; checks resume after the merge
+        conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+        let result = Self::merge_attached(&conn, source_path, strategy, confirm_token);
+        let _ = conn.execute_batch("PRAGMA foreign_keys = ON");
+        detach_source(&conn);
+
+        Ok(MergeResult {
+            source_path: source_path.to_string_lossy().into_owned(),
+            strategy,
+            tables: result?,
+        })
+    }
+
+    fn merge_attached(conn: &Connection, source_path: &Path, strategy: MergeStrategy, confirm_token: &str) -> Result<Vec<TableMergeResult>> {
+        let preview = preview_tables(conn)?;
+        if merge_token(source_path, strategy, &preview) != confirm_token {
+            return Err(constraint_error("Confirmation token does not match - preview the merge again"));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let mut results = Vec::new();
+
+        for table in preview {
+            let name = &table.table_name;
+            let columns = shared_columns(&tx, name)?;
+            let column_list = columns.join(", ");
+
+            let inserted = tx.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO main.{name} ({column_list})
+                     SELECT {column_list} FROM {SOURCE_SCHEMA}.{name} s
+                     WHERE NOT EXISTS (SELECT 1 FROM main.{name} m WHERE m.id = s.id)"
+                ),
+                [],
+            )?;
+
+            let update_filter = match strategy {
+                MergeStrategy::KeepLocal => None,
+                MergeStrategy::KeepSource => Some(String::new()),
+                MergeStrategy::Newest if columns.iter().any(|c| c == "updated_at") => Some(format!(
+                    " AND datetime(s.updated_at) > datetime({name}.updated_at)"
+                )),
+                MergeStrategy::Newest => None,
+            };
+
+            let updated = match update_filter {
+                Some(filter) => {
+                    let assignments = columns.iter()
+                        .filter(|c| c.as_str() != "id")
+                        .map(|c| format!("{c} = s.{c}"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    tx.execute(
+                        &format!(
+                            "UPDATE OR IGNORE main.{name} SET {assignments}
+                             FROM {SOURCE_SCHEMA}.{name} s
+                             WHERE s.id = {name}.id{filter}"
+                        ),
+                        [],
+                    )?
+                }
+                None => 0,
+            };
+
+            results.push(TableMergeResult {
+                skipped: (table.source_rows as usize).saturating_sub(inserted + updated),
+                table_name: table.table_name,
+                inserted,
+                updated,
+            });
+        }
+
+        tx.commit()?;
+        Ok(results)
+    }
+
+    pub async fn get_merge_strategy(&self) -> Result<MergeStrategy> {
+        Ok(self.get_setting(MERGE_STRATEGY_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_merge_strategy(&self, strategy: MergeStrategy) -> Result<()> {
+        self.set_setting(MERGE_STRATEGY_KEY, &strategy).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn merging_a_second_database_combines_rows_without_duplicate_ids() {
+        let dir = std::env::temp_dir().join(format!("merge-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source_path = dir.join("branch.db");
+
+        let db = test_db();
+        let (shared_id, _) = seed_book(&db, "LOCAL-A", 1);
+        seed_book(&db, "LOCAL-B", 1);
+
+        let source = DatabaseManager::new(source_path.to_str().unwrap()).unwrap();
+        seed_book(&source, "BRANCH", 2);
+        // The branch edited a book both databases share more recently
+        execute(
+            &source,
+            "INSERT INTO books (id, title, author, updated_at) VALUES (?1, 'Revised title', 'Test Author', datetime('now', '+1 hour'))",
+            [&shared_id],
+        );
+        drop(source);
+
+        let preview = db.preview_merge(&source_path, Some(MergeStrategy::Newest)).await.unwrap();
+        let books = preview.tables.iter().find(|t| t.table_name == "books").unwrap();
+        assert_eq!((books.source_rows, books.new_rows, books.conflicting_rows), (2, 1, 1));
+
+        let result = db.merge_database(&source_path, Some(MergeStrategy::Newest), &preview.confirm_token).await.unwrap();
+        let books = result.tables.iter().find(|t| t.table_name == "books").unwrap();
+        assert_eq!((books.inserted, books.updated, books.skipped), (1, 1, 0));
+
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM books", []), 3);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(DISTINCT id) FROM books", []), 3);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM book_copies", []), 4);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(DISTINCT id) FROM book_copies", []), 4);
+        assert_eq!(scalar::<String, _>(&db, "SELECT title FROM books WHERE id = ?1", [&shared_id]), "Revised title");
+
+        // The token was for the database as it stood before the merge
+        assert!(db.merge_database(&source_path, Some(MergeStrategy::Newest), &preview.confirm_token).await.is_err());
+
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
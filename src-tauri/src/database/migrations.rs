@@ -0,0 +1,194 @@
+use super::settings::{read_setting, write_setting, LOAN_RULES_KEY};
+use super::{datetimes, hashing, maintenance};
+use crate::models::LoanRules;
+use rusqlite::{Connection, Result};
+
+/// One forward step of the schema. `apply` must be safe to re-run against a
+/// database that already has the change, since databases created before
+/// versioning existed may have picked some of it up from earlier builds.
+struct Migration {
+    version: i64,
+    description: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+// Ordered by version. Append new steps at the end; never renumber or edit a shipped one.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Columns added after the initial schema shipped",
+        apply: add_late_columns,
+    },
+    Migration {
+        version: 2,
+        description: "Borrowing indexes for due-date, overdue and per-student lookups",
+        apply: add_borrowing_lookup_indexes,
+    },
+    Migration {
+        version: 3,
+        description: "Reservations table for the hold queue",
+        apply: add_reservations,
+    },
+    Migration {
+        version: 4,
+        description: "Seed default loan rules",
+        apply: seed_loan_rules,
+    },
+    Migration {
+        version: 5,
+        description: "Normalize stored timestamps to canonical RFC3339",
+        apply: datetimes::canonicalize_stored_timestamps,
+    },
+];
+
+pub fn latest_schema_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+fn add_late_columns(conn: &Connection) -> Result<()> {
+    maintenance::ensure_column(conn, "categories", "deleted", "INTEGER DEFAULT 0")?;
+    maintenance::ensure_column(conn, "fines", "paid_at", "TEXT")?;
+    maintenance::ensure_column(conn, "borrowings", "due_date_override", "INTEGER DEFAULT 0")?;
+    maintenance::ensure_column(conn, "borrowings", "renewal_count", "INTEGER DEFAULT 0")?;
+    maintenance::ensure_column(conn, "books", "acquisition_cost", "REAL")?;
+    maintenance::ensure_column(conn, "students", "gender", "TEXT")?;
+    maintenance::ensure_column(conn, "fines", "payment_method", "TEXT")?;
+    maintenance::ensure_column(conn, "sync_conflicts", "winner", "TEXT")?;
+    maintenance::ensure_column(conn, "sync_conflicts", "resolved_at", "TEXT")?;
+    for table in hashing::HASHED_TABLES {
+        maintenance::ensure_column(conn, table, "content_hash", "TEXT")?;
+        maintenance::ensure_column(conn, table, "pushed_hash", "TEXT")?;
+    }
+    Ok(())
+}
+
+fn add_borrowing_lookup_indexes(conn: &Connection) -> Result<()> {
+    conn.execute_batch("
+        CREATE INDEX IF NOT EXISTS idx_borrowings_due ON borrowings(due_date);
+        -- Overdue lookups: status = 'overdue' OR (status = 'active' AND due_date < today)
+        CREATE INDEX IF NOT EXISTS idx_borrowings_status_due ON borrowings(status, due_date);
+        CREATE INDEX IF NOT EXISTS idx_borrowings_student_status ON borrowings(student_id, status);
+    ")
+}
+
+fn add_reservations(conn: &Connection) -> Result<()> {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS reservations (
+            id TEXT PRIMARY KEY,
+            book_id TEXT NOT NULL,
+            student_id TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'waiting' CHECK (status IN ('waiting', 'ready', 'fulfilled', 'cancelled')),
+            notes TEXT,
+            notified_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+            updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+        -- One open reservation per student per book
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_reservations_open
+            ON reservations(book_id, student_id) WHERE status IN ('waiting', 'ready');
+        CREATE INDEX IF NOT EXISTS idx_reservations_queue ON reservations(book_id, status, created_at);
+    ")
+}
+
+// Store the built-in loan rules so the loan period is visible and editable from
+// the start; a database that already has rules keeps them
+fn seed_loan_rules(conn: &Connection) -> Result<()> {
+    if read_setting::<LoanRules>(conn, LOAN_RULES_KEY)?.is_none() {
+        write_setting(conn, LOAN_RULES_KEY, &LoanRules::default())?;
+    }
+    Ok(())
+}
+
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Apply every migration newer than the database's user_version, each in its
+/// own transaction together with the version bump, so a failed step leaves the
+/// database at the last version that completed.
+pub(crate) fn run_migrations(conn: &Connection) -> Result<()> {
+    conn.execute_batch("
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );
+    ")?;
+
+    let current = schema_version(conn)?;
+    let latest = latest_schema_version();
+    if current > latest {
+        eprintln!(
+            "Database schema version {} is newer than this build supports ({}); skipping migrations",
+            current, latest
+        );
+        return Ok(());
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.apply)(&tx)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_version (version, description) VALUES (?1, ?2)",
+            (migration.version, migration.description),
+        )?;
+        tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::{execute, scalar, test_db};
+    use crate::database::DatabaseManager;
+    use rusqlite::Params;
+
+    // The detail column of EXPLAIN QUERY PLAN, one entry per step
+    fn query_plan<P: Params>(db: &DatabaseManager, sql: &str, params: P) -> Vec<String> {
+        let conn = db.get_connection().lock().unwrap();
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql)).unwrap();
+        let steps = stmt.query_map(params, |row| row.get::<_, String>(3)).unwrap();
+        steps.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn overdue_and_per_student_loan_lookups_use_their_indexes() {
+        let db = test_db();
+
+        // Both halves of the overdue condition are answered from the status/due date index
+        let overdue = query_plan(
+            &db,
+            "SELECT COUNT(*) FROM borrowings
+             WHERE deleted = 0 AND returned_date IS NULL
+               AND (status = 'overdue' OR (status = 'active' AND due_date < date('now')))",
+            [],
+        );
+        assert!(overdue.iter().any(|step| step.contains("idx_borrowings_status_due (status=? AND due_date<?)")), "{:?}", overdue);
+        assert!(!overdue.iter().any(|step| step.starts_with("SCAN borrowings")), "{:?}", overdue);
+
+        let per_student = query_plan(
+            &db,
+            "SELECT id FROM borrowings
+             WHERE student_id = ?1 AND status IN ('active', 'overdue') AND deleted = 0
+             ORDER BY due_date",
+            ["student-1"],
+        );
+        assert!(
+            per_student.iter().any(|step| step.contains("idx_borrowings_student_status (student_id=? AND status=?)")),
+            "{:?}",
+            per_student
+        );
+    }
+
+    #[tokio::test]
+    async fn reservations_default_to_canonical_timestamps() {
+        let db = test_db();
+        execute(&db, "INSERT INTO reservations (id, book_id, student_id) VALUES ('r1', 'b1', 's1')", []);
+        assert!(scalar::<bool, _>(
+            &db,
+            "SELECT created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at) AND updated_at = created_at FROM reservations",
+            [],
+        ));
+    }
+}
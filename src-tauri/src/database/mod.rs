@@ -1,13 +1,45 @@
 use crate::models::*;
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result};
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
-use chrono::{DateTime, Utc, NaiveDateTime};
+use chrono::{DateTime, Utc, NaiveDate, NaiveDateTime, SecondsFormat};
 
 pub mod optimized;
+pub mod audit;
+pub mod settings;
+pub mod reports;
+pub mod maintenance;
+pub mod exports;
+pub mod imports;
+pub mod flagged_codes;
+pub mod hashing;
+pub mod trash;
+pub mod merge;
+pub mod pdf;
+pub mod group_borrowings;
+pub mod tags;
+pub mod theft_reports;
+pub mod fine_settings;
+pub mod backup;
+pub mod nightly;
+pub mod datetimes;
+pub mod renewals;
+pub mod checkout;
+pub mod self_check;
+pub mod batch;
+pub mod remote_records;
+pub mod conflicts;
+pub mod archive;
+pub mod book_duplicates;
+pub mod migrations;
+pub mod reservations;
+pub mod history;
+pub mod clear;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 // Helper function to parse datetime from SQLite format
-fn parse_sqlite_datetime(datetime_str: &str) -> Result<DateTime<Utc>, rusqlite::Error> {
+pub(crate) fn parse_sqlite_datetime(datetime_str: &str) -> Result<DateTime<Utc>, rusqlite::Error> {
     // Try RFC3339 format first (for new data)
     if let Ok(dt) = DateTime::parse_from_rfc3339(datetime_str) {
         return Ok(dt.with_timezone(&Utc));
@@ -27,6 +59,664 @@ fn parse_sqlite_datetime(datetime_str: &str) -> Result<DateTime<Utc>, rusqlite::
     Err(rusqlite::Error::InvalidColumnType(0, "datetime".to_string(), rusqlite::types::Type::Text))
 }
 
+// Helper to parse lowercase enum values stored in SQLite back into model enums
+fn parse_enum<T: serde::de::DeserializeOwned>(value: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+}
+
+pub(crate) const USER_SESSION_COLUMNS: &str =
+    "id, user_id, email, access_token, refresh_token, expires_at, user_metadata, role,
+     created_at, updated_at, last_activity, session_valid, offline_expiry, device_fingerprint";
+
+// Selected with USER_SESSION_COLUMNS
+fn row_to_user_session(row: &rusqlite::Row) -> Result<UserSession> {
+    let id_str: String = row.get(0)?;
+    let expires_str: String = row.get(5)?;
+    let created_str: String = row.get(8)?;
+    let updated_str: String = row.get(9)?;
+    let activity_str: String = row.get(10)?;
+    let offline_expiry_str: String = row.get(12)?;
+
+    Ok(UserSession {
+        id: Uuid::parse_str(&id_str)
+            .map_err(|_| rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text))?,
+        user_id: row.get(1)?,
+        email: row.get(2)?,
+        access_token: row.get(3)?,
+        refresh_token: row.get(4)?,
+        expires_at: parse_sqlite_datetime(&expires_str)?,
+        user_metadata: row.get(6)?,
+        role: row.get(7)?,
+        created_at: parse_sqlite_datetime(&created_str)?,
+        updated_at: parse_sqlite_datetime(&updated_str)?,
+        last_activity: parse_sqlite_datetime(&activity_str)?,
+        session_valid: row.get::<_, i32>(11)? == 1,
+        offline_expiry: parse_sqlite_datetime(&offline_expiry_str)?,
+        device_fingerprint: row.get(13)?,
+    })
+}
+
+// First session in query order that can be read. Corrupt rows are logged and
+// passed over so one bad row can't sign everybody out; find_corrupt_sessions lists them.
+fn first_readable_session<P: rusqlite::Params>(stmt: &mut rusqlite::Statement<'_>, params: P) -> Result<Option<UserSession>> {
+    let mut rows = stmt.query(params)?;
+    while let Some(row) = rows.next()? {
+        match row_to_user_session(row) {
+            Ok(session) => return Ok(Some(session)),
+            Err(e) => {
+                let id: String = row.get(0).unwrap_or_default();
+                eprintln!("Skipping unreadable session '{}': {}", id, e);
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn parse_sqlite_date(date_str: &str) -> Result<NaiveDate, rusqlite::Error> {
+    // Dates may be stored as plain dates or as full timestamps
+    NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+        .or_else(|_| parse_sqlite_datetime(date_str).map(|dt| dt.date_naive()))
+        .map_err(|_| rusqlite::Error::InvalidColumnType(0, "date".to_string(), rusqlite::types::Type::Text))
+}
+
+const BORROWING_COLUMNS: &str = "id, student_id, book_id, borrowed_date, due_date, returned_date,
+    status, fine_amount, notes, issued_by, returned_by, created_at, updated_at, fine_paid,
+    book_copy_id, condition_at_issue, condition_at_return, is_lost, tracking_code,
+    return_notes, copy_condition, group_borrowing_id, borrower_type, staff_id, due_date_override";
+
+// Maps a row selected with BORROWING_COLUMNS into a Borrowing
+fn row_to_borrowing(row: &rusqlite::Row) -> rusqlite::Result<Borrowing> {
+    let parse_uuid = |value: Option<String>| value.and_then(|s| Uuid::parse_str(&s).ok());
+    let id_str: String = row.get("id")?;
+    let borrowed_str: Option<String> = row.get("borrowed_date")?;
+    let due_str: String = row.get("due_date")?;
+    let returned_str: Option<String> = row.get("returned_date")?;
+    let status_str: Option<String> = row.get("status")?;
+    let borrower_type_str: Option<String> = row.get("borrower_type")?;
+    let created_str: String = row.get("created_at")?;
+    let updated_str: String = row.get("updated_at")?;
+    let due_date = parse_sqlite_date(&due_str)?;
+
+    Ok(Borrowing {
+        id: Uuid::parse_str(&id_str).map_err(|e| {
+            eprintln!("Failed to parse borrowing ID '{}': {}", id_str, e);
+            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+        })?,
+        student_id: parse_uuid(row.get("student_id")?),
+        book_id: parse_uuid(row.get("book_id")?),
+        borrowed_date: borrowed_str.as_deref().map(parse_sqlite_date).transpose()?.unwrap_or(due_date),
+        due_date,
+        returned_date: returned_str.as_deref().map(parse_sqlite_date).transpose()?,
+        status: status_str.as_deref().and_then(parse_enum).unwrap_or(BorrowingStatus::Active),
+        fine_amount: row.get::<_, Option<f64>>("fine_amount")?.unwrap_or(0.0),
+        notes: row.get("notes")?,
+        issued_by: parse_uuid(row.get("issued_by")?),
+        returned_by: parse_uuid(row.get("returned_by")?),
+        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+        fine_paid: row.get::<_, Option<bool>>("fine_paid")?.unwrap_or(false),
+        book_copy_id: parse_uuid(row.get("book_copy_id")?),
+        condition_at_issue: row.get::<_, Option<String>>("condition_at_issue")?.unwrap_or_else(|| "good".to_string()),
+        condition_at_return: row.get("condition_at_return")?,
+        is_lost: row.get::<_, Option<bool>>("is_lost")?.unwrap_or(false),
+        tracking_code: row.get("tracking_code")?,
+        return_notes: row.get("return_notes")?,
+        copy_condition: row.get("copy_condition")?,
+        group_borrowing_id: parse_uuid(row.get("group_borrowing_id")?),
+        borrower_type: borrower_type_str.as_deref().and_then(parse_enum).unwrap_or(BorrowerType::Student),
+        staff_id: parse_uuid(row.get("staff_id")?),
+        due_date_override: row.get::<_, Option<bool>>("due_date_override")?.unwrap_or(false),
+    })
+}
+
+// Enum values whose SQLite representation is snake_case (e.g. LostBook -> lost_book)
+fn snake_case_value<T: std::fmt::Debug>(value: &T) -> String {
+    let mut out = String::new();
+    for (i, c) in format!("{:?}", value).chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+const FINE_COLUMNS: &str = "id, student_id, borrowing_id, fine_type, amount, description,
+    status, created_at, updated_at, created_by, borrower_type, staff_id";
+
+// Maps a row selected with FINE_COLUMNS into a Fine
+fn row_to_fine(row: &rusqlite::Row) -> rusqlite::Result<Fine> {
+    let parse_uuid = |value: Option<String>| value.and_then(|s| Uuid::parse_str(&s).ok());
+    let id_str: String = row.get("id")?;
+    let fine_type_str: String = row.get("fine_type")?;
+    let status_str: Option<String> = row.get("status")?;
+    let borrower_type_str: Option<String> = row.get("borrower_type")?;
+    let created_str: String = row.get("created_at")?;
+    let updated_str: String = row.get("updated_at")?;
+
+    Ok(Fine {
+        id: Uuid::parse_str(&id_str).map_err(|e| {
+            eprintln!("Failed to parse fine ID '{}': {}", id_str, e);
+            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+        })?,
+        student_id: parse_uuid(row.get("student_id")?),
+        borrowing_id: parse_uuid(row.get("borrowing_id")?),
+        // Stored as snake_case while the serde names have no separators
+        fine_type: parse_enum(&fine_type_str.replace('_', "")).unwrap_or(FineType::Overdue),
+        amount: row.get("amount")?,
+        description: row.get("description")?,
+        status: status_str.as_deref().and_then(parse_enum).unwrap_or(FineStatus::Unpaid),
+        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+        created_by: parse_uuid(row.get("created_by")?),
+        borrower_type: borrower_type_str.as_deref().and_then(parse_enum).unwrap_or(BorrowerType::Student),
+        staff_id: parse_uuid(row.get("staff_id")?),
+    })
+}
+
+const STUDENT_COLUMNS: &str = "id, admission_number, first_name, last_name, email, phone, class_grade,
+    address, date_of_birth, enrollment_date, status, created_at, updated_at, class_id,
+    academic_year, is_repeating, legacy_student_id, gender";
+
+// Maps a row selected with STUDENT_COLUMNS into a Student
+fn row_to_student(row: &rusqlite::Row) -> rusqlite::Result<Student> {
+    let id_str: String = row.get("id")?;
+    let class_id_str: Option<String> = row.get("class_id")?;
+    let birth_str: Option<String> = row.get("date_of_birth")?;
+    let enrollment_str: Option<String> = row.get("enrollment_date")?;
+    let created_str: String = row.get("created_at")?;
+    let updated_str: String = row.get("updated_at")?;
+
+    Ok(Student {
+        id: Uuid::parse_str(&id_str).map_err(|e| {
+            eprintln!("Failed to parse student ID '{}': {:?}", id_str, e);
+            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+        })?,
+        admission_number: row.get("admission_number")?,
+        first_name: row.get("first_name")?,
+        last_name: row.get("last_name")?,
+        email: row.get("email")?,
+        phone: row.get("phone")?,
+        class_grade: row.get::<_, Option<String>>("class_grade")?.unwrap_or_else(|| "Unknown".to_string()),
+        address: row.get("address")?,
+        date_of_birth: birth_str.as_deref().and_then(|s| parse_sqlite_date(s).ok()),
+        enrollment_date: enrollment_str.as_deref()
+            .and_then(|s| parse_sqlite_date(s).ok())
+            .unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+        status: row.get::<_, Option<String>>("status")?.unwrap_or_else(|| "active".to_string()),
+        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+        class_id: class_id_str.and_then(|s| Uuid::parse_str(&s).ok()),
+        academic_year: row.get::<_, Option<String>>("academic_year")?.unwrap_or_default(),
+        is_repeating: row.get::<_, Option<bool>>("is_repeating")?.unwrap_or(false),
+        legacy_student_id: row.get("legacy_student_id")?,
+        gender: row.get("gender")?,
+    })
+}
+
+const STAFF_COLUMNS: &str = "id, staff_id, first_name, last_name, email, phone, department, position,
+    status, created_at, updated_at, legacy_staff_id";
+
+// Maps a row selected with STAFF_COLUMNS into a Staff
+fn row_to_staff(row: &rusqlite::Row) -> rusqlite::Result<Staff> {
+    let id_str: String = row.get(0)?;
+    let created_str: String = row.get(9)?;
+    let updated_str: String = row.get(10)?;
+
+    Ok(Staff {
+        id: Uuid::parse_str(&id_str).map_err(|e| {
+            eprintln!("Failed to parse staff ID '{}': {:?}", id_str, e);
+            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+        })?,
+        staff_id: row.get(1)?,
+        first_name: row.get(2)?,
+        last_name: row.get(3)?,
+        email: row.get(4)?,
+        phone: row.get(5)?,
+        department: row.get(6)?,
+        position: row.get(7)?,
+        status: row.get(8)?,
+        created_at: DateTime::parse_from_rfc3339(&created_str)
+            .unwrap_or_else(|_| Utc::now().into())
+            .with_timezone(&Utc),
+        updated_at: DateTime::parse_from_rfc3339(&updated_str)
+            .unwrap_or_else(|_| Utc::now().into())
+            .with_timezone(&Utc),
+        legacy_staff_id: row.get(11)?,
+    })
+}
+
+const CLASS_COLUMNS: &str = "id, class_name, form_level, class_section, max_books_allowed, is_active,
+    created_at, updated_at, academic_level_type";
+
+// Maps a row selected with CLASS_COLUMNS into a Class
+fn row_to_class(row: &rusqlite::Row) -> rusqlite::Result<Class> {
+    let id_str: String = row.get("id")?;
+    let created_str: String = row.get("created_at")?;
+    let updated_str: String = row.get("updated_at")?;
+    let academic_level_str: Option<String> = row.get("academic_level_type")?;
+
+    Ok(Class {
+        id: Uuid::parse_str(&id_str).map_err(|e| {
+            eprintln!("Failed to parse class ID '{}': {:?}", id_str, e);
+            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+        })?,
+        class_name: row.get("class_name")?,
+        form_level: row.get("form_level")?,
+        class_section: row.get("class_section")?,
+        max_books_allowed: row.get::<_, Option<i32>>("max_books_allowed")?.unwrap_or(2),
+        is_active: row.get::<_, Option<bool>>("is_active")?.unwrap_or(true),
+        created_at: parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+        academic_level_type: match academic_level_str.as_deref() {
+            Some("grade") => AcademicLevelType::Grade,
+            _ => AcademicLevelType::Form,
+        },
+    })
+}
+
+fn insert_fine(conn: &Connection, fine: &Fine) -> Result<()> {
+    conn.execute(
+        "INSERT INTO fines (id, student_id, borrowing_id, fine_type, amount, description,
+         status, created_at, updated_at, created_by, borrower_type, staff_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        (
+            fine.id.to_string(),
+            fine.student_id.map(|id| id.to_string()),
+            fine.borrowing_id.map(|id| id.to_string()),
+            snake_case_value(&fine.fine_type),
+            fine.amount,
+            &fine.description,
+            format!("{:?}", fine.status).to_lowercase(),
+            fine.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            fine.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            fine.created_by.map(|id| id.to_string()),
+            format!("{:?}", fine.borrower_type).to_lowercase(),
+            fine.staff_id.map(|id| id.to_string()),
+        ),
+    )?;
+    Ok(())
+}
+
+// Write a borrowing row as given; callers take care of copy counts
+fn insert_borrowing(conn: &Connection, borrowing: &Borrowing) -> Result<()> {
+    conn.execute(
+        "INSERT INTO borrowings (id, student_id, book_id, borrowed_date, due_date, returned_date,
+         status, fine_amount, notes, issued_by, returned_by, created_at, updated_at, fine_paid,
+         book_copy_id, condition_at_issue, condition_at_return, is_lost, tracking_code,
+         return_notes, copy_condition, group_borrowing_id, borrower_type, staff_id, due_date_override)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)",
+        rusqlite::params![
+            borrowing.id.to_string(),
+            borrowing.student_id.map(|id| id.to_string()),
+            borrowing.book_id.map(|id| id.to_string()),
+            borrowing.borrowed_date.to_string(),
+            borrowing.due_date.to_string(),
+            borrowing.returned_date.map(|d| d.to_string()),
+            format!("{:?}", borrowing.status).to_lowercase(),
+            borrowing.fine_amount,
+            &borrowing.notes,
+            borrowing.issued_by.map(|id| id.to_string()),
+            borrowing.returned_by.map(|id| id.to_string()),
+            borrowing.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            borrowing.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            borrowing.fine_paid,
+            borrowing.book_copy_id.map(|id| id.to_string()),
+            &borrowing.condition_at_issue,
+            &borrowing.condition_at_return,
+            borrowing.is_lost,
+            &borrowing.tracking_code,
+            &borrowing.return_notes,
+            &borrowing.copy_condition,
+            borrowing.group_borrowing_id.map(|id| id.to_string()),
+            format!("{:?}", borrowing.borrower_type).to_lowercase(),
+            borrowing.staff_id.map(|id| id.to_string()),
+            borrowing.due_date_override,
+        ],
+    )?;
+    Ok(())
+}
+
+// Close one loan inside the caller's transaction: put the copy back on the shelf and
+// raise the configured overdue and damage fines that apply
+fn return_borrowing(conn: &Connection, borrowing_id: &str, return_data: &ReturnData) -> Result<Vec<Fine>> {
+    let mut borrowing = conn.query_row(
+        &format!("SELECT {} FROM borrowings WHERE id = ?1 AND deleted = 0", BORROWING_COLUMNS),
+        [borrowing_id],
+        row_to_borrowing
+    )?;
+
+    if borrowing.status == BorrowingStatus::Returned {
+        return Err(constraint_error(format!("Borrowing {} has already been returned", borrowing_id)));
+    }
+
+    let book_id = borrowing.book_id.map(|id| id.to_string());
+    let book_copy_id = borrowing.book_copy_id.map(|id| id.to_string());
+    let returned_date = return_data.returned_date.unwrap_or_else(|| Utc::now().date_naive());
+    conn.execute(
+        "UPDATE borrowings SET status = 'returned', returned_date = ?2, condition_at_return = ?3,
+         return_notes = ?4, returned_by = ?5, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+        (
+            borrowing_id,
+            returned_date.to_string(),
+            &return_data.condition_at_return,
+            &return_data.return_notes,
+            return_data.returned_by.map(|id| id.to_string()),
+        ),
+    )?;
+
+    if let Some(copy_id) = &book_copy_id {
+        conn.execute(
+            "UPDATE book_copies SET status = 'available', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            [copy_id],
+        )?;
+    }
+
+    if let Some(book_id) = &book_id {
+        conn.execute(
+            "UPDATE books SET available_copies = MIN(available_copies + 1, total_copies), updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            [book_id],
+        )?;
+    }
+
+    borrowing.returned_date = Some(returned_date);
+    let rate_per_day = fine_settings::fine_rate(conn, &FineType::Overdue)?.unwrap_or(0.0);
+    let amount = DatabaseManager::calculate_overdue_fine(&borrowing, rate_per_day);
+    let mut fines = Vec::new();
+    if amount > 0.0 {
+        let days_overdue = (returned_date - borrowing.due_date).num_days();
+        fines.push(fine_settings::raise_fine(
+            conn, &borrowing, FineType::Overdue, amount,
+            format!("Returned {} days late", days_overdue), return_data.returned_by,
+        )?);
+    }
+
+    // Damage charge when the copy comes back damaged and a damaged fine is configured
+    if return_data.condition_at_return.as_deref().map(str::trim) == Some("damaged") {
+        if let Some(amount) = fine_settings::fine_rate(conn, &FineType::Damaged)?.filter(|a| *a > 0.0) {
+            let damage_fine = fine_settings::raise_fine(
+                conn, &borrowing, FineType::Damaged, amount,
+                "Returned damaged".to_string(), return_data.returned_by,
+            )?;
+            fines.push(damage_fine);
+        }
+    }
+    Ok(fines)
+}
+
+// Unpaid and partially paid fines still owed by a student
+fn outstanding_fines(conn: &Connection, student_id: &str) -> Result<f64> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(amount), 0) FROM fines
+         WHERE student_id = ?1 AND status IN ('unpaid', 'partial') AND deleted = 0",
+        [student_id],
+        |row| row.get(0)
+    )
+}
+
+// Everything that stops this borrower taking out this book, or this copy of it,
+// collected rather than stopping at the first so the desk can sort them all out at
+// once. Students are held to their class's max_books_allowed and the fine threshold,
+// staff to staff_max_active_loans. A borrower or book left as None isn't checked.
+fn checkout_blockers(
+    conn: &Connection,
+    borrower_type: &BorrowerType,
+    borrower_id: Option<&str>,
+    book_id: Option<&str>,
+    book_copy_id: Option<&str>,
+    rules: &LoanRules,
+    override_fine_block: bool,
+) -> Result<Vec<BorrowingError>> {
+    let mut blockers = Vec::new();
+
+    if let Some(borrower_id) = borrower_id {
+        // Students belong to a class by class_id, or by class_grade for records that predate it
+        let borrower: Option<(String, String, Option<i64>)> = match borrower_type {
+            BorrowerType::Student => conn.query_row(
+                "SELECT s.first_name || ' ' || s.last_name, COALESCE(s.status, 'active'),
+                        (SELECT COALESCE(c.max_books_allowed, 2) FROM classes c
+                         WHERE c.deleted = 0 AND (c.id = s.class_id OR (s.class_id IS NULL AND c.class_name = s.class_grade))
+                         LIMIT 1)
+                 FROM students s WHERE s.id = ?1 AND s.deleted = 0",
+                [borrower_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            ).optional()?,
+            BorrowerType::Staff => conn.query_row(
+                "SELECT first_name || ' ' || last_name, COALESCE(status, 'active'), ?2 FROM staff WHERE id = ?1 AND deleted = 0",
+                (borrower_id, rules.staff_max_active_loans),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            ).optional()?,
+        };
+
+        match borrower {
+            None => blockers.push(BorrowingError::BorrowerNotFound(borrower_id.to_string())),
+            Some((name, status, loan_limit)) => {
+                if status != "active" {
+                    blockers.push(BorrowingError::BorrowerInactive { name, status });
+                }
+
+                if *borrower_type == BorrowerType::Student && !override_fine_block {
+                    let threshold: Option<f64> = settings::read_setting(conn, settings::BORROW_BLOCK_THRESHOLD_KEY)?;
+                    if let Some(threshold) = threshold {
+                        let owed = outstanding_fines(conn, borrower_id)?;
+                        if owed > threshold {
+                            blockers.push(BorrowingError::FineThresholdExceeded { owed, threshold });
+                        }
+                    }
+                }
+
+                if let Some(limit) = loan_limit {
+                    let borrower_column = match borrower_type {
+                        BorrowerType::Student => "student_id",
+                        BorrowerType::Staff => "staff_id",
+                    };
+                    let active: i64 = conn.query_row(
+                        &format!(
+                            "SELECT COUNT(*) FROM borrowings
+                             WHERE {} = ?1 AND deleted = 0 AND returned_date IS NULL AND status IN ('active', 'overdue')",
+                            borrower_column
+                        ),
+                        [borrower_id],
+                        |row| row.get(0),
+                    )?;
+                    if active >= limit {
+                        blockers.push(BorrowingError::LoanLimitReached { active, limit });
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(book_id) = book_id {
+        let available_copies: Option<i64> = conn.query_row(
+            "SELECT available_copies FROM books WHERE id = ?1 AND deleted = 0",
+            [book_id],
+            |row| row.get(0),
+        ).optional()?;
+        // Copies held for another student's ready reservation aren't on offer
+        let holder = borrower_id.filter(|_| *borrower_type == BorrowerType::Student);
+        let held_for_others: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM reservations WHERE book_id = ?1 AND status = 'ready' AND student_id IS NOT ?2",
+            (book_id, holder),
+            |row| row.get(0),
+        )?;
+        // A scanned copy has to be the one on the shelf, whatever the book's count says
+        let copy: Option<(String, String)> = match book_copy_id {
+            Some(copy_id) => conn.query_row(
+                "SELECT COALESCE(book_code, tracking_code, id), COALESCE(status, 'available') FROM book_copies
+                 WHERE id = ?1 AND deleted = 0",
+                [copy_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).optional()?,
+            None => None,
+        };
+
+        match (available_copies, copy) {
+            (None, _) => blockers.push(BorrowingError::BookNotFound(book_id.to_string())),
+            (Some(_), Some((copy, status))) if status != "available" => {
+                blockers.push(BorrowingError::CopyUnavailable { copy, status });
+            }
+            (Some(available), _) if available <= held_for_others => blockers.push(BorrowingError::NoCopiesAvailable),
+            _ => {}
+        }
+    }
+
+    Ok(blockers)
+}
+
+// Checkout blockers and loan period cap every new borrowing goes through.
+// Already-closed loans are history being recorded, so only open ones are checked.
+fn apply_borrowing_rules(conn: &Connection, borrowing: &mut Borrowing, override_fine_block: bool, override_reason: Option<&str>) -> std::result::Result<(), BorrowingError> {
+    let borrower_id = match borrowing.borrower_type {
+        BorrowerType::Student => borrowing.student_id
+            .ok_or_else(|| constraint_error("A student borrowing needs a student_id"))?,
+        BorrowerType::Staff => borrowing.staff_id
+            .ok_or_else(|| constraint_error("A staff borrowing needs a staff_id"))?,
+    };
+
+    let rules: LoanRules = settings::read_setting(conn, settings::LOAN_RULES_KEY)?.unwrap_or_default();
+    if matches!(borrowing.status, BorrowingStatus::Active | BorrowingStatus::Overdue) {
+        let blockers = checkout_blockers(
+            conn,
+            &borrowing.borrower_type,
+            Some(&borrower_id.to_string()),
+            borrowing.book_id.map(|id| id.to_string()).as_deref(),
+            borrowing.book_copy_id.map(|id| id.to_string()).as_deref(),
+            &rules,
+            override_fine_block,
+        )?;
+        if let Some(blocker) = blockers.into_iter().next() {
+            return Err(blocker);
+        }
+    }
+
+    let max_due_date = rules.max_due_date(&borrowing.borrower_type, borrowing.borrowed_date);
+    borrowing.due_date_override = false;
+    if borrowing.due_date > max_due_date {
+        match override_reason.map(str::trim).filter(|reason| !reason.is_empty()) {
+            Some(reason) => {
+                let note = format!("Due date override: {}", reason);
+                borrowing.notes = Some(match borrowing.notes.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+                    Some(existing) => format!("{}\n{}", existing, note),
+                    None => note,
+                });
+                borrowing.due_date_override = true;
+            }
+            None => borrowing.due_date = max_due_date,
+        }
+    }
+    Ok(())
+}
+
+// Take a copy off the shelf and insert the borrowing, inside the caller's transaction
+fn store_borrowing(tx: &Connection, borrowing: &Borrowing) -> std::result::Result<(), BorrowingError> {
+    let is_open = matches!(borrowing.status, BorrowingStatus::Active | BorrowingStatus::Overdue);
+    if let (true, Some(book_id)) = (is_open, borrowing.book_id) {
+        let updated = tx.execute(
+            "UPDATE books SET available_copies = available_copies - 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+             WHERE id = ?1 AND deleted = 0 AND available_copies > 0",
+            [book_id.to_string()],
+        )?;
+        if updated == 0 {
+            return Err(BorrowingError::NoCopiesAvailable);
+        }
+
+        if let Some(copy_id) = borrowing.book_copy_id {
+            tx.execute(
+                "UPDATE book_copies SET status = 'borrowed', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+                [copy_id.to_string()],
+            )?;
+        }
+
+        if let Some(student_id) = borrowing.student_id {
+            reservations::fulfil_reservation(tx, &book_id.to_string(), &student_id.to_string())?;
+        }
+    }
+
+    insert_borrowing(tx, borrowing)?;
+    Ok(())
+}
+
+// Helper to surface business rule violations as rusqlite errors
+fn constraint_error(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
+        Some(message.into())
+    )
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BorrowingError {
+    #[error("Borrowing blocked: outstanding fines of {owed:.2} exceed the limit of {threshold:.2}")]
+    FineThresholdExceeded { owed: f64, threshold: f64 },
+
+    #[error("No copies available")]
+    NoCopiesAvailable,
+
+    #[error("Borrowing blocked: {active} books already on loan, the limit is {limit}")]
+    LoanLimitReached { active: i64, limit: i64 },
+
+    #[error("No student or staff member matches {0}")]
+    BorrowerNotFound(String),
+
+    #[error("Borrowing blocked: {name} is {status}")]
+    BorrowerInactive { name: String, status: String },
+
+    #[error("No book or copy matches {0}")]
+    BookNotFound(String),
+
+    #[error("Copy {copy} is {status}, not on the shelf")]
+    CopyUnavailable { copy: String, status: String },
+
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StudentError {
+    #[error("Admission number {admission_number} is already used by student {existing_id}")]
+    DuplicateAdmissionNumber { admission_number: String, existing_id: String },
+
+    #[error("Admission number {admission_number} is held by deleted student {existing_id}; restore or purge that student first")]
+    HeldByDeletedStudent { admission_number: String, existing_id: String },
+
+    #[error("Gender must be one of {}, got {0}", GENDERS.join(", "))]
+    InvalidGender(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+pub const GENDERS: &[&str] = &["female", "male", "other"];
+
+/// Lowercase a submitted gender and check it's one we record; blank means not given
+pub fn normalize_gender(gender: Option<&str>) -> std::result::Result<Option<String>, StudentError> {
+    match gender.map(|g| g.trim().to_lowercase()).filter(|g| !g.is_empty()) {
+        None => Ok(None),
+        Some(g) if GENDERS.contains(&g.as_str()) => Ok(Some(g)),
+        Some(g) => Err(StudentError::InvalidGender(g)),
+    }
+}
+
+// Admission numbers are compared case-insensitively, as the roster import does;
+// the student's own row doesn't count so updates can keep their number. Soft-deleted
+// students still hold theirs (the column is UNIQUE), so they get their own error.
+fn check_admission_number_free(conn: &Connection, admission_number: &str, student_id: &str) -> std::result::Result<(), StudentError> {
+    let existing: Option<(String, bool)> = conn.query_row(
+        "SELECT id, COALESCE(deleted, 0) AS deleted FROM students
+         WHERE LOWER(admission_number) = LOWER(?1) AND id != ?2
+         ORDER BY deleted LIMIT 1",
+        [admission_number.trim(), student_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional()?;
+    let admission_number = admission_number.trim().to_string();
+    match existing {
+        Some((existing_id, true)) => Err(StudentError::HeldByDeletedStudent { admission_number, existing_id }),
+        Some((existing_id, false)) => Err(StudentError::DuplicateAdmissionNumber { admission_number, existing_id }),
+        None => Ok(()),
+    }
+}
+
 pub struct DatabaseManager {
     connection: Arc<Mutex<Connection>>,
 }
@@ -41,6 +731,220 @@ pub struct LibraryStats {
     pub categories_count: i32,
 }
 
+// Everything a student had out, brought back in one go
+#[derive(Debug, serde::Serialize)]
+pub struct StudentReturnResult {
+    pub student_id: String,
+    pub returned_date: NaiveDate,
+    pub returned_borrowing_ids: Vec<String>,
+    pub fines: Vec<Fine>,
+    pub total_fine_amount: f64,
+    pub reservations_ready: Vec<reservations::Reservation>,
+}
+
+// One copy handed back. A copy other than the one issued files a theft report and
+// marks the loan lost instead of returning it. A copy that does come back is held
+// for the next reservation on its book, if there is one.
+#[derive(Debug)]
+pub struct BookReturnResult {
+    pub theft_report: Option<TheftReport>,
+    pub copy_change: Option<BookCopyChange>,
+    pub fines: Vec<Fine>,
+    pub flagged_code: Option<FlaggedCode>,
+    pub reservation_ready: Option<reservations::Reservation>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PaginatedBorrowings {
+    pub borrowings: Vec<serde_json::Value>,
+    pub current_page: usize,
+    pub page_size: usize,
+    pub total_count: usize,
+    pub total_pages: usize,
+    pub has_next: bool,
+    pub has_previous: bool,
+}
+
+// A copy after an update or delete, with the parent book's counts when they were adjusted
+#[derive(Debug, serde::Serialize)]
+pub struct BookCopyChange {
+    pub copy_id: String,
+    pub book_id: Option<String>,
+    pub condition: String,
+    pub status: String,
+    pub book_total_copies: Option<i64>,
+    pub book_available_copies: Option<i64>,
+}
+
+// Book copy conditions allowed by the book_copies CHECK constraint
+const COPY_CONDITIONS: &[&str] = &["good", "fair", "poor", "damaged", "lost"];
+
+// Lost and stolen copies no longer count towards the book's stock
+fn copy_out_of_stock(condition: &str, status: &str) -> bool {
+    condition == "lost" || matches!(status, "lost" | "stolen")
+}
+
+// Borrowing joined with its student, book and copy, as returned to the UI
+const BORROWING_DETAILS_SELECT: &str = "
+    SELECT
+        b.id, b.student_id, b.book_id, b.borrowed_date, b.due_date, b.returned_date,
+        b.status, b.fine_amount, b.notes, b.issued_by, b.returned_by, b.created_at, b.updated_at,
+        b.fine_paid, b.book_copy_id, b.condition_at_issue, b.condition_at_return, b.is_lost,
+        b.tracking_code, b.return_notes, b.copy_condition, b.group_borrowing_id, b.borrower_type, b.staff_id, b.due_date_override,
+        COALESCE(b.renewal_count, 0) as renewal_count,
+        s.first_name as student_first_name, s.last_name as student_last_name, s.admission_number,
+        st.first_name as staff_first_name, st.last_name as staff_last_name, st.staff_id as staff_number,
+        book.title as book_title, book.author as book_author, book.book_code,
+        bc.copy_number, bc.condition as copy_condition_status
+    FROM borrowings b
+    LEFT JOIN students s ON b.student_id = s.id
+    LEFT JOIN staff st ON b.staff_id = st.id
+    LEFT JOIN books book ON b.book_id = book.id
+    LEFT JOIN book_copies bc ON b.book_copy_id = bc.id
+";
+
+fn row_to_borrowing_details(row: &rusqlite::Row) -> Result<serde_json::Value> {
+    Ok(serde_json::json!({
+        "id": row.get::<_, String>("id")?,
+        "student_id": row.get::<_, Option<String>>("student_id")?,
+        "book_id": row.get::<_, Option<String>>("book_id")?,
+        "borrowed_date": row.get::<_, String>("borrowed_date")?,
+        "due_date": row.get::<_, String>("due_date")?,
+        "returned_date": row.get::<_, Option<String>>("returned_date")?,
+        "status": row.get::<_, String>("status")?,
+        "fine_amount": row.get::<_, Option<f64>>("fine_amount")?,
+        "notes": row.get::<_, Option<String>>("notes")?,
+        "issued_by": row.get::<_, Option<String>>("issued_by")?,
+        "returned_by": row.get::<_, Option<String>>("returned_by")?,
+        "created_at": row.get::<_, String>("created_at")?,
+        "updated_at": row.get::<_, String>("updated_at")?,
+        "fine_paid": row.get::<_, Option<bool>>("fine_paid")?,
+        "book_copy_id": row.get::<_, Option<String>>("book_copy_id")?,
+        "condition_at_issue": row.get::<_, Option<String>>("condition_at_issue")?,
+        "condition_at_return": row.get::<_, Option<String>>("condition_at_return")?,
+        "is_lost": row.get::<_, Option<bool>>("is_lost")?,
+        "tracking_code": row.get::<_, Option<String>>("tracking_code")?,
+        "return_notes": row.get::<_, Option<String>>("return_notes")?,
+        "copy_condition": row.get::<_, Option<String>>("copy_condition")?,
+        "group_borrowing_id": row.get::<_, Option<String>>("group_borrowing_id")?,
+        "borrower_type": row.get::<_, String>("borrower_type")?,
+        "staff_id": row.get::<_, Option<String>>("staff_id")?,
+        "due_date_override": row.get::<_, Option<bool>>("due_date_override")?.unwrap_or(false),
+        "renewal_count": row.get::<_, i64>("renewal_count")?,
+        "students": if row.get::<_, Option<String>>("student_first_name")?.is_some() {
+            Some(serde_json::json!({
+                "id": row.get::<_, Option<String>>("student_id")?,
+                "first_name": row.get::<_, Option<String>>("student_first_name")?,
+                "last_name": row.get::<_, Option<String>>("student_last_name")?,
+                "admission_number": row.get::<_, Option<String>>("admission_number")?
+            }))
+        } else {
+            None
+        },
+        "staff": if row.get::<_, Option<String>>("staff_first_name")?.is_some() {
+            Some(serde_json::json!({
+                "id": row.get::<_, Option<String>>("staff_id")?,
+                "first_name": row.get::<_, Option<String>>("staff_first_name")?,
+                "last_name": row.get::<_, Option<String>>("staff_last_name")?,
+                "staff_id": row.get::<_, Option<String>>("staff_number")?
+            }))
+        } else {
+            None
+        },
+        "books": if row.get::<_, Option<String>>("book_title")?.is_some() {
+            Some(serde_json::json!({
+                "id": row.get::<_, Option<String>>("book_id")?,
+                "title": row.get::<_, Option<String>>("book_title")?,
+                "author": row.get::<_, Option<String>>("book_author")?,
+                "book_code": row.get::<_, Option<String>>("book_code")?
+            }))
+        } else {
+            None
+        },
+        "book_copies": if row.get::<_, Option<String>>("copy_number")?.is_some() {
+            Some(serde_json::json!({
+                "id": row.get::<_, Option<String>>("book_copy_id")?,
+                "copy_number": row.get::<_, Option<String>>("copy_number")?,
+                "condition": row.get::<_, Option<String>>("copy_condition_status")?
+            }))
+        } else {
+            None
+        }
+    }))
+}
+
+fn row_to_book(row: &rusqlite::Row) -> Result<Book> {
+    row_to_book_at(row, 0)
+}
+
+// Same columns as row_to_book, starting at `offset` so a book can be selected alongside another record
+fn row_to_book_at(row: &rusqlite::Row, offset: usize) -> Result<Book> {
+    let id_str: String = row.get(offset)?;
+    let category_id_str: Option<String> = row.get(offset + 6)?;
+    let created_str: String = row.get(offset + 11)?;
+    let updated_str: String = row.get(offset + 12)?;
+
+    Ok(Book {
+        id: Uuid::parse_str(&id_str).map_err(|e| {
+            eprintln!("Failed to parse book ID '{}': {}", id_str, e);
+            rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
+        })?,
+        title: row.get(offset + 1)?,
+        author: row.get(offset + 2)?,
+        isbn: row.get(offset + 3)?,
+        genre: None, // Not in simplified schema
+        publisher: row.get(offset + 4)?,
+        publication_year: row.get(offset + 5)?,
+        category_id: category_id_str.and_then(|s| Uuid::parse_str(&s).ok()),
+        total_copies: row.get(offset + 7)?,
+        available_copies: row.get(offset + 8)?,
+        shelf_location: row.get(offset + 9)?,
+        cover_image_url: None,
+        description: row.get(offset + 10)?,
+        status: BookStatus::Available, // Default
+        condition: None,
+        book_code: None,
+        acquisition_year: None,
+        legacy_book_id: None,
+        legacy_isbn: None,
+        created_at: parse_sqlite_datetime(&created_str)
+            .map_err(|e| {
+                eprintln!("Failed to parse book created_at '{}': {}", created_str, e);
+                rusqlite::Error::InvalidColumnType(0, "created_at".to_string(), rusqlite::types::Type::Text)
+            })?,
+        updated_at: parse_sqlite_datetime(&updated_str)
+            .map_err(|e| {
+                eprintln!("Failed to parse book updated_at '{}': {}", updated_str, e);
+                rusqlite::Error::InvalidColumnType(0, "updated_at".to_string(), rusqlite::types::Type::Text)
+            })?,
+    })
+}
+
+// Shift a book's copy counts, never below zero or available above total. Returns the new (total, available).
+fn adjust_book_counts(conn: &Connection, book_id: &str, total_delta: i64, available_delta: i64) -> Result<(i64, i64)> {
+    conn.execute(
+        "UPDATE books SET total_copies = MAX(0, total_copies + ?2),
+         available_copies = MIN(MAX(0, total_copies + ?2), MAX(0, available_copies + ?3)),
+         updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+        (book_id, total_delta, available_delta),
+    )?;
+    conn.query_row(
+        "SELECT total_copies, available_copies FROM books WHERE id = ?1",
+        [book_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+}
+
+// Create missing tables and bring older databases up to the current columns.
+// Also builds the reference schema self_check compares against.
+fn apply_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(include_str!("schema.sql"))?;
+
+    migrations::run_migrations(conn)?;
+    maintenance::normalize_legacy_borrowing_status(conn)?;
+    Ok(())
+}
+
 impl DatabaseManager {
     pub fn new(db_path: &str) -> Result<Self> {
         let conn = Connection::open(db_path)?;
@@ -55,15 +959,24 @@ impl DatabaseManager {
             PRAGMA mmap_size = 268435456;
         ")?;
         
-        // Run the schema creation
-        let schema = include_str!("schema.sql");
-        conn.execute_batch(schema)?;
+        apply_schema(&conn)?;
         
         Ok(Self {
             connection: Arc::new(Mutex::new(conn)),
         })
     }
 
+    /// Empty database with the full schema applied, for unit tests
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        apply_schema(&conn)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(conn)),
+        })
+    }
+
     /// Get a reference to the connection for direct database operations
     pub fn get_connection(&self) -> &Arc<Mutex<Connection>> {
         &self.connection
@@ -97,8 +1010,8 @@ impl DatabaseManager {
                 book.available_copies,
                 &book.shelf_location,
                 &book.description,
-                book.created_at.to_rfc3339(),
-                book.updated_at.to_rfc3339(),
+                book.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                book.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
             ),
         )?;
         Ok(())
@@ -111,51 +1024,62 @@ impl DatabaseManager {
              FROM books WHERE deleted = 0 ORDER BY title"
         )?;
 
-        let books = stmt.query_map([], |row| {
-            let id_str: String = row.get(0)?;
-            let category_id_str: Option<String> = row.get(6)?;
-            let created_str: String = row.get(11)?;
-            let updated_str: String = row.get(12)?;
-            
-            Ok(Book {
-                id: Uuid::parse_str(&id_str).map_err(|e| {
-                    eprintln!("Failed to parse book ID '{}': {}", id_str, e);
-                    rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
-                })?,
-                title: row.get(1)?,
-                author: row.get(2)?,
-                isbn: row.get(3)?,
-                genre: None, // Not in simplified schema
-                publisher: row.get(4)?,
-                publication_year: row.get(5)?,
-                category_id: category_id_str.and_then(|s| Uuid::parse_str(&s).ok()),
-                total_copies: row.get(7)?,
-                available_copies: row.get(8)?,
-                shelf_location: row.get(9)?,
-                cover_image_url: None,
-                description: row.get(10)?,
-                status: BookStatus::Available, // Default
-                condition: None,
-                book_code: None,
-                acquisition_year: None,
-                legacy_book_id: None,
-                legacy_isbn: None,
-                created_at: parse_sqlite_datetime(&created_str)
-                    .map_err(|e| {
-                        eprintln!("Failed to parse book created_at '{}': {}", created_str, e);
-                        rusqlite::Error::InvalidColumnType(0, "created_at".to_string(), rusqlite::types::Type::Text)
-                    })?,
-                updated_at: parse_sqlite_datetime(&updated_str)
-                    .map_err(|e| {
-                        eprintln!("Failed to parse book updated_at '{}': {}", updated_str, e);
-                        rusqlite::Error::InvalidColumnType(0, "updated_at".to_string(), rusqlite::types::Type::Text)
-                    })?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
+        let books = stmt.query_map([], row_to_book)?.collect::<Result<Vec<_>, _>>()?;
 
         Ok(books)
     }
 
+    /// One page of books plus the total matching count, with search and category filters applied in SQL.
+    /// The category filter matches either the category id or its name.
+    pub async fn get_books_paginated(
+        &self,
+        page: usize,
+        page_size: usize,
+        category_filter: Option<&str>,
+        search_query: Option<&str>,
+    ) -> Result<(Vec<Book>, usize)> {
+        let conn = self.lock_connection()?;
+        let page_size = page_size.max(1);
+
+        let mut conditions = vec!["b.deleted = 0".to_string()];
+        let mut params: Vec<String> = Vec::new();
+
+        if let Some(category) = category_filter.map(str::trim).filter(|c| !c.is_empty()) {
+            params.push(category.to_lowercase());
+            conditions.push(format!("(LOWER(b.category_id) = ?{0} OR LOWER(c.name) = ?{0})", params.len()));
+        }
+
+        if let Some(query) = search_query.map(str::trim).filter(|q| !q.is_empty()) {
+            params.push(format!("%{}%", query.to_lowercase()));
+            conditions.push(format!(
+                "(LOWER(b.title) LIKE ?{0} OR LOWER(b.author) LIKE ?{0} OR LOWER(COALESCE(b.isbn, '')) LIKE ?{0})",
+                params.len()
+            ));
+        }
+
+        let from_clause = format!(
+            "FROM books b LEFT JOIN categories c ON b.category_id = c.id WHERE {}",
+            conditions.join(" AND ")
+        );
+
+        let total_count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) {}", from_clause),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT b.id, b.title, b.author, b.isbn, b.publisher, b.publication_year, b.category_id, b.total_copies,
+                    b.available_copies, b.shelf_location, b.description, b.created_at, b.updated_at
+             {} ORDER BY b.title, b.id LIMIT {} OFFSET {}",
+            from_clause, page_size, page * page_size
+        ))?;
+        let books = stmt.query_map(rusqlite::params_from_iter(params.iter()), row_to_book)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((books, total_count as usize))
+    }
+
     pub async fn get_books_with_details(&self) -> Result<Vec<BookWithDetails>> {
         let books = self.get_books().await?;
         Ok(books.into_iter().map(|book| BookWithDetails {
@@ -265,8 +1189,8 @@ impl DatabaseManager {
                 category.id.to_string(),
                 &category.name,
                 &category.description,
-                category.created_at.to_rfc3339(),
-                category.updated_at.to_rfc3339(),
+                category.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                category.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
             ),
         )?;
         Ok(())
@@ -275,7 +1199,7 @@ impl DatabaseManager {
     pub async fn get_students(&self) -> Result<Vec<Student>> {
         let conn = self.lock_connection()?;
         let mut stmt = conn.prepare(
-            "SELECT id, first_name, last_name, admission_number, class_id, email, phone, address, created_at, updated_at 
+            "SELECT id, first_name, last_name, admission_number, class_id, email, phone, address, created_at, updated_at, gender
              FROM students WHERE deleted = 0 ORDER BY first_name, last_name"
         )?;
 
@@ -308,17 +1232,20 @@ impl DatabaseManager {
                 academic_year: "2024".to_string(), // Default
                 is_repeating: false, // Default
                 legacy_student_id: None,
+                gender: row.get(10)?,
             })
         })?.collect::<Result<Vec<_>, _>>()?;
 
         Ok(students)
     }
 
-    pub async fn create_student(&self, student: &Student) -> Result<()> {
+    pub async fn create_student(&self, student: &Student) -> Result<(), StudentError> {
         let conn = self.lock_connection()?;
+        let gender = normalize_gender(student.gender.as_deref())?;
+        check_admission_number_free(&conn, &student.admission_number, &student.id.to_string())?;
         conn.execute(
-            "INSERT INTO students (id, first_name, last_name, admission_number, class_id, email, phone, address, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT INTO students (id, first_name, last_name, admission_number, class_id, email, phone, address, created_at, updated_at, gender)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             (
                 student.id.to_string(),
                 &student.first_name,
@@ -328,13 +1255,52 @@ impl DatabaseManager {
                 &student.email,
                 &student.phone,
                 &student.address,
-                student.created_at.to_rfc3339(),
-                student.updated_at.to_rfc3339(),
+                student.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                student.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                gender,
             ),
         )?;
         Ok(())
     }
 
+    /// Student with class, open borrowings and outstanding (unpaid/partial) fines
+    pub async fn get_student_with_fines(&self, student_id: &str) -> Result<StudentWithClass> {
+        let conn = self.lock_connection()?;
+
+        let student = conn.query_row(
+            &format!("SELECT {} FROM students WHERE id = ?1 AND deleted = 0", STUDENT_COLUMNS),
+            [student_id],
+            row_to_student
+        )?;
+
+        let class = match student.class_id {
+            Some(class_id) => conn.query_row(
+                &format!("SELECT {} FROM classes WHERE id = ?1", CLASS_COLUMNS),
+                [class_id.to_string()],
+                row_to_class
+            ).optional()?,
+            None => None,
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM borrowings
+             WHERE student_id = ?1 AND status IN ('active', 'overdue') AND deleted = 0
+             ORDER BY due_date",
+            BORROWING_COLUMNS
+        ))?;
+        let active_borrowings = stmt.query_map([student_id], row_to_borrowing)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let total_fines = outstanding_fines(&conn, student_id)?;
+
+        Ok(StudentWithClass {
+            student,
+            class,
+            active_borrowings,
+            total_fines,
+        })
+    }
+
     // Update methods
     pub async fn update_book(&self, book: &Book) -> Result<()> {
         let conn = self.lock_connection()?;
@@ -354,17 +1320,19 @@ impl DatabaseManager {
                 book.available_copies,
                 &book.shelf_location,
                 &book.description,
-                book.updated_at.to_rfc3339(),
+                book.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
             ),
         )?;
         Ok(())
     }
 
-    pub async fn update_student(&self, student: &Student) -> Result<()> {
+    pub async fn update_student(&self, student: &Student) -> Result<(), StudentError> {
         let conn = self.lock_connection()?;
+        let gender = normalize_gender(student.gender.as_deref())?;
+        check_admission_number_free(&conn, &student.admission_number, &student.id.to_string())?;
         conn.execute(
             "UPDATE students SET first_name = ?2, last_name = ?3, admission_number = ?4, 
-             class_id = ?5, email = ?6, phone = ?7, address = ?8, updated_at = ?9 WHERE id = ?1",
+             class_id = ?5, email = ?6, phone = ?7, address = ?8, updated_at = ?9, gender = ?10 WHERE id = ?1",
             (
                 student.id.to_string(),
                 &student.first_name,
@@ -374,7 +1342,8 @@ impl DatabaseManager {
                 &student.email,
                 &student.phone,
                 &student.address,
-                student.updated_at.to_rfc3339(),
+                student.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                gender,
             ),
         )?;
         Ok(())
@@ -384,7 +1353,7 @@ impl DatabaseManager {
     pub async fn delete_book(&self, book_id: &str) -> Result<()> {
         let conn = self.lock_connection()?;
         conn.execute(
-            "UPDATE books SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+            "UPDATE books SET deleted = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
             [book_id],
         )?;
         Ok(())
@@ -393,7 +1362,7 @@ impl DatabaseManager {
     pub async fn delete_student(&self, student_id: &str) -> Result<()> {
         let conn = self.lock_connection()?;
         conn.execute(
-            "UPDATE students SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+            "UPDATE students SET deleted = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
             [student_id],
         )?;
         Ok(())
@@ -414,14 +1383,18 @@ impl DatabaseManager {
             |row| row.get(0)
         ).unwrap_or(0);
         
+        // Open loans are stored as 'active' (or 'overdue' once marked), matching BorrowingStatus
         let active_borrowings: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM borrowings WHERE status = 'borrowed'",
+            "SELECT COUNT(*) FROM borrowings
+             WHERE deleted = 0 AND returned_date IS NULL AND status IN ('active', 'overdue')",
             [],
             |row| row.get(0)
         ).unwrap_or(0);
         
         let overdue_books: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM borrowings WHERE status = 'borrowed' AND due_date < date('now')",
+            "SELECT COUNT(*) FROM borrowings
+             WHERE deleted = 0 AND returned_date IS NULL
+               AND (status = 'overdue' OR (status = 'active' AND due_date < date('now')))",
             [],
             |row| row.get(0)
         ).unwrap_or(0);
@@ -458,14 +1431,14 @@ impl DatabaseManager {
                 &session.email,
                 &session.access_token,
                 &session.refresh_token,
-                session.expires_at.to_rfc3339(),
+                session.expires_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                 &session.user_metadata,
                 &session.role,
-                session.created_at.to_rfc3339(),
-                session.updated_at.to_rfc3339(),
-                session.last_activity.to_rfc3339(),
+                session.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                session.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                session.last_activity.to_rfc3339_opts(SecondsFormat::Millis, true),
                 session.session_valid as i32,
-                session.offline_expiry.to_rfc3339(),
+                session.offline_expiry.to_rfc3339_opts(SecondsFormat::Millis, true),
                 &session.device_fingerprint,
             ),
         )?;
@@ -475,94 +1448,30 @@ impl DatabaseManager {
 
     pub async fn get_valid_user_session(&self, user_id: &str) -> Result<Option<UserSession>> {
         let conn = self.lock_connection()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, user_id, email, access_token, refresh_token, expires_at, user_metadata, role,
-                    created_at, updated_at, last_activity, session_valid, offline_expiry, device_fingerprint
-             FROM user_sessions 
-             WHERE user_id = ?1 AND session_valid = 1 AND offline_expiry > datetime('now')
-             ORDER BY created_at DESC LIMIT 1"
-        )?;
-
-        let session_result = stmt.query_row([user_id], |row| {
-            let id_str: String = row.get(0)?;
-            let expires_str: String = row.get(5)?;
-            let created_str: String = row.get(8)?;
-            let updated_str: String = row.get(9)?;
-            let activity_str: String = row.get(10)?;
-            let offline_expiry_str: String = row.get(12)?;
-            
-            Ok(UserSession {
-                id: Uuid::parse_str(&id_str).unwrap(),
-                user_id: row.get(1)?,
-                email: row.get(2)?,
-                access_token: row.get(3)?,
-                refresh_token: row.get(4)?,
-                expires_at: parse_sqlite_datetime(&expires_str)?,
-                user_metadata: row.get(6)?,
-                role: row.get(7)?,
-                created_at: parse_sqlite_datetime(&created_str)?,
-                updated_at: parse_sqlite_datetime(&updated_str)?,
-                last_activity: parse_sqlite_datetime(&activity_str)?,
-                session_valid: row.get::<_, i32>(11)? == 1,
-                offline_expiry: parse_sqlite_datetime(&offline_expiry_str)?,
-                device_fingerprint: row.get(13)?,
-            })
-        });
-
-        match session_result {
-            Ok(session) => Ok(Some(session)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e),
-        }
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM user_sessions
+             WHERE user_id = ?1 AND session_valid = 1 AND datetime(offline_expiry) > datetime('now')
+             ORDER BY created_at DESC",
+            USER_SESSION_COLUMNS
+        ))?;
+        first_readable_session(&mut stmt, [user_id])
     }
 
     pub async fn get_any_valid_session(&self) -> Result<Option<UserSession>> {
         let conn = self.lock_connection()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, user_id, email, access_token, refresh_token, expires_at, user_metadata, role,
-                    created_at, updated_at, last_activity, session_valid, offline_expiry, device_fingerprint
-             FROM user_sessions 
-             WHERE session_valid = 1 AND offline_expiry > datetime('now')
-             ORDER BY last_activity DESC LIMIT 1"
-        )?;
-
-        let session_result = stmt.query_row([], |row| {
-            let id_str: String = row.get(0)?;
-            let expires_str: String = row.get(5)?;
-            let created_str: String = row.get(8)?;
-            let updated_str: String = row.get(9)?;
-            let activity_str: String = row.get(10)?;
-            let offline_expiry_str: String = row.get(12)?;
-            
-            Ok(UserSession {
-                id: Uuid::parse_str(&id_str).unwrap(),
-                user_id: row.get(1)?,
-                email: row.get(2)?,
-                access_token: row.get(3)?,
-                refresh_token: row.get(4)?,
-                expires_at: parse_sqlite_datetime(&expires_str)?,
-                user_metadata: row.get(6)?,
-                role: row.get(7)?,
-                created_at: parse_sqlite_datetime(&created_str)?,
-                updated_at: parse_sqlite_datetime(&updated_str)?,
-                last_activity: parse_sqlite_datetime(&activity_str)?,
-                session_valid: row.get::<_, i32>(11)? == 1,
-                offline_expiry: parse_sqlite_datetime(&offline_expiry_str)?,
-                device_fingerprint: row.get(13)?,
-            })
-        });
-
-        match session_result {
-            Ok(session) => Ok(Some(session)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e),
-        }
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM user_sessions
+             WHERE session_valid = 1 AND datetime(offline_expiry) > datetime('now')
+             ORDER BY last_activity DESC",
+            USER_SESSION_COLUMNS
+        ))?;
+        first_readable_session(&mut stmt, [])
     }
 
     pub async fn update_session_activity(&self, user_id: &str) -> Result<()> {
         let conn = self.lock_connection()?;
         conn.execute(
-            "UPDATE user_sessions SET last_activity = datetime('now'), updated_at = datetime('now') 
+            "UPDATE user_sessions SET last_activity = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') 
              WHERE user_id = ?1 AND session_valid = 1",
             [user_id],
         )?;
@@ -572,57 +1481,30 @@ impl DatabaseManager {
     pub async fn invalidate_user_session(&self, user_id: &str) -> Result<()> {
         let conn = self.lock_connection()?;
         conn.execute(
-            "UPDATE user_sessions SET session_valid = 0, updated_at = datetime('now') WHERE user_id = ?1",
+            "UPDATE user_sessions SET session_valid = 0, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE user_id = ?1",
             [user_id],
         )?;
         Ok(())
     }
 
-    pub async fn cleanup_expired_sessions(&self) -> Result<()> {
+    pub async fn cleanup_expired_sessions(&self) -> Result<usize> {
         let conn = self.lock_connection()?;
         conn.execute(
-            "DELETE FROM user_sessions WHERE offline_expiry < datetime('now', '-7 days')",
+            "DELETE FROM user_sessions WHERE datetime(offline_expiry) < datetime('now', '-7 days')",
             [],
-        )?;
-        Ok(())
+        )
     }
 
     // Staff management methods
     #[allow(dead_code)]
     pub async fn get_staff(&self) -> Result<Vec<Staff>> {
         let conn = self.lock_connection()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, staff_id, first_name, last_name, email, phone, department, position, status, created_at, updated_at, legacy_staff_id 
-             FROM staff WHERE deleted = 0 ORDER BY first_name, last_name"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM staff WHERE deleted = 0 ORDER BY first_name, last_name",
+            STAFF_COLUMNS
+        ))?;
 
-        let staff = stmt.query_map([], |row| {
-            let id_str: String = row.get(0)?;
-            let created_str: String = row.get(9)?;
-            let updated_str: String = row.get(10)?;
-            
-            Ok(Staff {
-                id: Uuid::parse_str(&id_str).map_err(|e| {
-                    eprintln!("Failed to parse staff ID '{}': {:?}", id_str, e);
-                    rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text)
-                })?,
-                staff_id: row.get(1)?,
-                first_name: row.get(2)?,
-                last_name: row.get(3)?,
-                email: row.get(4)?,
-                phone: row.get(5)?,
-                department: row.get(6)?,
-                position: row.get(7)?,
-                status: row.get(8)?,
-                created_at: DateTime::parse_from_rfc3339(&created_str)
-                    .unwrap_or_else(|_| Utc::now().into())
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&updated_str)
-                    .unwrap_or_else(|_| Utc::now().into())
-                    .with_timezone(&Utc),
-                legacy_staff_id: row.get(11)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
+        let staff = stmt.query_map([], row_to_staff)?.collect::<Result<Vec<_>, _>>()?;
 
         Ok(staff)
     }
@@ -643,8 +1525,8 @@ impl DatabaseManager {
                 &staff.department,
                 &staff.position,
                 &staff.status,
-                staff.created_at.to_rfc3339(),
-                staff.updated_at.to_rfc3339(),
+                staff.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                staff.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                 &staff.legacy_staff_id,
             ),
         )?;
@@ -667,7 +1549,7 @@ impl DatabaseManager {
                 &staff.department,
                 &staff.position,
                 &staff.status,
-                staff.updated_at.to_rfc3339(),
+                staff.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                 &staff.legacy_staff_id,
             ),
         )?;
@@ -678,7 +1560,7 @@ impl DatabaseManager {
     pub async fn delete_staff(&self, staff_id: &str) -> Result<()> {
         let conn = self.lock_connection()?;
         conn.execute(
-            "UPDATE staff SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+            "UPDATE staff SET deleted = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
             [staff_id],
         )?;
         Ok(())
@@ -739,8 +1621,8 @@ impl DatabaseManager {
                 &class.class_section,
                 class.max_books_allowed,
                 class.is_active,
-                class.created_at.to_rfc3339(),
-                class.updated_at.to_rfc3339(),
+                class.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                class.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                 format!("{:?}", class.academic_level_type).to_lowercase(),
             ),
         )?;
@@ -760,7 +1642,7 @@ impl DatabaseManager {
                 &class.class_section,
                 class.max_books_allowed,
                 class.is_active,
-                class.updated_at.to_rfc3339(),
+                class.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                 format!("{:?}", class.academic_level_type).to_lowercase(),
             ),
         )?;
@@ -771,7 +1653,7 @@ impl DatabaseManager {
     pub async fn delete_class(&self, class_id: &str) -> Result<()> {
         let conn = self.lock_connection()?;
         conn.execute(
-            "UPDATE classes SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+            "UPDATE classes SET deleted = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
             [class_id],
         )?;
         Ok(())
@@ -792,8 +1674,8 @@ impl DatabaseManager {
                 &book_copy.book_code,
                 format!("{:?}", book_copy.condition).to_lowercase(),
                 format!("{:?}", book_copy.status).to_lowercase(),
-                book_copy.created_at.to_rfc3339(),
-                book_copy.updated_at.to_rfc3339(),
+                book_copy.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                book_copy.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                 &book_copy.tracking_code,
                 &book_copy.notes,
                 &book_copy.legacy_book_id,
@@ -802,157 +1684,520 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Change a copy's condition, status, notes or tracking code. Marking a copy
+    /// lost or stolen takes it out of the parent book's total and available
+    /// counts in the same transaction, and finding it again puts it back.
+    pub async fn update_book_copy(&self, copy_id: &str, update: &BookCopyUpdate) -> Result<BookCopyChange> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (book_id, old_condition, old_status): (Option<String>, String, String) = tx.query_row(
+            "SELECT book_id, COALESCE(condition, 'good'), COALESCE(status, 'available')
+             FROM book_copies WHERE id = ?1 AND deleted = 0",
+            [copy_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        let condition = update.condition.as_ref().map(snake_case_value).unwrap_or_else(|| old_condition.clone());
+        if !COPY_CONDITIONS.contains(&condition.as_str()) {
+            return Err(constraint_error(format!(
+                "Condition '{}' is not valid for a book copy (expected one of {})", condition, COPY_CONDITIONS.join(", ")
+            )));
+        }
+        let status = update.status.as_ref().map(snake_case_value).unwrap_or_else(|| old_status.clone());
+
+        tx.execute(
+            "UPDATE book_copies SET condition = ?2, status = ?3, notes = COALESCE(?4, notes),
+             tracking_code = COALESCE(?5, tracking_code), updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            (copy_id, &condition, &status, &update.notes, &update.tracking_code),
+        )?;
+
+        let was_out = copy_out_of_stock(&old_condition, &old_status);
+        let is_out = copy_out_of_stock(&condition, &status);
+        let mut book_counts = None;
+        if let Some(book_id) = book_id.as_deref().filter(|_| was_out != is_out) {
+            let total_delta = if is_out { -1 } else { 1 };
+            // Only a copy sitting on the shelf affects what can be lent right now
+            let available_delta = match (is_out, old_status.as_str(), status.as_str()) {
+                (true, "available", _) => -1,
+                (false, _, "available") => 1,
+                _ => 0,
+            };
+            book_counts = Some(adjust_book_counts(&tx, book_id, total_delta, available_delta)?);
+        }
+
+        tx.commit()?;
+
+        Ok(BookCopyChange {
+            copy_id: copy_id.to_string(),
+            book_id,
+            condition,
+            status,
+            book_total_copies: book_counts.map(|(total, _)| total),
+            book_available_copies: book_counts.map(|(_, available)| available),
+        })
+    }
+
+    /// Soft delete a copy and drop it from the parent book's counts. Copies that
+    /// are out on loan have to come back first.
+    pub async fn delete_book_copy(&self, copy_id: &str) -> Result<BookCopyChange> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (book_id, condition, status): (Option<String>, String, String) = tx.query_row(
+            "SELECT book_id, COALESCE(condition, 'good'), COALESCE(status, 'available')
+             FROM book_copies WHERE id = ?1 AND deleted = 0",
+            [copy_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        if status == "borrowed" {
+            return Err(constraint_error(format!("Book copy {} is currently borrowed and cannot be deleted", copy_id)));
+        }
+
+        tx.execute(
+            "UPDATE book_copies SET deleted = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            [copy_id],
+        )?;
+
+        // Lost and stolen copies were already taken out of the counts
+        let mut book_counts = None;
+        if let Some(book_id) = book_id.as_deref().filter(|_| !copy_out_of_stock(&condition, &status)) {
+            let available_delta = if status == "available" { -1 } else { 0 };
+            book_counts = Some(adjust_book_counts(&tx, book_id, -1, available_delta)?);
+        }
+
+        tx.commit()?;
+
+        Ok(BookCopyChange {
+            copy_id: copy_id.to_string(),
+            book_id,
+            condition,
+            status,
+            book_total_copies: book_counts.map(|(total, _)| total),
+            book_available_copies: book_counts.map(|(_, available)| available),
+        })
+    }
+
     // Borrowing management methods
     #[allow(dead_code)]
-    pub async fn create_borrowing(&self, borrowing: &crate::models::Borrowing) -> Result<()> {
+    /// Creates a borrowing, capping its due date to the loan period unless an override reason is given.
+    /// Returns the borrowing as stored.
+    pub async fn create_borrowing(&self, borrowing: &crate::models::Borrowing, override_fine_block: bool, override_reason: Option<&str>) -> Result<Borrowing, BorrowingError> {
         let conn = self.lock_connection()?;
-        conn.execute(
-            "INSERT INTO borrowings (id, student_id, book_id, borrowed_date, due_date, returned_date,
-             status, fine_amount, notes, issued_by, returned_by, created_at, updated_at, fine_paid,
-             book_copy_id, condition_at_issue, condition_at_return, is_lost, tracking_code,
-             return_notes, copy_condition, group_borrowing_id, borrower_type, staff_id)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24)",
-            rusqlite::params![
-                borrowing.id.to_string(),
-                borrowing.student_id.map(|id| id.to_string()),
-                borrowing.book_id.map(|id| id.to_string()),
-                borrowing.borrowed_date.to_string(),
-                borrowing.due_date.to_string(),
-                borrowing.returned_date.map(|d| d.to_string()),
-                format!("{:?}", borrowing.status).to_lowercase(),
-                borrowing.fine_amount,
-                &borrowing.notes,
-                borrowing.issued_by.map(|id| id.to_string()),
-                borrowing.returned_by.map(|id| id.to_string()),
-                borrowing.created_at.to_rfc3339(),
-                borrowing.updated_at.to_rfc3339(),
-                borrowing.fine_paid,
-                borrowing.book_copy_id.map(|id| id.to_string()),
-                &borrowing.condition_at_issue,
-                &borrowing.condition_at_return,
-                borrowing.is_lost,
-                &borrowing.tracking_code,
-                &borrowing.return_notes,
-                &borrowing.copy_condition,
-                borrowing.group_borrowing_id.map(|id| id.to_string()),
-                format!("{:?}", borrowing.borrower_type).to_lowercase(),
-                borrowing.staff_id.map(|id| id.to_string()),
-            ],
-        )?;
-        Ok(())
+        let mut borrowing = borrowing.clone();
+        apply_borrowing_rules(&conn, &mut borrowing, override_fine_block, override_reason)?;
+
+        let tx = conn.unchecked_transaction()?;
+        store_borrowing(&tx, &borrowing)?;
+        tx.commit()?;
+        Ok(borrowing)
     }
 
     #[allow(dead_code)]
     pub async fn get_borrowings_with_details(&self) -> Result<Vec<serde_json::Value>> {
         let conn = self.lock_connection()?;
-        let mut stmt = conn.prepare("
-            SELECT 
-                b.id, b.student_id, b.book_id, b.borrowed_date, b.due_date, b.returned_date,
-                b.status, b.fine_amount, b.notes, b.issued_by, b.returned_by, b.created_at, b.updated_at,
-                b.fine_paid, b.book_copy_id, b.condition_at_issue, b.condition_at_return, b.is_lost,
-                b.tracking_code, b.return_notes, b.copy_condition, b.group_borrowing_id, b.borrower_type, b.staff_id,
-                s.first_name as student_first_name, s.last_name as student_last_name, s.admission_number,
-                book.title as book_title, book.author as book_author, book.book_code,
-                bc.copy_number, bc.condition as copy_condition_status
-            FROM borrowings b
-            LEFT JOIN students s ON b.student_id = s.id
-            LEFT JOIN books book ON b.book_id = book.id  
-            LEFT JOIN book_copies bc ON b.book_copy_id = bc.id
-            ORDER BY b.created_at DESC
-        ")?;
+        let mut stmt = conn.prepare(&format!("{} ORDER BY b.created_at DESC", BORROWING_DETAILS_SELECT))?;
 
-        let borrowing_iter = stmt.query_map([], |row| {
-            Ok(serde_json::json!({
-                "id": row.get::<_, String>("id")?,
-                "student_id": row.get::<_, Option<String>>("student_id")?,
-                "book_id": row.get::<_, Option<String>>("book_id")?,
-                "borrowed_date": row.get::<_, String>("borrowed_date")?,
-                "due_date": row.get::<_, String>("due_date")?,
-                "returned_date": row.get::<_, Option<String>>("returned_date")?,
-                "status": row.get::<_, String>("status")?,
-                "fine_amount": row.get::<_, Option<f64>>("fine_amount")?,
-                "notes": row.get::<_, Option<String>>("notes")?,
-                "issued_by": row.get::<_, Option<String>>("issued_by")?,
-                "returned_by": row.get::<_, Option<String>>("returned_by")?,
-                "created_at": row.get::<_, String>("created_at")?,
-                "updated_at": row.get::<_, String>("updated_at")?,
-                "fine_paid": row.get::<_, Option<bool>>("fine_paid")?,
-                "book_copy_id": row.get::<_, Option<String>>("book_copy_id")?,
-                "condition_at_issue": row.get::<_, Option<String>>("condition_at_issue")?,
-                "condition_at_return": row.get::<_, Option<String>>("condition_at_return")?,
-                "is_lost": row.get::<_, Option<bool>>("is_lost")?,
-                "tracking_code": row.get::<_, Option<String>>("tracking_code")?,
-                "return_notes": row.get::<_, Option<String>>("return_notes")?,
-                "copy_condition": row.get::<_, Option<String>>("copy_condition")?,
-                "group_borrowing_id": row.get::<_, Option<String>>("group_borrowing_id")?,
-                "borrower_type": row.get::<_, String>("borrower_type")?,
-                "staff_id": row.get::<_, Option<String>>("staff_id")?,
-                "students": if row.get::<_, Option<String>>("student_first_name")?.is_some() {
-                    Some(serde_json::json!({
-                        "id": row.get::<_, Option<String>>("student_id")?,
-                        "first_name": row.get::<_, Option<String>>("student_first_name")?,
-                        "last_name": row.get::<_, Option<String>>("student_last_name")?,
-                        "admission_number": row.get::<_, Option<String>>("admission_number")?
-                    }))
-                } else {
-                    None
-                },
-                "books": if row.get::<_, Option<String>>("book_title")?.is_some() {
-                    Some(serde_json::json!({
-                        "id": row.get::<_, Option<String>>("book_id")?,
-                        "title": row.get::<_, Option<String>>("book_title")?,
-                        "author": row.get::<_, Option<String>>("book_author")?,
-                        "book_code": row.get::<_, Option<String>>("book_code")?
-                    }))
-                } else {
-                    None
-                },
-                "book_copies": if row.get::<_, Option<String>>("copy_number")?.is_some() {
-                    Some(serde_json::json!({
-                        "id": row.get::<_, Option<String>>("book_copy_id")?,
-                        "copy_number": row.get::<_, Option<String>>("copy_number")?,
-                        "condition": row.get::<_, Option<String>>("copy_condition_status")?
-                    }))
-                } else {
-                    None
-                }
-            }))
-        })?;
+        let borrowings = stmt.query_map([], row_to_borrowing_details)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(borrowings)
+    }
+
+    /// One page of borrowings with the status and student filters applied in SQL.
+    /// "active" covers every unreturned loan; "overdue" is an unreturned loan past its due date.
+    pub async fn get_borrowings_paginated(
+        &self,
+        page: usize,
+        page_size: usize,
+        status_filter: Option<&str>,
+        student_id_filter: Option<&str>,
+    ) -> Result<PaginatedBorrowings> {
+        let conn = self.lock_connection()?;
+        let page_size = page_size.max(1);
+
+        let mut conditions = vec!["b.deleted = 0".to_string()];
+        let mut params: Vec<String> = Vec::new();
 
-        let mut borrowings = Vec::new();
-        for borrowing in borrowing_iter {
-            borrowings.push(borrowing?);
+        match status_filter.map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).as_deref() {
+            None | Some("all") => {}
+            Some("active") => conditions.push("b.returned_date IS NULL AND b.status IN ('active', 'overdue')".to_string()),
+            Some("overdue") => conditions.push(
+                "b.returned_date IS NULL AND b.status IN ('active', 'overdue') AND date(b.due_date) < date('now')".to_string()
+            ),
+            Some(status) => {
+                params.push(status.to_string());
+                conditions.push(format!("b.status = ?{}", params.len()));
+            }
         }
 
-        Ok(borrowings)
+        if let Some(student_id) = student_id_filter.filter(|s| !s.is_empty()) {
+            params.push(student_id.to_string());
+            conditions.push(format!("b.student_id = ?{}", params.len()));
+        }
+
+        let where_clause = conditions.join(" AND ");
+        let total_count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM borrowings b WHERE {}", where_clause),
+            rusqlite::params_from_iter(params.iter()),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE {} ORDER BY b.created_at DESC LIMIT {} OFFSET {}",
+            BORROWING_DETAILS_SELECT, where_clause, page_size, page * page_size
+        ))?;
+        let borrowings = stmt.query_map(rusqlite::params_from_iter(params.iter()), row_to_borrowing_details)?
+            .collect::<Result<Vec<_>>>()?;
+
+        let total_count = total_count as usize;
+        let total_pages = total_count.div_ceil(page_size);
+
+        Ok(PaginatedBorrowings {
+            borrowings,
+            current_page: page,
+            page_size,
+            total_count,
+            total_pages,
+            has_next: page + 1 < total_pages,
+            has_previous: page > 0,
+        })
+    }
+
+    /// Check a borrowed copy back in, all in one transaction. When tracking codes are
+    /// verified and the copy handed back isn't the one issued, a theft report is filed
+    /// in place of the return; otherwise the loan is returned and any flag on its code
+    /// is picked up for staff to see.
+    pub async fn return_book(&self, borrowing_id: &str, return_data: ReturnData, reported_by: Option<&str>) -> Result<BookReturnResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let returned_code = return_data.tracking_code.as_deref().map(str::trim).filter(|c| !c.is_empty());
+        if let Some(returned_code) = returned_code {
+            let verify = settings::read_setting(&tx, settings::VERIFY_RETURN_TRACKING_CODES_KEY)?.unwrap_or(false);
+            let filed = if verify {
+                theft_reports::report_mismatched_return(&tx, borrowing_id, returned_code, reported_by)?
+            } else {
+                None
+            };
+            if let Some((report, copy_change, fine)) = filed {
+                tx.commit()?;
+                return Ok(BookReturnResult {
+                    theft_report: Some(report),
+                    copy_change,
+                    fines: fine.into_iter().collect(),
+                    flagged_code: None,
+                    reservation_ready: None,
+                });
+            }
+        }
+
+        let fines = return_borrowing(&tx, borrowing_id, &return_data)?;
+        // A flagged copy coming back still completes the return, but staff need to see it
+        let flagged_code = flagged_codes::flagged_code_for_borrowing(&tx, borrowing_id)?;
+        let book_id: Option<String> = tx.query_row(
+            "SELECT book_id FROM borrowings WHERE id = ?1",
+            [borrowing_id],
+            |row| row.get(0),
+        )?;
+        let reservation_ready = match book_id {
+            Some(book_id) => reservations::promote_next_reservation(&tx, &book_id)?,
+            None => None,
+        };
+
+        tx.commit()?;
+        Ok(BookReturnResult {
+            theft_report: None,
+            copy_change: None,
+            fines,
+            flagged_code,
+            reservation_ready,
+        })
+    }
+
+    /// Return every open loan a student has, e.g. at the end of term, raising the
+    /// usual overdue fine on each. All or nothing: if any return fails none of
+    /// them are recorded. Freed copies go to waiting reservations.
+    pub async fn return_all_for_student(&self, student_id: &str, returned_date: NaiveDate, returned_by: Option<Uuid>) -> Result<StudentReturnResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut stmt = tx.prepare(
+            "SELECT id, book_id FROM borrowings
+             WHERE student_id = ?1 AND deleted = 0 AND returned_date IS NULL AND status IN ('active', 'overdue')
+             ORDER BY borrowed_date"
+        )?;
+        let open_loans = stmt.query_map([student_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        drop(stmt);
+
+        let return_data = ReturnData {
+            returned_by,
+            returned_date: Some(returned_date),
+            ..ReturnData::default()
+        };
+        let mut fines = Vec::new();
+        for (borrowing_id, _) in &open_loans {
+            fines.extend(return_borrowing(&tx, borrowing_id, &return_data)?);
+        }
+
+        let mut reservations_ready = Vec::new();
+        let mut book_ids: Vec<&String> = open_loans.iter().filter_map(|(_, book_id)| book_id.as_ref()).collect();
+        book_ids.sort();
+        book_ids.dedup();
+        for book_id in book_ids {
+            while let Some(reservation) = reservations::promote_next_reservation(&tx, book_id)? {
+                reservations_ready.push(reservation);
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(StudentReturnResult {
+            student_id: student_id.to_string(),
+            returned_date,
+            returned_borrowing_ids: open_loans.into_iter().map(|(id, _)| id).collect(),
+            total_fine_amount: fines.iter().map(|f| f.amount).sum(),
+            fines,
+            reservations_ready,
+        })
+    }
+
+    // Lost book recovery - reverses a lost borrowing when the book turns up
+    pub async fn unmark_borrowing_lost(&self, borrowing_id: &str, returned: bool, waive_fine: bool) -> Result<serde_json::Value> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (status, is_lost, book_id, book_copy_id): (String, bool, Option<String>, Option<String>) = tx.query_row(
+            "SELECT status, is_lost, book_id, book_copy_id FROM borrowings WHERE id = ?1 AND deleted = 0",
+            [borrowing_id],
+            |row| Ok((
+                row.get(0)?,
+                row.get::<_, Option<bool>>(1)?.unwrap_or(false),
+                row.get(2)?,
+                row.get(3)?,
+            ))
+        )?;
+
+        if status != "lost" && !is_lost {
+            return Err(constraint_error(format!("Borrowing {} is not marked as lost", borrowing_id)));
+        }
+
+        // Found books either come straight back to the shelf or stay with the borrower
+        let new_status = if returned { "returned" } else { "active" };
+        if returned {
+            tx.execute(
+                "UPDATE borrowings SET status = ?2, is_lost = 0, returned_date = date('now'), updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+                (borrowing_id, new_status),
+            )?;
+        } else {
+            tx.execute(
+                "UPDATE borrowings SET status = ?2, is_lost = 0, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+                (borrowing_id, new_status),
+            )?;
+        }
+
+        let copy_status = if returned { "available" } else { "borrowed" };
+        let mut written_off = false;
+        if let Some(copy_id) = &book_copy_id {
+            let previous: Option<(String, String)> = tx.query_row(
+                "SELECT COALESCE(condition, 'good'), COALESCE(status, 'available') FROM book_copies WHERE id = ?1 AND deleted = 0",
+                [copy_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).optional()?;
+            written_off = previous.is_some_and(|(condition, status)| copy_out_of_stock(&condition, &status));
+            tx.execute(
+                "UPDATE book_copies SET status = ?2, condition = CASE WHEN condition = 'lost' THEN 'good' ELSE condition END,
+                 updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+                (copy_id, copy_status),
+            )?;
+        }
+
+        // Reverse the inventory changes made when the book went missing: a copy written off
+        // as lost or stolen counts towards the stock again, and a returned one is back on the shelf
+        if let Some(book_id) = book_id.as_deref().filter(|_| written_off || returned) {
+            adjust_book_counts(&tx, book_id, i64::from(written_off), i64::from(returned))?;
+        }
+
+        let mut waived_amount = 0.0;
+        let mut fines_waived = 0;
+        if waive_fine {
+            waived_amount = tx.query_row(
+                "SELECT COALESCE(SUM(amount), 0) FROM fines
+                 WHERE borrowing_id = ?1 AND fine_type IN ('lost', 'lost_book')
+                 AND status IN ('unpaid', 'partial') AND deleted = 0",
+                [borrowing_id],
+                |row| row.get::<_, f64>(0)
+            )?;
+            fines_waived = tx.execute(
+                "UPDATE fines SET status = 'waived', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+                 WHERE borrowing_id = ?1 AND fine_type IN ('lost', 'lost_book')
+                 AND status IN ('unpaid', 'partial') AND deleted = 0",
+                [borrowing_id],
+            )?;
+            tx.execute(
+                "UPDATE borrowings SET fine_amount = MAX(COALESCE(fine_amount, 0) - ?2, 0) WHERE id = ?1",
+                (borrowing_id, waived_amount),
+            )?;
+        }
+
+        tx.commit()?;
+
+        Ok(serde_json::json!({
+            "borrowing_id": borrowing_id,
+            "status": new_status,
+            "book_copy_id": book_copy_id,
+            "copy_status": book_copy_id.as_ref().map(|_| copy_status),
+            "fines_waived": fines_waived,
+            "waived_amount": waived_amount,
+        }))
     }
 
     // Fine management methods
     #[allow(dead_code)]
     pub async fn create_fine(&self, fine: &crate::models::Fine) -> Result<()> {
         let conn = self.lock_connection()?;
-        conn.execute(
-            "INSERT INTO fines (id, student_id, borrowing_id, fine_type, amount, description,
-             status, created_at, updated_at, created_by, borrower_type, staff_id)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        insert_fine(&conn, fine)
+    }
+
+    pub async fn get_fines(&self, student_id: Option<&str>, status: Option<&str>) -> Result<Vec<Fine>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM fines
+             WHERE deleted = 0 AND (?1 IS NULL OR student_id = ?1) AND (?2 IS NULL OR status = ?2)
+             ORDER BY created_at DESC",
+            FINE_COLUMNS
+        ))?;
+
+        let fines = stmt.query_map((student_id, status), row_to_fine)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(fines)
+    }
+
+    pub async fn update_fine(&self, fine: &Fine) -> Result<()> {
+        let conn = self.lock_connection()?;
+        let updated = conn.execute(
+            "UPDATE fines SET student_id = ?2, borrowing_id = ?3, fine_type = ?4, amount = ?5, description = ?6,
+             status = ?7, updated_at = ?8, borrower_type = ?9, staff_id = ?10 WHERE id = ?1 AND deleted = 0",
             (
                 fine.id.to_string(),
                 fine.student_id.map(|id| id.to_string()),
                 fine.borrowing_id.map(|id| id.to_string()),
-                format!("{:?}", fine.fine_type).to_lowercase(),
+                snake_case_value(&fine.fine_type),
                 fine.amount,
                 &fine.description,
                 format!("{:?}", fine.status).to_lowercase(),
-                fine.created_at.to_rfc3339(),
-                fine.updated_at.to_rfc3339(),
-                fine.created_by.map(|id| id.to_string()),
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
                 format!("{:?}", fine.borrower_type).to_lowercase(),
                 fine.staff_id.map(|id| id.to_string()),
             ),
         )?;
+
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    /// Settle a fine as paid; the payment method (e.g. "cash", "mobile money") is
+    /// stored lowercased and left empty when not given
+    pub async fn pay_fine(&self, fine_id: &str, payment_method: Option<&str>) -> Result<()> {
+        let payment_method = payment_method
+            .map(|method| method.trim().to_lowercase())
+            .filter(|method| !method.is_empty());
+        self.settle_fine(fine_id, FineStatus::Paid, payment_method).await
+    }
+
+    pub async fn waive_fine(&self, fine_id: &str) -> Result<()> {
+        self.settle_fine(fine_id, FineStatus::Waived, None).await
+    }
+
+    // Marks a fine as settled and flags the borrowing once nothing is left owing on it
+    async fn settle_fine(&self, fine_id: &str, status: FineStatus, payment_method: Option<String>) -> Result<()> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let current_status: String = tx.query_row(
+            "SELECT status FROM fines WHERE id = ?1 AND deleted = 0",
+            [fine_id],
+            |row| row.get(0)
+        )?;
+        if !matches!(current_status.as_str(), "unpaid" | "partial") {
+            return Err(constraint_error(format!("Fine {} is already {}", fine_id, current_status)));
+        }
+
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let paid_at = (status == FineStatus::Paid).then(|| now.clone());
+        tx.execute(
+            "UPDATE fines SET status = ?2, paid_at = ?3, payment_method = ?4, updated_at = ?5 WHERE id = ?1",
+            (fine_id, format!("{:?}", status).to_lowercase(), paid_at, payment_method, now),
+        )?;
+
+        tx.execute(
+            "UPDATE borrowings SET fine_paid = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+             WHERE id = (SELECT borrowing_id FROM fines WHERE id = ?1)
+               AND NOT EXISTS (
+                   SELECT 1 FROM fines f WHERE f.borrowing_id = borrowings.id
+                   AND f.status IN ('unpaid', 'partial') AND f.deleted = 0
+               )",
+            [fine_id],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub async fn delete_fine(&self, fine_id: &str) -> Result<()> {
+        let conn = self.lock_connection()?;
+        conn.execute(
+            "UPDATE fines SET deleted = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            [fine_id],
+        )?;
+        Ok(())
+    }
+
+    /// Whole days past due (at return, or today if still out) multiplied by the daily rate
+    pub fn calculate_overdue_fine(borrowing: &Borrowing, rate_per_day: f64) -> f64 {
+        let end_date = borrowing.returned_date.unwrap_or_else(|| Utc::now().date_naive());
+        let days_overdue = (end_date - borrowing.due_date).num_days().max(0);
+        days_overdue as f64 * rate_per_day
+    }
+
+    // Sync push queue - local writes waiting to be sent to Supabase
+    pub async fn enqueue_operation(&self, table_name: &str, operation_type: &str, record_id: &str, payload: &serde_json::Value) -> Result<()> {
+        let conn = self.lock_connection()?;
+
+        // Keep the content hash current so the push can skip rows that haven't really changed
+        if operation_type != "delete" && hashing::HASHED_TABLES.contains(&table_name) {
+            hashing::refresh_content_hash_in(&conn, table_name, record_id)?;
+        }
+        if maintenance::SYNC_FLAG_TABLES.contains(&table_name) {
+            conn.execute(&format!("UPDATE {} SET synced = 0 WHERE id = ?1", table_name), [record_id])?;
+        }
+
+        conn.execute(
+            "INSERT INTO sync_queue (id, table_name, operation_type, record_id, payload, created_at, retry_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), 0)",
+            (
+                Uuid::new_v4().to_string(),
+                table_name,
+                operation_type,
+                record_id,
+                payload.to_string(),
+            ),
+        )?;
         Ok(())
     }
 
+    pub async fn get_sync_queue_count(&self) -> Result<i32> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare("SELECT COUNT(*) FROM sync_queue")?;
+        let count: i32 = stmt.query_row([], |row| row.get(0))?;
+        Ok(count)
+    }
+
     // Additional methods for professional sync UI
     #[allow(dead_code)]
     pub async fn get_books_count(&self) -> Result<i32> {
@@ -1046,15 +2291,9 @@ impl DatabaseManager {
         let conn = self.lock_connection()?;
         
         // Delete data from all tables in reverse dependency order
-        conn.execute("DELETE FROM borrowings", [])?;
-        conn.execute("DELETE FROM fines", [])?;
-        conn.execute("DELETE FROM book_copies", [])?;
-        conn.execute("DELETE FROM books", [])?;
-        conn.execute("DELETE FROM students", [])?;
-        conn.execute("DELETE FROM staff", [])?;
-        conn.execute("DELETE FROM categories", [])?;
-        conn.execute("DELETE FROM classes", [])?;
-        conn.execute("DELETE FROM borrowing_settings", [])?;
+        for table in clear::CLEARABLE_TABLES {
+            conn.execute(&format!("DELETE FROM {}", table), [])?;
+        }
         conn.execute("DELETE FROM user_sessions", [])?;
         
         // Reset auto-increment counters (if using AUTOINCREMENT)
@@ -1106,3 +2345,362 @@ impl DatabaseManager {
         Ok(counts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn unmarking_a_lost_loan_puts_the_copy_back_and_waives_the_fine() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "LOST", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+        db.upsert_fine_setting(&FineType::LostBook, 500.0, None).await.unwrap();
+
+        // A different copy handed back marks the loan lost and writes the issued copy off
+        db.set_verify_return_tracking_codes(true).await.unwrap();
+        let mismatched = ReturnData { tracking_code: Some("LOST-99".to_string()), ..ReturnData::default() };
+        let result = db.return_book(&loan_id, mismatched, None).await.unwrap();
+        assert!(result.theft_report.is_some());
+        assert_eq!(scalar::<i64, _>(&db, "SELECT total_copies FROM books WHERE id = ?1", [&book_id]), 0);
+
+        let result = db.unmark_borrowing_lost(&loan_id, true, true).await.unwrap();
+        assert_eq!(result["status"], "returned");
+        assert_eq!(result["fines_waived"], 1);
+
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM book_copies WHERE id = ?1", [&copies[0]]), "available");
+        assert_eq!(scalar::<i64, _>(&db, "SELECT total_copies FROM books WHERE id = ?1", [&book_id]), 1);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 1);
+
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [&loan_id]), "returned");
+        assert!(!scalar::<bool, _>(&db, "SELECT is_lost FROM borrowings WHERE id = ?1", [&loan_id]));
+        assert!(scalar::<Option<String>, _>(&db, "SELECT returned_date FROM borrowings WHERE id = ?1", [&loan_id]).is_some());
+        assert_eq!(scalar::<f64, _>(&db, "SELECT fine_amount FROM borrowings WHERE id = ?1", [&loan_id]), 0.0);
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM fines WHERE borrowing_id = ?1", [&loan_id]), "waived");
+    }
+
+    #[tokio::test]
+    async fn unmarking_a_loan_that_is_not_lost_fails() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "LOST", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+
+        assert!(db.unmark_borrowing_lost(&loan_id, true, true).await.is_err());
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [&loan_id]), "active");
+    }
+
+    #[tokio::test]
+    async fn returning_a_loan_puts_the_copy_back_on_the_shelf() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "RET", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+
+        let result = db.return_book(&loan_id, ReturnData::default(), None).await.unwrap();
+        assert!(result.fines.is_empty());
+        assert!(result.theft_report.is_none());
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [&loan_id]), "returned");
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT returned_date FROM borrowings WHERE id = ?1", [&loan_id]),
+            days_from_today(0).to_string()
+        );
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM book_copies WHERE id = ?1", [&copies[0]]), "available");
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 1);
+    }
+
+    #[tokio::test]
+    async fn returning_a_loan_twice_fails_and_leaves_the_count_alone() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "RET", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let first = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+        seed_loan(&db, &student_id, &book_id, &copies[1], 7);
+
+        db.return_book(&first, ReturnData::default(), None).await.unwrap();
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 1);
+
+        let err = db.return_book(&first, ReturnData::default(), None).await.unwrap_err();
+        assert!(err.to_string().contains("already been returned"));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 1);
+    }
+
+    #[tokio::test]
+    async fn mismatched_copy_is_reported_instead_of_returned() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "SWAP", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+        db.set_verify_return_tracking_codes(true).await.unwrap();
+
+        let swapped = ReturnData { tracking_code: Some("SWAP-2".to_string()), ..ReturnData::default() };
+        let result = db.return_book(&loan_id, swapped, None).await.unwrap();
+        let report = result.theft_report.expect("theft report filed");
+        assert_eq!((report.expected_tracking_code.as_str(), report.returned_tracking_code.as_str()), ("SWAP-1", "SWAP-2"));
+        assert_eq!(result.copy_change.map(|c| c.status), Some("stolen".to_string()));
+
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [&loan_id]), "lost");
+        assert_eq!(scalar::<Option<String>, _>(&db, "SELECT returned_date FROM borrowings WHERE id = ?1", [&loan_id]), None);
+        assert_eq!(book_counts(&db, &book_id), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn matching_or_unverified_codes_return_and_pick_up_flags() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "CHECK", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let flagged = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+        let unverified = seed_loan(&db, &student_id, &book_id, &copies[1], 7);
+        db.register_flagged_code("CHECK-1", "Reported missing", None).await.unwrap();
+        db.set_verify_return_tracking_codes(true).await.unwrap();
+
+        let matching = ReturnData { tracking_code: Some(" check-1 ".to_string()), ..ReturnData::default() };
+        let result = db.return_book(&flagged, matching, None).await.unwrap();
+        assert!(result.theft_report.is_none());
+        assert_eq!(result.flagged_code.map(|f| f.reason), Some("Reported missing".to_string()));
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [&flagged]), "returned");
+
+        // With verification off a different code is just a note on an ordinary return
+        db.set_verify_return_tracking_codes(false).await.unwrap();
+        let other = ReturnData { tracking_code: Some("CHECK-9".to_string()), ..ReturnData::default() };
+        let result = db.return_book(&unverified, other, None).await.unwrap();
+        assert!(result.theft_report.is_none() && result.flagged_code.is_none());
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM theft_reports", []), 0);
+        assert_eq!(book_counts(&db, &book_id), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn student_over_the_fine_threshold_is_blocked() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "FINE", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_fine(&db, &student_id, 150.0);
+        db.set_borrow_block_threshold(Some(100.0)).await.unwrap();
+
+        let err = db.create_borrowing(&new_borrowing(&student_id, &book_id, None), false, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::FineThresholdExceeded { owed, threshold } if owed == 150.0 && threshold == 100.0));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 1);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM borrowings", []), 0);
+    }
+
+    #[tokio::test]
+    async fn fine_block_can_be_overridden() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "FINE", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_fine(&db, &student_id, 150.0);
+        db.set_borrow_block_threshold(Some(100.0)).await.unwrap();
+
+        db.create_borrowing(&new_borrowing(&student_id, &book_id, None), true, None).await.unwrap();
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 0);
+    }
+
+    #[tokio::test]
+    async fn student_under_the_fine_threshold_can_borrow() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "FINE", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_fine(&db, &student_id, 50.0);
+        db.set_borrow_block_threshold(Some(100.0)).await.unwrap();
+
+        db.create_borrowing(&new_borrowing(&student_id, &book_id, None), false, None).await.unwrap();
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM borrowings", []), 1);
+    }
+
+    #[tokio::test]
+    async fn borrowing_past_the_last_copy_is_refused() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "LAST", 1);
+        let first = seed_student(&db, "ADM-1");
+        let second = seed_student(&db, "ADM-2");
+
+        db.create_borrowing(&new_borrowing(&first, &book_id, None), false, None).await.unwrap();
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 0);
+
+        let err = db.create_borrowing(&new_borrowing(&second, &book_id, None), false, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::NoCopiesAvailable));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT available_copies FROM books WHERE id = ?1", [&book_id]), 0);
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM borrowings WHERE book_id = ?1", [&book_id]), 1);
+    }
+
+
+    #[tokio::test]
+    async fn due_date_past_the_loan_period_is_capped() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "DUE", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let mut borrowing = new_borrowing(&student_id, &book_id, None);
+        borrowing.due_date = days_from_today(30);
+
+        let stored = db.create_borrowing(&borrowing, false, None).await.unwrap();
+        assert_eq!(stored.due_date, days_from_today(14));
+        assert!(!stored.due_date_override);
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT due_date FROM borrowings WHERE id = ?1", [stored.id.to_string()]),
+            days_from_today(14).to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn due_date_override_with_a_reason_is_kept() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "DUE", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let mut borrowing = new_borrowing(&student_id, &book_id, None);
+        borrowing.due_date = days_from_today(30);
+
+        // A blank reason is no reason
+        let capped = db.create_borrowing(&borrowing, false, Some("  ")).await.unwrap();
+        assert_eq!(capped.due_date, days_from_today(14));
+        db.return_book(&capped.id.to_string(), ReturnData::default(), None).await.unwrap();
+
+        borrowing.id = Uuid::new_v4();
+        let stored = db.create_borrowing(&borrowing, false, Some("Holiday reading project")).await.unwrap();
+        assert_eq!(stored.due_date, days_from_today(30));
+        assert!(stored.due_date_override);
+        assert_eq!(stored.notes.as_deref(), Some("Due date override: Holiday reading project"));
+        assert!(scalar::<bool, _>(&db, "SELECT due_date_override FROM borrowings WHERE id = ?1", [stored.id.to_string()]));
+    }
+
+
+    #[tokio::test]
+    async fn book_pages_are_disjoint_and_cover_every_book() {
+        let db = test_db();
+        for code in ["PG1", "PG2", "PG3", "PG4", "PG5"] {
+            seed_book(&db, code, 1);
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for (page, expected_len) in [(0, 2), (1, 2), (2, 1)] {
+            let (books, total) = db.get_books_paginated(page, 2, None, None).await.unwrap();
+            assert_eq!(total, 5);
+            assert_eq!(books.len(), expected_len);
+            for book in books {
+                assert!(seen.insert(book.id), "book {} appeared on two pages", book.id);
+            }
+        }
+        assert_eq!(seen.len(), 5);
+        assert!(db.get_books_paginated(3, 2, None, None).await.unwrap().0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn category_filter_restricts_book_pages() {
+        let db = test_db();
+        let fiction = seed_category(&db, "Fiction");
+        let science = seed_category(&db, "Science");
+        for code in ["CF1", "CF2", "CF3"] {
+            let (book_id, _) = seed_book(&db, code, 1);
+            execute(&db, "UPDATE books SET category_id = ?1 WHERE id = ?2", [&fiction, &book_id]);
+        }
+        let (science_book, _) = seed_book(&db, "CF4", 1);
+        execute(&db, "UPDATE books SET category_id = ?1 WHERE id = ?2", [&science, &science_book]);
+        seed_book(&db, "CF5", 1);
+
+        let (by_name, total) = db.get_books_paginated(0, 10, Some("fiction"), None).await.unwrap();
+        assert_eq!((by_name.len(), total), (3, 3));
+        assert!(by_name.iter().all(|b| b.category_id.map(|id| id.to_string()) == Some(fiction.clone())));
+
+        let (by_id, total) = db.get_books_paginated(0, 2, Some(&science), None).await.unwrap();
+        assert_eq!((by_id.len(), total), (1, 1));
+        assert_eq!(by_id[0].id.to_string(), science_book);
+
+        let (searched, total) = db.get_books_paginated(0, 10, Some("Fiction"), Some("cf2")).await.unwrap();
+        assert_eq!((searched.len(), total), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn changing_a_copy_condition_leaves_book_counts_alone() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "COND", 2);
+
+        let update = BookCopyUpdate {
+            condition: Some(BookCondition::Fair),
+            notes: Some("Spine taped".to_string()),
+            ..Default::default()
+        };
+        let change = db.update_book_copy(&copies[0], &update).await.unwrap();
+        assert_eq!((change.condition.as_str(), change.status.as_str()), ("fair", "available"));
+        assert_eq!(change.book_total_copies, None);
+        assert_eq!(scalar::<String, _>(&db, "SELECT notes FROM book_copies WHERE id = ?1", [&copies[0]]), "Spine taped");
+        assert_eq!(book_counts(&db, &book_id), (2, 2));
+
+        // Copies only take the conditions the book_copies table allows
+        let update = BookCopyUpdate { condition: Some(BookCondition::Excellent), ..Default::default() };
+        assert!(db.update_book_copy(&copies[0], &update).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn lost_and_stolen_copies_come_off_the_book_counts() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "CASCADE", 3);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_loan(&db, &student_id, &book_id, &copies[2], 7);
+        let counts = || book_counts(&db, &book_id);
+        assert_eq!(counts(), (3, 2));
+
+        let lost = BookCopyUpdate { status: Some(CopyStatus::Lost), ..Default::default() };
+        let change = db.update_book_copy(&copies[0], &lost).await.unwrap();
+        assert_eq!((change.book_total_copies, change.book_available_copies), (Some(2), Some(1)));
+        assert_eq!(counts(), (2, 1));
+
+        // A copy stolen while on loan was never on the shelf to begin with
+        let stolen = BookCopyUpdate { status: Some(CopyStatus::Stolen), ..Default::default() };
+        db.update_book_copy(&copies[2], &stolen).await.unwrap();
+        assert_eq!(counts(), (1, 1));
+
+        let found = BookCopyUpdate { status: Some(CopyStatus::Available), ..Default::default() };
+        db.update_book_copy(&copies[0], &found).await.unwrap();
+        assert_eq!(counts(), (2, 2));
+
+        // Deleting a stolen copy doesn't take it off the counts a second time
+        db.delete_book_copy(&copies[2]).await.unwrap();
+        assert_eq!(counts(), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn library_stats_count_open_loans_by_their_stored_status() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "STATS", 3);
+        let student_id = seed_student(&db, "ADM-1");
+        db.create_borrowing(&new_borrowing(&student_id, &book_id, Some(&copies[0])), false, None).await.unwrap();
+        seed_loan(&db, &student_id, &book_id, &copies[1], -3);
+        let returned = seed_loan(&db, &student_id, &book_id, &copies[2], 5);
+        db.return_book(&returned, ReturnData::default(), None).await.unwrap();
+
+        assert_eq!(scalar::<String, _>(&db, "SELECT DISTINCT status FROM borrowings WHERE returned_date IS NULL", []), "active");
+        let stats = db.get_library_stats().await.unwrap();
+        assert_eq!((stats.total_books, stats.total_students), (1, 1));
+        assert_eq!((stats.total_borrowings, stats.overdue_books), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn admission_numbers_stay_unique_across_create_update_and_delete() {
+        let db = test_db();
+        let first_id = seed_student(&db, "ADM-1");
+        let first = db.get_students().await.unwrap().remove(0);
+
+        // Same number in a different case is still taken
+        let duplicate = Student { id: Uuid::new_v4(), admission_number: " adm-1 ".to_string(), ..first.clone() };
+        match db.create_student(&duplicate).await {
+            Err(StudentError::DuplicateAdmissionNumber { admission_number, existing_id }) => {
+                assert_eq!((admission_number.as_str(), existing_id.as_str()), ("adm-1", first_id.as_str()));
+            }
+            other => panic!("expected a duplicate admission number, got {:?}", other.map(|_| ())),
+        }
+
+        // A student keeps their own number through an update
+        let renamed = Student { first_name: "Renamed".to_string(), ..first.clone() };
+        db.update_student(&renamed).await.unwrap();
+        assert_eq!(scalar::<String, _>(&db, "SELECT first_name FROM students WHERE id = ?1", [&first_id]), "Renamed");
+
+        // A deleted student still holds the number, and the error says so
+        db.delete_student(&first_id).await.unwrap();
+        match db.create_student(&duplicate).await {
+            Err(StudentError::HeldByDeletedStudent { existing_id, .. }) => assert_eq!(existing_id, first_id),
+            other => panic!("expected the deleted holder to be reported, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM students", []), 1);
+    }
+}
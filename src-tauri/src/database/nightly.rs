@@ -0,0 +1,258 @@
+use super::backup::timestamped_backup_path;
+use super::settings::{read_setting, write_setting, MAINTENANCE_SCHEDULE_KEY};
+use super::DatabaseManager;
+use crate::models::*;
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{Connection, Result, Transaction, TransactionBehavior};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalCheckpoint {
+    pub busy: bool,
+    pub log_frames: i64,
+    pub checkpointed_frames: i64,
+    pub wal_bytes_before: Option<u64>,
+    pub wal_bytes_after: Option<u64>,
+}
+
+// Size of the -wal file next to the database, if it exists (in-memory databases have none)
+fn wal_file_size(conn: &Connection) -> Option<u64> {
+    let db_path = conn.path().filter(|p| !p.is_empty())?;
+    std::fs::metadata(format!("{}-wal", db_path)).ok().map(|m| m.len())
+}
+
+/// Fold the WAL back into the main file and truncate it. Called after large
+/// imports, which otherwise leave the WAL at its peak size until the next checkpoint.
+pub(crate) fn checkpoint(conn: &Connection) -> Result<WalCheckpoint> {
+    let wal_bytes_before = wal_file_size(conn);
+    let (busy, log_frames, checkpointed_frames) = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+        Ok((row.get::<_, i64>(0)? != 0, row.get(1)?, row.get(2)?))
+    })?;
+    Ok(WalCheckpoint {
+        busy,
+        log_frames,
+        checkpointed_frames,
+        wal_bytes_before,
+        wal_bytes_after: wal_file_size(conn),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceTaskResult {
+    pub task: String,
+    pub success: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub tasks: Vec<MaintenanceTaskResult>,
+    pub sessions_removed: usize,
+    pub loans_marked_overdue: usize,
+    pub availability_corrections: usize,
+    pub backup_path: Option<String>,
+    pub succeeded: bool,
+}
+
+impl MaintenanceReport {
+    fn record<T, E: std::fmt::Display>(&mut self, task: &str, outcome: std::result::Result<T, E>, detail: impl FnOnce(&T) -> String) -> Option<T> {
+        let (success, detail, value) = match outcome {
+            Ok(value) => (true, detail(&value), Some(value)),
+            Err(e) => (false, e.to_string(), None),
+        };
+        self.tasks.push(MaintenanceTaskResult { task: task.to_string(), success, detail });
+        value
+    }
+}
+
+impl DatabaseManager {
+    /// Flip active loans past their due date to overdue so the status column can be
+    /// filtered and synced. Returned loans are never touched. Returns the ids that
+    /// changed, each already queued for sync.
+    pub async fn mark_overdue_borrowings(&self) -> Result<Vec<String>> {
+        let ids = {
+            let conn = self.lock_connection()?;
+            let tx = conn.unchecked_transaction()?;
+
+            let mut stmt = tx.prepare(
+                "SELECT id FROM borrowings
+                 WHERE deleted = 0 AND status = 'active' AND returned_date IS NULL AND due_date < date('now')"
+            )?;
+            let ids = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+            drop(stmt);
+
+            for id in &ids {
+                tx.execute(
+                    "UPDATE borrowings SET status = 'overdue', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+                     WHERE id = ?1 AND status = 'active' AND returned_date IS NULL",
+                    [id],
+                )?;
+            }
+            tx.commit()?;
+            ids
+        };
+
+        let payload = serde_json::json!({"status": "overdue"});
+        for id in &ids {
+            self.enqueue_operation("borrowings", "update", id, &payload).await?;
+        }
+        Ok(ids)
+    }
+
+    /// Fold the WAL back into the main file and truncate it
+    pub async fn checkpoint_wal(&self) -> Result<WalCheckpoint> {
+        let conn = self.lock_connection()?;
+        checkpoint(&conn)
+    }
+
+    pub async fn get_maintenance_schedule(&self) -> Result<MaintenanceSchedule> {
+        Ok(self.get_setting(MAINTENANCE_SCHEDULE_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_maintenance_schedule(&self, enabled: bool, hour: u32, backup_dir: Option<String>) -> Result<MaintenanceSchedule> {
+        let conn = self.lock_connection()?;
+        let mut schedule: MaintenanceSchedule = read_setting(&conn, MAINTENANCE_SCHEDULE_KEY)?.unwrap_or_default();
+        schedule.enabled = enabled;
+        schedule.hour = hour;
+        schedule.backup_dir = backup_dir;
+        write_setting(&conn, MAINTENANCE_SCHEDULE_KEY, &schedule)?;
+        Ok(schedule)
+    }
+
+    /// Record today's scheduled run if one is due, atomically like claim_auto_sync
+    pub async fn claim_nightly_maintenance(&self, now: DateTime<Local>) -> Result<bool> {
+        let conn = self.lock_connection()?;
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)?;
+
+        let mut schedule: MaintenanceSchedule = read_setting(&tx, MAINTENANCE_SCHEDULE_KEY)?.unwrap_or_default();
+        if !schedule.is_due(now) {
+            return Ok(false);
+        }
+
+        schedule.last_run = Some(now.with_timezone(&Utc));
+        write_setting(&tx, MAINTENANCE_SCHEDULE_KEY, &schedule)?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Housekeeping bundle run by the nightly scheduler or on demand. Every task
+    /// runs even when an earlier one fails; the report says what each did.
+    pub async fn run_nightly_maintenance(&self) -> Result<MaintenanceReport> {
+        let schedule = self.get_maintenance_schedule().await?;
+        let mut report = MaintenanceReport {
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            tasks: Vec::new(),
+            sessions_removed: 0,
+            loans_marked_overdue: 0,
+            availability_corrections: 0,
+            backup_path: None,
+            succeeded: false,
+        };
+
+        let removed = report.record("cleanup_expired_sessions", self.cleanup_expired_sessions().await,
+            |removed| format!("{} expired sessions removed", removed));
+        report.sessions_removed = removed.unwrap_or(0);
+
+        let overdue = report.record("mark_overdue_borrowings", self.mark_overdue_borrowings().await,
+            |ids| format!("{} loans marked overdue", ids.len()));
+        report.loans_marked_overdue = overdue.map_or(0, |ids| ids.len());
+
+        let availability = report.record("recompute_availability", self.recompute_availability().await,
+            |r| format!("{} of {} books corrected", r.books_corrected, r.books_checked));
+        report.availability_corrections = availability.map_or(0, |r| r.books_corrected);
+
+        report.record("prune_conflict_log", self.prune_conflict_log().await,
+            |removed| format!("{} old sync conflicts removed", removed));
+
+        report.record("wal_checkpoint", self.checkpoint_wal().await,
+            |c| format!("{} of {} WAL frames checkpointed{}", c.checkpointed_frames, c.log_frames,
+                if c.busy { ", database was busy" } else { "" }));
+
+        if let Some(dir) = schedule.backup_dir.as_deref().filter(|d| !d.trim().is_empty()) {
+            let path = timestamped_backup_path(Path::new(dir), Local::now());
+            let outcome = match std::fs::create_dir_all(dir) {
+                Ok(()) => self.backup_database(&path).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if report.record("backup", outcome, |_| format!("Backed up to {}", path.display())).is_some() {
+                report.backup_path = Some(path.display().to_string());
+            }
+        }
+
+        report.succeeded = report.tasks.iter().all(|t| t.success);
+        report.finished_at = Utc::now();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn nightly_maintenance_runs_every_task_and_totals_the_results() {
+        let backup_dir = std::env::temp_dir().join(format!("nightly-{}", uuid::Uuid::new_v4()));
+        let db = test_db();
+        for (id, expiry) in [("stale", "-30 days"), ("current", "+7 days")] {
+            execute(
+                &db,
+                "INSERT INTO user_sessions (id, user_id, email, access_token, expires_at, offline_expiry)
+                 VALUES (?1, 'user', 'user@school.test', 'token', strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), datetime('now', ?2))",
+                [id, expiry],
+            );
+        }
+        let (book_id, copies) = seed_book(&db, "NIGHT", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let late = seed_loan(&db, &student_id, &book_id, &copies[0], -3);
+        seed_loan(&db, &student_id, &book_id, &copies[1], 3);
+        let (drifted, _) = seed_book(&db, "DRIFT", 3);
+        execute(&db, "UPDATE books SET available_copies = 0 WHERE id = ?1", [&drifted]);
+        db.set_maintenance_schedule(true, 2, Some(backup_dir.to_string_lossy().into_owned())).await.unwrap();
+
+        let report = db.run_nightly_maintenance().await.unwrap();
+        let tasks: Vec<(&str, bool)> = report.tasks.iter().map(|t| (t.task.as_str(), t.success)).collect();
+        assert_eq!(tasks, [
+            ("cleanup_expired_sessions", true),
+            ("mark_overdue_borrowings", true),
+            ("recompute_availability", true),
+            ("prune_conflict_log", true),
+            ("wal_checkpoint", true),
+            ("backup", true),
+        ]);
+        assert!(report.succeeded);
+        assert_eq!((report.sessions_removed, report.loans_marked_overdue, report.availability_corrections), (1, 1, 1));
+        assert_eq!(report.tasks[1].detail, "1 loans marked overdue");
+
+        assert_eq!(scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [&late]), "overdue");
+        assert_eq!(book_counts(&db, &drifted), (3, 3));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM user_sessions", []), 1);
+        assert!(std::path::Path::new(&report.backup_path.expect("backup written")).is_file());
+
+        std::fs::remove_dir_all(&backup_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn past_due_loans_flip_to_overdue_and_others_are_left_alone() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "DUE", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let past_due = seed_loan(&db, &student_id, &book_id, &copies[0], -1);
+        let future_due = seed_loan(&db, &student_id, &book_id, &copies[1], 1);
+        let returned = seed_dated_loan(&db, &student_id, &book_id, "2025-01-01", "2025-01-15", Some("2025-02-01"));
+        let status = |id: &str| scalar::<String, _>(&db, "SELECT status FROM borrowings WHERE id = ?1", [id]);
+
+        assert_eq!(db.mark_overdue_borrowings().await.unwrap(), [past_due.clone()]);
+        assert_eq!(status(&past_due), "overdue");
+        assert_eq!(status(&future_due), "active");
+        assert_eq!(status(&returned), "returned");
+        assert_eq!(scalar::<String, _>(&db, "SELECT record_id FROM sync_queue", []), past_due);
+
+        // Already overdue loans aren't flipped or queued again
+        assert!(db.mark_overdue_borrowings().await.unwrap().is_empty());
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM sync_queue", []), 1);
+    }
+}
@@ -3,7 +3,7 @@ use rusqlite::{Connection, Result, params};
 use std::sync::{Arc, Mutex};
 use tokio::task;
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 
 // Helper functions for row conversion
 #[allow(dead_code)]
@@ -65,6 +65,7 @@ fn row_to_student(row: &rusqlite::Row) -> rusqlite::Result<Student> {
         academic_year: row.get(14)?,
         is_repeating: row.get::<_, i32>(15)? == 1,
         legacy_student_id: row.get(16)?,
+        gender: None,
     })
 }
 
@@ -166,8 +167,8 @@ impl OptimizedDatabaseManager {
                         book.available_copies,
                         book.shelf_location,
                         book.description,
-                        book.created_at.to_rfc3339(),
-                        book.updated_at.to_rfc3339(),
+                        book.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                        book.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                     ])?;
                     inserted += 1;
                 }
@@ -365,8 +366,8 @@ impl OptimizedDatabaseManager {
                                 book.available_copies,
                                 book.shelf_location,
                                 book.description,
-                                book.created_at.to_rfc3339(),
-                                book.updated_at.to_rfc3339(),
+                                book.created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                                book.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                             ]
                         );
                         
@@ -397,7 +398,7 @@ impl OptimizedDatabaseManager {
                                 book.available_copies,
                                 book.shelf_location,
                                 book.description,
-                                book.updated_at.to_rfc3339(),
+                                book.updated_at.to_rfc3339_opts(SecondsFormat::Millis, true),
                             ]
                         );
                         
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+// A4 in points
+const PAGE_WIDTH: usize = 595;
+const PAGE_HEIGHT: usize = 842;
+const MARGIN: usize = 50;
+const LINE_HEIGHT: usize = 14;
+
+// The standard Type1 fonts only cover ASCII reliably, anything else prints as '?'
+fn escape_pdf_text(text: &str) -> String {
+    text.chars().map(|c| match c {
+        '(' | ')' | '\\' => format!("\\{}", c),
+        c if c.is_ascii() && !c.is_ascii_control() => c.to_string(),
+        _ => "?".to_string(),
+    }).collect()
+}
+
+/// Write a title and lines of plain text as a minimal PDF using the built-in Helvetica font
+pub fn write_text_pdf(path: &Path, title: &str, lines: &[String]) -> std::io::Result<()> {
+    // Room for the title and the blank line under it
+    let lines_per_page = (PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT - 2;
+    let pages: Vec<&[String]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(lines_per_page).collect()
+    };
+
+    // Objects 1-3 are the catalog, page tree and font; each page adds a page and a content stream
+    let kids = (0..pages.len())
+        .map(|i| format!("{} 0 R", 4 + i * 2))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+
+    for (i, page_lines) in pages.iter().enumerate() {
+        let mut content = format!(
+            "BT\n/F1 16 Tf\n{} {} Td\n{} TL\n({}) Tj\n/F1 11 Tf\nT* T*\n",
+            MARGIN, PAGE_HEIGHT - MARGIN, LINE_HEIGHT, escape_pdf_text(title)
+        );
+        for line in page_lines.iter() {
+            content.push_str(&format!("({}) Tj T*\n", escape_pdf_text(line)));
+        }
+        content.push_str("ET");
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, 5 + i * 2
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content));
+    }
+
+    let mut document = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(document.len());
+        document.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+
+    let xref_offset = document.len();
+    document.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        document.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    document.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1, xref_offset
+    ));
+
+    std::fs::write(path, document)
+}
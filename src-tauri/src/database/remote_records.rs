@@ -0,0 +1,78 @@
+use super::backup::{main_columns, sql_value};
+use super::exports::SUPABASE_EXPORT_TABLES;
+use super::hashing::{refresh_content_hash_in, row_as_json, HASHED_TABLES};
+use super::maintenance::SYNC_FLAG_TABLES;
+use super::{constraint_error, DatabaseManager};
+use crate::sync::normalize::LOCAL_ONLY_FIELDS;
+use rusqlite::{params_from_iter, OptionalExtension, Result};
+use serde_json::{Map, Value};
+
+/// Only tables that exist on the Supabase side can be re-pulled
+pub fn check_remote_table(table: &str) -> Result<()> {
+    if SUPABASE_EXPORT_TABLES.contains(&table) {
+        Ok(())
+    } else {
+        Err(constraint_error(format!("{} is not a Supabase table", table)))
+    }
+}
+
+impl DatabaseManager {
+    /// Overwrite the local copy of one record with the row Supabase returned and
+    /// hand back the stored row. Remote fields without a local column are dropped.
+    pub async fn upsert_remote_record(&self, table: &str, remote: &Map<String, Value>) -> Result<Value> {
+        check_remote_table(table)?;
+        let Some(remote_id) = remote.get("id").and_then(Value::as_str) else {
+            return Err(constraint_error("Remote record has no id"));
+        };
+
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        // Older local rows may still hold the id as bare hex; keep whichever form is stored
+        let record_id: String = tx.query_row(
+            &format!("SELECT id FROM {} WHERE id = ?1 OR id = ?2", table),
+            (remote_id, remote_id.replace('-', "")),
+            |row| row.get(0),
+        ).optional()?.unwrap_or_else(|| remote_id.to_string());
+
+        let columns: Vec<String> = main_columns(&tx, table)?.into_iter()
+            .filter(|column| column != "id" && remote.contains_key(column) && !LOCAL_ONLY_FIELDS.contains(&column.as_str()))
+            .collect();
+
+        let mut names = vec!["id".to_string()];
+        names.extend(columns.iter().cloned());
+        let placeholders = (1..=names.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+        let updates = if columns.is_empty() {
+            "NOTHING".to_string()
+        } else {
+            format!("UPDATE SET {}", columns.iter()
+                .map(|column| format!("{0} = excluded.{0}", column))
+                .collect::<Vec<_>>()
+                .join(", "))
+        };
+
+        let mut values = vec![rusqlite::types::Value::Text(record_id.clone())];
+        values.extend(columns.iter().map(|column| sql_value(&remote[column])));
+        tx.execute(
+            &format!("INSERT INTO {} ({}) VALUES ({}) ON CONFLICT(id) DO {}", table, names.join(", "), placeholders, updates),
+            params_from_iter(values),
+        )?;
+
+        // The local copy now matches Supabase, so nothing about it is left to push
+        if SYNC_FLAG_TABLES.contains(&table) {
+            tx.execute(&format!("UPDATE {} SET synced = 1 WHERE id = ?1", table), [&record_id])?;
+        }
+        if HASHED_TABLES.contains(&table) {
+            refresh_content_hash_in(&tx, table, &record_id)?;
+            tx.execute(&format!("UPDATE {} SET pushed_hash = content_hash WHERE id = ?1", table), [&record_id])?;
+        }
+        tx.execute(
+            "DELETE FROM sync_queue WHERE table_name = ?1 AND record_id = ?2",
+            (table, &record_id),
+        )?;
+
+        let row = tx.query_row(&format!("SELECT * FROM {} WHERE id = ?1", table), [&record_id], row_as_json)?;
+        tx.commit()?;
+        Ok(row)
+    }
+}
@@ -0,0 +1,253 @@
+use super::settings::{read_setting, LOAN_RULES_KEY};
+use super::{constraint_error, parse_enum, parse_sqlite_date, row_to_borrowing_details, DatabaseManager, BORROWING_DETAILS_SELECT};
+use crate::models::*;
+use chrono::{NaiveDate, Utc};
+use rusqlite::Result;
+use serde::{Deserialize, Serialize};
+
+/// Which open loans a bulk due date extension applies to; every field left out matches all
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DueDateFilter {
+    pub due_from: Option<NaiveDate>,
+    pub due_to: Option<NaiveDate>,
+    pub borrower_type: Option<BorrowerType>,
+    pub book_id: Option<String>,
+}
+
+// Open loans matching the filter; ?1..?4 are due_from, due_to, borrower_type, book_id
+const EXTENDABLE_LOANS_WHERE: &str = "
+    WHERE deleted = 0 AND returned_date IS NULL AND status IN ('active', 'overdue')
+      AND (?1 IS NULL OR date(due_date) >= ?1)
+      AND (?2 IS NULL OR date(due_date) <= ?2)
+      AND (?3 IS NULL OR COALESCE(borrower_type, 'student') = ?3)
+      AND (?4 IS NULL OR book_id = ?4)";
+
+fn filter_params(filter: &DueDateFilter) -> (Option<String>, Option<String>, Option<&'static str>, Option<String>) {
+    (
+        filter.due_from.map(|d| d.to_string()),
+        filter.due_to.map(|d| d.to_string()),
+        filter.borrower_type.as_ref().map(|t| match t {
+            BorrowerType::Student => "student",
+            BorrowerType::Staff => "staff",
+        }),
+        filter.book_id.clone(),
+    )
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenewalResult {
+    pub borrowing_id: String,
+    pub previous_due_date: NaiveDate,
+    pub new_due_date: NaiveDate,
+    pub renewal_count: i64,
+    pub max_renewals: i64,
+    pub renewals_remaining: i64,
+}
+
+impl DatabaseManager {
+    /// Push an open loan's due date out by extra_days (a standard loan period by
+    /// default). Each loan can be renewed at most max_renewals times, and an
+    /// overdue loan has to come back before it can go out again.
+    pub async fn renew_book(&self, borrowing_id: &str, extra_days: Option<i64>) -> Result<RenewalResult> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (status, returned_date, due_date, renewal_count, borrower_type): (String, Option<String>, String, i64, String) = tx.query_row(
+            "SELECT status, returned_date, due_date, COALESCE(renewal_count, 0), COALESCE(borrower_type, 'student')
+             FROM borrowings WHERE id = ?1 AND deleted = 0",
+            [borrowing_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )?;
+        if returned_date.is_some() || status == "returned" || status == "lost" {
+            return Err(constraint_error(format!("Borrowing {} is no longer open and cannot be renewed", borrowing_id)));
+        }
+
+        let previous_due_date = parse_sqlite_date(&due_date)?;
+        if status == "overdue" || previous_due_date < Utc::now().date_naive() {
+            return Err(constraint_error(format!(
+                "Borrowing {} was due on {} and is overdue; it must be returned, not renewed",
+                borrowing_id, previous_due_date
+            )));
+        }
+
+        let rules: LoanRules = read_setting(&tx, LOAN_RULES_KEY)?.unwrap_or_default();
+        if rules.renewals_remaining(renewal_count) == 0 {
+            return Err(constraint_error(format!(
+                "Borrowing {} has been renewed {} times, the limit is {} (0 renewals remaining)",
+                borrowing_id, renewal_count, rules.max_renewals
+            )));
+        }
+
+        let borrower_type = parse_enum(&borrower_type).unwrap_or(BorrowerType::Student);
+        let extra_days = extra_days.unwrap_or(rules.loan_period_for(&borrower_type));
+        if extra_days < 1 {
+            return Err(constraint_error("A renewal must add at least 1 day"));
+        }
+
+        let new_due_date = previous_due_date + chrono::Duration::days(extra_days);
+        tx.execute(
+            "UPDATE borrowings SET due_date = ?2, renewal_count = ?3, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            (borrowing_id, new_due_date.to_string(), renewal_count + 1),
+        )?;
+        tx.commit()?;
+
+        Ok(RenewalResult {
+            borrowing_id: borrowing_id.to_string(),
+            previous_due_date,
+            new_due_date,
+            renewal_count: renewal_count + 1,
+            max_renewals: rules.max_renewals,
+            renewals_remaining: rules.renewals_remaining(renewal_count + 1),
+        })
+    }
+
+    /// One borrowing with its student, book and copy, plus how many renewals it has left
+    pub async fn get_borrowing_details(&self, borrowing_id: &str) -> Result<serde_json::Value> {
+        let conn = self.lock_connection()?;
+        let mut details = conn.query_row(
+            &format!("{} WHERE b.id = ?1 AND b.deleted = 0", BORROWING_DETAILS_SELECT),
+            [borrowing_id],
+            row_to_borrowing_details,
+        )?;
+
+        let rules: LoanRules = read_setting(&conn, LOAN_RULES_KEY)?.unwrap_or_default();
+        let renewal_count = details["renewal_count"].as_i64().unwrap_or(0);
+        details["max_renewals"] = serde_json::json!(rules.max_renewals);
+        details["renewals_remaining"] = serde_json::json!(rules.renewals_remaining(renewal_count));
+        Ok(details)
+    }
+
+    /// How many open loans bulk_extend_due_dates would move with this filter
+    pub async fn count_extendable_loans(&self, filter: &DueDateFilter) -> Result<i64> {
+        let conn = self.lock_connection()?;
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM borrowings {}", EXTENDABLE_LOANS_WHERE),
+            filter_params(filter),
+            |row| row.get(0),
+        )
+    }
+
+    /// Push every matching open loan's due date out by `days`, e.g. after an
+    /// unplanned closure. Overdue loans whose new due date isn't past go back to
+    /// active. Returns each changed loan with its new due date and status.
+    pub async fn bulk_extend_due_dates(&self, days: i64, filter: &DueDateFilter) -> Result<Vec<(String, String, String)>> {
+        if days < 1 {
+            return Err(constraint_error("Due dates must be extended by at least 1 day"));
+        }
+
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut stmt = tx.prepare(&format!("SELECT id FROM borrowings {}", EXTENDABLE_LOANS_WHERE))?;
+        let ids = stmt.query_map(filter_params(filter), |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        drop(stmt);
+
+        let shift = format!("+{} days", days);
+        let mut changed = Vec::with_capacity(ids.len());
+        for id in ids {
+            tx.execute(
+                "UPDATE borrowings SET due_date = date(due_date, ?2),
+                 status = CASE WHEN status = 'overdue' AND date(due_date, ?2) >= date('now') THEN 'active' ELSE status END,
+                 updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+                 WHERE id = ?1",
+                (&id, &shift),
+            )?;
+            let (due_date, status): (String, String) = tx.query_row(
+                "SELECT due_date, status FROM borrowings WHERE id = ?1",
+                [&id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            changed.push((id, due_date, status));
+        }
+
+        tx.commit()?;
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn renewals_stop_at_the_limit_with_none_remaining() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "RENEW", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 3);
+        db.set_loan_rules(&LoanRules { max_renewals: 3, ..LoanRules::default() }).await.unwrap();
+
+        for expected_remaining in [2, 1, 0] {
+            let renewal = db.renew_book(&loan_id, Some(7)).await.unwrap();
+            assert_eq!(renewal.new_due_date, renewal.previous_due_date + chrono::Duration::days(7));
+            assert_eq!((renewal.max_renewals, renewal.renewals_remaining), (3, expected_remaining));
+        }
+
+        let err = db.renew_book(&loan_id, Some(7)).await.unwrap_err();
+        assert!(err.to_string().contains("0 renewals remaining"), "{}", err);
+        let details = db.get_borrowing_details(&loan_id).await.unwrap();
+        assert_eq!(details["renewals_remaining"], 0);
+        assert_eq!(details["max_renewals"], 3);
+    }
+
+    #[tokio::test]
+    async fn bulk_extension_moves_open_loans_and_leaves_returned_ones() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "CLOSED", 4);
+        let student_id = seed_student(&db, "ADM-1");
+        let future = seed_loan(&db, &student_id, &book_id, &copies[0], 5);
+        let recently_overdue = seed_loan(&db, &student_id, &book_id, &copies[1], -3);
+        let long_overdue = seed_loan(&db, &student_id, &book_id, &copies[2], -20);
+        execute(&db, "UPDATE borrowings SET status = 'overdue' WHERE id IN (?1, ?2)", [&recently_overdue, &long_overdue]);
+        let returned = seed_loan(&db, &student_id, &book_id, &copies[3], 2);
+        db.return_book(&returned, ReturnData::default(), None).await.unwrap();
+        let returned_due: String = scalar(&db, "SELECT due_date FROM borrowings WHERE id = ?1", [&returned]);
+
+        let all = DueDateFilter::default();
+        assert_eq!(db.count_extendable_loans(&all).await.unwrap(), 3);
+        assert!(db.bulk_extend_due_dates(0, &all).await.is_err());
+
+        let mut changed = db.bulk_extend_due_dates(10, &all).await.unwrap();
+        changed.sort_by_key(|(id, _, _)| [&future, &recently_overdue, &long_overdue].iter().position(|loan| *loan == id));
+        assert_eq!(
+            changed,
+            [
+                (future.clone(), days_from_today(15).to_string(), "active".to_string()),
+                (recently_overdue.clone(), days_from_today(7).to_string(), "active".to_string()),
+                (long_overdue.clone(), days_from_today(-10).to_string(), "overdue".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT due_date || ' ' || status FROM borrowings WHERE id = ?1", [&returned]),
+            format!("{} returned", returned_due)
+        );
+    }
+
+    #[tokio::test]
+    async fn a_rejected_renewal_leaves_the_loan_untouched() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "CAP", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 3);
+        let overdue_id = seed_loan(&db, &student_id, &book_id, &copies[1], -2);
+        db.set_loan_rules(&LoanRules { max_renewals: 1, ..LoanRules::default() }).await.unwrap();
+        let loan_state = |id: &str| scalar::<String, _>(&db, "SELECT due_date || ' ' || renewal_count FROM borrowings WHERE id = ?1", [id]);
+
+        db.renew_book(&loan_id, Some(7)).await.unwrap();
+        let renewed = loan_state(&loan_id);
+        assert_eq!(renewed, format!("{} 1", days_from_today(10)));
+
+        let err = db.renew_book(&loan_id, Some(7)).await.unwrap_err();
+        assert!(err.to_string().contains("the limit is 1"), "{}", err);
+        assert_eq!(loan_state(&loan_id), renewed);
+
+        let before = loan_state(&overdue_id);
+        let err = db.renew_book(&overdue_id, Some(7)).await.unwrap_err();
+        assert!(err.to_string().contains("is overdue"), "{}", err);
+        assert_eq!(loan_state(&overdue_id), before);
+    }
+}
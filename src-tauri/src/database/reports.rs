@@ -0,0 +1,1720 @@
+use super::settings::{read_setting, SHELF_CAPACITIES_KEY};
+use super::archive::borrowings_source;
+use super::DatabaseManager;
+use chrono::NaiveDate;
+use rusqlite::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShelfOccupancy {
+    pub shelf_location: String,
+    pub title_count: i64,
+    pub item_count: i64,
+    pub on_shelf_count: i64,
+    pub capacity: Option<i64>,
+    pub occupancy_percent: Option<f64>,
+    pub over_capacity: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowerTypeFineStats {
+    pub borrower_type: String,
+    pub fine_count: i64,
+    pub borrower_count: i64,
+    pub total_amount: f64,
+    pub average_amount: f64,
+    pub average_per_borrower: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendGranularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl TrendGranularity {
+    // strftime pattern used to bucket dates into periods
+    fn strftime_format(self) -> &'static str {
+        match self {
+            TrendGranularity::Day => "%Y-%m-%d",
+            TrendGranularity::Week => "%Y-W%W",
+            TrendGranularity::Month => "%Y-%m",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaffIssuancePoint {
+    pub period: String,
+    pub checkouts: i64,
+    pub returns: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverdueTrendPoint {
+    pub period: String,
+    pub loans_due: i64,
+    pub became_overdue: i64,
+    pub overdue_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NeverBorrowedBook {
+    pub book_id: String,
+    pub title: String,
+    pub author: String,
+    pub book_code: Option<String>,
+    pub shelf_location: Option<String>,
+    pub total_copies: i64,
+    pub acquisition_year: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BookFineTotal {
+    pub book_id: String,
+    pub title: String,
+    pub author: String,
+    pub book_code: Option<String>,
+    pub fine_count: i64,
+    pub total_amount: f64,
+    pub unpaid_amount: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeToFirstLoanStats {
+    pub books_considered: i64,
+    pub borrowed_count: i64,
+    pub never_borrowed_count: i64,
+    pub mean_days: Option<f64>,
+    pub median_days: Option<f64>,
+    pub min_days: Option<i64>,
+    pub max_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassAllowanceUtilization {
+    pub class_id: String,
+    pub class_name: String,
+    pub max_books_allowed: i64,
+    pub student_count: i64,
+    pub active_loans: i64,
+    pub students_at_cap: i64,
+    pub average_loans_per_student: f64,
+    pub utilization_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryAvailability {
+    pub category_id: Option<String>,
+    pub category_name: String,
+    pub title_count: i64,
+    pub total_copies: i64,
+    pub available_copies: i64,
+    pub unavailable_copies: i64,
+    pub availability_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DueReportEntry {
+    pub borrowing_id: String,
+    pub borrower_type: String,
+    pub borrower_id: Option<String>,
+    pub borrower_number: Option<String>,
+    pub borrower_name: Option<String>,
+    pub class_grade: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub book_title: Option<String>,
+    pub book_code: Option<String>,
+    pub due_date: NaiveDate,
+    pub days_remaining: i64,
+    pub days_overdue: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DueReport {
+    pub as_of: NaiveDate,
+    pub days_ahead: i64,
+    pub due_soon: Vec<DueReportEntry>,
+    pub overdue: Vec<DueReportEntry>,
+}
+
+// Open loans with the borrower's contact details, students and staff alike
+const DUE_REPORT_QUERY: &str = "
+    SELECT b.id, COALESCE(b.borrower_type, 'student'),
+           COALESCE(b.student_id, b.staff_id),
+           COALESCE(s.admission_number, st.staff_id),
+           COALESCE(s.first_name || ' ' || s.last_name, st.first_name || ' ' || st.last_name),
+           s.class_grade,
+           NULLIF(TRIM(COALESCE(s.email, st.email)), ''),
+           NULLIF(TRIM(COALESCE(s.phone, st.phone)), ''),
+           bk.title, bk.book_code, b.due_date,
+           CAST(julianday(b.due_date) - julianday(date('now')) AS INTEGER) as days_until_due
+    FROM borrowings b
+    LEFT JOIN students s ON b.student_id = s.id
+    LEFT JOIN staff st ON b.staff_id = st.id
+    LEFT JOIN books bk ON b.book_id = bk.id
+    WHERE b.deleted = 0 AND b.returned_date IS NULL AND b.status IN ('active', 'overdue')";
+
+fn row_to_due_entry(row: &rusqlite::Row) -> Result<DueReportEntry> {
+    let due_date: String = row.get(10)?;
+    let days_until_due: i64 = row.get(11)?;
+    Ok(DueReportEntry {
+        borrowing_id: row.get(0)?,
+        borrower_type: row.get(1)?,
+        borrower_id: row.get(2)?,
+        borrower_number: row.get(3)?,
+        borrower_name: row.get(4)?,
+        class_grade: row.get(5)?,
+        email: row.get(6)?,
+        phone: row.get(7)?,
+        book_title: row.get(8)?,
+        book_code: row.get(9)?,
+        due_date: super::parse_sqlite_date(&due_date)?,
+        days_remaining: days_until_due.max(0),
+        days_overdue: (-days_until_due).max(0),
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BookBorrowCount {
+    pub book_id: String,
+    pub title: String,
+    pub author: String,
+    pub borrow_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BorrowerActivity {
+    pub borrower_type: String,
+    pub borrower_id: String,
+    pub borrower_name: Option<String>,
+    pub borrow_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthlyBorrowings {
+    pub month: String,
+    pub borrowings: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryShare {
+    pub category_id: Option<String>,
+    pub category_name: String,
+    pub title_count: i64,
+    pub copy_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DashboardAnalytics {
+    pub most_borrowed_books: Vec<BookBorrowCount>,
+    pub most_active_borrowers: Vec<BorrowerActivity>,
+    pub borrowings_per_month: Vec<MonthlyBorrowings>,
+    pub category_distribution: Vec<CategoryShare>,
+    pub outstanding_fines: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConditionCount {
+    pub condition: String,
+    pub copy_count: i64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenewedTitle {
+    pub book_id: String,
+    pub title: String,
+    pub author: String,
+    pub loan_count: i64,
+    pub renewal_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenewalStats {
+    pub loan_count: i64,
+    pub renewed_loan_count: i64,
+    pub total_renewals: i64,
+    pub average_renewals_per_loan: f64,
+    pub most_renewed_titles: Vec<RenewedTitle>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AcquisitionSpendPoint {
+    pub period: String,
+    pub books_added: i64,
+    pub total_cost: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AcquisitionSpend {
+    pub periods: Vec<AcquisitionSpendPoint>,
+    pub total_cost: f64,
+    pub books_missing_cost: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnavailablePopularBook {
+    pub book_id: String,
+    pub title: String,
+    pub author: String,
+    pub book_code: Option<String>,
+    pub total_copies: i64,
+    pub on_loan: i64,
+    pub recent_borrow_count: i64,
+    pub borrows_per_copy: f64,
+    pub next_due_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncTableMetrics {
+    pub table_name: String,
+    pub request_count: i64,
+    pub total_rows: i64,
+    pub average_latency_ms: f64,
+    pub max_latency_ms: i64,
+    pub rows_per_second: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GenderCount {
+    pub gender: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassLevelGenderCount {
+    pub class_level: String,
+    pub gender: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DemographicBreakdown {
+    pub total_students: i64,
+    pub by_gender: Vec<GenderCount>,
+    pub by_class_level: Vec<ClassLevelGenderCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentMethodTotal {
+    pub payment_method: String,
+    pub payments: i64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ActiveBorrowerCount {
+    pub total: i64,
+    pub students: i64,
+    pub staff: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub checkouts: i64,
+    pub returns: i64,
+    pub fines_collected_count: i64,
+    pub fines_collected_amount: f64,
+    pub overdue_created: i64,
+    pub new_students: i64,
+    pub new_staff: i64,
+    pub new_members: i64,
+}
+
+impl DailySummary {
+    /// Plain text lines for the printable end-of-day sheet
+    pub fn to_lines(&self) -> Vec<String> {
+        vec![
+            format!("Date: {}", self.date),
+            String::new(),
+            format!("Checkouts: {}", self.checkouts),
+            format!("Returns: {}", self.returns),
+            format!("Fines collected: {} ({:.2})", self.fines_collected_count, self.fines_collected_amount),
+            format!("Loans newly overdue: {}", self.overdue_created),
+            format!("New members: {} ({} students, {} staff)", self.new_members, self.new_students, self.new_staff),
+        ]
+    }
+}
+
+impl DatabaseManager {
+    /// Items per shelf compared against the configured shelf capacities
+    pub async fn get_shelf_occupancy(&self) -> Result<Vec<ShelfOccupancy>> {
+        let conn = self.lock_connection()?;
+        let mut capacities: HashMap<String, i64> = read_setting(&conn, SHELF_CAPACITIES_KEY)?.unwrap_or_default();
+
+        let mut stmt = conn.prepare(
+            "SELECT TRIM(shelf_location) as shelf, COUNT(*), COALESCE(SUM(total_copies), 0), COALESCE(SUM(available_copies), 0)
+             FROM books
+             WHERE deleted = 0 AND shelf_location IS NOT NULL AND TRIM(shelf_location) != ''
+             GROUP BY TRIM(shelf_location)
+             ORDER BY shelf"
+        )?;
+
+        let mut shelves = stmt.query_map([], |row| {
+            Ok(ShelfOccupancy {
+                shelf_location: row.get(0)?,
+                title_count: row.get(1)?,
+                item_count: row.get(2)?,
+                on_shelf_count: row.get(3)?,
+                capacity: None,
+                occupancy_percent: None,
+                over_capacity: false,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        for shelf in shelves.iter_mut() {
+            if let Some(capacity) = capacities.remove(&shelf.shelf_location) {
+                shelf.capacity = Some(capacity);
+                shelf.occupancy_percent = (capacity > 0).then(|| shelf.item_count as f64 / capacity as f64 * 100.0);
+                shelf.over_capacity = shelf.item_count > capacity;
+            }
+        }
+
+        // Configured shelves that currently hold nothing
+        for (shelf_location, capacity) in capacities {
+            shelves.push(ShelfOccupancy {
+                shelf_location,
+                title_count: 0,
+                item_count: 0,
+                on_shelf_count: 0,
+                capacity: Some(capacity),
+                occupancy_percent: Some(0.0),
+                over_capacity: false,
+            });
+        }
+        shelves.sort_by(|a, b| a.shelf_location.cmp(&b.shelf_location));
+
+        Ok(shelves)
+    }
+
+    /// Mean and total fine amounts split by borrower type within an optional date range
+    pub async fn get_average_fine_by_borrower_type(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<BorrowerTypeFineStats>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(borrower_type, 'student') as borrower_type,
+                    COUNT(*) as fine_count,
+                    COUNT(DISTINCT CASE WHEN COALESCE(borrower_type, 'student') = 'staff' THEN staff_id ELSE student_id END) as borrower_count,
+                    COALESCE(SUM(amount), 0) as total_amount,
+                    COALESCE(AVG(amount), 0) as average_amount
+             FROM fines
+             WHERE deleted = 0
+               AND (?1 IS NULL OR date(created_at) >= ?1)
+               AND (?2 IS NULL OR date(created_at) <= ?2)
+             GROUP BY COALESCE(borrower_type, 'student')
+             ORDER BY borrower_type"
+        )?;
+
+        let stats = stmt.query_map(
+            (from.map(|d| d.to_string()), to.map(|d| d.to_string())),
+            |row| {
+                let borrower_count: i64 = row.get(2)?;
+                let total_amount: f64 = row.get(3)?;
+                Ok(BorrowerTypeFineStats {
+                    borrower_type: row.get(0)?,
+                    fine_count: row.get(1)?,
+                    borrower_count,
+                    total_amount,
+                    average_amount: row.get(4)?,
+                    average_per_borrower: if borrower_count > 0 { total_amount / borrower_count as f64 } else { 0.0 },
+                })
+            }
+        )?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(stats)
+    }
+
+    /// Checkouts issued and returns received by one operator, bucketed by period
+    pub async fn get_staff_issuance_trend(
+        &self,
+        staff_id: &str,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        granularity: TrendGranularity,
+        include_archived: bool,
+    ) -> Result<Vec<StaffIssuancePoint>> {
+        let conn = self.lock_connection()?;
+        let source = borrowings_source(include_archived);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT period, SUM(checkouts), SUM(returns) FROM (
+                 SELECT strftime(?4, borrowed_date) as period, 1 as checkouts, 0 as returns
+                 FROM {0}
+                 WHERE deleted = 0 AND issued_by = ?1
+                   AND (?2 IS NULL OR date(borrowed_date) >= ?2)
+                   AND (?3 IS NULL OR date(borrowed_date) <= ?3)
+                 UNION ALL
+                 SELECT strftime(?4, returned_date), 0, 1
+                 FROM {0}
+                 WHERE deleted = 0 AND returned_by = ?1 AND returned_date IS NOT NULL
+                   AND (?2 IS NULL OR date(returned_date) >= ?2)
+                   AND (?3 IS NULL OR date(returned_date) <= ?3)
+             )
+             WHERE period IS NOT NULL
+             GROUP BY period
+             ORDER BY period",
+            source
+        ))?;
+
+        let points = stmt.query_map(
+            (staff_id, from.map(|d| d.to_string()), to.map(|d| d.to_string()), granularity.strftime_format()),
+            |row| {
+                Ok(StaffIssuancePoint {
+                    period: row.get(0)?,
+                    checkouts: row.get(1)?,
+                    returns: row.get(2)?,
+                })
+            }
+        )?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(points)
+    }
+
+    /// Loans falling due in each period and how many of them went overdue, either
+    /// returned after the due date or still out past it. Periods are bucketed by due date.
+    pub async fn get_overdue_trend(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        granularity: TrendGranularity,
+        include_archived: bool,
+    ) -> Result<Vec<OverdueTrendPoint>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT strftime(?3, due_date) as period,
+                    COUNT(*) as loans_due,
+                    SUM(CASE
+                        WHEN returned_date IS NOT NULL AND date(returned_date) > date(due_date) THEN 1
+                        WHEN returned_date IS NULL AND status IN ('active', 'overdue') AND date(due_date) < date('now') THEN 1
+                        ELSE 0
+                    END) as became_overdue
+             FROM {}
+             WHERE deleted = 0
+               AND (?1 IS NULL OR date(due_date) >= ?1)
+               AND (?2 IS NULL OR date(due_date) <= ?2)
+               AND date(due_date) <= date('now')
+             GROUP BY period
+             HAVING period IS NOT NULL
+             ORDER BY period",
+            borrowings_source(include_archived)
+        ))?;
+
+        let points = stmt.query_map(
+            (from.map(|d| d.to_string()), to.map(|d| d.to_string()), granularity.strftime_format()),
+            |row| {
+                let loans_due: i64 = row.get(1)?;
+                let became_overdue: i64 = row.get(2)?;
+                Ok(OverdueTrendPoint {
+                    period: row.get(0)?,
+                    loans_due,
+                    became_overdue,
+                    overdue_percent: if loans_due > 0 { became_overdue as f64 / loans_due as f64 * 100.0 } else { 0.0 },
+                })
+            }
+        )?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(points)
+    }
+
+    /// Weeding candidates - titles with no borrowing on record, optionally only those acquired before a year
+    pub async fn get_books_never_borrowed(&self, acquired_before: Option<i32>) -> Result<Vec<NeverBorrowedBook>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT bk.id, bk.title, bk.author, bk.book_code, bk.shelf_location, bk.total_copies,
+                    COALESCE(bk.acquisition_year, CAST(strftime('%Y', bk.created_at) AS INTEGER)) as acquired
+             FROM books bk
+             WHERE bk.deleted = 0
+               AND NOT EXISTS (
+                   SELECT 1 FROM borrowings b WHERE b.book_id = bk.id AND b.deleted = 0
+               )
+               AND NOT EXISTS (SELECT 1 FROM borrowings_archive a WHERE a.book_id = bk.id)
+               AND (?1 IS NULL OR COALESCE(bk.acquisition_year, CAST(strftime('%Y', bk.created_at) AS INTEGER)) < ?1)
+             ORDER BY acquired, bk.title"
+        )?;
+
+        let books = stmt.query_map([acquired_before], |row| {
+            Ok(NeverBorrowedBook {
+                book_id: row.get(0)?,
+                title: row.get(1)?,
+                author: row.get(2)?,
+                book_code: row.get(3)?,
+                shelf_location: row.get(4)?,
+                total_copies: row.get(5)?,
+                acquisition_year: row.get(6)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(books)
+    }
+
+    /// Circulation desk figures for a single day.
+    /// A loan counts as newly overdue on the first day after its due date if it was still out.
+    pub async fn get_daily_summary(&self, date: NaiveDate) -> Result<DailySummary> {
+        let conn = self.lock_connection()?;
+        let summary = conn.query_row(
+            "SELECT
+                 (SELECT COUNT(*) FROM borrowings WHERE deleted = 0 AND date(borrowed_date) = ?1),
+                 (SELECT COUNT(*) FROM borrowings WHERE deleted = 0 AND date(returned_date) = ?1),
+                 (SELECT COUNT(*) FROM fines
+                  WHERE deleted = 0 AND status IN ('paid', 'collected', 'cleared')
+                    AND date(COALESCE(paid_at, updated_at)) = ?1),
+                 (SELECT COALESCE(SUM(amount), 0) FROM fines
+                  WHERE deleted = 0 AND status IN ('paid', 'collected', 'cleared')
+                    AND date(COALESCE(paid_at, updated_at)) = ?1),
+                 (SELECT COUNT(*) FROM borrowings
+                  WHERE deleted = 0 AND date(due_date, '+1 day') = ?1
+                    AND (returned_date IS NULL OR date(returned_date) > date(due_date))),
+                 (SELECT COUNT(*) FROM students WHERE deleted = 0 AND date(created_at) = ?1),
+                 (SELECT COUNT(*) FROM staff WHERE deleted = 0 AND date(created_at) = ?1)",
+            [date.to_string()],
+            |row| {
+                let new_students: i64 = row.get(5)?;
+                let new_staff: i64 = row.get(6)?;
+                Ok(DailySummary {
+                    date,
+                    checkouts: row.get(0)?,
+                    returns: row.get(1)?,
+                    fines_collected_count: row.get(2)?,
+                    fines_collected_amount: row.get(3)?,
+                    overdue_created: row.get(4)?,
+                    new_students,
+                    new_staff,
+                    new_members: new_students + new_staff,
+                })
+            },
+        )?;
+
+        Ok(summary)
+    }
+
+    /// Days each book sat between being added and its first loan, for books added within the range
+    pub async fn get_time_to_first_loan(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<TimeToFirstLoanStats> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT CAST(julianday(date(MIN(b.borrowed_date))) - julianday(date(bk.created_at)) AS INTEGER)
+             FROM books bk
+             LEFT JOIN borrowings b ON b.book_id = bk.id AND b.deleted = 0
+             WHERE bk.deleted = 0
+               AND (?1 IS NULL OR date(bk.created_at) >= ?1)
+               AND (?2 IS NULL OR date(bk.created_at) <= ?2)
+             GROUP BY bk.id"
+        )?;
+
+        let delays: Vec<Option<i64>> = stmt.query_map(
+            (from.map(|d| d.to_string()), to.map(|d| d.to_string())),
+            |row| row.get(0),
+        )?.collect::<Result<Vec<_>, _>>()?;
+
+        // Loans recorded before the book was entered count as immediate
+        let mut days: Vec<i64> = delays.iter().flatten().map(|d| (*d).max(0)).collect();
+        days.sort_unstable();
+
+        let mean_days = (!days.is_empty()).then(|| days.iter().sum::<i64>() as f64 / days.len() as f64);
+        let median_days = match days.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(days[n / 2] as f64),
+            n => Some((days[n / 2 - 1] + days[n / 2]) as f64 / 2.0),
+        };
+
+        Ok(TimeToFirstLoanStats {
+            books_considered: delays.len() as i64,
+            borrowed_count: days.len() as i64,
+            never_borrowed_count: (delays.len() - days.len()) as i64,
+            mean_days,
+            median_days,
+            min_days: days.first().copied(),
+            max_days: days.last().copied(),
+        })
+    }
+
+    /// Titles ranked by the fines raised on their borrowings within an optional date range
+    pub async fn get_fines_by_book(&self, from: Option<NaiveDate>, to: Option<NaiveDate>, limit: Option<u32>) -> Result<Vec<BookFineTotal>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT bk.id, bk.title, bk.author, bk.book_code,
+                    COUNT(f.id) as fine_count,
+                    COALESCE(SUM(f.amount), 0) as total_amount,
+                    COALESCE(SUM(CASE WHEN f.status IN ('unpaid', 'partial') THEN f.amount ELSE 0 END), 0) as unpaid_amount
+             FROM fines f
+             JOIN borrowings b ON f.borrowing_id = b.id
+             JOIN books bk ON b.book_id = bk.id
+             WHERE f.deleted = 0
+               AND (?1 IS NULL OR date(f.created_at) >= ?1)
+               AND (?2 IS NULL OR date(f.created_at) <= ?2)
+             GROUP BY bk.id
+             ORDER BY total_amount DESC, fine_count DESC, bk.title
+             LIMIT ?3"
+        )?;
+
+        // A negative LIMIT means no limit in SQLite
+        let limit = limit.map(i64::from).unwrap_or(-1);
+        let totals = stmt.query_map(
+            (from.map(|d| d.to_string()), to.map(|d| d.to_string()), limit),
+            |row| {
+                Ok(BookFineTotal {
+                    book_id: row.get(0)?,
+                    title: row.get(1)?,
+                    author: row.get(2)?,
+                    book_code: row.get(3)?,
+                    fine_count: row.get(4)?,
+                    total_amount: row.get(5)?,
+                    unpaid_amount: row.get(6)?,
+                })
+            }
+        )?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(totals)
+    }
+
+    /// Average open loans per student in each active class against the class's
+    /// max_books_allowed. Students belong to a class by class_id, or by
+    /// class_grade matching the class name for records that predate class_id.
+    pub async fn get_class_allowance_utilization(&self) -> Result<Vec<ClassAllowanceUtilization>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.class_name, COALESCE(c.max_books_allowed, 2),
+                    COUNT(s.id) as student_count,
+                    COALESCE(SUM(s.active_loans), 0) as active_loans,
+                    COALESCE(SUM(CASE WHEN s.active_loans >= COALESCE(c.max_books_allowed, 2) THEN 1 ELSE 0 END), 0) as at_cap
+             FROM classes c
+             LEFT JOIN (
+                 SELECT st.id, st.class_id, st.class_grade,
+                        (SELECT COUNT(*) FROM borrowings b
+                         WHERE b.student_id = st.id AND b.deleted = 0
+                           AND b.returned_date IS NULL AND b.status IN ('active', 'overdue')) as active_loans
+                 FROM students st
+                 WHERE st.deleted = 0 AND COALESCE(st.status, 'active') = 'active'
+             ) s ON s.class_id = c.id OR (s.class_id IS NULL AND s.class_grade = c.class_name)
+             WHERE c.deleted = 0 AND c.is_active = 1
+             GROUP BY c.id
+             ORDER BY c.form_level, c.class_name"
+        )?;
+
+        let classes = stmt.query_map([], |row| {
+            let max_books_allowed: i64 = row.get(2)?;
+            let student_count: i64 = row.get(3)?;
+            let active_loans: i64 = row.get(4)?;
+            let average_loans_per_student = if student_count > 0 {
+                active_loans as f64 / student_count as f64
+            } else {
+                0.0
+            };
+
+            Ok(ClassAllowanceUtilization {
+                class_id: row.get(0)?,
+                class_name: row.get(1)?,
+                max_books_allowed,
+                student_count,
+                active_loans,
+                students_at_cap: row.get(5)?,
+                average_loans_per_student,
+                // A class with no allowance or no students has nothing to measure against
+                utilization_percent: (max_books_allowed > 0 && student_count > 0)
+                    .then(|| average_loans_per_student / max_books_allowed as f64 * 100.0),
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(classes)
+    }
+
+    /// Distinct borrowers currently holding at least one book, however many loans each has
+    pub async fn get_active_borrower_count(&self) -> Result<ActiveBorrowerCount> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(borrower_type, 'student') as borrower_type,
+                    COUNT(DISTINCT CASE WHEN COALESCE(borrower_type, 'student') = 'staff' THEN staff_id ELSE student_id END)
+             FROM borrowings
+             WHERE deleted = 0 AND returned_date IS NULL AND status IN ('active', 'overdue')
+             GROUP BY COALESCE(borrower_type, 'student')"
+        )?;
+
+        let mut counts = ActiveBorrowerCount::default();
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (borrower_type, count) = row?;
+            match borrower_type.as_str() {
+                "staff" => counts.staff += count,
+                _ => counts.students += count,
+            }
+        }
+        counts.total = counts.students + counts.staff;
+
+        Ok(counts)
+    }
+
+    /// Available against total copies per category for the dashboard heatmap.
+    /// Titles with copy records are counted from their copies; older titles
+    /// without any fall back to the counters on the book row.
+    pub async fn get_availability_heatmap(&self) -> Result<Vec<CategoryAvailability>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT c.id, COALESCE(c.name, 'Uncategorized'), COUNT(bk.id),
+                    COALESCE(SUM(CASE WHEN cp.copy_total > 0 THEN cp.copy_total ELSE bk.total_copies END), 0),
+                    COALESCE(SUM(CASE WHEN cp.copy_total > 0 THEN cp.copy_available ELSE bk.available_copies END), 0)
+             FROM books bk
+             LEFT JOIN categories c ON bk.category_id = c.id
+             LEFT JOIN (
+                 SELECT book_id, COUNT(*) as copy_total,
+                        SUM(CASE WHEN status = 'available' THEN 1 ELSE 0 END) as copy_available
+                 FROM book_copies
+                 WHERE deleted = 0 AND COALESCE(status, '') NOT IN ('lost', 'stolen')
+                   AND COALESCE(condition, '') != 'lost'
+                 GROUP BY book_id
+             ) cp ON cp.book_id = bk.id
+             WHERE bk.deleted = 0
+             GROUP BY c.id
+             ORDER BY COALESCE(c.name, 'Uncategorized')"
+        )?;
+
+        let cells = stmt.query_map([], |row| {
+            let total_copies: i64 = row.get(3)?;
+            let available_copies: i64 = row.get(4)?;
+            Ok(CategoryAvailability {
+                category_id: row.get(0)?,
+                category_name: row.get(1)?,
+                title_count: row.get(2)?,
+                total_copies,
+                available_copies,
+                unavailable_copies: (total_copies - available_copies).max(0),
+                availability_percent: (total_copies > 0)
+                    .then(|| available_copies as f64 / total_copies as f64 * 100.0),
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(cells)
+    }
+
+    /// Morning worklist: loans falling due within days_ahead (soonest first) and
+    /// loans already overdue (most overdue first). Borrowers without an email or
+    /// phone are still listed, with the contact left empty.
+    pub async fn get_due_report(&self, days_ahead: i64) -> Result<DueReport> {
+        let conn = self.lock_connection()?;
+
+        let mut stmt = conn.prepare(&format!(
+            "{} AND b.due_date >= date('now') AND b.due_date <= date('now', '+' || ?1 || ' days')
+             ORDER BY b.due_date, bk.title",
+            DUE_REPORT_QUERY
+        ))?;
+        let due_soon = stmt.query_map([days_ahead], row_to_due_entry)?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(&format!(
+            "{} AND b.due_date < date('now') ORDER BY b.due_date, bk.title",
+            DUE_REPORT_QUERY
+        ))?;
+        let overdue = stmt.query_map([], row_to_due_entry)?.collect::<Result<Vec<_>, _>>()?;
+
+        let as_of: String = conn.query_row("SELECT date('now')", [], |row| row.get(0))?;
+        Ok(DueReport {
+            as_of: super::parse_sqlite_date(&as_of)?,
+            days_ahead,
+            due_soon,
+            overdue,
+        })
+    }
+
+    /// Home screen charts, each computed with one grouped query. The monthly
+    /// series covers the last 12 months including months with no loans.
+    pub async fn get_dashboard_analytics(&self, top: u32) -> Result<DashboardAnalytics> {
+        let conn = self.lock_connection()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT bk.id, bk.title, bk.author, COUNT(b.id) as borrow_count
+             FROM borrowings b
+             JOIN books bk ON b.book_id = bk.id
+             WHERE b.deleted = 0 AND bk.deleted = 0
+             GROUP BY bk.id
+             ORDER BY borrow_count DESC, bk.title
+             LIMIT ?1"
+        )?;
+        let most_borrowed_books = stmt.query_map([top], |row| {
+            Ok(BookBorrowCount {
+                book_id: row.get(0)?,
+                title: row.get(1)?,
+                author: row.get(2)?,
+                borrow_count: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(b.borrower_type, 'student') as borrower_type,
+                    COALESCE(b.student_id, b.staff_id) as borrower_id,
+                    COALESCE(MAX(s.first_name || ' ' || s.last_name), MAX(st.first_name || ' ' || st.last_name)),
+                    COUNT(*) as borrow_count
+             FROM borrowings b
+             LEFT JOIN students s ON b.student_id = s.id
+             LEFT JOIN staff st ON b.staff_id = st.id
+             WHERE b.deleted = 0 AND COALESCE(b.student_id, b.staff_id) IS NOT NULL
+             GROUP BY borrower_type, borrower_id
+             ORDER BY borrow_count DESC
+             LIMIT ?1"
+        )?;
+        let most_active_borrowers = stmt.query_map([top], |row| {
+            Ok(BorrowerActivity {
+                borrower_type: row.get(0)?,
+                borrower_id: row.get(1)?,
+                borrower_name: row.get(2)?,
+                borrow_count: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE months(month_start, n) AS (
+                 SELECT date('now', 'start of month', '-11 months'), 0
+                 UNION ALL
+                 SELECT date(month_start, '+1 month'), n + 1 FROM months WHERE n < 11
+             )
+             SELECT strftime('%Y-%m', m.month_start), COALESCE(counts.borrowings, 0)
+             FROM months m
+             LEFT JOIN (
+                 SELECT strftime('%Y-%m', borrowed_date) as month, COUNT(*) as borrowings
+                 FROM borrowings
+                 WHERE deleted = 0 AND borrowed_date >= date('now', 'start of month', '-11 months')
+                 GROUP BY month
+             ) counts ON counts.month = strftime('%Y-%m', m.month_start)
+             ORDER BY m.month_start"
+        )?;
+        let borrowings_per_month = stmt.query_map([], |row| {
+            Ok(MonthlyBorrowings { month: row.get(0)?, borrowings: row.get(1)? })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT c.id, COALESCE(c.name, 'Uncategorized'), COUNT(bk.id), COALESCE(SUM(bk.total_copies), 0)
+             FROM books bk
+             LEFT JOIN categories c ON bk.category_id = c.id
+             WHERE bk.deleted = 0
+             GROUP BY c.id
+             ORDER BY COUNT(bk.id) DESC, COALESCE(c.name, 'Uncategorized')"
+        )?;
+        let category_distribution = stmt.query_map([], |row| {
+            Ok(CategoryShare {
+                category_id: row.get(0)?,
+                category_name: row.get(1)?,
+                title_count: row.get(2)?,
+                copy_count: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let outstanding_fines: f64 = conn.query_row(
+            "SELECT COALESCE(SUM(amount), 0) FROM fines WHERE status IN ('unpaid', 'partial') AND deleted = 0",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(DashboardAnalytics {
+            most_borrowed_books,
+            most_active_borrowers,
+            borrowings_per_month,
+            category_distribution,
+            outstanding_fines,
+        })
+    }
+
+    /// Copies per condition across the collection, or for one book. Every
+    /// condition is listed even when no copy has it; missing conditions count as unknown.
+    pub async fn get_condition_distribution(&self, book_id: Option<&str>) -> Result<Vec<ConditionCount>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(NULLIF(TRIM(condition), ''), 'unknown'), COUNT(*)
+             FROM book_copies
+             WHERE deleted = 0 AND (?1 IS NULL OR book_id = ?1)
+             GROUP BY 1"
+        )?;
+        let counted: HashMap<String, i64> = stmt.query_map([book_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let total: i64 = counted.values().sum();
+        let mut conditions: Vec<String> = super::COPY_CONDITIONS.iter().map(|c| c.to_string()).collect();
+        let mut others: Vec<String> = counted.keys()
+            .filter(|c| !conditions.contains(c))
+            .cloned()
+            .collect();
+        others.sort();
+        conditions.extend(others);
+
+        Ok(conditions.into_iter()
+            .map(|condition| {
+                let copy_count = counted.get(&condition).copied().unwrap_or(0);
+                ConditionCount {
+                    percent: if total > 0 { copy_count as f64 / total as f64 * 100.0 } else { 0.0 },
+                    condition,
+                    copy_count,
+                }
+            })
+            .collect())
+    }
+
+    /// Record what a title cost to buy; None clears it
+    pub async fn set_book_cost(&self, book_id: &str, cost: Option<f64>) -> Result<()> {
+        if cost.is_some_and(|c| !c.is_finite() || c < 0.0) {
+            return Err(super::constraint_error("Acquisition cost must be zero or more"));
+        }
+
+        let conn = self.lock_connection()?;
+        let updated = conn.execute(
+            "UPDATE books SET acquisition_cost = ?2, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1 AND deleted = 0",
+            (book_id, cost),
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    /// Money spent on books added in each period, by the date they were catalogued.
+    /// Books without a cost count as zero and are tallied in books_missing_cost.
+    pub async fn get_acquisition_spend(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        granularity: TrendGranularity,
+    ) -> Result<AcquisitionSpend> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT strftime(?3, created_at) as period, COUNT(*), COALESCE(SUM(acquisition_cost), 0),
+                    SUM(CASE WHEN acquisition_cost IS NULL THEN 1 ELSE 0 END)
+             FROM books
+             WHERE deleted = 0
+               AND (?1 IS NULL OR date(created_at) >= ?1)
+               AND (?2 IS NULL OR date(created_at) <= ?2)
+             GROUP BY period
+             HAVING period IS NOT NULL
+             ORDER BY period"
+        )?;
+
+        let mut periods = Vec::new();
+        let mut books_missing_cost = 0;
+        let mut rows = stmt.query((from.map(|d| d.to_string()), to.map(|d| d.to_string()), granularity.strftime_format()))?;
+        while let Some(row) = rows.next()? {
+            books_missing_cost += row.get::<_, i64>(3)?;
+            periods.push(AcquisitionSpendPoint {
+                period: row.get(0)?,
+                books_added: row.get(1)?,
+                total_cost: row.get(2)?,
+            });
+        }
+
+        Ok(AcquisitionSpend {
+            total_cost: periods.iter().map(|p| p.total_cost).sum(),
+            periods,
+            books_missing_cost,
+        })
+    }
+
+    /// How often loans issued in the range were renewed, and the titles renewed most
+    pub async fn get_renewal_stats(&self, from: Option<NaiveDate>, to: Option<NaiveDate>, top: u32) -> Result<RenewalStats> {
+        let conn = self.lock_connection()?;
+        let range = (from.map(|d| d.to_string()), to.map(|d| d.to_string()));
+
+        let (loan_count, renewed_loan_count, total_renewals): (i64, i64, i64) = conn.query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(COALESCE(renewal_count, 0) > 0), 0),
+                    COALESCE(SUM(COALESCE(renewal_count, 0)), 0)
+             FROM borrowings
+             WHERE deleted = 0
+               AND (?1 IS NULL OR date(borrowed_date) >= ?1)
+               AND (?2 IS NULL OR date(borrowed_date) <= ?2)",
+            range.clone(),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT bk.id, bk.title, bk.author, COUNT(b.id), SUM(COALESCE(b.renewal_count, 0)) as renewals
+             FROM borrowings b
+             JOIN books bk ON b.book_id = bk.id
+             WHERE b.deleted = 0 AND COALESCE(b.renewal_count, 0) > 0
+               AND (?1 IS NULL OR date(b.borrowed_date) >= ?1)
+               AND (?2 IS NULL OR date(b.borrowed_date) <= ?2)
+             GROUP BY bk.id
+             ORDER BY renewals DESC, bk.title
+             LIMIT ?3"
+        )?;
+        let most_renewed_titles = stmt.query_map((range.0, range.1, top), |row| {
+            Ok(RenewedTitle {
+                book_id: row.get(0)?,
+                title: row.get(1)?,
+                author: row.get(2)?,
+                loan_count: row.get(3)?,
+                renewal_count: row.get(4)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(RenewalStats {
+            loan_count,
+            renewed_loan_count,
+            total_renewals,
+            average_renewals_per_loan: if loan_count > 0 { total_renewals as f64 / loan_count as f64 } else { 0.0 },
+            most_renewed_titles,
+        })
+    }
+
+    /// Titles with no copy on the shelf that were borrowed within the last `days`,
+    /// ranked by recent borrows per copy - the ones most in need of extra copies
+    pub async fn get_currently_unavailable_popular_books(&self, days: i64, limit: u32) -> Result<Vec<UnavailablePopularBook>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT bk.id, bk.title, bk.author, bk.book_code, bk.total_copies,
+                    SUM(b.returned_date IS NULL AND b.status IN ('active', 'overdue')) as on_loan,
+                    SUM(date(b.borrowed_date) >= date('now', '-' || ?1 || ' days')) as recent_borrows,
+                    MIN(CASE WHEN b.returned_date IS NULL AND b.status IN ('active', 'overdue') THEN b.due_date END)
+             FROM books bk
+             JOIN borrowings b ON b.book_id = bk.id AND b.deleted = 0
+             WHERE bk.deleted = 0 AND bk.available_copies <= 0
+             GROUP BY bk.id
+             HAVING recent_borrows > 0
+             ORDER BY CAST(recent_borrows AS REAL) / MAX(bk.total_copies, 1) DESC, recent_borrows DESC, bk.title
+             LIMIT ?2"
+        )?;
+
+        let books = stmt.query_map((days, limit), |row| {
+            let total_copies: i64 = row.get(4)?;
+            let recent_borrow_count: i64 = row.get(6)?;
+            Ok(UnavailablePopularBook {
+                book_id: row.get(0)?,
+                title: row.get(1)?,
+                author: row.get(2)?,
+                book_code: row.get(3)?,
+                total_copies,
+                on_loan: row.get(5)?,
+                recent_borrow_count,
+                borrows_per_copy: recent_borrow_count as f64 / total_copies.max(1) as f64,
+                next_due_date: row.get(7)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(books)
+    }
+
+    /// Average pull request latency and row throughput per table, from the timings
+    /// recorded during syncs in the date range
+    pub async fn get_sync_metrics(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<SyncTableMetrics>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT table_name, COUNT(*), SUM(row_count), AVG(duration_ms), MAX(duration_ms), SUM(duration_ms)
+             FROM sync_metrics
+             WHERE (?1 IS NULL OR date(recorded_at) >= ?1)
+               AND (?2 IS NULL OR date(recorded_at) <= ?2)
+             GROUP BY table_name
+             ORDER BY AVG(duration_ms) DESC"
+        )?;
+
+        let metrics = stmt.query_map((from.map(|d| d.to_string()), to.map(|d| d.to_string())), |row| {
+            let total_rows: i64 = row.get(2)?;
+            let total_ms: i64 = row.get(5)?;
+            Ok(SyncTableMetrics {
+                table_name: row.get(0)?,
+                request_count: row.get(1)?,
+                total_rows,
+                average_latency_ms: row.get(3)?,
+                max_latency_ms: row.get(4)?,
+                rows_per_second: if total_ms > 0 { total_rows as f64 * 1000.0 / total_ms as f64 } else { 0.0 },
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(metrics)
+    }
+
+    /// Active students counted by gender, overall and per class level (e.g. "Form 2").
+    /// Students with no gender recorded are counted as "unspecified".
+    pub async fn get_demographic_breakdown(&self) -> Result<DemographicBreakdown> {
+        let conn = self.lock_connection()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(NULLIF(TRIM(s.gender), ''), 'unspecified') as gender,
+                    COALESCE(
+                        CASE WHEN c.id IS NOT NULL
+                             THEN (CASE COALESCE(c.academic_level_type, 'form') WHEN 'grade' THEN 'Grade ' ELSE 'Form ' END) || c.form_level
+                        END,
+                        NULLIF(TRIM(s.class_grade), ''),
+                        'Unassigned'
+                    ) as class_level,
+                    COUNT(*)
+             FROM students s
+             LEFT JOIN classes c ON s.class_id = c.id
+             WHERE s.deleted = 0 AND COALESCE(s.status, 'active') = 'active'
+             GROUP BY class_level, gender
+             ORDER BY class_level, gender"
+        )?;
+        let by_class_level = stmt.query_map([], |row| {
+            Ok(ClassLevelGenderCount {
+                gender: row.get(0)?,
+                class_level: row.get(1)?,
+                count: row.get(2)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut totals: HashMap<&str, i64> = HashMap::new();
+        for level in &by_class_level {
+            *totals.entry(level.gender.as_str()).or_default() += level.count;
+        }
+        let mut by_gender: Vec<GenderCount> = totals.into_iter()
+            .map(|(gender, count)| GenderCount { gender: gender.to_string(), count })
+            .collect();
+        by_gender.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.gender.cmp(&b.gender)));
+
+        Ok(DemographicBreakdown {
+            total_students: by_gender.iter().map(|g| g.count).sum(),
+            by_gender,
+            by_class_level,
+        })
+    }
+
+    /// Paid fines totalled by payment method over the payment date range; fines
+    /// paid without a recorded method are grouped as "unspecified"
+    pub async fn get_payments_by_method(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<PaymentMethodTotal>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(NULLIF(TRIM(payment_method), ''), 'unspecified') as method,
+                    COUNT(*), COALESCE(SUM(amount), 0)
+             FROM fines
+             WHERE status = 'paid' AND deleted = 0
+               AND (?1 IS NULL OR date(COALESCE(paid_at, updated_at)) >= ?1)
+               AND (?2 IS NULL OR date(COALESCE(paid_at, updated_at)) <= ?2)
+             GROUP BY method
+             ORDER BY SUM(amount) DESC"
+        )?;
+
+        let totals = stmt.query_map((from.map(|d| d.to_string()), to.map(|d| d.to_string())), |row| {
+            Ok(PaymentMethodTotal {
+                payment_method: row.get(0)?,
+                payments: row.get(1)?,
+                total_amount: row.get(2)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(totals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn shelf_occupancy_flags_over_capacity_shelves() {
+        let db = test_db();
+        let (first, copies) = seed_book(&db, "SHA", 3);
+        let (second, _) = seed_book(&db, "SHB", 2);
+        let (third, _) = seed_book(&db, "SHC", 1);
+        execute(&db, "UPDATE books SET shelf_location = 'S1' WHERE id IN (?1, ?2)", [&first, &second]);
+        execute(&db, "UPDATE books SET shelf_location = ' S2 ' WHERE id = ?1", [&third]);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_loan(&db, &student_id, &first, &copies[0], 7);
+        let capacities = HashMap::from([("S1".to_string(), 4), ("S2".to_string(), 10), ("S3".to_string(), 5)]);
+        db.set_shelf_capacities(&capacities).await.unwrap();
+
+        let shelves = db.get_shelf_occupancy().await.unwrap();
+        let names: Vec<&str> = shelves.iter().map(|s| s.shelf_location.as_str()).collect();
+        assert_eq!(names, ["S1", "S2", "S3"]);
+
+        let s1 = &shelves[0];
+        assert_eq!((s1.title_count, s1.item_count, s1.on_shelf_count), (2, 5, 4));
+        assert_eq!(s1.occupancy_percent, Some(125.0));
+        assert!(s1.over_capacity);
+
+        let s2 = &shelves[1];
+        assert_eq!((s2.title_count, s2.item_count, s2.capacity), (1, 1, Some(10)));
+        assert_eq!(s2.occupancy_percent, Some(10.0));
+        assert!(!s2.over_capacity);
+
+        // A configured shelf with nothing on it is still listed
+        let s3 = &shelves[2];
+        assert_eq!((s3.item_count, s3.capacity, s3.occupancy_percent), (0, Some(5), Some(0.0)));
+    }
+
+    #[tokio::test]
+    async fn average_fines_are_split_by_borrower_type() {
+        let db = test_db();
+        let first = seed_student(&db, "ADM-1");
+        let second = seed_student(&db, "ADM-2");
+        seed_fine(&db, &first, 100.0);
+        seed_fine(&db, &first, 20.0);
+        seed_fine(&db, &second, 30.0);
+        let staff_id = seed_staff(&db, "T-1");
+        execute(
+            &db,
+            "INSERT INTO fines (id, staff_id, borrower_type, fine_type, amount) VALUES ('staff-fine', ?1, 'staff', 'overdue', 40)",
+            [&staff_id],
+        );
+
+        let stats = db.get_average_fine_by_borrower_type(None, None).await.unwrap();
+        assert_eq!(stats.len(), 2);
+
+        let staff = &stats[0];
+        assert_eq!(staff.borrower_type, "staff");
+        assert_eq!((staff.fine_count, staff.borrower_count), (1, 1));
+        assert_eq!((staff.total_amount, staff.average_amount, staff.average_per_borrower), (40.0, 40.0, 40.0));
+
+        let students = &stats[1];
+        assert_eq!(students.borrower_type, "student");
+        assert_eq!((students.fine_count, students.borrower_count), (3, 2));
+        assert_eq!((students.total_amount, students.average_amount, students.average_per_borrower), (150.0, 50.0, 75.0));
+    }
+
+
+    #[tokio::test]
+    async fn staff_issuance_trend_buckets_checkouts_and_returns() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "ISS", 3);
+        let student_id = seed_student(&db, "ADM-1");
+        let returned = seed_dated_loan(&db, &student_id, &book_id, "2026-03-02", "2026-03-16", Some("2026-03-10"));
+        let open = seed_dated_loan(&db, &student_id, &book_id, "2026-03-02", "2026-03-16", None);
+        let someone_else = seed_dated_loan(&db, &student_id, &book_id, "2026-03-03", "2026-03-17", None);
+        execute(&db, "UPDATE borrowings SET issued_by = 'desk-1' WHERE id IN (?1, ?2)", [&returned, &open]);
+        execute(&db, "UPDATE borrowings SET returned_by = 'desk-1' WHERE id = ?1", [&returned]);
+        execute(&db, "UPDATE borrowings SET issued_by = 'desk-2' WHERE id = ?1", [&someone_else]);
+
+        let daily = db.get_staff_issuance_trend("desk-1", None, None, TrendGranularity::Day, false).await.unwrap();
+        let daily: Vec<(&str, i64, i64)> = daily.iter().map(|p| (p.period.as_str(), p.checkouts, p.returns)).collect();
+        assert_eq!(daily, [("2026-03-02", 2, 0), ("2026-03-10", 0, 1)]);
+
+        let monthly = db.get_staff_issuance_trend("desk-1", None, None, TrendGranularity::Month, false).await.unwrap();
+        assert_eq!(monthly.len(), 1);
+        assert_eq!((monthly[0].period.as_str(), monthly[0].checkouts, monthly[0].returns), ("2026-03", 2, 1));
+
+        let from = NaiveDate::from_ymd_opt(2026, 3, 5);
+        let ranged = db.get_staff_issuance_trend("desk-1", from, None, TrendGranularity::Day, false).await.unwrap();
+        assert_eq!(ranged.len(), 1);
+        assert_eq!(ranged[0].returns, 1);
+    }
+
+
+    #[tokio::test]
+    async fn never_borrowed_lists_only_titles_without_loans() {
+        let db = test_db();
+        let (borrowed, _) = seed_book(&db, "NB1", 1);
+        let (idle, _) = seed_book(&db, "NB2", 1);
+        let (old_idle, _) = seed_book(&db, "NB3", 1);
+        let (archived_only, _) = seed_book(&db, "NB4", 1);
+        execute(&db, "UPDATE books SET acquisition_year = 2010 WHERE id = ?1", [&old_idle]);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_dated_loan(&db, &student_id, &borrowed, "2026-01-05", "2026-01-19", Some("2026-01-12"));
+        execute(
+            &db,
+            "INSERT INTO borrowings_archive (id, book_id, due_date, created_at, updated_at)
+             VALUES ('archived-loan', ?1, '2020-01-19', '2020-01-05', '2020-01-05')",
+            [&archived_only],
+        );
+
+        let books = db.get_books_never_borrowed(None).await.unwrap();
+        let ids: Vec<&str> = books.iter().map(|b| b.book_id.as_str()).collect();
+        // Oldest acquisitions first
+        assert_eq!(ids, [old_idle.as_str(), idle.as_str()]);
+        assert_eq!(books[0].acquisition_year, Some(2010));
+
+        let before_2015 = db.get_books_never_borrowed(Some(2015)).await.unwrap();
+        assert_eq!(before_2015.len(), 1);
+        assert_eq!(before_2015[0].book_id, old_idle);
+    }
+
+
+    #[tokio::test]
+    async fn daily_summary_counts_one_days_desk_activity() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "DAY", 5);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_dated_loan(&db, &student_id, &book_id, "2026-03-10", "2026-03-24", None);
+        seed_dated_loan(&db, &student_id, &book_id, "2026-03-10", "2026-03-24", None);
+        seed_dated_loan(&db, &student_id, &book_id, "2026-03-01", "2026-03-15", Some("2026-03-10"));
+        seed_dated_loan(&db, &student_id, &book_id, "2026-02-23", "2026-03-09", None);
+        seed_dated_loan(&db, &student_id, &book_id, "2026-02-23", "2026-03-09", Some("2026-03-09"));
+
+        let paid = seed_fine(&db, &student_id, 20.0);
+        execute(&db, "UPDATE fines SET status = 'paid', paid_at = '2026-03-10T09:00:00.000Z' WHERE id = ?1", [&paid]);
+        let paid_earlier = seed_fine(&db, &student_id, 5.0);
+        execute(&db, "UPDATE fines SET status = 'paid', paid_at = '2026-03-09T09:00:00.000Z' WHERE id = ?1", [&paid_earlier]);
+        seed_fine(&db, &student_id, 7.0);
+
+        let new_student = seed_student(&db, "ADM-2");
+        execute(&db, "UPDATE students SET created_at = '2026-03-10 08:00:00' WHERE id = ?1", [&new_student]);
+        let new_staff = seed_staff(&db, "T-1");
+        execute(&db, "UPDATE staff SET created_at = '2026-03-10T07:00:00.000Z' WHERE id = ?1", [&new_staff]);
+
+        let summary = db.get_daily_summary(NaiveDate::from_ymd_opt(2026, 3, 10).unwrap()).await.unwrap();
+        assert_eq!((summary.checkouts, summary.returns), (2, 1));
+        assert_eq!((summary.fines_collected_count, summary.fines_collected_amount), (1, 20.0));
+        assert_eq!(summary.overdue_created, 1);
+        assert_eq!((summary.new_students, summary.new_staff, summary.new_members), (1, 1, 2));
+        assert!(summary.to_lines().contains(&"Checkouts: 2".to_string()));
+    }
+
+
+    #[tokio::test]
+    async fn time_to_first_loan_measures_from_cataloguing() {
+        let db = test_db();
+        let student_id = seed_student(&db, "ADM-1");
+        let mut catalogued = Vec::new();
+        for code in ["FL1", "FL2", "FL3", "FL4"] {
+            let (book_id, _) = seed_book(&db, code, 1);
+            execute(&db, "UPDATE books SET created_at = '2026-01-01 10:00:00' WHERE id = ?1", [&book_id]);
+            catalogued.push(book_id);
+        }
+        // Outside the range below
+        seed_book(&db, "FL5", 1);
+
+        seed_dated_loan(&db, &student_id, &catalogued[0], "2026-01-11", "2026-01-25", Some("2026-01-20"));
+        seed_dated_loan(&db, &student_id, &catalogued[0], "2026-02-01", "2026-02-15", None);
+        seed_dated_loan(&db, &student_id, &catalogued[1], "2026-01-05", "2026-01-19", Some("2026-01-10"));
+        // Loaned before it was entered, which counts as zero days
+        seed_dated_loan(&db, &student_id, &catalogued[2], "2025-12-30", "2026-01-13", Some("2026-01-10"));
+
+        let from = NaiveDate::from_ymd_opt(2026, 1, 1);
+        let to = NaiveDate::from_ymd_opt(2026, 1, 31);
+        let stats = db.get_time_to_first_loan(from, to).await.unwrap();
+        assert_eq!((stats.books_considered, stats.borrowed_count, stats.never_borrowed_count), (4, 3, 1));
+        assert_eq!((stats.min_days, stats.max_days), (Some(0), Some(10)));
+        assert_eq!(stats.median_days, Some(4.0));
+        assert!((stats.mean_days.unwrap() - 14.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn fines_are_totalled_per_book() {
+        let db = test_db();
+        let (often_late, _) = seed_book(&db, "LATE", 1);
+        let (rarely_late, _) = seed_book(&db, "ONTIME", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let first = seed_dated_loan(&db, &student_id, &often_late, "2026-02-01", "2026-02-15", Some("2026-02-20"));
+        let second = seed_dated_loan(&db, &student_id, &often_late, "2026-03-01", "2026-03-15", Some("2026-03-25"));
+        let third = seed_dated_loan(&db, &student_id, &rarely_late, "2026-03-01", "2026-03-15", Some("2026-03-16"));
+        let fine = |id: &str, loan: &str, amount: f64, status: &str, created: &str| {
+            execute(
+                &db,
+                "INSERT INTO fines (id, student_id, borrowing_id, fine_type, amount, status, created_at)
+                 VALUES (?1, ?2, ?3, 'overdue', ?4, ?5, ?6)",
+                (id, &student_id, loan, amount, status, created),
+            );
+        };
+        fine("f1", &first, 50.0, "paid", "2026-02-20 10:00:00");
+        fine("f2", &second, 100.0, "unpaid", "2026-03-25 10:00:00");
+        fine("f3", &third, 10.0, "unpaid", "2026-03-16 10:00:00");
+        // Fines not tied to a loan can't be put down to any book
+        seed_fine(&db, &student_id, 500.0);
+
+        let totals = db.get_fines_by_book(None, None, None).await.unwrap();
+        let totals: Vec<(&str, i64, f64, f64)> = totals.iter()
+            .map(|t| (t.title.as_str(), t.fine_count, t.total_amount, t.unpaid_amount))
+            .collect();
+        assert_eq!(totals, [("Book LATE", 2, 150.0, 100.0), ("Book ONTIME", 1, 10.0, 10.0)]);
+
+        let march = db.get_fines_by_book(NaiveDate::from_ymd_opt(2026, 3, 1), NaiveDate::from_ymd_opt(2026, 3, 31), Some(1)).await.unwrap();
+        assert_eq!(march.len(), 1);
+        assert_eq!((march[0].book_id.as_str(), march[0].total_amount), (often_late.as_str(), 100.0));
+    }
+
+    #[tokio::test]
+    async fn class_allowance_utilization_averages_open_loans_against_the_cap() {
+        let db = test_db();
+        execute(
+            &db,
+            "INSERT INTO classes (id, class_name, form_level, max_books_allowed, is_active) VALUES
+                ('c1', 'Form 1', 1, 2, 1), ('c2', 'Form 2', 2, 4, 1), ('c3', 'Form 3', 3, 2, 0), ('c4', 'Form 4', 4, 2, 1)",
+            [],
+        );
+        let (book_id, _) = seed_book(&db, "CLS", 5);
+        let at_cap = seed_student(&db, "ADM-1");
+        let none_open = seed_student(&db, "ADM-2");
+        let one_open = seed_student(&db, "ADM-3");
+        // Placed by class_id even though class_grade says otherwise
+        let form_two = seed_student(&db, "ADM-4");
+        execute(&db, "UPDATE students SET class_id = 'c2', class_grade = 'Transfer' WHERE id = ?1", [&form_two]);
+        for student in [&at_cap, &at_cap, &one_open, &form_two] {
+            seed_dated_loan(&db, student, &book_id, "2026-03-01", "2026-03-15", None);
+        }
+        seed_dated_loan(&db, &none_open, &book_id, "2026-03-01", "2026-03-15", Some("2026-03-10"));
+
+        let classes = db.get_class_allowance_utilization().await.unwrap();
+        let names: Vec<&str> = classes.iter().map(|c| c.class_name.as_str()).collect();
+        assert_eq!(names, ["Form 1", "Form 2", "Form 4"]);
+
+        let form_one = &classes[0];
+        assert_eq!((form_one.student_count, form_one.active_loans, form_one.students_at_cap), (3, 3, 1));
+        assert_eq!(form_one.average_loans_per_student, 1.0);
+        assert_eq!(form_one.utilization_percent, Some(50.0));
+
+        let form_two = &classes[1];
+        assert_eq!((form_two.student_count, form_two.active_loans, form_two.max_books_allowed), (1, 1, 4));
+        assert_eq!(form_two.utilization_percent, Some(25.0));
+
+        // No students, nothing to measure
+        assert_eq!((classes[2].student_count, classes[2].utilization_percent), (0, None));
+    }
+
+    #[tokio::test]
+    async fn overdue_trend_buckets_loans_by_due_period() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "TREND", 5);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_dated_loan(&db, &student_id, &book_id, "2025-01-01", "2025-01-10", Some("2025-01-08"));
+        seed_dated_loan(&db, &student_id, &book_id, "2025-01-06", "2025-01-20", Some("2025-01-25"));
+        seed_dated_loan(&db, &student_id, &book_id, "2025-01-11", "2025-01-25", None);
+        // Handed back on the due date itself is still on time
+        seed_dated_loan(&db, &student_id, &book_id, "2025-01-22", "2025-02-05", Some("2025-02-05"));
+        // Not due yet, so it can't have become overdue
+        let due = days_from_today(10).to_string();
+        seed_dated_loan(&db, &student_id, &book_id, "2025-01-22", &due, None);
+
+        let trend = db.get_overdue_trend(None, None, TrendGranularity::Month, false).await.unwrap();
+        let trend: Vec<(&str, i64, i64)> = trend.iter().map(|p| (p.period.as_str(), p.loans_due, p.became_overdue)).collect();
+        assert_eq!(trend, [("2025-01", 3, 2), ("2025-02", 1, 0)]);
+
+        let january = db.get_overdue_trend(NaiveDate::from_ymd_opt(2025, 1, 15), NaiveDate::from_ymd_opt(2025, 1, 31), TrendGranularity::Day, false).await.unwrap();
+        let january: Vec<(&str, i64, f64)> = january.iter().map(|p| (p.period.as_str(), p.became_overdue, p.overdue_percent)).collect();
+        assert_eq!(january, [("2025-01-20", 1, 100.0), ("2025-01-25", 1, 100.0)]);
+    }
+
+    #[tokio::test]
+    async fn active_borrowers_are_counted_once_however_many_loans_they_hold() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "KPI", 10);
+        let busy = seed_student(&db, "ADM-1");
+        let single = seed_student(&db, "ADM-2");
+        let done = seed_student(&db, "ADM-3");
+        for student in [&busy, &busy, &busy, &single] {
+            seed_dated_loan(&db, student, &book_id, "2026-03-01", "2026-03-15", None);
+        }
+        seed_dated_loan(&db, &done, &book_id, "2026-03-01", "2026-03-15", Some("2026-03-10"));
+
+        let staff_id = seed_staff(&db, "T-1");
+        for _ in 0..2 {
+            let loan = seed_dated_loan(&db, &busy, &book_id, "2026-03-01", "2026-03-31", None);
+            execute(&db, "UPDATE borrowings SET borrower_type = 'staff', student_id = NULL, staff_id = ?2 WHERE id = ?1", [&loan, &staff_id]);
+        }
+
+        let counts = db.get_active_borrower_count().await.unwrap();
+        assert_eq!((counts.students, counts.staff, counts.total), (2, 1, 3));
+    }
+
+    #[tokio::test]
+    async fn availability_heatmap_counts_copies_per_category() {
+        let db = test_db();
+        let fiction = seed_category(&db, "Fiction");
+        let (with_copies, copies) = seed_book(&db, "HEAT-A", 3);
+        let student_id = seed_student(&db, "ADM-1");
+        seed_loan(&db, &student_id, &with_copies, &copies[0], 7);
+        execute(&db, "UPDATE book_copies SET status = 'lost' WHERE id = ?1", [&copies[1]]);
+        // Older titles without copy records fall back to the counters on the book
+        let (counters_only, _) = seed_book(&db, "HEAT-B", 0);
+        execute(&db, "UPDATE books SET total_copies = 4, available_copies = 2 WHERE id = ?1", [&counters_only]);
+        execute(&db, "UPDATE books SET category_id = ?1 WHERE id IN (?2, ?3)", [&fiction, &with_copies, &counters_only]);
+        seed_book(&db, "HEAT-C", 1);
+        let (deleted, _) = seed_book(&db, "HEAT-D", 5);
+        execute(&db, "UPDATE books SET deleted = 1, category_id = ?1 WHERE id = ?2", [&fiction, &deleted]);
+
+        let cells = db.get_availability_heatmap().await.unwrap();
+        let cells: Vec<(&str, i64, i64, i64, Option<f64>)> = cells.iter()
+            .map(|c| (c.category_name.as_str(), c.title_count, c.total_copies, c.unavailable_copies, c.availability_percent))
+            .collect();
+        assert_eq!(cells, [
+            ("Fiction", 2, 6, 3, Some(50.0)),
+            ("Uncategorized", 1, 1, 0, Some(100.0)),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn condition_distribution_counts_copies_per_condition() {
+        let db = test_db();
+        let (first_book, first_copies) = seed_book(&db, "COND-A", 4);
+        execute(&db, "UPDATE book_copies SET condition = 'fair' WHERE id = ?1", [&first_copies[2]]);
+        execute(&db, "UPDATE book_copies SET condition = 'damaged' WHERE id = ?1", [&first_copies[3]]);
+        let (_, second_copies) = seed_book(&db, "COND-B", 3);
+        execute(&db, "UPDATE book_copies SET condition = 'poor' WHERE id = ?1", [&second_copies[0]]);
+        execute(&db, "UPDATE book_copies SET condition = NULL WHERE id = ?1", [&second_copies[1]]);
+        execute(&db, "UPDATE book_copies SET deleted = 1 WHERE id = ?1", [&second_copies[2]]);
+
+        let counts = |rows: Vec<ConditionCount>| -> Vec<(String, i64)> {
+            rows.into_iter().map(|c| (c.condition, c.copy_count)).collect()
+        };
+        let everything = db.get_condition_distribution(None).await.unwrap();
+        let good = everything.iter().find(|c| c.condition == "good").unwrap();
+        assert!((good.percent - 100.0 / 3.0).abs() < 1e-9);
+        assert_eq!(counts(everything), [
+            ("good".to_string(), 2),
+            ("fair".to_string(), 1),
+            ("poor".to_string(), 1),
+            ("damaged".to_string(), 1),
+            ("lost".to_string(), 0),
+            ("unknown".to_string(), 1),
+        ]);
+
+        let one_book = db.get_condition_distribution(Some(&first_book)).await.unwrap();
+        assert_eq!(counts(one_book), [
+            ("good".to_string(), 2),
+            ("fair".to_string(), 1),
+            ("poor".to_string(), 0),
+            ("damaged".to_string(), 1),
+            ("lost".to_string(), 0),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn acquisition_spend_sums_costs_per_month_and_counts_missing_costs() {
+        let db = test_db();
+        for (code, created_at, cost) in [
+            ("BUY-A", "2025-01-05T10:00:00.000Z", Some(12.5)),
+            ("BUY-B", "2025-01-20T10:00:00.000Z", Some(20.0)),
+            ("BUY-C", "2025-02-03T10:00:00.000Z", Some(8.0)),
+            ("BUY-D", "2025-02-14T10:00:00.000Z", None),
+        ] {
+            let (book_id, _) = seed_book(&db, code, 1);
+            execute(&db, "UPDATE books SET created_at = ?2 WHERE id = ?1", (&book_id, created_at));
+            db.set_book_cost(&book_id, cost).await.unwrap();
+        }
+        let (deleted, _) = seed_book(&db, "BUY-E", 1);
+        execute(&db, "UPDATE books SET created_at = '2025-02-20T10:00:00.000Z', acquisition_cost = 99, deleted = 1 WHERE id = ?1", [&deleted]);
+        assert!(db.set_book_cost(&deleted, Some(5.0)).await.is_err());
+        let (other, _) = seed_book(&db, "BUY-F", 1);
+        assert!(db.set_book_cost(&other, Some(-1.0)).await.is_err());
+        execute(&db, "UPDATE books SET created_at = '2024-12-31T10:00:00.000Z' WHERE id = ?1", [&other]);
+
+        let spend = db.get_acquisition_spend(NaiveDate::from_ymd_opt(2025, 1, 1), None, TrendGranularity::Month).await.unwrap();
+        let periods: Vec<(&str, i64, f64)> = spend.periods.iter()
+            .map(|p| (p.period.as_str(), p.books_added, p.total_cost))
+            .collect();
+        assert_eq!(periods, [("2025-01", 2, 32.5), ("2025-02", 2, 8.0)]);
+        assert_eq!((spend.total_cost, spend.books_missing_cost), (40.5, 1));
+
+        let february = db.get_acquisition_spend(NaiveDate::from_ymd_opt(2025, 2, 1), NaiveDate::from_ymd_opt(2025, 2, 28), TrendGranularity::Month).await.unwrap();
+        assert_eq!((february.periods.len(), february.total_cost, february.books_missing_cost), (1, 8.0, 1));
+    }
+
+    #[tokio::test]
+    async fn renewal_stats_total_and_rank_renewals_in_range() {
+        let db = test_db();
+        let student_id = seed_student(&db, "ADM-1");
+        let (often, _) = seed_book(&db, "REN-A", 1);
+        let (sometimes, _) = seed_book(&db, "REN-B", 1);
+        let (never, _) = seed_book(&db, "REN-C", 1);
+        for (book_id, borrowed, renewals) in [
+            (&often, "2025-03-03", 2),
+            (&often, "2025-03-10", 1),
+            (&sometimes, "2025-03-12", 2),
+            (&never, "2025-03-20", 0),
+            (&never, "2025-01-05", 5),
+        ] {
+            let loan_id = seed_dated_loan(&db, &student_id, book_id, borrowed, "2025-04-30", Some("2025-04-30"));
+            execute(&db, "UPDATE borrowings SET renewal_count = ?2 WHERE id = ?1", (&loan_id, renewals));
+        }
+
+        let stats = db.get_renewal_stats(NaiveDate::from_ymd_opt(2025, 3, 1), NaiveDate::from_ymd_opt(2025, 3, 31), 2).await.unwrap();
+        assert_eq!((stats.loan_count, stats.renewed_loan_count, stats.total_renewals), (4, 3, 5));
+        assert_eq!(stats.average_renewals_per_loan, 1.25);
+        let titles: Vec<(&str, i64, i64)> = stats.most_renewed_titles.iter()
+            .map(|t| (t.title.as_str(), t.loan_count, t.renewal_count))
+            .collect();
+        assert_eq!(titles, [("Book REN-A", 2, 3), ("Book REN-B", 1, 2)]);
+
+        let all_time = db.get_renewal_stats(None, None, 1).await.unwrap();
+        assert_eq!((all_time.loan_count, all_time.total_renewals), (5, 10));
+        assert_eq!(all_time.most_renewed_titles[0].book_id, never);
+    }
+
+    #[tokio::test]
+    async fn a_popular_book_that_is_out_ranks_above_an_unpopular_one() {
+        let db = test_db();
+        let student_id = seed_student(&db, "ADM-1");
+        let (cold, cold_copies) = seed_book(&db, "COLD", 1);
+        seed_loan(&db, &student_id, &cold, &cold_copies[0], 7);
+        let (hot, hot_copies) = seed_book(&db, "HOT", 1);
+        seed_loan(&db, &student_id, &hot, &hot_copies[0], 10);
+        for days_ago in [25, 18, 11] {
+            let borrowed = days_from_today(-days_ago).to_string();
+            let returned = days_from_today(-days_ago + 5).to_string();
+            seed_dated_loan(&db, &student_id, &hot, &borrowed, &returned, Some(&returned));
+        }
+        // Busy but still on the shelf, and out but only borrowed long ago
+        let (on_shelf, _) = seed_book(&db, "SHELF", 2);
+        seed_dated_loan(&db, &student_id, &on_shelf, &days_from_today(-3).to_string(), &days_from_today(11).to_string(), None);
+        let (stale, stale_copies) = seed_book(&db, "STALE", 1);
+        seed_loan(&db, &student_id, &stale, &stale_copies[0], -80);
+
+        let books = db.get_currently_unavailable_popular_books(30, 10).await.unwrap();
+        let ranked: Vec<(&str, i64, i64)> = books.iter()
+            .map(|b| (b.title.as_str(), b.recent_borrow_count, b.on_loan))
+            .collect();
+        assert_eq!(ranked, [("Book HOT", 4, 1), ("Book COLD", 1, 1)]);
+        assert_eq!(books[0].next_due_date.as_deref(), Some(days_from_today(10).to_string().as_str()));
+    }
+
+    #[tokio::test]
+    async fn demographic_breakdown_counts_gender_per_class_level() {
+        let db = test_db();
+        execute(&db, "INSERT INTO classes (id, class_name, form_level, academic_level_type) VALUES ('g7', 'Grade 7 East', 7, 'grade')", []);
+        for (admission_number, gender, class_grade, class_id) in [
+            ("F1-A", Some("female"), "Form 1", None),
+            ("F1-B", Some("female"), "Form 1", None),
+            ("F1-C", Some("male"), "Form 1", None),
+            ("F1-D", None, "Form 1", None),
+            ("F2-A", Some("other"), "Form 2", None),
+            ("F2-B", Some(""), "Form 2", None),
+            ("G7-A", Some("male"), "Form 4", Some("g7")),
+        ] {
+            let student_id = seed_student(&db, admission_number);
+            execute(
+                &db,
+                "UPDATE students SET gender = ?2, class_grade = ?3, class_id = ?4 WHERE id = ?1",
+                (&student_id, gender, class_grade, class_id),
+            );
+        }
+        // Neither a deleted nor a graduated student is counted
+        let deleted = seed_student(&db, "GONE");
+        execute(&db, "UPDATE students SET gender = 'female', deleted = 1 WHERE id = ?1", [&deleted]);
+        let graduated = seed_student(&db, "GRAD");
+        execute(&db, "UPDATE students SET gender = 'female', status = 'graduated' WHERE id = ?1", [&graduated]);
+
+        let breakdown = db.get_demographic_breakdown().await.unwrap();
+        assert_eq!(breakdown.total_students, 7);
+        let by_gender: Vec<(&str, i64)> = breakdown.by_gender.iter().map(|g| (g.gender.as_str(), g.count)).collect();
+        assert_eq!(by_gender, [("female", 2), ("male", 2), ("unspecified", 2), ("other", 1)]);
+
+        // A class assignment wins over the free-text class_grade
+        let by_class: Vec<(&str, &str, i64)> = breakdown.by_class_level.iter()
+            .map(|c| (c.class_level.as_str(), c.gender.as_str(), c.count))
+            .collect();
+        assert_eq!(
+            by_class,
+            [
+                ("Form 1", "female", 2),
+                ("Form 1", "male", 1),
+                ("Form 1", "unspecified", 1),
+                ("Form 2", "other", 1),
+                ("Form 2", "unspecified", 1),
+                ("Grade 7", "male", 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn payments_are_totalled_per_method() {
+        let db = test_db();
+        let student_id = seed_student(&db, "ADM-1");
+        for (amount, method) in [
+            (50.0, Some("Cash")),
+            (30.0, Some(" cash ")),
+            (100.0, Some("Mobile Money")),
+            (20.0, None),
+            (15.0, Some("")),
+        ] {
+            let fine_id = seed_fine(&db, &student_id, amount);
+            db.pay_fine(&fine_id, method).await.unwrap();
+        }
+        // Neither waived nor outstanding fines are payments
+        let waived = seed_fine(&db, &student_id, 500.0);
+        db.waive_fine(&waived).await.unwrap();
+        seed_fine(&db, &student_id, 700.0);
+
+        let totals = db.get_payments_by_method(None, None).await.unwrap();
+        let totals: Vec<(&str, i64, f64)> = totals.iter()
+            .map(|t| (t.payment_method.as_str(), t.payments, t.total_amount))
+            .collect();
+        assert_eq!(totals, [("mobile money", 1, 100.0), ("cash", 2, 80.0), ("unspecified", 2, 35.0)]);
+
+        let today = days_from_today(0);
+        assert_eq!(db.get_payments_by_method(Some(today), Some(today)).await.unwrap().len(), 3);
+        assert!(db.get_payments_by_method(Some(days_from_today(1)), None).await.unwrap().is_empty());
+    }
+}
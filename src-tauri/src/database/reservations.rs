@@ -0,0 +1,276 @@
+use super::{constraint_error, DatabaseManager};
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reservation {
+    pub id: String,
+    pub book_id: String,
+    pub book_title: Option<String>,
+    pub student_id: String,
+    pub student_name: Option<String>,
+    pub status: String,
+    pub queue_position: Option<i64>,
+    pub notes: Option<String>,
+    pub notified_at: Option<String>,
+    pub created_at: String,
+}
+
+// A reservation is open while it waits for a copy ('waiting') or has one held for it ('ready')
+const OPEN_STATUSES: &str = "('waiting', 'ready')";
+
+// queue_position counts the open reservations for the same book placed up to this one
+const RESERVATION_SELECT: &str = "
+    SELECT r.id, r.book_id, b.title, r.student_id, s.first_name || ' ' || s.last_name,
+           r.status,
+           CASE WHEN r.status IN ('waiting', 'ready') THEN (
+               SELECT COUNT(*) FROM reservations q
+               WHERE q.book_id = r.book_id AND q.status IN ('waiting', 'ready')
+                 AND (q.created_at < r.created_at OR (q.created_at = r.created_at AND q.rowid <= r.rowid))
+           ) END,
+           r.notes, r.notified_at, r.created_at
+    FROM reservations r
+    LEFT JOIN books b ON b.id = r.book_id
+    LEFT JOIN students s ON s.id = r.student_id";
+
+fn row_to_reservation(row: &rusqlite::Row) -> Result<Reservation> {
+    Ok(Reservation {
+        id: row.get(0)?,
+        book_id: row.get(1)?,
+        book_title: row.get(2)?,
+        student_id: row.get(3)?,
+        student_name: row.get(4)?,
+        status: row.get(5)?,
+        queue_position: row.get(6)?,
+        notes: row.get(7)?,
+        notified_at: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+fn reservation_by_id(conn: &Connection, reservation_id: &str) -> Result<Reservation> {
+    conn.query_row(&format!("{} WHERE r.id = ?1", RESERVATION_SELECT), [reservation_id], row_to_reservation)
+}
+
+/// Hold a copy for the longest-waiting reservation on the book, as long as the
+/// shelf has a copy that isn't already held for someone. Returns the reservation
+/// that was made ready, if any.
+pub(crate) fn promote_next_reservation(conn: &Connection, book_id: &str) -> Result<Option<Reservation>> {
+    let available: i64 = conn.query_row(
+        "SELECT COALESCE(available_copies, 0) FROM books WHERE id = ?1 AND deleted = 0",
+        [book_id],
+        |row| row.get(0),
+    ).optional()?.unwrap_or(0);
+    let held: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM reservations WHERE book_id = ?1 AND status = 'ready'",
+        [book_id],
+        |row| row.get(0),
+    )?;
+    if available <= held {
+        return Ok(None);
+    }
+
+    let next: Option<String> = conn.query_row(
+        "SELECT id FROM reservations WHERE book_id = ?1 AND status = 'waiting'
+         ORDER BY created_at, rowid
+         LIMIT 1",
+        [book_id],
+        |row| row.get(0),
+    ).optional()?;
+    let Some(reservation_id) = next else {
+        return Ok(None);
+    };
+
+    conn.execute(
+        "UPDATE reservations SET status = 'ready', notified_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+        [&reservation_id],
+    )?;
+    reservation_by_id(conn, &reservation_id).map(Some)
+}
+
+/// Close the student's open reservation on a book once they borrow it
+pub(crate) fn fulfil_reservation(conn: &Connection, book_id: &str, student_id: &str) -> Result<usize> {
+    conn.execute(
+        &format!(
+            "UPDATE reservations SET status = 'fulfilled', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+             WHERE book_id = ?1 AND student_id = ?2 AND status IN {}",
+            OPEN_STATUSES
+        ),
+        (book_id, student_id),
+    )
+}
+
+impl DatabaseManager {
+    /// Put a student in the queue for a book whose copies are all out. A student
+    /// can only hold one open reservation per book.
+    pub async fn create_reservation(&self, book_id: &str, student_id: &str, notes: Option<&str>) -> Result<Reservation> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let available: i64 = tx.query_row(
+            "SELECT COALESCE(available_copies, 0) FROM books WHERE id = ?1 AND deleted = 0",
+            [book_id],
+            |row| row.get(0),
+        ).optional()?
+            .ok_or_else(|| constraint_error(format!("Book {} not found", book_id)))?;
+        let student_found: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM students WHERE id = ?1 AND deleted = 0",
+            [student_id],
+            |row| row.get(0),
+        )?;
+        if student_found == 0 {
+            return Err(constraint_error(format!("Student {} not found", student_id)));
+        }
+
+        let existing: Option<String> = tx.query_row(
+            &format!("SELECT id FROM reservations WHERE book_id = ?1 AND student_id = ?2 AND status IN {}", OPEN_STATUSES),
+            (book_id, student_id),
+            |row| row.get(0),
+        ).optional()?;
+        if let Some(existing) = existing {
+            return Err(constraint_error(format!(
+                "Student {} already has reservation {} open for this book", student_id, existing
+            )));
+        }
+
+        let held: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM reservations WHERE book_id = ?1 AND status = 'ready'",
+            [book_id],
+            |row| row.get(0),
+        )?;
+        if available > held {
+            return Err(constraint_error("A copy of this book is on the shelf and can be borrowed now"));
+        }
+
+        let reservation_id = Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO reservations (id, book_id, student_id, status, notes) VALUES (?1, ?2, ?3, 'waiting', ?4)",
+            (&reservation_id, book_id, student_id, notes.map(str::trim).filter(|n| !n.is_empty())),
+        )?;
+        let reservation = reservation_by_id(&tx, &reservation_id)?;
+
+        tx.commit()?;
+        Ok(reservation)
+    }
+
+    /// Open reservations for a book in queue order, or its whole reservation
+    /// history with include_closed
+    pub async fn get_reservations_for_book(&self, book_id: &str, include_closed: bool) -> Result<Vec<Reservation>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE r.book_id = ?1 AND (?2 OR r.status IN {})
+             ORDER BY r.status NOT IN {}, r.created_at, r.rowid",
+            RESERVATION_SELECT, OPEN_STATUSES, OPEN_STATUSES
+        ))?;
+        let reservations = stmt.query_map((book_id, include_closed), row_to_reservation)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(reservations)
+    }
+
+    /// Cancel an open reservation. A copy that was being held for it passes to
+    /// the next student in the queue, who is returned alongside.
+    pub async fn cancel_reservation(&self, reservation_id: &str) -> Result<(Reservation, Option<Reservation>)> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (book_id, status): (String, String) = tx.query_row(
+            "SELECT book_id, status FROM reservations WHERE id = ?1",
+            [reservation_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if status != "waiting" && status != "ready" {
+            return Err(constraint_error(format!("Reservation {} is already {}", reservation_id, status)));
+        }
+
+        tx.execute(
+            "UPDATE reservations SET status = 'cancelled', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+            [reservation_id],
+        )?;
+        let next = promote_next_reservation(&tx, &book_id)?;
+        let cancelled = reservation_by_id(&tx, reservation_id)?;
+
+        tx.commit()?;
+        Ok((cancelled, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::*;
+    use crate::database::BorrowingError;
+    use crate::models::{BorrowerType, ReturnData};
+
+    #[tokio::test]
+    async fn returned_copies_go_to_the_reservation_queue_in_order() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "SET", 1);
+        let holder = seed_student(&db, "ADM-0");
+        let loan_id = seed_loan(&db, &holder, &book_id, &copies[0], 7);
+        let [first, second, third] = ["ADM-1", "ADM-2", "ADM-3"].map(|admission| seed_student(&db, admission));
+
+        let mut reservation_ids = Vec::new();
+        for student_id in [&first, &second, &third] {
+            reservation_ids.push(db.create_reservation(&book_id, student_id, None).await.unwrap().id);
+        }
+        assert!(db.create_reservation(&book_id, &first, None).await.is_err());
+
+        let queue = |reservations: Vec<super::Reservation>| {
+            reservations.into_iter()
+                .map(|r| (r.student_id, r.status, r.queue_position))
+                .collect::<Vec<_>>()
+        };
+        let entry = |student: &String, status: &str, position| (student.clone(), status.to_string(), Some(position));
+        assert_eq!(
+            queue(db.get_reservations_for_book(&book_id, false).await.unwrap()),
+            [entry(&first, "waiting", 1), entry(&second, "waiting", 2), entry(&third, "waiting", 3)]
+        );
+
+        // Leaving the queue moves everyone behind up
+        db.cancel_reservation(&reservation_ids[1]).await.unwrap();
+        assert_eq!(
+            queue(db.get_reservations_for_book(&book_id, false).await.unwrap()),
+            [entry(&first, "waiting", 1), entry(&third, "waiting", 2)]
+        );
+
+        // The returned copy is held for the front of the queue as part of the return
+        let returned = db.return_book(&loan_id, ReturnData::default(), None).await.unwrap();
+        let ready = returned.reservation_ready.expect("a reservation made ready");
+        assert_eq!((ready.student_id.as_str(), ready.status.as_str()), (first.as_str(), "ready"));
+        assert!(ready.notified_at.is_some());
+        assert_eq!(
+            queue(db.get_reservations_for_book(&book_id, false).await.unwrap()),
+            [entry(&first, "ready", 1), entry(&third, "waiting", 2)]
+        );
+
+        // Passed on when the student it was held for cancels
+        let (_, next) = db.cancel_reservation(&reservation_ids[0]).await.unwrap();
+        assert_eq!(next.map(|r| (r.student_id, r.status)), Some((third.clone(), "ready".to_string())));
+        assert_eq!(db.get_reservations_for_book(&book_id, true).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn a_held_copy_is_only_lent_to_the_student_it_is_held_for() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "HOLD", 1);
+        let [holder, reserver, other] = ["ADM-0", "ADM-1", "ADM-2"].map(|admission| seed_student(&db, admission));
+        let loan_id = seed_loan(&db, &holder, &book_id, &copies[0], 7);
+        db.create_reservation(&book_id, &reserver, None).await.unwrap();
+        db.return_book(&loan_id, ReturnData::default(), None).await.unwrap();
+
+        let verdict = db.validate_checkout("ADM-2", "HOLD-1").await.unwrap();
+        assert!(!verdict.allowed);
+        assert_eq!(verdict.blocking_reasons, ["No copies available"]);
+        let err = db.create_borrowing(&new_borrowing(&other, &book_id, Some(&copies[0])), false, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::NoCopiesAvailable));
+        let err = db.checkout_book("HOLD-1", BorrowerType::Student, "ADM-2", None, None).await.unwrap_err();
+        assert!(matches!(err, BorrowingError::NoCopiesAvailable));
+
+        assert!(db.validate_checkout("ADM-1", "HOLD-1").await.unwrap().allowed);
+        db.checkout_book("HOLD-1", BorrowerType::Student, "ADM-1", None, None).await.unwrap();
+        assert_eq!(
+            scalar::<String, _>(&db, "SELECT status FROM reservations WHERE student_id = ?1", [&reserver]),
+            "fulfilled"
+        );
+    }
+}
@@ -9,8 +9,11 @@ CREATE TABLE IF NOT EXISTS categories (
     id TEXT PRIMARY KEY,
     name TEXT NOT NULL UNIQUE,
     description TEXT,
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    content_hash TEXT,
+    pushed_hash TEXT,
+    deleted INTEGER DEFAULT 0
 );
 
 -- Books Table
@@ -29,13 +32,16 @@ CREATE TABLE IF NOT EXISTS books (
     description TEXT,
     status TEXT DEFAULT 'available' CHECK (status IN ('available', 'unavailable', 'damaged', 'lost')),
     category_id TEXT,
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     condition TEXT CHECK (condition IN ('excellent', 'good', 'fair', 'damaged', 'lost', 'stolen')),
     book_code TEXT UNIQUE,
     acquisition_year INTEGER DEFAULT (strftime('%Y', 'now')),
+    acquisition_cost REAL,
     legacy_book_id INTEGER UNIQUE,
     legacy_isbn TEXT,
+    content_hash TEXT,
+    pushed_hash TEXT,
     synced INTEGER DEFAULT 0,
     sync_version INTEGER DEFAULT 1,
     deleted INTEGER DEFAULT 0
@@ -48,8 +54,8 @@ CREATE TABLE IF NOT EXISTS book_copies (
     book_code TEXT NOT NULL,
     condition TEXT DEFAULT 'good' CHECK (condition IN ('good', 'fair', 'poor', 'damaged', 'lost')),
     status TEXT DEFAULT 'available' CHECK (status IN ('available', 'borrowed', 'maintenance', 'lost', 'stolen')),
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     tracking_code TEXT UNIQUE,
     notes TEXT,
     legacy_book_id INTEGER,
@@ -66,9 +72,11 @@ CREATE TABLE IF NOT EXISTS classes (
     class_section TEXT,
     max_books_allowed INTEGER DEFAULT 2,
     is_active INTEGER DEFAULT 1,
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     academic_level_type TEXT DEFAULT 'form' CHECK (academic_level_type IN ('form', 'grade')),
+    content_hash TEXT,
+    pushed_hash TEXT,
     synced INTEGER DEFAULT 0,
     sync_version INTEGER DEFAULT 1,
     deleted INTEGER DEFAULT 0
@@ -87,12 +95,15 @@ CREATE TABLE IF NOT EXISTS students (
     date_of_birth TEXT,
     enrollment_date TEXT DEFAULT (date('now')),
     status TEXT DEFAULT 'active',
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     class_id TEXT,
     academic_year TEXT DEFAULT '2024/2025',
     is_repeating INTEGER DEFAULT 0,
     legacy_student_id INTEGER UNIQUE,
+    gender TEXT,
+    content_hash TEXT,
+    pushed_hash TEXT,
     synced INTEGER DEFAULT 0,
     sync_version INTEGER DEFAULT 1,
     deleted INTEGER DEFAULT 0
@@ -109,9 +120,11 @@ CREATE TABLE IF NOT EXISTS staff (
     department TEXT,
     position TEXT,
     status TEXT DEFAULT 'active' CHECK (status IN ('active', 'inactive')),
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     legacy_staff_id INTEGER UNIQUE,
+    content_hash TEXT,
+    pushed_hash TEXT,
     synced INTEGER DEFAULT 0,
     sync_version INTEGER DEFAULT 1,
     deleted INTEGER DEFAULT 0
@@ -130,8 +143,8 @@ CREATE TABLE IF NOT EXISTS borrowings (
     notes TEXT,
     issued_by TEXT,
     returned_by TEXT,
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     fine_paid INTEGER DEFAULT 0,
     book_copy_id TEXT,
     condition_at_issue TEXT DEFAULT 'good',
@@ -143,11 +156,47 @@ CREATE TABLE IF NOT EXISTS borrowings (
     group_borrowing_id TEXT,
     borrower_type TEXT DEFAULT 'student' CHECK (borrower_type IN ('student', 'staff')),
     staff_id TEXT,
+    due_date_override INTEGER DEFAULT 0,
+    renewal_count INTEGER DEFAULT 0,
+    content_hash TEXT,
+    pushed_hash TEXT,
     synced INTEGER DEFAULT 0,
     sync_version INTEGER DEFAULT 1,
     deleted INTEGER DEFAULT 0
 );
 
+-- Returned loans moved out of borrowings by archive_old_borrowings
+CREATE TABLE IF NOT EXISTS borrowings_archive (
+    id TEXT PRIMARY KEY,
+    student_id TEXT,
+    book_id TEXT,
+    borrowed_date TEXT,
+    due_date TEXT NOT NULL,
+    returned_date TEXT,
+    status TEXT,
+    fine_amount REAL DEFAULT 0,
+    notes TEXT,
+    issued_by TEXT,
+    returned_by TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    fine_paid INTEGER DEFAULT 0,
+    book_copy_id TEXT,
+    condition_at_issue TEXT,
+    condition_at_return TEXT,
+    is_lost INTEGER DEFAULT 0,
+    tracking_code TEXT,
+    return_notes TEXT,
+    copy_condition TEXT,
+    group_borrowing_id TEXT,
+    borrower_type TEXT DEFAULT 'student',
+    staff_id TEXT,
+    due_date_override INTEGER DEFAULT 0,
+    renewal_count INTEGER DEFAULT 0,
+    deleted INTEGER DEFAULT 0,
+    archived_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
 -- Group Borrowings Table
 CREATE TABLE IF NOT EXISTS group_borrowings (
     id TEXT PRIMARY KEY,
@@ -168,8 +217,8 @@ CREATE TABLE IF NOT EXISTS group_borrowings (
     student_count INTEGER DEFAULT 1,
     issued_by TEXT,
     returned_by TEXT,
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     student_ids TEXT DEFAULT '[]' -- JSON array as text
 );
 
@@ -182,11 +231,15 @@ CREATE TABLE IF NOT EXISTS fines (
     amount REAL DEFAULT 0 NOT NULL,
     description TEXT,
     status TEXT DEFAULT 'unpaid' CHECK (status IN ('unpaid', 'paid', 'cleared', 'collected', 'partial', 'waived')),
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     created_by TEXT,
     borrower_type TEXT DEFAULT 'student' CHECK (borrower_type IN ('student', 'staff')),
     staff_id TEXT,
+    paid_at TEXT,
+    payment_method TEXT,
+    content_hash TEXT,
+    pushed_hash TEXT,
     synced INTEGER DEFAULT 0,
     sync_version INTEGER DEFAULT 1,
     deleted INTEGER DEFAULT 0
@@ -198,8 +251,8 @@ CREATE TABLE IF NOT EXISTS fine_settings (
     fine_type TEXT NOT NULL UNIQUE CHECK (fine_type IN ('overdue', 'damaged', 'lost_book', 'stolen_book', 'theft_victim', 'condition_poor', 'condition_fair', 'condition_excellent', 'condition_good', 'late_return', 'replacement_cost', 'processing_fee')),
     amount REAL DEFAULT 0 NOT NULL,
     description TEXT,
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
 );
 
 -- Theft Reports Table
@@ -218,8 +271,43 @@ CREATE TABLE IF NOT EXISTS theft_reports (
     investigation_notes TEXT,
     resolved_date TEXT,
     resolved_by TEXT,
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Application Settings Table (JSON values keyed by setting name)
+CREATE TABLE IF NOT EXISTS app_settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Overdue Escalation Actions Table
+CREATE TABLE IF NOT EXISTS escalation_actions (
+    id TEXT PRIMARY KEY,
+    borrowing_id TEXT NOT NULL,
+    stage TEXT NOT NULL,
+    action TEXT NOT NULL CHECK (action IN ('reminder', 'fine', 'parent_notice', 'block')),
+    days_overdue INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    UNIQUE (borrowing_id, stage)
+);
+
+-- Flagged Tracking Codes Table (lost/stolen copies that should alert staff when scanned)
+CREATE TABLE IF NOT EXISTS flagged_codes (
+    code TEXT PRIMARY KEY COLLATE NOCASE,
+    reason TEXT NOT NULL,
+    book_copy_id TEXT,
+    flagged_by TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Book Tags Table (free-text labels alongside categories, stored lowercased)
+CREATE TABLE IF NOT EXISTS book_tags (
+    book_id TEXT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (book_id, tag)
 );
 
 -- User Sessions Table for Offline Authentication
@@ -232,9 +320,9 @@ CREATE TABLE IF NOT EXISTS user_sessions (
     expires_at TEXT NOT NULL,
     user_metadata TEXT, -- JSON blob for user profile data
     role TEXT DEFAULT 'user',
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
-    last_activity TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    last_activity TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     session_valid INTEGER DEFAULT 1, -- 0 = invalid, 1 = valid
     offline_expiry TEXT NOT NULL, -- Extended expiry for offline use
     device_fingerprint TEXT
@@ -246,21 +334,42 @@ CREATE TABLE IF NOT EXISTS sync_log (
     table_name TEXT NOT NULL,
     record_id TEXT NOT NULL,
     operation TEXT NOT NULL CHECK (operation IN ('insert', 'update', 'delete')),
-    timestamp TEXT NOT NULL DEFAULT (datetime('now')),
+    timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     synced INTEGER DEFAULT 0,
     retry_count INTEGER DEFAULT 0,
     error_message TEXT,
     payload TEXT -- JSON payload for the operation
 );
 
+-- Local changes waiting to be pushed to Supabase, drained oldest first
+CREATE TABLE IF NOT EXISTS sync_queue (
+    id TEXT PRIMARY KEY,
+    table_name TEXT NOT NULL,
+    operation_type TEXT NOT NULL CHECK (operation_type IN ('insert', 'update', 'delete')),
+    record_id TEXT NOT NULL,
+    payload TEXT NOT NULL, -- JSON payload for the operation
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    retry_count INTEGER DEFAULT 0,
+    last_error TEXT
+);
+
 CREATE TABLE IF NOT EXISTS sync_state (
     table_name TEXT PRIMARY KEY,
-    last_sync TEXT NOT NULL DEFAULT (datetime('now')),
+    last_sync TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     sync_token TEXT,
     total_records INTEGER DEFAULT 0,
     synced_records INTEGER DEFAULT 0
 );
 
+-- One row per Supabase pull request, for diagnosing slow syncs
+CREATE TABLE IF NOT EXISTS sync_metrics (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_name TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    row_count INTEGER NOT NULL DEFAULT 0,
+    recorded_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
 -- Conflict Resolution Table
 CREATE TABLE IF NOT EXISTS sync_conflicts (
     id TEXT PRIMARY KEY,
@@ -269,9 +378,11 @@ CREATE TABLE IF NOT EXISTS sync_conflicts (
     local_data TEXT NOT NULL, -- JSON
     remote_data TEXT NOT NULL, -- JSON
     conflict_type TEXT NOT NULL, -- 'update_conflict', 'delete_conflict'
-    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
     resolved INTEGER DEFAULT 0,
-    resolution_strategy TEXT -- 'local_wins', 'remote_wins', 'manual'
+    resolution_strategy TEXT, -- 'local_wins', 'remote_wins', 'manual'
+    winner TEXT, -- 'local' or 'remote'
+    resolved_at TEXT
 );
 
 -- Performance Indexes
@@ -283,6 +394,7 @@ CREATE INDEX IF NOT EXISTS idx_books_sync ON books(synced, sync_version);
 CREATE INDEX IF NOT EXISTS idx_book_copies_book ON book_copies(book_id);
 CREATE INDEX IF NOT EXISTS idx_book_copies_status ON book_copies(status);
 CREATE INDEX IF NOT EXISTS idx_book_copies_tracking ON book_copies(tracking_code);
+CREATE INDEX IF NOT EXISTS idx_book_copies_code ON book_copies(book_code);
 CREATE INDEX IF NOT EXISTS idx_book_copies_sync ON book_copies(synced, sync_version);
 
 CREATE INDEX IF NOT EXISTS idx_students_class ON students(class_id);
@@ -292,6 +404,7 @@ CREATE INDEX IF NOT EXISTS idx_students_sync ON students(synced, sync_version);
 
 CREATE INDEX IF NOT EXISTS idx_borrowings_student ON borrowings(student_id);
 CREATE INDEX IF NOT EXISTS idx_borrowings_book ON borrowings(book_id);
+CREATE INDEX IF NOT EXISTS idx_borrowings_copy ON borrowings(book_copy_id);
 CREATE INDEX IF NOT EXISTS idx_borrowings_status ON borrowings(status);
 CREATE INDEX IF NOT EXISTS idx_borrowings_dates ON borrowings(borrowed_date, due_date);
 CREATE INDEX IF NOT EXISTS idx_borrowings_sync ON borrowings(synced, sync_version);
@@ -304,43 +417,56 @@ CREATE INDEX IF NOT EXISTS idx_fines_sync ON fines(synced, sync_version);
 CREATE INDEX IF NOT EXISTS idx_sync_log_table ON sync_log(table_name);
 CREATE INDEX IF NOT EXISTS idx_sync_log_synced ON sync_log(synced);
 CREATE INDEX IF NOT EXISTS idx_sync_log_timestamp ON sync_log(timestamp);
+CREATE INDEX IF NOT EXISTS idx_sync_queue_created ON sync_queue(created_at);
+CREATE INDEX IF NOT EXISTS idx_sync_conflicts_record ON sync_conflicts(table_name, record_id);
+CREATE INDEX IF NOT EXISTS idx_borrowings_archive_student ON borrowings_archive(student_id);
+CREATE INDEX IF NOT EXISTS idx_borrowings_archive_book ON borrowings_archive(book_id);
+
+CREATE INDEX IF NOT EXISTS idx_escalation_actions_borrowing ON escalation_actions(borrowing_id);
 
 -- User Sessions Indexes
+CREATE INDEX IF NOT EXISTS idx_book_tags_tag ON book_tags(tag);
 CREATE INDEX IF NOT EXISTS idx_user_sessions_user_id ON user_sessions(user_id);
 CREATE INDEX IF NOT EXISTS idx_user_sessions_email ON user_sessions(email);
 CREATE INDEX IF NOT EXISTS idx_user_sessions_expires ON user_sessions(expires_at);
 CREATE INDEX IF NOT EXISTS idx_user_sessions_offline_expiry ON user_sessions(offline_expiry);
 CREATE INDEX IF NOT EXISTS idx_user_sessions_valid ON user_sessions(session_valid);
+CREATE INDEX IF NOT EXISTS idx_sync_metrics_recorded ON sync_metrics(recorded_at);
 
 -- Triggers for automatic updated_at timestamps
 CREATE TRIGGER IF NOT EXISTS update_categories_timestamp 
     AFTER UPDATE ON categories 
+    WHEN NEW.updated_at IS OLD.updated_at AND NEW.created_at IS OLD.created_at
     BEGIN 
-        UPDATE categories SET updated_at = datetime('now') WHERE id = NEW.id;
+        UPDATE categories SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
     END;
 
 CREATE TRIGGER IF NOT EXISTS update_books_timestamp 
     AFTER UPDATE ON books 
+    WHEN NEW.updated_at IS OLD.updated_at AND NEW.created_at IS OLD.created_at
     BEGIN 
-        UPDATE books SET updated_at = datetime('now') WHERE id = NEW.id;
+        UPDATE books SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
     END;
 
 CREATE TRIGGER IF NOT EXISTS update_book_copies_timestamp 
     AFTER UPDATE ON book_copies 
+    WHEN NEW.updated_at IS OLD.updated_at AND NEW.created_at IS OLD.created_at
     BEGIN 
-        UPDATE book_copies SET updated_at = datetime('now') WHERE id = NEW.id;
+        UPDATE book_copies SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
     END;
 
 CREATE TRIGGER IF NOT EXISTS update_students_timestamp 
     AFTER UPDATE ON students 
+    WHEN NEW.updated_at IS OLD.updated_at AND NEW.created_at IS OLD.created_at
     BEGIN 
-        UPDATE students SET updated_at = datetime('now') WHERE id = NEW.id;
+        UPDATE students SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
     END;
 
 CREATE TRIGGER IF NOT EXISTS update_borrowings_timestamp 
     AFTER UPDATE ON borrowings 
+    WHEN NEW.updated_at IS OLD.updated_at AND NEW.created_at IS OLD.created_at
     BEGIN 
-        UPDATE borrowings SET updated_at = datetime('now') WHERE id = NEW.id;
+        UPDATE borrowings SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
     END;
 
 -- Sync tracking triggers
@@ -0,0 +1,133 @@
+use super::migrations::{latest_schema_version, schema_version};
+use super::{apply_schema, DatabaseManager};
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingColumn {
+    pub table: String,
+    pub column: String,
+    pub expected_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfCheckReport {
+    pub passed: bool,
+    pub tables_checked: usize,
+    pub schema_version: i64,
+    pub latest_schema_version: i64,
+    pub missing_tables: Vec<String>,
+    pub missing_columns: Vec<MissingColumn>,
+    pub integrity_ok: bool,
+    pub integrity_messages: Vec<String>,
+    pub problems: Vec<String>,
+}
+
+// table -> [(column, declared type)] for every user table in the database
+fn table_columns(conn: &Connection) -> Result<BTreeMap<String, Vec<(String, String)>>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+    )?;
+    let tables = stmt.query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut columns = BTreeMap::new();
+    let mut stmt = conn.prepare("SELECT name, type FROM pragma_table_info(?1) ORDER BY cid")?;
+    for table in tables {
+        let table_columns = stmt.query_map([&table], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        columns.insert(table, table_columns);
+    }
+    Ok(columns)
+}
+
+// What a freshly created database looks like, built from the same schema and migrations
+fn expected_columns() -> Result<BTreeMap<String, Vec<(String, String)>>> {
+    let reference = Connection::open_in_memory()?;
+    apply_schema(&reference)?;
+    table_columns(&reference)
+}
+
+impl DatabaseManager {
+    /// In-app equivalent of the external db checker: every table and column the
+    /// schema defines must be present, and SQLite's integrity_check must pass
+    pub async fn self_check(&self) -> Result<SelfCheckReport> {
+        let expected = expected_columns()?;
+        let conn = self.lock_connection()?;
+        let actual = table_columns(&conn)?;
+        let version = schema_version(&conn)?;
+        let latest_version = latest_schema_version();
+
+        let mut missing_tables = Vec::new();
+        let mut missing_columns = Vec::new();
+        for (table, columns) in &expected {
+            let Some(present) = actual.get(table) else {
+                missing_tables.push(table.clone());
+                continue;
+            };
+            for (column, expected_type) in columns {
+                if !present.iter().any(|(name, _)| name == column) {
+                    missing_columns.push(MissingColumn {
+                        table: table.clone(),
+                        column: column.clone(),
+                        expected_type: expected_type.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let integrity_messages = stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        let integrity_ok = integrity_messages.len() == 1 && integrity_messages[0] == "ok";
+
+        let mut problems: Vec<String> = missing_tables.iter()
+            .map(|table| format!("Table {} is missing", table))
+            .collect();
+        problems.extend(missing_columns.iter().map(|c| {
+            format!("Column {}.{} ({}) is missing", c.table, c.column, c.expected_type)
+        }));
+        if version < latest_version {
+            problems.push(format!("Schema version {} is behind the latest ({})", version, latest_version));
+        }
+        if !integrity_ok {
+            problems.extend(integrity_messages.iter().map(|m| format!("Integrity check: {}", m)));
+        }
+
+        Ok(SelfCheckReport {
+            passed: problems.is_empty(),
+            tables_checked: expected.len(),
+            schema_version: version,
+            latest_schema_version: latest_version,
+            missing_tables,
+            missing_columns,
+            integrity_ok,
+            integrity_messages,
+            problems,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn a_missing_column_fails_the_check_by_name() {
+        let db = test_db();
+        let report = db.self_check().await.unwrap();
+        assert!(report.passed, "{:?}", report.problems);
+        assert!(report.integrity_ok);
+
+        execute(&db, "ALTER TABLE books DROP COLUMN acquisition_cost", []);
+        let report = db.self_check().await.unwrap();
+        assert!(!report.passed);
+        assert!(report.missing_tables.is_empty());
+        let missing: Vec<(&str, &str)> = report.missing_columns.iter()
+            .map(|c| (c.table.as_str(), c.column.as_str()))
+            .collect();
+        assert_eq!(missing, [("books", "acquisition_cost")]);
+        assert_eq!(report.problems, ["Column books.acquisition_cost (REAL) is missing"]);
+    }
+}
@@ -0,0 +1,366 @@
+use super::{constraint_error, DatabaseManager};
+use crate::models::*;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Result, Transaction, TransactionBehavior};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub const ESCALATION_POLICY_KEY: &str = "escalation_policy";
+pub const BORROW_BLOCK_THRESHOLD_KEY: &str = "borrow_block_threshold";
+pub const SHELF_CAPACITIES_KEY: &str = "shelf_capacities";
+pub const LOAN_RULES_KEY: &str = "loan_rules";
+pub const AUTO_SYNC_KEY: &str = "auto_sync";
+pub const ENUM_ALIASES_KEY: &str = "enum_aliases";
+pub const TRASH_RETENTION_DAYS_KEY: &str = "trash_retention_days";
+pub const MERGE_STRATEGY_KEY: &str = "merge_strategy";
+pub const VERIFY_RETURN_TRACKING_CODES_KEY: &str = "verify_return_tracking_codes";
+pub const MAINTENANCE_SCHEDULE_KEY: &str = "maintenance_schedule";
+pub const BACKUP_SCHEDULE_KEY: &str = "backup_schedule";
+pub const LAST_BACKUP_AT_KEY: &str = "last_backup_at";
+pub const CONFLICT_LOG_RETENTION_DAYS_KEY: &str = "conflict_log_retention_days";
+pub const DEFAULT_LOAN_DAYS_KEY: &str = "default_loan_days";
+
+// Single-value settings the get_setting/set_setting commands reach by name.
+// default_loan_days is loan_rules.loan_period_days, not a row of its own.
+pub const NAMED_SETTINGS: &[&str] = &[
+    DEFAULT_LOAN_DAYS_KEY,
+    BORROW_BLOCK_THRESHOLD_KEY,
+    TRASH_RETENTION_DAYS_KEY,
+    CONFLICT_LOG_RETENTION_DAYS_KEY,
+    VERIFY_RETURN_TRACKING_CODES_KEY,
+];
+
+// Settings are stored as JSON text so any serializable config can live in one table
+pub(crate) fn read_setting<T: DeserializeOwned>(conn: &Connection, key: &str) -> Result<Option<T>> {
+    let raw: Option<String> = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        [key],
+        |row| row.get(0)
+    ).optional()?;
+
+    match raw {
+        Some(raw) => serde_json::from_str(&raw)
+            .map(Some)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn write_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<()> {
+    let raw = serde_json::to_string(value)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        (key, raw),
+    )?;
+    Ok(())
+}
+
+// Enum values stored with their serde names (e.g. "parent_notice")
+fn serde_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+impl DatabaseManager {
+    pub async fn get_setting<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let conn = self.lock_connection()?;
+        read_setting(&conn, key)
+    }
+
+    pub async fn set_setting<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let conn = self.lock_connection()?;
+        write_setting(&conn, key, value)
+    }
+
+    pub async fn delete_setting(&self, key: &str) -> Result<()> {
+        let conn = self.lock_connection()?;
+        conn.execute("DELETE FROM app_settings WHERE key = ?1", [key])?;
+        Ok(())
+    }
+
+    // Borrowing block for students with outstanding fines
+    pub async fn get_borrow_block_threshold(&self) -> Result<Option<f64>> {
+        self.get_setting(BORROW_BLOCK_THRESHOLD_KEY).await
+    }
+
+    pub async fn set_borrow_block_threshold(&self, threshold: Option<f64>) -> Result<()> {
+        match threshold {
+            Some(threshold) => self.set_setting(BORROW_BLOCK_THRESHOLD_KEY, &threshold).await,
+            None => self.delete_setting(BORROW_BLOCK_THRESHOLD_KEY).await,
+        }
+    }
+
+    // Shelf capacities (shelf location -> max items)
+    pub async fn get_shelf_capacities(&self) -> Result<HashMap<String, i64>> {
+        Ok(self.get_setting(SHELF_CAPACITIES_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_shelf_capacities(&self, capacities: &HashMap<String, i64>) -> Result<()> {
+        self.set_setting(SHELF_CAPACITIES_KEY, capacities).await
+    }
+
+    // Loan period rules
+    pub async fn get_loan_rules(&self) -> Result<LoanRules> {
+        Ok(self.get_setting(LOAN_RULES_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_loan_rules(&self, rules: &LoanRules) -> Result<()> {
+        self.set_setting(LOAN_RULES_KEY, rules).await
+    }
+
+    // Standard student loan period, changed without touching the other loan rules
+    pub async fn set_default_loan_days(&self, days: i64) -> Result<()> {
+        let conn = self.lock_connection()?;
+        let mut rules: LoanRules = read_setting(&conn, LOAN_RULES_KEY)?.unwrap_or_default();
+        rules.loan_period_days = days;
+        write_setting(&conn, LOAN_RULES_KEY, &rules)
+    }
+
+    /// Current value of one of the NAMED_SETTINGS, with its default filled in
+    pub async fn get_named_setting(&self, key: &str) -> Result<serde_json::Value> {
+        use serde_json::json;
+        Ok(match key {
+            DEFAULT_LOAN_DAYS_KEY => json!(self.get_loan_rules().await?.loan_period_days),
+            BORROW_BLOCK_THRESHOLD_KEY => json!(self.get_borrow_block_threshold().await?),
+            TRASH_RETENTION_DAYS_KEY => json!(self.get_trash_retention_days().await?),
+            CONFLICT_LOG_RETENTION_DAYS_KEY => json!(self.get_conflict_log_retention_days().await?),
+            VERIFY_RETURN_TRACKING_CODES_KEY => json!(self.get_verify_return_tracking_codes().await?),
+            _ => return Err(constraint_error(format!("Unknown setting {}", key))),
+        })
+    }
+
+    // How long soft-deleted records stay restorable
+    pub async fn get_trash_retention_days(&self) -> Result<i64> {
+        Ok(self.get_setting(TRASH_RETENTION_DAYS_KEY).await?.unwrap_or(super::trash::DEFAULT_TRASH_RETENTION_DAYS))
+    }
+
+    pub async fn set_trash_retention_days(&self, days: i64) -> Result<()> {
+        self.set_setting(TRASH_RETENTION_DAYS_KEY, &days).await
+    }
+
+    // How long sync conflict log entries are kept before nightly maintenance drops them
+    pub async fn get_conflict_log_retention_days(&self) -> Result<i64> {
+        Ok(self.get_setting(CONFLICT_LOG_RETENTION_DAYS_KEY).await?.unwrap_or(super::conflicts::DEFAULT_CONFLICT_LOG_RETENTION_DAYS))
+    }
+
+    pub async fn set_conflict_log_retention_days(&self, days: i64) -> Result<()> {
+        self.set_setting(CONFLICT_LOG_RETENTION_DAYS_KEY, &days).await
+    }
+
+    // Off by default so libraries that don't track copy codes see normal returns
+    pub async fn get_verify_return_tracking_codes(&self) -> Result<bool> {
+        Ok(self.get_setting(VERIFY_RETURN_TRACKING_CODES_KEY).await?.unwrap_or(false))
+    }
+
+    pub async fn set_verify_return_tracking_codes(&self, enabled: bool) -> Result<()> {
+        self.set_setting(VERIFY_RETURN_TRACKING_CODES_KEY, &enabled).await
+    }
+
+    // Extra alias -> canonical value mappings used by the enum repair
+    pub async fn get_enum_aliases(&self) -> Result<HashMap<String, String>> {
+        Ok(self.get_setting(ENUM_ALIASES_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_enum_aliases(&self, aliases: &HashMap<String, String>) -> Result<()> {
+        self.set_setting(ENUM_ALIASES_KEY, aliases).await
+    }
+
+    // Startup auto-sync throttling
+    pub async fn get_auto_sync_state(&self) -> Result<AutoSyncState> {
+        Ok(self.get_setting(AUTO_SYNC_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_auto_sync_interval(&self, minutes: i64) -> Result<()> {
+        let conn = self.lock_connection()?;
+        let mut state: AutoSyncState = read_setting(&conn, AUTO_SYNC_KEY)?.unwrap_or_default();
+        state.min_interval_minutes = minutes;
+        write_setting(&conn, AUTO_SYNC_KEY, &state)
+    }
+
+    pub async fn mark_sync_seeded(&self) -> Result<()> {
+        let conn = self.lock_connection()?;
+        let mut state: AutoSyncState = read_setting(&conn, AUTO_SYNC_KEY)?.unwrap_or_default();
+        state.seeded = true;
+        write_setting(&conn, AUTO_SYNC_KEY, &state)
+    }
+
+    /// Record an auto-sync attempt if one is due. The immediate transaction makes
+    /// the check and the write atomic, so two app instances starting together
+    /// can't both claim the run.
+    pub async fn claim_auto_sync(&self, now: DateTime<Utc>) -> Result<bool> {
+        let conn = self.lock_connection()?;
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)?;
+
+        let mut state: AutoSyncState = read_setting(&tx, AUTO_SYNC_KEY)?.unwrap_or_default();
+        if !state.is_due(now) {
+            return Ok(false);
+        }
+
+        state.last_auto_sync = Some(now);
+        write_setting(&tx, AUTO_SYNC_KEY, &state)?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    // Overdue escalation
+    pub async fn get_escalation_policy(&self) -> Result<EscalationPolicy> {
+        Ok(self.get_setting(ESCALATION_POLICY_KEY).await?.unwrap_or_default())
+    }
+
+    pub async fn set_escalation_policy(&self, policy: &EscalationPolicy) -> Result<()> {
+        self.set_setting(ESCALATION_POLICY_KEY, policy).await
+    }
+
+    /// Work out the current escalation stage for every overdue loan and record any stage not yet actioned
+    pub async fn apply_escalation(&self) -> Result<Vec<EscalationRecord>> {
+        let conn = self.lock_connection()?;
+        let policy: EscalationPolicy = read_setting(&conn, ESCALATION_POLICY_KEY)?.unwrap_or_default();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, CAST(julianday(date('now')) - julianday(due_date) AS INTEGER) as days_overdue
+             FROM borrowings
+             WHERE deleted = 0 AND status IN ('active', 'overdue')
+               AND returned_date IS NULL AND due_date < date('now')
+             ORDER BY due_date"
+        )?;
+        let overdue: Vec<(String, i64)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+
+        let tx = conn.unchecked_transaction()?;
+        let mut records = Vec::new();
+        for (borrowing_id, days_overdue) in overdue {
+            let Some(stage) = policy.stage_for(days_overdue) else {
+                continue;
+            };
+
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO escalation_actions (id, borrowing_id, stage, action, days_overdue)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    Uuid::new_v4().to_string(),
+                    &borrowing_id,
+                    &stage.name,
+                    serde_name(&stage.action),
+                    days_overdue,
+                ),
+            )?;
+
+            records.push(EscalationRecord {
+                borrowing_id,
+                stage: stage.name.clone(),
+                action: stage.action.clone(),
+                days_overdue,
+                newly_recorded: inserted > 0,
+            });
+        }
+        tx.commit()?;
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::*;
+    use crate::models::*;
+
+    #[tokio::test]
+    async fn overdue_depths_map_to_escalation_stages() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "ESC", 5);
+        let student_id = seed_student(&db, "ADM-1");
+        let not_due = seed_loan(&db, &student_id, &book_id, &copies[0], 2);
+        let reminder = seed_loan(&db, &student_id, &book_id, &copies[1], -3);
+        let fine = seed_loan(&db, &student_id, &book_id, &copies[2], -10);
+        let parent_notice = seed_loan(&db, &student_id, &book_id, &copies[3], -20);
+        let block = seed_loan(&db, &student_id, &book_id, &copies[4], -45);
+
+        let records = db.apply_escalation().await.unwrap();
+        let stage_of = |loan_id: &str| {
+            records.iter()
+                .find(|r| r.borrowing_id == loan_id)
+                .map(|r| (r.stage.as_str(), r.action.clone(), r.days_overdue))
+        };
+        assert_eq!(stage_of(&not_due), None);
+        assert_eq!(stage_of(&reminder), Some(("reminder", EscalationAction::Reminder, 3)));
+        assert_eq!(stage_of(&fine), Some(("fine", EscalationAction::Fine, 10)));
+        assert_eq!(stage_of(&parent_notice), Some(("parent_notice", EscalationAction::ParentNotice, 20)));
+        assert_eq!(stage_of(&block), Some(("block", EscalationAction::Block, 45)));
+        assert!(records.iter().all(|r| r.newly_recorded));
+
+        // A second run finds the same stages but has nothing new to record
+        let again = db.apply_escalation().await.unwrap();
+        assert_eq!(again.len(), 4);
+        assert!(again.iter().all(|r| !r.newly_recorded));
+        assert_eq!(scalar::<i64, _>(&db, "SELECT COUNT(*) FROM escalation_actions", []), 4);
+    }
+
+    #[tokio::test]
+    async fn custom_policy_replaces_the_default_stages() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "ESC", 1);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], -10);
+
+        let policy = EscalationPolicy {
+            stages: vec![EscalationStage { name: "warn".to_string(), min_days_overdue: 5, action: EscalationAction::Reminder }],
+        };
+        db.set_escalation_policy(&policy).await.unwrap();
+
+        let records = db.apply_escalation().await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].borrowing_id, loan_id);
+        assert_eq!(records[0].stage, "warn");
+    }
+
+
+    #[tokio::test]
+    async fn second_quick_startup_skips_the_auto_sync() {
+        let dir = std::env::temp_dir().join(format!("auto-sync-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("library.db");
+        let path = path.to_str().unwrap();
+        let started = chrono::Utc::now();
+
+        // Each startup opens the database afresh, as a relaunched app would
+        let first = crate::database::DatabaseManager::new(path).unwrap();
+        assert!(first.claim_auto_sync(started).await.unwrap());
+        drop(first);
+
+        let second = crate::database::DatabaseManager::new(path).unwrap();
+        assert!(!second.claim_auto_sync(started + chrono::Duration::minutes(2)).await.unwrap());
+        // Once the interval has passed a startup may sync again, until the database is seeded
+        assert!(second.claim_auto_sync(started + chrono::Duration::minutes(61)).await.unwrap());
+        second.mark_sync_seeded().await.unwrap();
+        assert!(!second.claim_auto_sync(started + chrono::Duration::days(2)).await.unwrap());
+        drop(second);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn changing_default_loan_days_moves_the_computed_due_date() {
+        let db = test_db();
+        seed_book(&db, "LOAN", 2);
+        seed_student(&db, "ADM-1");
+        assert_eq!(db.get_named_setting(super::DEFAULT_LOAN_DAYS_KEY).await.unwrap(), 14);
+
+        let before = db.checkout_book("LOAN", BorrowerType::Student, "ADM-1", None, None).await.unwrap();
+        assert_eq!(before.borrowing.due_date, days_from_today(14));
+
+        db.set_default_loan_days(21).await.unwrap();
+        assert_eq!(db.get_named_setting(super::DEFAULT_LOAN_DAYS_KEY).await.unwrap(), 21);
+        let after = db.checkout_book("LOAN", BorrowerType::Student, "ADM-1", None, None).await.unwrap();
+        assert_eq!(after.borrowing.due_date, days_from_today(21));
+
+        // Only the student loan period changes
+        assert_eq!(db.get_loan_rules().await.unwrap().staff_loan_period_days, LoanRules::default().staff_loan_period_days);
+    }
+}
@@ -0,0 +1,118 @@
+use super::{constraint_error, row_to_book, DatabaseManager};
+use crate::models::*;
+use rusqlite::Result;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub book_count: i64,
+}
+
+// Tags are compared lowercased and trimmed so "Summer-Reading " and "summer-reading" match
+fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err(constraint_error("Tag cannot be empty"));
+    }
+    Ok(tag)
+}
+
+impl DatabaseManager {
+    /// Tag a book, returning the normalized tag. Tagging twice is a no-op.
+    pub async fn add_book_tag(&self, book_id: &str, tag: &str) -> Result<String> {
+        let tag = normalize_tag(tag)?;
+        let conn = self.lock_connection()?;
+
+        let exists: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM books WHERE id = ?1 AND deleted = 0",
+            [book_id],
+            |row| row.get(0),
+        )?;
+        if exists == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+
+        conn.execute(
+            "INSERT OR IGNORE INTO book_tags (book_id, tag) VALUES (?1, ?2)",
+            (book_id, &tag),
+        )?;
+        Ok(tag)
+    }
+
+    /// Returns whether the book had the tag
+    pub async fn remove_book_tag(&self, book_id: &str, tag: &str) -> Result<bool> {
+        let tag = normalize_tag(tag)?;
+        let conn = self.lock_connection()?;
+        let removed = conn.execute(
+            "DELETE FROM book_tags WHERE book_id = ?1 AND tag = ?2",
+            (book_id, &tag),
+        )?;
+        Ok(removed > 0)
+    }
+
+    pub async fn get_books_by_tag(&self, tag: &str) -> Result<Vec<Book>> {
+        let tag = normalize_tag(tag)?;
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT b.id, b.title, b.author, b.isbn, b.publisher, b.publication_year, b.category_id, b.total_copies,
+                    b.available_copies, b.shelf_location, b.description, b.created_at, b.updated_at
+             FROM book_tags t
+             JOIN books b ON t.book_id = b.id
+             WHERE t.tag = ?1 AND b.deleted = 0
+             ORDER BY b.title"
+        )?;
+        let books = stmt.query_map([&tag], row_to_book)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(books)
+    }
+
+    /// Every tag in use with the number of books carrying it, most used first
+    pub async fn get_all_tags(&self) -> Result<Vec<TagCount>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT t.tag, COUNT(*) as book_count
+             FROM book_tags t
+             JOIN books b ON t.book_id = b.id
+             WHERE b.deleted = 0
+             GROUP BY t.tag
+             ORDER BY book_count DESC, t.tag"
+        )?;
+        let tags = stmt.query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                book_count: row.get(1)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn tagged_books_are_found_by_tag_and_counted() {
+        let db = test_db();
+        let (first, _) = seed_book(&db, "TAG-A", 1);
+        let (second, _) = seed_book(&db, "TAG-B", 1);
+
+        assert_eq!(db.add_book_tag(&first, " Summer-Reading ").await.unwrap(), "summer-reading");
+        db.add_book_tag(&second, "summer-reading").await.unwrap();
+        // Tagging twice is harmless
+        db.add_book_tag(&second, "SUMMER-READING").await.unwrap();
+        db.add_book_tag(&second, "exam-prep").await.unwrap();
+        assert!(db.add_book_tag(&first, "   ").await.is_err());
+
+        let titles: Vec<String> = db.get_books_by_tag("Summer-Reading").await.unwrap().into_iter().map(|b| b.title).collect();
+        assert_eq!(titles, ["Book TAG-A", "Book TAG-B"]);
+
+        let counts: Vec<(String, i64)> = db.get_all_tags().await.unwrap().into_iter().map(|t| (t.tag, t.book_count)).collect();
+        assert_eq!(counts, [("summer-reading".to_string(), 2), ("exam-prep".to_string(), 1)]);
+
+        assert!(db.remove_book_tag(&first, "summer-reading").await.unwrap());
+        assert!(!db.remove_book_tag(&first, "summer-reading").await.unwrap());
+        let titles: Vec<String> = db.get_books_by_tag("summer-reading").await.unwrap().into_iter().map(|b| b.title).collect();
+        assert_eq!(titles, ["Book TAG-B"]);
+    }
+}
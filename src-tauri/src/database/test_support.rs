@@ -0,0 +1,170 @@
+// Seed data for the database unit tests. Rows are written with plain SQL so a
+// test only names the columns it cares about; everything else takes the schema default.
+
+use super::DatabaseManager;
+use crate::models::{BorrowerType, Borrowing, BorrowingStatus};
+use chrono::{Duration, NaiveDate, Utc};
+use rusqlite::types::FromSql;
+use rusqlite::Params;
+use uuid::Uuid;
+
+pub(crate) fn test_db() -> DatabaseManager {
+    DatabaseManager::open_in_memory().expect("open in-memory database")
+}
+
+pub(crate) fn execute<P: Params>(db: &DatabaseManager, sql: &str, params: P) -> usize {
+    let conn = db.get_connection().lock().unwrap();
+    conn.execute(sql, params).expect(sql)
+}
+
+pub(crate) fn scalar<T: FromSql, P: Params>(db: &DatabaseManager, sql: &str, params: P) -> T {
+    let conn = db.get_connection().lock().unwrap();
+    conn.query_row(sql, params, |row| row.get(0)).expect(sql)
+}
+
+pub(crate) fn days_from_today(days: i64) -> NaiveDate {
+    Utc::now().date_naive() + Duration::days(days)
+}
+
+/// A book with `copies` copies on the shelf. Each copy is coded and tracked as `<book_code>-<n>`.
+/// Returns the book id and the copy ids in copy order.
+pub(crate) fn seed_book(db: &DatabaseManager, book_code: &str, copies: i64) -> (String, Vec<String>) {
+    let conn = db.get_connection().lock().unwrap();
+    let book_id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO books (id, title, author, total_copies, available_copies, book_code)
+         VALUES (?1, ?2, 'Test Author', ?3, ?3, ?4)",
+        (&book_id, format!("Book {}", book_code), copies, book_code),
+    ).unwrap();
+
+    let copy_ids = (1..=copies)
+        .map(|copy_number| {
+            let copy_id = Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO book_copies (id, book_id, copy_number, book_code, tracking_code)
+                 VALUES (?1, ?2, ?3, ?4, ?4)",
+                (&copy_id, &book_id, copy_number, format!("{}-{}", book_code, copy_number)),
+            ).unwrap();
+            copy_id
+        })
+        .collect();
+    (book_id, copy_ids)
+}
+
+pub(crate) fn seed_student(db: &DatabaseManager, admission_number: &str) -> String {
+    let student_id = Uuid::new_v4().to_string();
+    execute(
+        db,
+        "INSERT INTO students (id, admission_number, first_name, last_name, class_grade)
+         VALUES (?1, ?2, 'Test', ?2, 'Form 1')",
+        (&student_id, admission_number),
+    );
+    student_id
+}
+
+/// An open loan of a copy, due `due_in_days` from today (negative for overdue). The copy
+/// and the book's available count are left the way a checkout leaves them.
+pub(crate) fn seed_loan(db: &DatabaseManager, student_id: &str, book_id: &str, copy_id: &str, due_in_days: i64) -> String {
+    let loan_id = Uuid::new_v4().to_string();
+    let due_date = days_from_today(due_in_days);
+    let borrowed_date = due_date - Duration::days(14);
+    execute(
+        db,
+        "INSERT INTO borrowings (id, student_id, book_id, book_copy_id, tracking_code, borrowed_date, due_date, status)
+         VALUES (?1, ?2, ?3, ?4, (SELECT tracking_code FROM book_copies WHERE id = ?4), ?5, ?6, 'active')",
+        (&loan_id, student_id, book_id, copy_id, borrowed_date.to_string(), due_date.to_string()),
+    );
+    execute(db, "UPDATE book_copies SET status = 'borrowed' WHERE id = ?1", [copy_id]);
+    execute(db, "UPDATE books SET available_copies = available_copies - 1 WHERE id = ?1", [book_id]);
+    loan_id
+}
+
+/// An unpaid fine owed by a student, not tied to any loan
+pub(crate) fn seed_fine(db: &DatabaseManager, student_id: &str, amount: f64) -> String {
+    let fine_id = Uuid::new_v4().to_string();
+    execute(
+        db,
+        "INSERT INTO fines (id, student_id, fine_type, amount, status) VALUES (?1, ?2, 'overdue', ?3, 'unpaid')",
+        (&fine_id, student_id, amount),
+    );
+    fine_id
+}
+
+/// A loan to a student that hasn't been stored yet, borrowed today for 14 days
+pub(crate) fn new_borrowing(student_id: &str, book_id: &str, copy_id: Option<&str>) -> Borrowing {
+    let now = Utc::now();
+    let parse = |id: &str| Uuid::parse_str(id).expect("uuid");
+    Borrowing {
+        id: Uuid::new_v4(),
+        student_id: Some(parse(student_id)),
+        book_id: Some(parse(book_id)),
+        borrowed_date: now.date_naive(),
+        due_date: now.date_naive() + Duration::days(14),
+        returned_date: None,
+        status: BorrowingStatus::Active,
+        fine_amount: 0.0,
+        notes: None,
+        issued_by: None,
+        returned_by: None,
+        created_at: now,
+        updated_at: now,
+        fine_paid: false,
+        book_copy_id: copy_id.map(parse),
+        condition_at_issue: "good".to_string(),
+        condition_at_return: None,
+        is_lost: false,
+        tracking_code: None,
+        return_notes: None,
+        copy_condition: None,
+        group_borrowing_id: None,
+        borrower_type: BorrowerType::Student,
+        staff_id: None,
+        due_date_override: false,
+    }
+}
+
+pub(crate) fn seed_category(db: &DatabaseManager, name: &str) -> String {
+    let category_id = Uuid::new_v4().to_string();
+    execute(db, "INSERT INTO categories (id, name) VALUES (?1, ?2)", (&category_id, name));
+    category_id
+}
+
+pub(crate) fn seed_staff(db: &DatabaseManager, staff_number: &str) -> String {
+    let staff_id = Uuid::new_v4().to_string();
+    execute(
+        db,
+        "INSERT INTO staff (id, staff_id, first_name, last_name) VALUES (?1, ?2, 'Staff', ?2)",
+        (&staff_id, staff_number),
+    );
+    staff_id
+}
+
+/// A loan on fixed dates for report fixtures, returned when `returned` is given.
+/// Copies and book counts are left alone.
+pub(crate) fn seed_dated_loan(
+    db: &DatabaseManager,
+    student_id: &str,
+    book_id: &str,
+    borrowed: &str,
+    due: &str,
+    returned: Option<&str>,
+) -> String {
+    let loan_id = Uuid::new_v4().to_string();
+    execute(
+        db,
+        "INSERT INTO borrowings (id, student_id, book_id, borrowed_date, due_date, returned_date, status)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, CASE WHEN ?6 IS NULL THEN 'active' ELSE 'returned' END)",
+        (&loan_id, student_id, book_id, borrowed, due, returned),
+    );
+    loan_id
+}
+
+/// A book's (total_copies, available_copies)
+pub(crate) fn book_counts(db: &DatabaseManager, book_id: &str) -> (i64, i64) {
+    let conn = db.get_connection().lock().unwrap();
+    conn.query_row(
+        "SELECT total_copies, available_copies FROM books WHERE id = ?1",
+        [book_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).unwrap()
+}
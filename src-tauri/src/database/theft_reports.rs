@@ -0,0 +1,308 @@
+use super::{adjust_book_counts, constraint_error, copy_out_of_stock, fine_settings, parse_enum, parse_sqlite_date, parse_sqlite_datetime, row_to_borrowing, BookCopyChange, DatabaseManager, BORROWING_COLUMNS};
+use crate::models::*;
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension, Result};
+use uuid::Uuid;
+
+const THEFT_REPORT_COLUMNS: &str = "id, student_id, book_id, book_copy_id, borrowing_id, expected_tracking_code,
+    returned_tracking_code, theft_reason, reported_date, reported_by, status, investigation_notes,
+    resolved_date, resolved_by, created_at, updated_at";
+
+fn row_to_theft_report(row: &rusqlite::Row) -> Result<TheftReport> {
+    let parse_uuid = |value: Option<String>| value.and_then(|s| Uuid::parse_str(&s).ok());
+    let id_str: String = row.get("id")?;
+    let reported_str: Option<String> = row.get("reported_date")?;
+    let resolved_str: Option<String> = row.get("resolved_date")?;
+    let status_str: Option<String> = row.get("status")?;
+    let created_str: String = row.get("created_at")?;
+    let updated_str: String = row.get("updated_at")?;
+    let created_at = parse_sqlite_datetime(&created_str).unwrap_or_else(|_| Utc::now());
+
+    Ok(TheftReport {
+        id: Uuid::parse_str(&id_str)
+            .map_err(|_| rusqlite::Error::InvalidColumnType(0, "id".to_string(), rusqlite::types::Type::Text))?,
+        student_id: parse_uuid(row.get("student_id")?),
+        book_id: parse_uuid(row.get("book_id")?),
+        book_copy_id: parse_uuid(row.get("book_copy_id")?),
+        borrowing_id: parse_uuid(row.get("borrowing_id")?),
+        expected_tracking_code: row.get("expected_tracking_code")?,
+        returned_tracking_code: row.get("returned_tracking_code")?,
+        theft_reason: row.get("theft_reason")?,
+        reported_date: reported_str.as_deref().map(parse_sqlite_date).transpose()?.unwrap_or(created_at.date_naive()),
+        reported_by: parse_uuid(row.get("reported_by")?),
+        status: status_str.as_deref().and_then(parse_enum).unwrap_or(TheftStatus::Reported),
+        investigation_notes: row.get("investigation_notes")?,
+        resolved_date: resolved_str.as_deref().map(parse_sqlite_date).transpose()?,
+        resolved_by: parse_uuid(row.get("resolved_by")?),
+        created_at,
+        updated_at: parse_sqlite_datetime(&updated_str).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn find_theft_report(conn: &Connection, report_id: &str) -> Result<TheftReport> {
+    conn.query_row(
+        &format!("SELECT {} FROM theft_reports WHERE id = ?1", THEFT_REPORT_COLUMNS),
+        [report_id],
+        row_to_theft_report,
+    )
+}
+
+// Reported -> Investigating -> Resolved/Closed; a resolved report can still be closed
+fn can_move_to(from: &TheftStatus, to: &TheftStatus) -> bool {
+    use TheftStatus::*;
+    matches!(
+        (from, to),
+        (Reported, Investigating) | (Reported, Resolved) | (Reported, Closed)
+            | (Investigating, Resolved) | (Investigating, Closed)
+            | (Resolved, Closed)
+    )
+}
+
+// Files the report inside the caller's transaction and takes the issued copy out of stock
+fn file_theft_report(
+    conn: &Connection,
+    borrowing_id: &str,
+    returned_tracking_code: &str,
+    theft_reason: Option<&str>,
+    reported_by: Option<&str>,
+) -> Result<(TheftReport, Option<BookCopyChange>)> {
+    let returned_tracking_code = returned_tracking_code.trim();
+    if returned_tracking_code.is_empty() {
+        return Err(constraint_error("The returned tracking code is required"));
+    }
+
+    let (student_id, book_id, book_copy_id, expected): (Option<String>, Option<String>, Option<String>, Option<String>) = conn.query_row(
+        "SELECT b.student_id, b.book_id, b.book_copy_id, COALESCE(b.tracking_code, bc.tracking_code)
+         FROM borrowings b
+         LEFT JOIN book_copies bc ON b.book_copy_id = bc.id
+         WHERE b.id = ?1 AND b.deleted = 0",
+        [borrowing_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+
+    let expected = expected
+        .ok_or_else(|| constraint_error(format!("Borrowing {} has no tracking code on record", borrowing_id)))?;
+    if expected.trim().eq_ignore_ascii_case(returned_tracking_code) {
+        return Err(constraint_error(format!("Tracking code {} matches the copy that was issued", returned_tracking_code)));
+    }
+
+    let report_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    conn.execute(
+        "INSERT INTO theft_reports (id, student_id, book_id, book_copy_id, borrowing_id, expected_tracking_code,
+         returned_tracking_code, theft_reason, reported_date, reported_by, status, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, date('now'), ?9, 'reported', ?10, ?10)",
+        rusqlite::params![
+            &report_id,
+            &student_id,
+            &book_id,
+            &book_copy_id,
+            borrowing_id,
+            expected.trim(),
+            returned_tracking_code,
+            theft_reason.map(str::trim).filter(|r| !r.is_empty()),
+            reported_by,
+            &now,
+        ],
+    )?;
+
+    let mut copy_change = None;
+    if let Some(copy_id) = &book_copy_id {
+        let previous: Option<(String, String)> = conn.query_row(
+            "SELECT COALESCE(condition, 'good'), COALESCE(status, 'available') FROM book_copies WHERE id = ?1 AND deleted = 0",
+            [copy_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+
+        if let Some((condition, old_status)) = previous {
+            conn.execute(
+                "UPDATE book_copies SET status = 'stolen', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+                [copy_id],
+            )?;
+
+            let mut book_counts = None;
+            if let Some(book_id) = book_id.as_deref().filter(|_| !copy_out_of_stock(&condition, &old_status)) {
+                let available_delta = if old_status == "available" { -1 } else { 0 };
+                book_counts = Some(adjust_book_counts(conn, book_id, -1, available_delta)?);
+            }
+
+            copy_change = Some(BookCopyChange {
+                copy_id: copy_id.clone(),
+                book_id: book_id.clone(),
+                condition,
+                status: "stolen".to_string(),
+                book_total_copies: book_counts.map(|(total, _)| total),
+                book_available_copies: book_counts.map(|(_, available)| available),
+            });
+        }
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO flagged_codes (code, reason, book_copy_id, flagged_by) VALUES (?1, ?2, ?3, ?4)",
+        (expected.trim(), format!("Stolen - theft report {}", report_id), &book_copy_id, reported_by),
+    )?;
+
+    let report = find_theft_report(conn, &report_id)?;
+    Ok((report, copy_change))
+}
+
+/// Check the tracking code handed back against the one issued, inside the caller's
+/// transaction. On a mismatch a theft report is filed, the borrowing is marked lost
+/// instead of returned and the configured lost book fine is raised; None means the
+/// codes match (or nothing was issued) and a normal return can go ahead.
+pub(super) fn report_mismatched_return(
+    conn: &Connection,
+    borrowing_id: &str,
+    returned_tracking_code: &str,
+    reported_by: Option<&str>,
+) -> Result<Option<(TheftReport, Option<BookCopyChange>, Option<Fine>)>> {
+    let (status, expected): (String, Option<String>) = conn.query_row(
+        "SELECT COALESCE(b.status, 'active'), COALESCE(b.tracking_code, bc.tracking_code)
+         FROM borrowings b
+         LEFT JOIN book_copies bc ON b.book_copy_id = bc.id
+         WHERE b.id = ?1 AND b.deleted = 0",
+        [borrowing_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if status == "returned" {
+        return Err(constraint_error(format!("Borrowing {} has already been returned", borrowing_id)));
+    }
+    match expected.as_deref().map(str::trim) {
+        Some(expected) if !expected.eq_ignore_ascii_case(returned_tracking_code.trim()) => {}
+        _ => return Ok(None),
+    }
+
+    let reason = format!("Returned copy {} does not match the issued copy", returned_tracking_code.trim());
+    let (report, copy_change) = file_theft_report(conn, borrowing_id, returned_tracking_code, Some(&reason), reported_by)?;
+    conn.execute(
+        "UPDATE borrowings SET is_lost = 1, status = 'lost', updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = ?1",
+        [borrowing_id],
+    )?;
+
+    let fine = match fine_settings::fine_rate(conn, &FineType::LostBook)?.filter(|amount| *amount > 0.0) {
+        Some(amount) => {
+            let borrowing = conn.query_row(
+                &format!("SELECT {} FROM borrowings WHERE id = ?1", BORROWING_COLUMNS),
+                [borrowing_id],
+                row_to_borrowing,
+            )?;
+            let created_by = reported_by.and_then(|id| Uuid::parse_str(id).ok());
+            Some(fine_settings::raise_fine(conn, &borrowing, FineType::LostBook, amount, reason, created_by)?)
+        }
+        None => None,
+    };
+
+    Ok(Some((report, copy_change, fine)))
+}
+
+impl DatabaseManager {
+    /// Record that the copy handed back for a borrowing carries a different tracking
+    /// code from the one issued. The issued copy is marked stolen, taken out of the
+    /// book's stock and its code flagged so it alerts if it turns up again.
+    pub async fn create_theft_report(
+        &self,
+        borrowing_id: &str,
+        returned_tracking_code: &str,
+        theft_reason: Option<&str>,
+        reported_by: Option<&str>,
+    ) -> Result<(TheftReport, Option<BookCopyChange>)> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+        let filed = file_theft_report(&tx, borrowing_id, returned_tracking_code, theft_reason, reported_by)?;
+        tx.commit()?;
+        Ok(filed)
+    }
+
+    pub async fn get_theft_reports(&self, status: Option<&str>) -> Result<Vec<TheftReport>> {
+        let conn = self.lock_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM theft_reports
+             WHERE (?1 IS NULL OR status = ?1)
+             ORDER BY reported_date DESC, created_at DESC",
+            THEFT_REPORT_COLUMNS
+        ))?;
+        let reports = stmt.query_map([status], row_to_theft_report)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(reports)
+    }
+
+    /// Move a report along its workflow. Notes are appended to the existing
+    /// investigation notes, and resolving or closing stamps the resolved date.
+    pub async fn update_theft_report_status(
+        &self,
+        report_id: &str,
+        status: TheftStatus,
+        notes: Option<&str>,
+        resolved_by: Option<&str>,
+    ) -> Result<TheftReport> {
+        let conn = self.lock_connection()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let report = find_theft_report(&tx, report_id)?;
+        if !can_move_to(&report.status, &status) {
+            let from = format!("{:?}", report.status).to_lowercase();
+            let to = format!("{:?}", status).to_lowercase();
+            return Err(constraint_error(format!("Theft report {} cannot move from {} to {}", report_id, from, to)));
+        }
+
+        let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+        let investigation_notes = match (report.investigation_notes.as_deref().filter(|n| !n.trim().is_empty()), notes) {
+            (Some(existing), Some(new)) => Some(format!("{}\n{}", existing, new)),
+            (existing, new) => new.or(existing).map(str::to_string),
+        };
+        let finished = matches!(status, TheftStatus::Resolved | TheftStatus::Closed);
+
+        tx.execute(
+            "UPDATE theft_reports SET status = ?2, investigation_notes = ?3,
+             resolved_date = CASE WHEN ?4 THEN COALESCE(resolved_date, date('now')) ELSE resolved_date END,
+             resolved_by = CASE WHEN ?4 THEN COALESCE(?5, resolved_by) ELSE resolved_by END,
+             updated_at = ?6 WHERE id = ?1",
+            (
+                report_id,
+                format!("{:?}", status).to_lowercase(),
+                &investigation_notes,
+                finished,
+                resolved_by,
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            ),
+        )?;
+
+        let report = find_theft_report(&tx, report_id)?;
+        tx.commit()?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn resolving_a_theft_report_stamps_the_date_and_keeps_the_notes() {
+        let db = test_db();
+        let (book_id, copies) = seed_book(&db, "THEFT", 2);
+        let student_id = seed_student(&db, "ADM-1");
+        let loan_id = seed_loan(&db, &student_id, &book_id, &copies[0], 7);
+
+        let (report, _) = db.create_theft_report(&loan_id, "SOMETHING-ELSE", None, None).await.unwrap();
+        let report_id = report.id.to_string();
+        assert_eq!(report.status, TheftStatus::Reported);
+        assert_eq!(report.resolved_date, None);
+
+        let investigating = db.update_theft_report_status(&report_id, TheftStatus::Investigating, Some("Called parents"), None).await.unwrap();
+        assert_eq!(investigating.resolved_date, None);
+
+        let librarian = Uuid::new_v4().to_string();
+        let resolved = db.update_theft_report_status(&report_id, TheftStatus::Resolved, Some(" Copy recovered "), Some(&librarian)).await.unwrap();
+        assert_eq!(resolved.status, TheftStatus::Resolved);
+        assert_eq!(resolved.resolved_date, Some(Utc::now().date_naive()));
+        assert_eq!(resolved.resolved_by.map(|id| id.to_string()), Some(librarian));
+        assert_eq!(resolved.investigation_notes.as_deref(), Some("Called parents\nCopy recovered"));
+
+        // A resolved report can only be closed, never reopened
+        assert!(db.update_theft_report_status(&report_id, TheftStatus::Investigating, None, None).await.is_err());
+        let closed = db.update_theft_report_status(&report_id, TheftStatus::Closed, None, None).await.unwrap();
+        assert_eq!(closed.resolved_date, resolved.resolved_date);
+        assert_eq!(closed.investigation_notes, resolved.investigation_notes);
+    }
+}
@@ -0,0 +1,242 @@
+use super::hashing::{compute_row_hash, row_as_json};
+use super::settings::{read_setting, TRASH_RETENTION_DAYS_KEY};
+use super::{constraint_error, DatabaseManager};
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+// Local-only columns left out of the payload re-sent when a record is restored
+const LOCAL_COLUMNS: &[&str] = &["content_hash", "pushed_hash", "synced", "sync_version", "deleted"];
+
+// Children before parents; books still referenced by a copy wait for the copy to go first
+const PURGE_TABLES: &[(&str, &str)] = &[
+    ("fines", ""),
+    ("borrowings", ""),
+    ("book_copies", ""),
+    ("books", " AND NOT EXISTS (SELECT 1 FROM book_copies bc WHERE bc.book_id = books.id)"),
+    ("students", ""),
+    ("staff", ""),
+    ("classes", ""),
+    ("categories", ""),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeTableCount {
+    pub table_name: String,
+    pub rows: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgePreview {
+    pub older_than_days: i64,
+    pub tables: Vec<PurgeTableCount>,
+    pub total_rows: i64,
+    pub confirmation_token: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletedRecord {
+    pub id: String,
+    pub table_name: String,
+    pub label: String,
+    pub deleted_at: String,
+    pub restorable: bool,
+}
+
+// Display label for each table the trash view covers
+fn trash_label_sql(table: &str) -> Result<&'static str> {
+    match table {
+        "books" => Ok("title || ' - ' || author"),
+        "students" => Ok("first_name || ' ' || last_name || ' (' || admission_number || ')'"),
+        "staff" => Ok("first_name || ' ' || last_name || ' (' || staff_id || ')'"),
+        _ => Err(constraint_error(format!("{} has no trash view", table))),
+    }
+}
+
+fn retention_days(conn: &Connection) -> Result<i64> {
+    Ok(read_setting(conn, TRASH_RETENTION_DAYS_KEY)?.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS))
+}
+
+fn purge_counts(conn: &Connection, cutoff: &str) -> Result<Vec<PurgeTableCount>> {
+    PURGE_TABLES.iter()
+        .map(|(table, extra)| {
+            let rows = conn.query_row(
+                &format!("SELECT COUNT(*) FROM {table} WHERE deleted = 1 AND datetime(updated_at) < datetime('now', ?1){extra}"),
+                [cutoff],
+                |row| row.get(0),
+            )?;
+            Ok(PurgeTableCount { table_name: table.to_string(), rows })
+        })
+        .collect()
+}
+
+// Ties the token to the exact counts shown, so anything new to purge needs a fresh preview
+fn purge_token(older_than_days: i64, tables: &[PurgeTableCount]) -> String {
+    compute_row_hash(&serde_json::json!({
+        "older_than_days": older_than_days,
+        "tables": tables,
+    }))
+}
+
+impl DatabaseManager {
+    /// Rows purge_deleted would permanently remove, per table, with the token needed to go ahead
+    pub async fn get_purge_preview(&self, older_than_days: i64) -> Result<PurgePreview> {
+        let conn = self.lock_connection()?;
+        let tables = purge_counts(&conn, &format!("-{} days", older_than_days))?;
+
+        Ok(PurgePreview {
+            older_than_days,
+            total_rows: tables.iter().map(|t| t.rows).sum(),
+            confirmation_token: purge_token(older_than_days, &tables),
+            tables,
+        })
+    }
+
+    /// Permanently remove soft-deleted rows last touched before the cutoff
+    pub async fn purge_deleted(&self, older_than_days: i64, confirmation_token: &str) -> Result<Vec<PurgeTableCount>> {
+        let conn = self.lock_connection()?;
+        let cutoff = format!("-{} days", older_than_days);
+
+        let tx = conn.unchecked_transaction()?;
+        if purge_token(older_than_days, &purge_counts(&tx, &cutoff)?) != confirmation_token {
+            return Err(constraint_error("Confirmation token does not match - preview the purge again"));
+        }
+
+        let mut purged = Vec::new();
+        for (table, extra) in PURGE_TABLES {
+            let rows = tx.execute(
+                &format!("DELETE FROM {table} WHERE deleted = 1 AND datetime(updated_at) < datetime('now', ?1){extra}"),
+                [&cutoff],
+            )?;
+            purged.push(PurgeTableCount { table_name: table.to_string(), rows: rows as i64 });
+        }
+
+        tx.commit()?;
+        Ok(purged)
+    }
+
+    fn deleted_records(&self, table: &str) -> Result<Vec<DeletedRecord>> {
+        let label = trash_label_sql(table)?;
+        let conn = self.lock_connection()?;
+        let cutoff = format!("-{} days", retention_days(&conn)?);
+
+        // updated_at is stamped by the soft delete, so it doubles as the deletion time
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, {label}, updated_at, datetime(updated_at) >= datetime('now', ?1)
+             FROM {table} WHERE deleted = 1
+             ORDER BY updated_at DESC"
+        ))?;
+
+        let records = stmt.query_map([&cutoff], |row| {
+            Ok(DeletedRecord {
+                id: row.get(0)?,
+                table_name: table.to_string(),
+                label: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                deleted_at: row.get(2)?,
+                restorable: row.get(3)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+
+    /// Undo a soft delete made within the retention window. Returns the restored row
+    /// without local bookkeeping columns so it can be queued for sync again.
+    fn restore_record(&self, table: &str, id: &str) -> Result<Value> {
+        trash_label_sql(table)?;
+        let conn = self.lock_connection()?;
+        let cutoff = format!("-{} days", retention_days(&conn)?);
+
+        let restored = conn.execute(
+            &format!(
+                "UPDATE {table} SET deleted = 0, updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+                 WHERE id = ?1 AND deleted = 1 AND datetime(updated_at) >= datetime('now', ?2)"
+            ),
+            (id, &cutoff),
+        )?;
+
+        if restored == 0 {
+            let deleted: Option<bool> = conn.query_row(
+                &format!("SELECT deleted = 1 FROM {table} WHERE id = ?1"),
+                [id],
+                |row| row.get(0),
+            ).optional()?;
+
+            return match deleted {
+                None => Err(rusqlite::Error::QueryReturnedNoRows),
+                Some(false) => Err(constraint_error(format!("{} is not deleted", id))),
+                Some(true) => Err(constraint_error(format!("{} was deleted outside the retention window", id))),
+            };
+        }
+
+        let mut row = conn.query_row(&format!("SELECT * FROM {table} WHERE id = ?1"), [id], row_as_json)?;
+        if let Value::Object(fields) = &mut row {
+            for column in LOCAL_COLUMNS {
+                fields.remove(*column);
+            }
+        }
+        Ok(row)
+    }
+
+    pub async fn get_deleted_books(&self) -> Result<Vec<DeletedRecord>> {
+        self.deleted_records("books")
+    }
+
+    pub async fn get_deleted_students(&self) -> Result<Vec<DeletedRecord>> {
+        self.deleted_records("students")
+    }
+
+    pub async fn get_deleted_staff(&self) -> Result<Vec<DeletedRecord>> {
+        self.deleted_records("staff")
+    }
+
+    pub async fn restore_book(&self, book_id: &str) -> Result<Value> {
+        self.restore_record("books", book_id)
+    }
+
+    pub async fn restore_student(&self, student_id: &str) -> Result<Value> {
+        self.restore_record("students", student_id)
+    }
+
+    pub async fn restore_staff(&self, staff_id: &str) -> Result<Value> {
+        self.restore_record("staff", staff_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::test_support::*;
+
+    #[tokio::test]
+    async fn deleted_book_leaves_get_books_until_restored() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "TRASH", 1);
+        let listed = |books: Vec<crate::models::Book>| books.iter().any(|b| b.id.to_string() == book_id);
+
+        db.delete_book(&book_id).await.unwrap();
+        assert!(!listed(db.get_books().await.unwrap()));
+        let trash = db.get_deleted_books().await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].label, "Book TRASH - Test Author");
+        assert!(trash[0].restorable);
+
+        let restored = db.restore_book(&book_id).await.unwrap();
+        assert_eq!(restored["id"], book_id.as_str());
+        assert!(restored.get("deleted").is_none());
+        assert!(listed(db.get_books().await.unwrap()));
+        assert!(db.get_deleted_books().await.unwrap().is_empty());
+        assert!(db.restore_book(&book_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn deletion_outside_the_retention_window_cannot_be_restored() {
+        let db = test_db();
+        let (book_id, _) = seed_book(&db, "OLD", 1);
+        execute(&db, "UPDATE books SET deleted = 1, updated_at = datetime('now', '-90 days') WHERE id = ?1", [&book_id]);
+
+        assert!(!db.get_deleted_books().await.unwrap()[0].restorable);
+        assert!(db.restore_book(&book_id).await.is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, NaiveDate, Utc};
+use chrono::{DateTime, Local, NaiveDate, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -197,6 +197,8 @@ pub struct Student {
     pub academic_year: String,
     pub is_repeating: bool,
     pub legacy_student_id: Option<i32>,
+    #[serde(default)]
+    pub gender: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -241,6 +243,8 @@ pub struct Borrowing {
     pub group_borrowing_id: Option<Uuid>,
     pub borrower_type: BorrowerType,
     pub staff_id: Option<Uuid>,
+    #[serde(default)]
+    pub due_date_override: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -297,10 +301,10 @@ pub struct FineSetting {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TheftReport {
     pub id: Uuid,
-    pub student_id: Uuid,
-    pub book_id: Uuid,
-    pub book_copy_id: Uuid,
-    pub borrowing_id: Uuid,
+    pub student_id: Option<Uuid>,
+    pub book_id: Option<Uuid>,
+    pub book_copy_id: Option<Uuid>,
+    pub borrowing_id: Option<Uuid>,
     pub expected_tracking_code: String,
     pub returned_tracking_code: String,
     pub theft_reason: Option<String>,
@@ -314,6 +318,227 @@ pub struct TheftReport {
     pub updated_at: DateTime<Utc>,
 }
 
+// Overdue escalation policy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscalationAction {
+    Reminder,
+    Fine,
+    ParentNotice,
+    Block,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationStage {
+    pub name: String,
+    pub min_days_overdue: i64,
+    pub action: EscalationAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationPolicy {
+    pub stages: Vec<EscalationStage>,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        let stage = |name: &str, min_days_overdue: i64, action: EscalationAction| EscalationStage {
+            name: name.to_string(),
+            min_days_overdue,
+            action,
+        };
+
+        Self {
+            stages: vec![
+                stage("reminder", 1, EscalationAction::Reminder),
+                stage("fine", 7, EscalationAction::Fine),
+                stage("parent_notice", 14, EscalationAction::ParentNotice),
+                stage("block", 30, EscalationAction::Block),
+            ],
+        }
+    }
+}
+
+impl EscalationPolicy {
+    /// The deepest stage whose threshold has been reached, if any
+    pub fn stage_for(&self, days_overdue: i64) -> Option<&EscalationStage> {
+        self.stages
+            .iter()
+            .filter(|stage| days_overdue >= stage.min_days_overdue)
+            .max_by_key(|stage| stage.min_days_overdue)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationRecord {
+    pub borrowing_id: String,
+    pub stage: String,
+    pub action: EscalationAction,
+    pub days_overdue: i64,
+    pub newly_recorded: bool,
+}
+
+// Tracking code of a lost/stolen copy that raises an alert when scanned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlaggedCode {
+    pub code: String,
+    pub reason: String,
+    pub book_copy_id: Option<String>,
+    pub flagged_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Payload sent by the UI when a borrowed book comes back
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReturnData {
+    #[serde(default, alias = "condition")]
+    pub condition_at_return: Option<String>,
+    #[serde(default, alias = "notes")]
+    pub return_notes: Option<String>,
+    #[serde(default)]
+    pub returned_by: Option<Uuid>,
+    #[serde(default)]
+    pub returned_date: Option<NaiveDate>,
+    // Code on the copy handed back, checked against the issued one when verification is on
+    #[serde(default, alias = "returned_tracking_code")]
+    pub tracking_code: Option<String>,
+}
+
+// Fields the UI may change on a single book copy; omitted fields are left as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BookCopyUpdate {
+    #[serde(default)]
+    pub condition: Option<BookCondition>,
+    #[serde(default)]
+    pub status: Option<CopyStatus>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tracking_code: Option<String>,
+}
+
+// Standard loan rules applied when a borrowing is created
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoanRules {
+    pub loan_period_days: i64,
+    pub max_renewals: i64,
+    pub staff_loan_period_days: i64,
+    pub staff_max_active_loans: i64,
+}
+
+impl Default for LoanRules {
+    fn default() -> Self {
+        Self { loan_period_days: 14, max_renewals: 2, staff_loan_period_days: 30, staff_max_active_loans: 10 }
+    }
+}
+
+impl LoanRules {
+    pub fn loan_period_for(&self, borrower_type: &BorrowerType) -> i64 {
+        match borrower_type {
+            BorrowerType::Student => self.loan_period_days,
+            BorrowerType::Staff => self.staff_loan_period_days,
+        }
+    }
+
+    /// Latest due date allowed without an override
+    pub fn max_due_date(&self, borrower_type: &BorrowerType, borrowed_date: NaiveDate) -> NaiveDate {
+        borrowed_date + chrono::Duration::days(self.loan_period_for(borrower_type))
+    }
+
+    pub fn renewals_remaining(&self, renewal_count: i64) -> i64 {
+        (self.max_renewals - renewal_count).max(0)
+    }
+}
+
+// Startup auto-sync bookkeeping, persisted so quick restarts can't stack syncs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoSyncState {
+    pub seeded: bool,
+    pub last_auto_sync: Option<DateTime<Utc>>,
+    pub min_interval_minutes: i64,
+}
+
+impl Default for AutoSyncState {
+    fn default() -> Self {
+        Self {
+            seeded: false,
+            last_auto_sync: None,
+            min_interval_minutes: 60,
+        }
+    }
+}
+
+impl AutoSyncState {
+    /// Auto-sync only seeds an empty database, and at most once per interval
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        !self.seeded && self.last_auto_sync.map_or(true, |last| {
+            now - last >= chrono::Duration::minutes(self.min_interval_minutes)
+        })
+    }
+}
+
+// Nightly housekeeping schedule; hour is local time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceSchedule {
+    pub enabled: bool,
+    pub hour: u32,
+    pub backup_dir: Option<String>,
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+impl Default for MaintenanceSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hour: 2,
+            backup_dir: None,
+            last_run: None,
+        }
+    }
+}
+
+impl MaintenanceSchedule {
+    /// Once per local day, at or after the configured hour. A machine that was off
+    /// at that hour catches up the next time it runs.
+    pub fn is_due(&self, now: DateTime<Local>) -> bool {
+        self.enabled && now.hour() >= self.hour && self.last_run.map_or(true, |last| {
+            last.with_timezone(&Local).date_naive() < now.date_naive()
+        })
+    }
+}
+
+// Rolling snapshots written to the backups folder next to the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSchedule {
+    pub enabled: bool,
+    pub interval_hours: i64,
+    pub keep: usize,
+    pub last_backup: Option<DateTime<Utc>>,
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            keep: 7,
+            last_backup: None,
+        }
+    }
+}
+
+impl BackupSchedule {
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.enabled && self.last_backup.map_or(true, |last| {
+            now - last >= chrono::Duration::hours(self.interval_hours)
+        })
+    }
+}
+
 // Sync-related models
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncLog {
@@ -26,6 +26,7 @@ impl ConflictResolver for DefaultConflictResolver {
                 }
             }
             ConflictResolutionStrategy::Merge => self.merge_values(conflict).await,
+            ConflictResolutionStrategy::FieldMerge => field_merge(conflict),
             ConflictResolutionStrategy::Manual => {
                 Err(SyncError::Conflict("Manual resolution required".to_string()))
             }
@@ -33,6 +34,46 @@ impl ConflictResolver for DefaultConflictResolver {
     }
 }
 
+// Whether the local value should win for one column that differs between the sides.
+// With no base to compare against, both sides count as having changed it.
+fn local_wins_column(local: &Value, remote: &Value, base: Option<&Value>, remote_newer: bool) -> bool {
+    let local_changed = base.map_or(true, |base| base != local);
+    let remote_changed = base.map_or(true, |base| base != remote);
+    match (local_changed, remote_changed) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => !remote_newer,
+    }
+}
+
+/// Merge two versions of a record column by column. Columns only one side changed
+/// keep that change, so a local shelf_location edit and a remote description edit
+/// both survive; columns changed on both sides go to the newer updated_at.
+pub fn field_merge(conflict: &SyncConflict) -> SyncResult<Value> {
+    let remote_newer = conflict.remote_metadata.updated_at > conflict.local_metadata.updated_at;
+
+    let (Some(local_obj), Some(remote_obj)) = (conflict.local.as_object(), conflict.remote.as_object()) else {
+        // A single column, e.g. from FieldLevelConflictResolver
+        let local_wins = conflict.local == conflict.remote
+            || local_wins_column(&conflict.local, &conflict.remote, conflict.base.as_ref(), remote_newer);
+        return Ok(if local_wins { conflict.local.clone() } else { conflict.remote.clone() });
+    };
+    let base_obj = conflict.base.as_ref().and_then(Value::as_object);
+
+    let mut merged = remote_obj.clone();
+    for (key, local) in local_obj {
+        let Some(remote) = remote_obj.get(key) else {
+            merged.insert(key.clone(), local.clone());
+            continue;
+        };
+        if local != remote && local_wins_column(local, remote, base_obj.and_then(|base| base.get(key)), remote_newer) {
+            merged.insert(key.clone(), local.clone());
+        }
+    }
+
+    Ok(Value::Object(merged))
+}
+
 impl DefaultConflictResolver {
     async fn merge_values(&self, conflict: &SyncConflict) -> SyncResult<Value> {
         let mut merged = json!({});
@@ -199,6 +240,7 @@ impl ConflictResolver for FieldLevelConflictResolver {
                         remote: remote.clone(),
                         local_metadata: conflict.local_metadata.clone(),
                         remote_metadata: conflict.remote_metadata.clone(),
+                        base: conflict.base.as_ref().and_then(|base| base.get(key)).cloned(),
                     };
                     
                     let field_resolver = DefaultConflictResolver;
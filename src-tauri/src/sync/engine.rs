@@ -1,18 +1,23 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 
 use crate::sync::{
-    error::SyncResult,
-    traits::{ConflictResolver, LocalDataStore, RemoteDataSource, SyncStrategy, SyncSummary, SyncStatus},
+    error::{SyncError, SyncResult},
+    rows::{RemoteBook, RemoteCategory, RemoteRow, RemoteStaff, RemoteStudent},
+    traits::{ConflictResolutionStrategy, ConflictResolver, LocalDataStore, RemoteDataSource, SyncStrategy, SyncSummary, SyncStatus},
 };
 
 pub struct SyncEngine {
     remote: Arc<dyn RemoteDataSource>,
     local: Arc<dyn LocalDataStore>,
     conflict_resolver: Arc<dyn ConflictResolver>,
+    conflict_strategy: ConflictResolutionStrategy,
     strategies: Arc<RwLock<std::collections::HashMap<String, Arc<dyn SyncStrategy>>>>,
     pub status: Arc<RwLock<SyncStatus>>,
+    // Last connectivity result, readable from sync code without touching the status lock
+    online: Arc<AtomicBool>,
     pub db: Arc<crate::database::DatabaseManager>,
     pub config: crate::sync::remote::supabase::SupabaseConfig,
     pub client: reqwest::Client,
@@ -34,6 +39,7 @@ impl SyncEngine {
             remote,
             local,
             conflict_resolver,
+            conflict_strategy: ConflictResolutionStrategy::NewestWins,
             strategies: Arc::new(RwLock::new(std::collections::HashMap::new())),
             status: Arc::new(RwLock::new(SyncStatus {
             is_online: false,
@@ -43,6 +49,7 @@ impl SyncEngine {
             database_initialized: false,
             initial_sync_completed: false,
         })),
+            online: Arc::new(AtomicBool::new(false)),
             db,
             config,
             client,
@@ -92,11 +99,17 @@ impl SyncEngine {
 
     pub async fn check_connectivity(&self) -> bool {
         let is_online = self.remote.check_connectivity().await;
+        self.online.store(is_online, Ordering::Relaxed);
         let mut status = self.status.write().await;
         status.is_online = is_online;
         is_online
     }
 
+    /// Result of the most recent connectivity check; never waits on the status lock
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
     pub async fn trigger_data_pull(&self) -> SyncResult<()> {
         use chrono::Utc;
         
@@ -148,7 +161,7 @@ impl SyncEngine {
             }
             
             info!("Data pull completed successfully");
-            Ok::<(), anyhow::Error>(())
+            Ok::<(), SyncError>(())
         }.await;
         
         let mut status = self.status.write().await;
@@ -163,25 +176,26 @@ impl SyncEngine {
             Err(e) => {
                 status.last_error = Some(e.to_string());
                 warn!("Data pull failed: {}", e);
-                return Err(crate::sync::error::SyncError::InvalidData(e.to_string()));
+                return Err(e);
             }
         }
         
         Ok(())
     }
 
-    async fn fetch_books_from_supabase(&self) -> Result<Vec<crate::models::Book>, anyhow::Error> {
-        use crate::models::{Book, BookStatus, BookCondition};
-        use uuid::Uuid;
-        use chrono::{DateTime, Utc};
+    /// Page through a whole Supabase table, parsing each row into its DTO. Bad
+    /// rows are logged one by one and then fail the fetch, so drift in the remote
+    /// schema stops the pull instead of being stored as placeholder data.
+    async fn fetch_remote_rows<R: RemoteRow>(&self, table: &str) -> SyncResult<Vec<R::Model>> {
         use std::time::Duration;
-        
-        let mut books = Vec::new();
+
+        let mut records = Vec::new();
+        let mut failures = Vec::new();
         let mut offset = 0;
         let limit = 1000;
-        
+
         loop {
-            let url = format!("{}/rest/v1/books?select=*&limit={}&offset={}", self.config.url, limit, offset);
+            let url = format!("{}/rest/v1/{}?select=*&limit={}&offset={}", self.config.url, table, limit, offset);
             let response = self.client
                 .get(&url)
                 .header("apikey", &self.config.anon_key)
@@ -191,253 +205,62 @@ impl SyncEngine {
                 .await?;
 
             if !response.status().is_success() {
-                return Err(anyhow::anyhow!("Failed to fetch books: HTTP {}", response.status()));
+                return Err(SyncError::from_status(response.status(), &format!("Failed to fetch {}", table)));
             }
 
-            let json: serde_json::Value = response.json().await?;
-            
-            if let Some(array) = json.as_array() {
-                for item in array {
-                    let book = Book {
-                        id: Uuid::parse_str(item["id"].as_str().unwrap_or_default()).unwrap_or_else(|_| Uuid::new_v4()),
-                        title: item["title"].as_str().unwrap_or("Unknown").to_string(),
-                        author: item["author"].as_str().unwrap_or("Unknown").to_string(),
-                        isbn: item["isbn"].as_str().map(|s| s.to_string()),
-                        genre: item["genre"].as_str().map(|s| s.to_string()),
-                        publisher: item["publisher"].as_str().map(|s| s.to_string()),
-                        publication_year: item["publication_year"].as_i64().map(|y| y as i32),
-                        category_id: item["category_id"].as_str().and_then(|s| Uuid::parse_str(s).ok()),
-                        total_copies: item["total_copies"].as_i64().unwrap_or(1) as i32,
-                        available_copies: item["available_copies"].as_i64().unwrap_or(1) as i32,
-                        shelf_location: item["shelf_location"].as_str().map(|s| s.to_string()),
-                        cover_image_url: item["cover_image_url"].as_str().map(|s| s.to_string()),
-                        description: item["description"].as_str().map(|s| s.to_string()),
-                        status: BookStatus::Available,
-                        condition: item["condition"].as_str().and_then(|s| match s {
-                            "excellent" => Some(BookCondition::Excellent),
-                            "good" => Some(BookCondition::Good),
-                            "fair" => Some(BookCondition::Fair),
-                            "poor" => Some(BookCondition::Poor),
-                            "damaged" => Some(BookCondition::Damaged),
-                            "lost" => Some(BookCondition::Lost),
-                            "stolen" => Some(BookCondition::Stolen),
-                            _ => None,
-                        }),
-                        book_code: item["book_code"].as_str().map(|s| s.to_string()),
-                        acquisition_year: item["acquisition_year"].as_i64().map(|y| y as i32),
-                        legacy_book_id: item["legacy_book_id"].as_i64().map(|i| i as i32),
-                        legacy_isbn: item["legacy_isbn"].as_str().map(|s| s.to_string()),
-                        created_at: item["created_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                        updated_at: item["updated_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                    };
-                    books.push(book);
+            let page: Vec<serde_json::Value> = response.json().await?;
+            let page_len = page.len();
+            for (index, item) in page.into_iter().enumerate() {
+                let row_label = item["id"].as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("#{}", offset + index));
+                let parsed = serde_json::from_value::<R>(item)
+                    .map_err(|e| e.to_string())
+                    .and_then(R::into_model);
+                match parsed {
+                    Ok(record) => records.push(record),
+                    Err(e) => {
+                        warn!("Could not read {} row {}: {}", table, row_label, e);
+                        failures.push(format!("{}: {}", row_label, e));
+                    }
                 }
-                if array.len() < limit {
-                    break;
-                }
-            } else {
+            }
+            if page_len < limit {
                 break;
             }
             offset += limit;
         }
-        Ok(books)
-    }
 
-    async fn fetch_categories_from_supabase(&self) -> Result<Vec<crate::models::Category>, anyhow::Error> {
-        use crate::models::Category;
-        use uuid::Uuid;
-        use chrono::{DateTime, Utc};
-        use std::time::Duration;
-        
-        let mut categories = Vec::new();
-        let mut offset = 0;
-        let limit = 1000;
-        
-        loop {
-            let url = format!("{}/rest/v1/categories?select=*&limit={}&offset={}", self.config.url, limit, offset);
-            let response = self.client
-                .get(&url)
-                .header("apikey", &self.config.anon_key)
-                .header("Authorization", format!("Bearer {}", self.config.anon_key))
-                .timeout(Duration::from_secs(30))
-                .send()
-                .await?;
-
-            if !response.status().is_success() {
-                return Err(anyhow::anyhow!("Failed to fetch categories: HTTP {}", response.status()));
-            }
-
-            let json: serde_json::Value = response.json().await?;
-            
-            if let Some(array) = json.as_array() {
-                for item in array {
-                    let category = Category {
-                        id: Uuid::parse_str(item["id"].as_str().unwrap_or_default()).unwrap_or_else(|_| Uuid::new_v4()),
-                        name: item["name"].as_str().unwrap_or("Unknown").to_string(),
-                        description: item["description"].as_str().map(|s| s.to_string()),
-                        created_at: item["created_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                        updated_at: item["updated_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                    };
-                    categories.push(category);
-                }
-                if array.len() < limit {
-                    break;
-                }
-            } else {
-                break;
-            }
-            offset += limit;
+        if !failures.is_empty() {
+            return Err(SyncError::Deserialize(format!(
+                "{} {} rows could not be read (first: {})",
+                failures.len(), table, failures[0]
+            )));
         }
-        Ok(categories)
+        Ok(records)
     }
 
-    async fn fetch_students_from_supabase(&self) -> Result<Vec<crate::models::Student>, anyhow::Error> {
-        use crate::models::Student;
-        use uuid::Uuid;
-        use chrono::{DateTime, Utc, NaiveDate};
-        use std::time::Duration;
-        
-        let mut students = Vec::new();
-        let mut offset = 0;
-        let limit = 1000;
-        
-        loop {
-            let url = format!("{}/rest/v1/students?select=*&limit={}&offset={}", self.config.url, limit, offset);
-            let response = self.client
-                .get(&url)
-                .header("apikey", &self.config.anon_key)
-                .header("Authorization", format!("Bearer {}", self.config.anon_key))
-                .timeout(Duration::from_secs(30))
-                .send()
-                .await?;
-
-            if !response.status().is_success() {
-                return Err(anyhow::anyhow!("Failed to fetch students: HTTP {}", response.status()));
-            }
-
-            let json: serde_json::Value = response.json().await?;
-            
-            if let Some(array) = json.as_array() {
-                for item in array {
-                    let student = Student {
-                        id: Uuid::parse_str(item["id"].as_str().unwrap_or_default()).unwrap_or_else(|_| Uuid::new_v4()),
-                        admission_number: item["admission_number"].as_str().unwrap_or("").to_string(),
-                        first_name: item["first_name"].as_str().unwrap_or("").to_string(),
-                        last_name: item["last_name"].as_str().unwrap_or("").to_string(),
-                        email: item["email"].as_str().map(|s| s.to_string()),
-                        phone: item["phone"].as_str().map(|s| s.to_string()),
-                        class_grade: item["class_grade"].as_str().unwrap_or("").to_string(),
-                        address: item["address"].as_str().map(|s| s.to_string()),
-                        date_of_birth: item["date_of_birth"].as_str()
-                            .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()),
-                        enrollment_date: item["enrollment_date"].as_str()
-                            .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
-                            .unwrap_or_else(|| Utc::now().date_naive()),
-                        status: item["status"].as_str().unwrap_or("active").to_string(),
-                        class_id: item["class_id"].as_str().and_then(|s| Uuid::parse_str(s).ok()),
-                        academic_year: item["academic_year"].as_str().unwrap_or("2024").to_string(),
-                        is_repeating: item["is_repeating"].as_bool().unwrap_or(false),
-                        legacy_student_id: item["legacy_student_id"].as_i64().map(|i| i as i32),
-                        created_at: item["created_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                        updated_at: item["updated_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                    };
-                    students.push(student);
-                }
-                if array.len() < limit {
-                    break;
-                }
-            } else {
-                break;
-            }
-            offset += limit;
-        }
-        Ok(students)
+    async fn fetch_books_from_supabase(&self) -> SyncResult<Vec<crate::models::Book>> {
+        self.fetch_remote_rows::<RemoteBook>("books").await
     }
 
-    async fn fetch_staff_from_supabase(&self) -> Result<Vec<crate::models::Staff>, anyhow::Error> {
-        use crate::models::Staff;
-        use uuid::Uuid;
-        use chrono::{DateTime, Utc};
-        use std::time::Duration;
-        
-        let mut staff_list = Vec::new();
-        let mut offset = 0;
-        let limit = 1000;
-        
-        loop {
-            let url = format!("{}/rest/v1/staff?select=*&limit={}&offset={}", self.config.url, limit, offset);
-            let response = self.client
-                .get(&url)
-                .header("apikey", &self.config.anon_key)
-                .header("Authorization", format!("Bearer {}", self.config.anon_key))
-                .timeout(Duration::from_secs(30))
-                .send()
-                .await?;
+    async fn fetch_categories_from_supabase(&self) -> SyncResult<Vec<crate::models::Category>> {
+        self.fetch_remote_rows::<RemoteCategory>("categories").await
+    }
 
-            if !response.status().is_success() {
-                return Err(anyhow::anyhow!("Failed to fetch staff: HTTP {}", response.status()));
-            }
+    async fn fetch_students_from_supabase(&self) -> SyncResult<Vec<crate::models::Student>> {
+        self.fetch_remote_rows::<RemoteStudent>("students").await
+    }
 
-            let json: serde_json::Value = response.json().await?;
-            
-            if let Some(array) = json.as_array() {
-                for item in array {
-                    let staff = Staff {
-                        id: Uuid::parse_str(item["id"].as_str().unwrap_or_default()).unwrap_or_else(|_| Uuid::new_v4()),
-                        staff_id: item["staff_id"].as_str().unwrap_or("").to_string(),
-                        first_name: item["first_name"].as_str().unwrap_or("").to_string(),
-                        last_name: item["last_name"].as_str().unwrap_or("").to_string(),
-                        email: item["email"].as_str().map(|s| s.to_string()),
-                        phone: item["phone"].as_str().map(|s| s.to_string()),
-                        department: item["department"].as_str().map(|s| s.to_string()),
-                        position: item["position"].as_str().map(|s| s.to_string()),
-                        status: item["status"].as_str().unwrap_or("active").to_string(),
-                        legacy_staff_id: item["legacy_staff_id"].as_i64().map(|i| i as i32),
-                        created_at: item["created_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                        updated_at: item["updated_at"].as_str()
-                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .unwrap_or_else(Utc::now),
-                    };
-                    staff_list.push(staff);
-                }
-                if array.len() < limit {
-                    break;
-                }
-            } else {
-                break;
-            }
-            offset += limit;
-        }
-        Ok(staff_list)
+    async fn fetch_staff_from_supabase(&self) -> SyncResult<Vec<crate::models::Staff>> {
+        self.fetch_remote_rows::<RemoteStaff>("staff").await
     }
 
     #[allow(dead_code)]
     pub async fn sync_table(&self, table_name: &str) -> SyncResult<SyncSummary> {
         let mut status = self.status.write().await;
         if status.is_syncing {
-            return Err(crate::sync::error::SyncError::SyncInProgress);
+            return Err(SyncError::SyncInProgress);
         }
         status.is_syncing = true;
         status.last_error = None;
@@ -467,13 +290,13 @@ impl SyncEngine {
     async fn perform_table_sync(&self, table_name: &str) -> SyncResult<SyncSummary> {
         // Check connectivity
         if !self.check_connectivity().await {
-            return Err(crate::sync::error::SyncError::InvalidData("No internet connection".to_string()));
+            return Err(SyncError::InvalidData("No internet connection".to_string()));
         }
 
         let strategies = self.strategies.read().await;
         let strategy = strategies
             .get(table_name)
-            .ok_or_else(|| crate::sync::error::SyncError::Config(format!("No strategy registered for table: {}", table_name)))?;
+            .ok_or_else(|| SyncError::Config(format!("No strategy registered for table: {}", table_name)))?;
 
         let summary = strategy
             .sync_table(
@@ -481,6 +304,7 @@ impl SyncEngine {
                 self.remote.as_ref(),
                 self.local.as_ref(),
                 self.conflict_resolver.as_ref(),
+                self.conflict_strategy,
             )
             .await?;
 
@@ -491,7 +315,7 @@ impl SyncEngine {
     pub async fn sync_all_tables(&self) -> SyncResult<Vec<SyncSummary>> {
         let mut status = self.status.write().await;
         if status.is_syncing {
-            return Err(crate::sync::error::SyncError::SyncInProgress);
+            return Err(SyncError::SyncInProgress);
         }
         status.is_syncing = true;
         status.last_error = None;
@@ -525,7 +349,7 @@ impl SyncEngine {
     #[allow(dead_code)]
     async fn perform_all_tables_sync(&self) -> SyncResult<Vec<SyncSummary>> {
         if !self.check_connectivity().await {
-            return Err(crate::sync::error::SyncError::InvalidData("No internet connection".to_string()));
+            return Err(SyncError::InvalidData("No internet connection".to_string()));
         }
 
         let strategies = self.strategies.read().await;
@@ -562,11 +386,9 @@ impl SyncEngine {
             loop {
                 interval.tick().await;
                 
-                let current_status = status.read().await;
-                if !current_status.is_online || current_status.is_syncing {
+                if !engine.is_online() || status.read().await.is_syncing {
                     continue;
                 }
-                drop(current_status);
 
                 if let Err(e) = engine.sync_all_tables().await {
                     tracing::error!("Background sync failed: {}", e);
@@ -586,11 +408,10 @@ impl SyncEngine {
 
     #[allow(dead_code)]
     pub async fn initialize(&self) -> SyncResult<()> {
+        // Check connectivity before taking the write lock; check_connectivity takes it too
+        self.check_connectivity().await;
         let mut status = self.status.write().await;
         
-        // Check connectivity
-        status.is_online = self.check_connectivity().await;
-        
         // Initialize database
         status.database_initialized = true;
         
@@ -612,8 +433,10 @@ impl SyncEngine {
             remote: self.remote.clone(),
             local: self.local.clone(),
             conflict_resolver: self.conflict_resolver.clone(),
+            conflict_strategy: self.conflict_strategy,
             strategies: self.strategies.clone(),
             status: self.status.clone(),
+            online: self.online.clone(),
             db: self.db.clone(),
             config: self.config.clone(),
             client: self.client.clone(),
@@ -627,6 +450,7 @@ pub struct SyncEngineBuilder {
     remote: Option<Arc<dyn RemoteDataSource>>,
     local: Option<Arc<dyn LocalDataStore>>,
     conflict_resolver: Option<Arc<dyn ConflictResolver>>,
+    conflict_strategy: ConflictResolutionStrategy,
     strategies: std::collections::HashMap<String, Arc<dyn SyncStrategy>>,
 }
 
@@ -636,6 +460,7 @@ impl SyncEngineBuilder {
             remote: None,
             local: None,
             conflict_resolver: None,
+            conflict_strategy: ConflictResolutionStrategy::NewestWins,
             strategies: std::collections::HashMap::new(),
         }
     }
@@ -655,6 +480,13 @@ impl SyncEngineBuilder {
         self
     }
 
+    // How the resolver settles records changed on both sides; newest wins unless set
+    #[allow(dead_code)]
+    pub fn with_conflict_strategy(mut self, strategy: ConflictResolutionStrategy) -> Self {
+        self.conflict_strategy = strategy;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_strategy(mut self, table_name: String, strategy: Arc<dyn SyncStrategy>) -> Self {
         self.strategies.insert(table_name, strategy);
@@ -663,18 +495,19 @@ impl SyncEngineBuilder {
 
     pub fn build(self) -> SyncResult<SyncEngine> {
         let remote = self.remote.ok_or_else(|| 
-            crate::sync::error::SyncError::Config("Remote data source required".to_string()))?;
+            SyncError::Config("Remote data source required".to_string()))?;
         
         let local = self.local.ok_or_else(|| 
-            crate::sync::error::SyncError::Config("Local data store required".to_string()))?;
+            SyncError::Config("Local data store required".to_string()))?;
         
         let conflict_resolver = self.conflict_resolver.ok_or_else(|| 
-            crate::sync::error::SyncError::Config("Conflict resolver required".to_string()))?;
+            SyncError::Config("Conflict resolver required".to_string()))?;
 
         let engine = SyncEngine {
             remote: remote,
             local: local,
             conflict_resolver: conflict_resolver,
+            conflict_strategy: self.conflict_strategy,
             strategies: Arc::new(RwLock::new(self.strategies)),
             status: Arc::new(RwLock::new(SyncStatus {
                 is_online: false,
@@ -684,16 +517,65 @@ impl SyncEngineBuilder {
                 database_initialized: false,
                 initial_sync_completed: false,
             })),
+            online: Arc::new(AtomicBool::new(false)),
             db: Arc::new(crate::database::DatabaseManager::new(":memory:").unwrap()), // Placeholder
             config: crate::sync::remote::supabase::SupabaseConfig {
             url: String::new(),
             anon_key: String::new(),
             batch_size: 100,
+            fallback_url: None,
         }, // Placeholder
-            client: reqwest::Client::new(),
+            client: crate::simple_sync::http_client().clone(),
             supabase_client: None,
         };
 
         Ok(engine)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::traits::{SyncMetadata, SyncOperation};
+    use crate::sync::{DefaultConflictResolver, SqliteLocalDataStore};
+    use async_trait::async_trait;
+    use chrono::{DateTime, Utc};
+    use serde_json::Value;
+
+    struct AlwaysOnline;
+
+    #[async_trait]
+    impl RemoteDataSource for AlwaysOnline {
+        async fn fetch_changes(&self, _: &str, _: Option<DateTime<Utc>>, _: Option<usize>, _: Option<usize>) -> SyncResult<Vec<(Value, SyncMetadata)>> {
+            Ok(Vec::new())
+        }
+
+        async fn push_changes(&self, _: &str, _: &[SyncOperation]) -> SyncResult<Vec<SyncMetadata>> {
+            Ok(Vec::new())
+        }
+
+        async fn check_connectivity(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn is_online_reads_through_a_held_status_lock() {
+        let pool = sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap();
+        let engine = SyncEngineBuilder::new()
+            .with_remote(Arc::new(AlwaysOnline))
+            .with_local(Arc::new(SqliteLocalDataStore::new(pool)))
+            .with_conflict_resolver(Arc::new(DefaultConflictResolver))
+            .build()
+            .unwrap();
+        assert!(!engine.is_online());
+        assert!(engine.check_connectivity().await);
+
+        // A sync holding the status lock must not stall the check: on this
+        // single-threaded runtime a blocking read would hang here for good
+        let status = engine.status.write().await;
+        assert!(engine.is_online());
+        drop(status);
+        assert!(engine.status.read().await.is_online);
+    }
+}
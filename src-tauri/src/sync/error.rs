@@ -1,40 +1,91 @@
+use reqwest::StatusCode;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum SyncError {
     #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
-    
-    #[error("Database error: {0}")]
-    Database(#[from] sqlx::Error),
-    
-    #[error("Serialization error: {0}")]
-    Serialization(#[from] serde_json::Error),
-    
-    #[allow(dead_code)]
-    #[error("Authentication error: {0}")]
-    Auth(String),
-    
+    Network(reqwest::Error),
+
+    #[error("Supabase rejected the credentials: {0}")]
+    AuthRejected(String),
+
+    #[error("Rate limited by Supabase: {0}")]
+    RateLimited(String),
+
+    #[error("Supabase returned HTTP {status}: {message}")]
+    Http { status: u16, message: String },
+
+    #[error("Could not read response: {0}")]
+    Deserialize(String),
+
+    #[error("Local database error: {0}")]
+    LocalDb(sqlx::Error),
+
+    #[error("Referenced record missing locally: {0}")]
+    ForeignKeyMissing(String),
+
     #[error("Conflict resolution failed: {0}")]
     Conflict(String),
-    
+
     #[error("Invalid data: {0}")]
     InvalidData(String),
-    
-    #[allow(dead_code)]
-    #[error("Rate limit exceeded")]
-    RateLimit,
-    
+
     #[allow(dead_code)]
     #[error("Operation timeout")]
     Timeout,
-    
+
     #[error("Sync already in progress")]
     #[allow(dead_code)]
     SyncInProgress,
-    
+
     #[error("Configuration error: {0}")]
     Config(String),
 }
 
+impl SyncError {
+    /// Classify a non-success response; context says what was being fetched or pushed
+    pub fn from_status(status: StatusCode, context: &str) -> Self {
+        let message = format!("{} (HTTP {})", context, status);
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => SyncError::AuthRejected(message),
+            StatusCode::TOO_MANY_REQUESTS => SyncError::RateLimited(message),
+            _ => SyncError::Http { status: status.as_u16(), message: context.to_string() },
+        }
+    }
+
+    /// Only transport failures and rate limiting are worth retrying as-is;
+    /// anything else fails the same way until data or config changes
+    #[allow(dead_code)]
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, SyncError::Network(_) | SyncError::RateLimited(_))
+    }
+}
+
+impl From<reqwest::Error> for SyncError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            SyncError::Deserialize(error.to_string())
+        } else {
+            SyncError::Network(error)
+        }
+    }
+}
+
+impl From<sqlx::Error> for SyncError {
+    fn from(error: sqlx::Error) -> Self {
+        let message = error.to_string();
+        if message.contains("FOREIGN KEY constraint failed") {
+            SyncError::ForeignKeyMissing(message)
+        } else {
+            SyncError::LocalDb(error)
+        }
+    }
+}
+
+impl From<serde_json::Error> for SyncError {
+    fn from(error: serde_json::Error) -> Self {
+        SyncError::Deserialize(error.to_string())
+    }
+}
+
 pub type SyncResult<T> = Result<T, SyncError>;
@@ -3,7 +3,8 @@ use sqlx::SqlitePool;
 
 use crate::sync::{
     SyncEngineBuilder, SupabaseConfig, SupabaseRemoteDataSource, 
-    SqliteLocalDataStore, DefaultConflictResolver, TwoWaySyncStrategy
+    SqliteLocalDataStore, DefaultConflictResolver, TwoWaySyncStrategy,
+    traits::ConflictResolutionStrategy,
 };
 
 // Example: Setting up the new sync architecture
@@ -17,6 +18,7 @@ pub async fn setup_sync_engine() -> Result<(), Box<dyn std::error::Error>> {
         url: "https://your-project.supabase.co".to_string(),
         anon_key: "your-anon-key".to_string(),
         batch_size: 100,
+        fallback_url: None,
     };
     let remote = Arc::new(SupabaseRemoteDataSource::new(supabase_config)?);
     
@@ -31,6 +33,7 @@ pub async fn setup_sync_engine() -> Result<(), Box<dyn std::error::Error>> {
         .with_remote(remote)
         .with_local(local)
         .with_conflict_resolver(conflict_resolver)
+        .with_conflict_strategy(ConflictResolutionStrategy::FieldMerge)
         .with_strategy("books".to_string(), Arc::new(TwoWaySyncStrategy))
         .with_strategy("students".to_string(), Arc::new(TwoWaySyncStrategy))
         .with_strategy("borrowings".to_string(), Arc::new(TwoWaySyncStrategy))
@@ -96,11 +99,11 @@ impl Syncable for Book {
     }
     
     fn from_value(value: serde_json::Value) -> SyncResult<Self> {
-        serde_json::from_value(value).map_err(|e| crate::sync::error::SyncError::Serialization(e))
+        serde_json::from_value(value).map_err(crate::sync::error::SyncError::from)
     }
     
     fn to_value(&self) -> SyncResult<serde_json::Value> {
-        serde_json::to_value(self).map_err(|e| crate::sync::error::SyncError::Serialization(e))
+        serde_json::to_value(self).map_err(crate::sync::error::SyncError::from)
     }
 }
 
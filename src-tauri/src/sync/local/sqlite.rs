@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
 use sqlx::{Pool, Sqlite};
+use std::collections::HashSet;
 
 use crate::sync::{
     error::{SyncError, SyncResult},
@@ -33,10 +34,42 @@ impl SqliteLocalDataStore {
         )
         .execute(&self.pool)
         .await
-        .map_err(|e| SyncError::Database(e))?;
+        .map_err(SyncError::from)?;
 
         Ok(())
     }
+
+    /// Column names of a data table, read from the live schema. Table and column
+    /// names are spliced into statements with format!, so only a table this
+    /// returns for, and columns in its result, may be used that way.
+    async fn table_columns(&self, table_name: &str) -> SyncResult<HashSet<String>> {
+        if table_name.starts_with("sqlite_") || table_name == "sync_metadata" {
+            return Err(SyncError::InvalidData(format!("{} is not a syncable table", table_name)));
+        }
+
+        let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+            .bind(table_name)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(SyncError::from)?;
+        if columns.is_empty() {
+            return Err(SyncError::InvalidData(format!("Unknown table: {}", table_name)));
+        }
+        Ok(columns.into_iter().collect())
+    }
+}
+
+fn check_columns<'a>(
+    table_name: &str,
+    known: &HashSet<String>,
+    columns: impl IntoIterator<Item = &'a String>,
+) -> SyncResult<()> {
+    for column in columns {
+        if !known.contains(column) {
+            return Err(SyncError::InvalidData(format!("{} has no column {}", table_name, column)));
+        }
+    }
+    Ok(())
 }
 
 #[async_trait]
@@ -47,6 +80,7 @@ impl LocalDataStore for SqliteLocalDataStore {
         since: Option<DateTime<Utc>>,
     ) -> SyncResult<Vec<SyncOperation>> {
         self.ensure_sync_table_exists().await?;
+        self.table_columns(table_name).await?;
 
         let query = match since {
             Some(_since_time) => format!(
@@ -88,7 +122,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                     .await
             }
         }
-        .map_err(|e| SyncError::Database(e))?;
+        .map_err(SyncError::from)?;
 
         let mut changes = Vec::new();
         for (record_id, local_version, remote_version, is_deleted, data) in rows {
@@ -135,6 +169,7 @@ impl LocalDataStore for SqliteLocalDataStore {
         changes: &[SyncOperation],
     ) -> SyncResult<()> {
         self.ensure_sync_table_exists().await?;
+        let known_columns = self.table_columns(table_name).await?;
 
         for change in changes {
             match change {
@@ -148,6 +183,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                         .keys()
                         .cloned()
                         .collect();
+                    check_columns(table_name, &known_columns, &columns)?;
                     
                     let placeholders: Vec<String> = columns.iter()
                         .map(|_| "?".to_string())
@@ -167,7 +203,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                         }
                     }
 
-                    query.execute(&self.pool).await.map_err(|e| SyncError::Database(e))?;
+                    query.execute(&self.pool).await.map_err(SyncError::from)?;
 
                     // Update sync metadata
                     sqlx::query(
@@ -183,7 +219,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                     .bind(metadata.version)
                     .execute(&self.pool)
                     .await
-                    .map_err(|e| SyncError::Database(e))?;
+                    .map_err(SyncError::from)?;
                 }
                 SyncOperation::Update { data, metadata } => {
                     let id = &metadata.id;
@@ -196,6 +232,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                         .filter(|k| *k != "id")
                         .cloned()
                         .collect();
+                    check_columns(table_name, &known_columns, &columns)?;
                     
                     let set_clause: Vec<String> = columns.iter()
                         .map(|c| format!("{} = ?", c))
@@ -215,7 +252,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                     }
                     query = query.bind(id);
 
-                    query.execute(&self.pool).await.map_err(|e| SyncError::Database(e))?;
+                    query.execute(&self.pool).await.map_err(SyncError::from)?;
 
                     // Update sync metadata
                     sqlx::query(
@@ -231,7 +268,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                     .bind(id)
                     .execute(&self.pool)
                     .await
-                    .map_err(|e| SyncError::Database(e))?;
+                    .map_err(SyncError::from)?;
                 }
                 SyncOperation::Delete { id, metadata } => {
                     // Soft delete from main table
@@ -240,7 +277,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                         .bind(id)
                         .execute(&self.pool)
                         .await
-                        .map_err(|e| SyncError::Database(e))?;
+                        .map_err(SyncError::from)?;
 
                     // Update sync metadata
                     sqlx::query(
@@ -255,7 +292,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                     .bind(id)
                     .execute(&self.pool)
                     .await
-                    .map_err(|e| SyncError::Database(e))?;
+                    .map_err(SyncError::from)?;
                 }
             }
         }
@@ -272,7 +309,7 @@ impl LocalDataStore for SqliteLocalDataStore {
         .bind(table_name)
         .fetch_one(&self.pool)
         .await
-        .map_err(|e| SyncError::Database(e))?;
+        .map_err(SyncError::from)?;
 
         match result {
             Some(timestamp) => DateTime::parse_from_rfc3339(&timestamp)
@@ -299,7 +336,7 @@ impl LocalDataStore for SqliteLocalDataStore {
         .bind(time)
         .execute(&self.pool)
         .await
-        .map_err(|e| SyncError::Database(e))?;
+        .map_err(SyncError::from)?;
 
         Ok(())
     }
@@ -334,6 +371,7 @@ impl LocalDataStore for SqliteLocalDataStore {
                     }
                     merged
                 }
+                ConflictResolutionStrategy::FieldMerge => crate::sync::conflict::field_merge(conflict)?,
                 ConflictResolutionStrategy::Manual => {
                     return Err(SyncError::Conflict(
                         "Manual conflict resolution required".to_string(),
@@ -5,6 +5,8 @@ pub mod conflict;
 pub mod strategy;
 pub mod remote;
 pub mod local;
+pub mod normalize;
+pub mod rows;
 
 // These imports are used in the commented-out code below
 // use chrono::{DateTime, Utc};
@@ -0,0 +1,73 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::{Map, Value};
+
+/// Columns holding record ids; SQLite may keep them as 32-char hex without hyphens
+pub const UUID_FIELDS: &[&str] = &[
+    "id", "book_id", "book_copy_id", "student_id", "staff_id", "category_id", "class_id",
+    "borrowing_id", "group_borrowing_id", "fine_id", "user_id", "issued_by", "returned_by",
+    "created_by", "reported_by", "resolved_by",
+];
+
+/// Enum-like columns Supabase stores in lowercase
+pub const ENUM_FIELDS: &[&str] = &[
+    "status", "condition", "type", "role", "gender", "fine_type", "borrower_type",
+    "academic_level_type", "condition_at_issue", "condition_at_return", "copy_condition",
+];
+
+/// Columns kept as 0/1 integers in SQLite and booleans in Supabase
+pub const BOOLEAN_FIELDS: &[&str] = &["is_active", "is_lost", "is_repeating", "fine_paid", "due_date_override"];
+
+/// Local bookkeeping columns that have no counterpart in Supabase
+pub const LOCAL_ONLY_FIELDS: &[&str] = &["content_hash", "pushed_hash", "synced", "sync_version", "deleted"];
+
+/// Hyphenated form of a 32-character hex id; anything else is returned unchanged
+pub fn text_to_uuid(text: &str) -> String {
+    if text.len() == 32 && text.chars().all(|c| c.is_ascii_hexdigit()) {
+        format!("{}-{}-{}-{}-{}",
+            &text[0..8], &text[8..12], &text[12..16], &text[16..20], &text[20..32]).to_lowercase()
+    } else {
+        text.to_string()
+    }
+}
+
+// SQLite timestamps come as "YYYY-MM-DD HH:MM:SS" (datetime('now')) or RFC3339
+fn timestamp_to_rfc3339(text: &str) -> Option<String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some(dt.with_timezone(&Utc).to_rfc3339());
+    }
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .map(|naive| naive.and_utc().to_rfc3339())
+}
+
+/// Rewrite a local row into the shape Supabase expects: hyphenated UUIDs, lowercase
+/// enums, RFC3339 timestamps, real booleans, and no local-only columns
+pub fn normalize_for_supabase(row: &mut Map<String, Value>) {
+    for field in LOCAL_ONLY_FIELDS {
+        row.remove(*field);
+    }
+
+    for (key, value) in row.iter_mut() {
+        let key = key.as_str();
+        match value {
+            Value::String(text) if UUID_FIELDS.contains(&key) => *text = text_to_uuid(text.trim()),
+            Value::String(text) if ENUM_FIELDS.contains(&key) => *text = text.trim().to_lowercase(),
+            Value::String(text) if key.ends_with("_at") => {
+                if let Some(converted) = timestamp_to_rfc3339(text.trim()) {
+                    *text = converted;
+                }
+            }
+            // group_borrowings keeps its member list as JSON text
+            Value::String(text) if key == "student_ids" => {
+                if let Ok(Value::Array(ids)) = serde_json::from_str::<Value>(text) {
+                    *value = Value::Array(ids.into_iter().map(|id| match id {
+                        Value::String(id) => Value::String(text_to_uuid(&id)),
+                        other => other,
+                    }).collect());
+                }
+            }
+            Value::Number(n) if BOOLEAN_FIELDS.contains(&key) => *value = Value::Bool(n.as_i64().unwrap_or(0) != 0),
+            _ => {}
+        }
+    }
+}
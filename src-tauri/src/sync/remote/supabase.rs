@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use reqwest::{Client, header};
 use serde_json::Value;
+use tracing::warn;
 
 use crate::sync::{
     error::{SyncError, SyncResult},
@@ -13,6 +14,7 @@ pub struct SupabaseConfig {
     pub url: String,
     pub anon_key: String,
     pub batch_size: usize,
+    pub fallback_url: Option<String>,
 }
 
 pub struct SupabaseRemoteDataSource {
@@ -33,7 +35,7 @@ impl SupabaseRemoteDataSource {
                 headers
             })
             .build()
-            .map_err(|e| SyncError::Network(e))?;
+            .map_err(SyncError::from)?;
 
         Ok(Self { client, config })
     }
@@ -67,16 +69,16 @@ impl RemoteDataSource for SupabaseRemoteDataSource {
             .get(&url)
             .send()
             .await
-            .map_err(|e| SyncError::Network(e))?;
+            .map_err(SyncError::from)?;
 
         if !response.status().is_success() {
-            return Err(SyncError::InvalidData(format!("Failed to fetch changes: {}", response.status())));
+            return Err(SyncError::from_status(response.status(), &format!("Failed to fetch {} changes", table_name)));
         }
 
         let data: Vec<Value> = response
             .json()
             .await
-            .map_err(|e| SyncError::Network(e))?;
+            .map_err(SyncError::from)?;
 
         let mut results = Vec::new();
         for item in data {
@@ -132,7 +134,7 @@ impl RemoteDataSource for SupabaseRemoteDataSource {
                         .delete(&url)
                         .send()
                         .await
-                        .map_err(|e| SyncError::Network(e))?;
+                        .map_err(SyncError::from)?;
 
                     if response.status().is_success() {
                         results.push(metadata.clone());
@@ -149,7 +151,7 @@ impl RemoteDataSource for SupabaseRemoteDataSource {
                 .json(data)
                 .send()
                 .await
-                .map_err(|e| SyncError::Network(e))?;
+                .map_err(SyncError::from)?;
 
             if response.status().is_success() {
                 results.push(metadata.clone());
@@ -159,11 +161,22 @@ impl RemoteDataSource for SupabaseRemoteDataSource {
         Ok(results)
     }
 
+    // Any answer from the Supabase REST endpoint means the network is up, including
+    // 401/404 - only a failed request or a 5xx falls through to the fallback host
     async fn check_connectivity(&self) -> bool {
-        let url = format!("{}/rest/v1/", self.config.url);
-        match self.client.head(&url).send().await {
-            Ok(response) => response.status().is_success(),
-            Err(_) => false,
+        if !self.config.url.is_empty() {
+            let url = format!("{}/rest/v1/", self.config.url);
+            match self.client.head(&url).header("apikey", &self.config.anon_key).send().await {
+                Ok(response) if !response.status().is_server_error() => return true,
+                Ok(response) => warn!("Supabase health check returned {}", response.status()),
+                Err(e) => warn!("Supabase unreachable: {}", e),
+            }
+        }
+
+        // Plain shared client so the Supabase auth headers never go to the fallback host
+        match &self.config.fallback_url {
+            Some(fallback) => crate::simple_sync::http_client().head(fallback).send().await.is_ok(),
+            None => false,
         }
     }
 }
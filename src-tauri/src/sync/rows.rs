@@ -0,0 +1,182 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::database::normalize_gender;
+use crate::models::{Book, BookCondition, BookStatus, Category, Staff, Student};
+
+/// A row as Supabase returns it. Columns the table declares NOT NULL are
+/// required fields, so a renamed or retyped column fails the row instead of
+/// turning into a placeholder value.
+pub trait RemoteRow: DeserializeOwned {
+    type Model;
+
+    fn into_model(self) -> Result<Self::Model, String>;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoteBook {
+    id: Uuid,
+    title: String,
+    author: String,
+    isbn: Option<String>,
+    genre: Option<String>,
+    publisher: Option<String>,
+    publication_year: Option<i32>,
+    category_id: Option<Uuid>,
+    total_copies: i32,
+    available_copies: i32,
+    shelf_location: Option<String>,
+    cover_image_url: Option<String>,
+    description: Option<String>,
+    status: Option<BookStatus>,
+    condition: Option<BookCondition>,
+    book_code: Option<String>,
+    acquisition_year: Option<i32>,
+    legacy_book_id: Option<i32>,
+    legacy_isbn: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl RemoteRow for RemoteBook {
+    type Model = Book;
+
+    fn into_model(self) -> Result<Book, String> {
+        Ok(Book {
+            id: self.id,
+            title: self.title,
+            author: self.author,
+            isbn: self.isbn,
+            genre: self.genre,
+            publisher: self.publisher,
+            publication_year: self.publication_year,
+            category_id: self.category_id,
+            total_copies: self.total_copies,
+            available_copies: self.available_copies,
+            shelf_location: self.shelf_location,
+            cover_image_url: self.cover_image_url,
+            description: self.description,
+            status: self.status.unwrap_or(BookStatus::Available),
+            condition: self.condition,
+            book_code: self.book_code,
+            acquisition_year: self.acquisition_year,
+            legacy_book_id: self.legacy_book_id,
+            legacy_isbn: self.legacy_isbn,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoteCategory {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl RemoteRow for RemoteCategory {
+    type Model = Category;
+
+    fn into_model(self) -> Result<Category, String> {
+        Ok(Category {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoteStudent {
+    id: Uuid,
+    admission_number: String,
+    first_name: String,
+    last_name: String,
+    email: Option<String>,
+    phone: Option<String>,
+    class_grade: String,
+    address: Option<String>,
+    date_of_birth: Option<NaiveDate>,
+    enrollment_date: Option<NaiveDate>,
+    status: Option<String>,
+    class_id: Option<Uuid>,
+    academic_year: Option<String>,
+    is_repeating: Option<bool>,
+    legacy_student_id: Option<i32>,
+    gender: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl RemoteRow for RemoteStudent {
+    type Model = Student;
+
+    fn into_model(self) -> Result<Student, String> {
+        let gender = normalize_gender(self.gender.as_deref()).map_err(|e| e.to_string())?;
+        Ok(Student {
+            id: self.id,
+            admission_number: self.admission_number,
+            first_name: self.first_name,
+            last_name: self.last_name,
+            email: self.email,
+            phone: self.phone,
+            class_grade: self.class_grade,
+            address: self.address,
+            date_of_birth: self.date_of_birth,
+            // The columns below have database defaults, so a null just means the default
+            enrollment_date: self.enrollment_date.unwrap_or_else(|| Utc::now().date_naive()),
+            status: self.status.unwrap_or_else(|| "active".to_string()),
+            class_id: self.class_id,
+            academic_year: self.academic_year.unwrap_or_else(|| "2024/2025".to_string()),
+            is_repeating: self.is_repeating.unwrap_or(false),
+            legacy_student_id: self.legacy_student_id,
+            gender,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoteStaff {
+    id: Uuid,
+    staff_id: String,
+    first_name: String,
+    last_name: String,
+    email: Option<String>,
+    phone: Option<String>,
+    department: Option<String>,
+    position: Option<String>,
+    status: Option<String>,
+    legacy_staff_id: Option<i32>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl RemoteRow for RemoteStaff {
+    type Model = Staff;
+
+    fn into_model(self) -> Result<Staff, String> {
+        Ok(Staff {
+            id: self.id,
+            staff_id: self.staff_id,
+            first_name: self.first_name,
+            last_name: self.last_name,
+            email: self.email,
+            phone: self.phone,
+            department: self.department,
+            position: self.position,
+            status: self.status.unwrap_or_else(|| "active".to_string()),
+            legacy_staff_id: self.legacy_staff_id,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
@@ -4,7 +4,7 @@ use std::time::Instant;
 
 use crate::sync::{
     error::SyncResult,
-    traits::{ConflictResolver, LocalDataStore, RemoteDataSource, SyncStrategy, SyncSummary, SyncOperation},
+    traits::{ConflictResolutionStrategy, ConflictResolver, LocalDataStore, RemoteDataSource, SyncConflict, SyncStrategy, SyncSummary, SyncOperation},
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -23,7 +23,8 @@ impl SyncStrategy for TwoWaySyncStrategy {
         table_name: &str,
         remote: &dyn RemoteDataSource,
         local: &dyn LocalDataStore,
-        _conflict_resolver: &dyn ConflictResolver,
+        conflict_resolver: &dyn ConflictResolver,
+        conflict_strategy: ConflictResolutionStrategy,
     ) -> SyncResult<SyncSummary> {
         let start_time = Instant::now();
         
@@ -35,6 +36,7 @@ impl SyncStrategy for TwoWaySyncStrategy {
         
         // Process changes incrementally
         let mut conflicts = Vec::new();
+        let mut resolved = Vec::new();
         let mut processed = 0;
         
         // Handle remote changes first
@@ -60,15 +62,33 @@ impl SyncStrategy for TwoWaySyncStrategy {
                 };
                 
                 // Simple conflict detection - if remote has changes for same ID
-                let has_remote_conflict = latest_remote.iter().any(|(_, meta)| meta.id == *id);
+                let remote_version = latest_remote.iter().find(|(_, meta)| meta.id == *id);
                 
-                if has_remote_conflict {
-                    conflicts.push(local_change);
-                } else {
-                    safe_local_changes.push(local_change);
+                match (remote_version, &local_change) {
+                    (Some((remote_data, remote_metadata)), SyncOperation::Create { data, metadata } | SyncOperation::Update { data, metadata }) => {
+                        let conflict = SyncConflict {
+                            local: data.clone(),
+                            remote: remote_data.clone(),
+                            local_metadata: metadata.clone(),
+                            remote_metadata: remote_metadata.clone(),
+                            base: None,
+                        };
+                        match conflict_resolver.resolve(&conflict, conflict_strategy).await {
+                            Ok(data) => resolved.push(SyncOperation::Update { data, metadata: metadata.clone() }),
+                            Err(_) => conflicts.push(local_change),
+                        }
+                    }
+                    (Some(_), SyncOperation::Delete { .. }) => conflicts.push(local_change),
+                    (None, _) => safe_local_changes.push(local_change),
                 }
             }
             
+            // Resolved records are written back on both sides
+            if !resolved.is_empty() {
+                local.apply_changes(table_name, &resolved).await?;
+                safe_local_changes.extend(resolved.iter().cloned());
+            }
+            
             if !safe_local_changes.is_empty() {
                 remote.push_changes(table_name, &safe_local_changes).await?;
                 processed += safe_local_changes.len();
@@ -83,8 +103,8 @@ impl SyncStrategy for TwoWaySyncStrategy {
             table_name: table_name.to_string(),
             remote_changes: 0,
             local_changes: processed,
-            conflicts: conflicts.len(),
-            resolved: 0,
+            conflicts: conflicts.len() + resolved.len(),
+            resolved: resolved.len(),
             errors: Vec::new(),
             sync_duration_ms: start_time.elapsed().as_millis() as u64,
         })
@@ -106,6 +126,7 @@ impl SyncStrategy for OneWaySyncStrategy {
         remote: &dyn RemoteDataSource,
         local: &dyn LocalDataStore,
         _conflict_resolver: &dyn ConflictResolver,
+        _conflict_strategy: ConflictResolutionStrategy,
     ) -> SyncResult<SyncSummary> {
         let start_time = Instant::now();
         
@@ -167,6 +188,7 @@ impl SyncStrategy for IncrementalSyncStrategy {
         remote: &dyn RemoteDataSource,
         local: &dyn LocalDataStore,
         _conflict_resolver: &dyn ConflictResolver,
+        _conflict_strategy: ConflictResolutionStrategy,
     ) -> SyncResult<SyncSummary> {
         let start_time = Instant::now();
         let mut total_summary = SyncSummary {
@@ -28,6 +28,10 @@ pub struct SyncConflict {
     pub remote: Value,
     pub local_metadata: SyncMetadata,
     pub remote_metadata: SyncMetadata,
+    /// The record as of the last sync, when known, so a field merge can tell which
+    /// side changed a column rather than only that the two sides differ
+    #[serde(default)]
+    pub base: Option<Value>,
 }
 
 
@@ -94,6 +98,9 @@ pub enum ConflictResolutionStrategy {
     NewestWins,
     #[allow(dead_code)]
     Merge,
+    /// Keep each column's change from whichever side made it; newest wins per column
+    #[allow(dead_code)]
+    FieldMerge,
     #[allow(dead_code)]
     Manual,
 }
@@ -116,6 +123,7 @@ pub trait SyncStrategy: Send + Sync {
         remote: &dyn RemoteDataSource,
         local: &dyn LocalDataStore,
         conflict_resolver: &dyn ConflictResolver,
+        conflict_strategy: ConflictResolutionStrategy,
     ) -> SyncResult<SyncSummary>;
 }
 
@@ -25,6 +25,23 @@ export interface SyncStats {
   theftReports?: number;
 }
 
+export interface SyncResult {
+  entity: string;
+  inserted: number;
+  skipped: number;
+  failed: number;
+  errors: string[];
+  duration_ms: number;
+}
+
+// e.g. "1,200 inserted, 34 skipped"
+export const describeSyncResult = (result: SyncResult): string => {
+  const parts = [`${result.inserted.toLocaleString()} inserted`];
+  if (result.skipped > 0) parts.push(`${result.skipped.toLocaleString()} skipped`);
+  if (result.failed > 0) parts.push(`${result.failed.toLocaleString()} failed`);
+  return parts.join(', ');
+};
+
 export const useProfessionalSync = () => {
   const [syncProgress, setSyncProgress] = useState<SyncProgress>({
     issyncing: false,
@@ -48,23 +65,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<any>('sync_books_only', { limit });
+      const result = await invoke<SyncResult>('sync_books_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Books sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "Books Synchronized",
-        description: `Successfully synced books from Supabase`,
+        description: `Synced books from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { books: result.recordsSync || 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0 };
+      return { books: result.inserted, students: 0, categories: 0, borrowings: 0, bookCopies: 0 };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -93,23 +111,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<any>('sync_categories_only');
+      const result = await invoke<SyncResult>('sync_categories_only');
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Categories sync completed',
         progress: 100,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "Categories Synchronized",
-        description: `Successfully synced categories from Supabase`,
+        description: `Synced categories from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { categories: result.recordsSync || 0, books: 0, students: 0, borrowings: 0, bookCopies: 0 };
+      return { categories: result.inserted, books: 0, students: 0, borrowings: 0, bookCopies: 0 };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -139,23 +158,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<any>('sync_students_only', { limit });
+      const result = await invoke<SyncResult>('sync_students_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Students sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "Students Synchronized",
-        description: `Successfully synced students from Supabase`,
+        description: `Synced students from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { students: result.recordsSync || 0, books: 0, categories: 0, borrowings: 0, bookCopies: 0 };
+      return { students: result.inserted, books: 0, categories: 0, borrowings: 0, bookCopies: 0 };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -185,23 +205,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_borrowings_only', { limit });
+      const result = await invoke<SyncResult>('sync_borrowings_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Borrowings sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "Borrowings Synchronized",
-        description: `Successfully synced ${result} borrowings from Supabase`,
+        description: `Synced borrowings from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { borrowings: result || 0, books: 0, students: 0, categories: 0, bookCopies: 0 };
+      return { borrowings: result.inserted, books: 0, students: 0, categories: 0, bookCopies: 0 };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -231,23 +252,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_staff_only', { limit });
+      const result = await invoke<SyncResult>('sync_staff_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Staff sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "Staff Synchronized",
-        description: `Successfully synced ${result} staff members from Supabase`,
+        description: `Synced staff members from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { staff: result || 0, books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0 };
+      return { staff: result.inserted, books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0 };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -277,23 +299,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_classes_only');
+      const result = await invoke<SyncResult>('sync_classes_only');
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Classes sync completed',
         progress: 100,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "Classes Synchronized",
-        description: `Successfully synced ${result} classes from Supabase`,
+        description: `Synced classes from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { classes: result || 0, books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0 };
+      return { classes: result.inserted, books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0 };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -323,19 +346,23 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<string>('pull_all_database');
+      const results = await invoke<SyncResult[]>('pull_all_database');
+      const inserted = results.reduce((sum, r) => sum + r.inserted, 0);
+      const skipped = results.reduce((sum, r) => sum + r.skipped, 0);
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: '🎉 Complete database pull finished!',
         progress: 100,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: results.flatMap(r => r.errors.map(e => `${r.entity}: ${e}`))
       }));
 
       toast({
         title: "🚀 Complete Database Pulled!",
-        description: result,
+        description: `${inserted.toLocaleString()} records pulled across ${results.length} tables` +
+          (skipped > 0 ? `, ${skipped.toLocaleString()} skipped` : ''),
         variant: "default",
       });
 
@@ -376,23 +403,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_book_copies_only', { limit });
+      const result = await invoke<SyncResult>('sync_book_copies_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Book copies sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "📚 Book Copies Synchronized",
-        description: `Successfully synced ${result.toLocaleString()} book copies from Supabase`,
+        description: `Synced book copies from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { bookCopies: result || 0, books: 0, students: 0, categories: 0, borrowings: 0 };
+      return { bookCopies: result.inserted, books: 0, students: 0, categories: 0, borrowings: 0 };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -422,23 +450,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_fines_only', { limit });
+      const result = await invoke<SyncResult>('sync_fines_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Fines sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "💰 Fines Synchronized",
-        description: `Successfully synced ${result.toLocaleString()} fines from Supabase`,
+        description: `Synced fines from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, fines: result || 0 };
+      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, fines: result.inserted };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -467,23 +496,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_fine_settings_only');
+      const result = await invoke<SyncResult>('sync_fine_settings_only');
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Fine settings sync completed',
         progress: 100,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "⚙️ Fine Settings Synchronized",
-        description: `Successfully synced ${result.toLocaleString()} fine settings from Supabase`,
+        description: `Synced fine settings from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, fineSettings: result || 0 };
+      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, fineSettings: result.inserted };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -513,23 +543,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_group_borrowings_only', { limit });
+      const result = await invoke<SyncResult>('sync_group_borrowings_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Group borrowings sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "👥 Group Borrowings Synchronized",
-        description: `Successfully synced ${result.toLocaleString()} group borrowings from Supabase`,
+        description: `Synced group borrowings from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, groupBorrowings: result || 0 };
+      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, groupBorrowings: result.inserted };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,
@@ -559,23 +590,24 @@ export const useProfessionalSync = () => {
     }));
 
     try {
-      const result = await invoke<number>('sync_theft_reports_only', { limit });
+      const result = await invoke<SyncResult>('sync_theft_reports_only', { limit });
       
       setSyncProgress(prev => ({
         ...prev,
         issyncing: false,
         currentTask: 'Theft reports sync completed',
         progress: limit,
-        lastSync: new Date().toISOString()
+        lastSync: new Date().toISOString(),
+        errors: result.errors
       }));
 
       toast({
         title: "🚨 Theft Reports Synchronized",
-        description: `Successfully synced ${result.toLocaleString()} theft reports from Supabase`,
+        description: `Synced theft reports from Supabase: ${describeSyncResult(result)}`,
         variant: "default",
       });
 
-      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, theftReports: result || 0 };
+      return { books: 0, students: 0, categories: 0, borrowings: 0, bookCopies: 0, theftReports: result.inserted };
     } catch (error: any) {
       setSyncProgress(prev => ({
         ...prev,